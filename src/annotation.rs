@@ -0,0 +1,242 @@
+use std::fmt;
+
+use image::Rgba;
+use pgn_reader::Nag;
+use regex::Regex;
+use shakmaty::Square;
+
+/// Colors used by %cal and %csl annotations, identified by their first letter.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AnnotationColor {
+    #[default]
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl AnnotationColor {
+    /// Parse a color letter. Unknown letters fall back to green.
+    pub fn from_char(c: char) -> Self {
+        match c.to_ascii_uppercase() {
+            'R' => AnnotationColor::Red,
+            'Y' => AnnotationColor::Yellow,
+            'B' => AnnotationColor::Blue,
+            _ => AnnotationColor::Green,
+        }
+    }
+
+    /// Colors as used by lichess to draw annotations.
+    pub fn rgba(&self) -> Rgba<u8> {
+        match self {
+            AnnotationColor::Green => Rgba([21, 120, 27, 255]),
+            AnnotationColor::Red => Rgba([136, 32, 32, 255]),
+            AnnotationColor::Yellow => Rgba([230, 143, 0, 255]),
+            AnnotationColor::Blue => Rgba([0, 48, 136, 255]),
+        }
+    }
+}
+
+/// Arrows and circles annotated in a move's comment with [%cal ...] and [%csl ...].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Annotations {
+    pub arrows: Vec<(AnnotationColor, Square, Square)>,
+    pub circles: Vec<(AnnotationColor, Square)>,
+}
+
+impl Annotations {
+    /// Parse all %cal and %csl commands in a comment, like "[%cal Ge2e4,Rd1h5] [%csl Gd4]".
+    /// Entries that can't be parsed are skipped.
+    pub fn parse(comment: &str) -> Self {
+        let re = Regex::new(r"\[%(cal|csl)\s+([^\]]*)\]").unwrap();
+        let mut annotations = Annotations::default();
+
+        for caps in re.captures_iter(comment) {
+            for entry in caps[2].split(',').map(|e| e.trim()) {
+                if !entry.is_ascii() {
+                    continue;
+                }
+                let (color, squares) = match entry.chars().next() {
+                    Some(c) if entry.len() % 2 == 1 => (AnnotationColor::from_char(c), &entry[1..]),
+                    Some(_) => (AnnotationColor::default(), entry),
+                    None => continue,
+                };
+
+                match (&caps[1], squares.len()) {
+                    ("cal", 4) => {
+                        if let (Ok(from), Ok(to)) = (
+                            squares[..2].parse::<Square>(),
+                            squares[2..].parse::<Square>(),
+                        ) {
+                            annotations.arrows.push((color, from, to));
+                        }
+                    }
+                    ("csl", 2) => {
+                        if let Ok(square) = squares.parse::<Square>() {
+                            annotations.circles.push((color, square));
+                        }
+                    }
+                    _ => log::debug!("Skipping annotation: {}", entry),
+                }
+            }
+        }
+
+        annotations
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.arrows.is_empty() && self.circles.is_empty()
+    }
+
+    pub fn extend(&mut self, other: Annotations) {
+        self.arrows.extend(other.arrows);
+        self.circles.extend(other.circles);
+    }
+}
+
+/// Text of a comment without its commands, like [%clk 0:02:59] or [%cal Ge2e4], and with its
+/// whitespace collapsed. Returns None for comments with nothing else, like clock-only ones.
+pub fn comment_text(comment: &str) -> Option<String> {
+    let re = Regex::new(r"\[%[^\]]*\]").unwrap();
+    let text = re
+        .replace_all(comment, " ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Assessment of a move given with a NAG, like $4 or ?? for a blunder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveAssessment {
+    Good,
+    Mistake,
+    Brilliant,
+    Blunder,
+    Interesting,
+    Dubious,
+}
+
+impl MoveAssessment {
+    /// Assessment of one of the move NAGs, $1 to $6. Returns None for any other NAG, like
+    /// those assessing the position.
+    pub fn from_nag(nag: Nag) -> Option<Self> {
+        match nag.0 {
+            1 => Some(MoveAssessment::Good),
+            2 => Some(MoveAssessment::Mistake),
+            3 => Some(MoveAssessment::Brilliant),
+            4 => Some(MoveAssessment::Blunder),
+            5 => Some(MoveAssessment::Interesting),
+            6 => Some(MoveAssessment::Dubious),
+            _ => None,
+        }
+    }
+
+    /// Badge background color, as used by lichess for glyphs.
+    pub fn color(&self) -> Rgba<u8> {
+        match self {
+            MoveAssessment::Good => Rgba([34, 172, 56, 255]),
+            MoveAssessment::Mistake => Rgba([230, 159, 0, 255]),
+            MoveAssessment::Brilliant => Rgba([22, 130, 38, 255]),
+            MoveAssessment::Blunder => Rgba([223, 83, 83, 255]),
+            MoveAssessment::Interesting => Rgba([234, 69, 216, 255]),
+            MoveAssessment::Dubious => Rgba([86, 180, 233, 255]),
+        }
+    }
+}
+
+impl fmt::Display for MoveAssessment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveAssessment::Good => write!(f, "!"),
+            MoveAssessment::Mistake => write!(f, "?"),
+            MoveAssessment::Brilliant => write!(f, "!!"),
+            MoveAssessment::Blunder => write!(f, "??"),
+            MoveAssessment::Interesting => write!(f, "!?"),
+            MoveAssessment::Dubious => write!(f, "?!"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_annotations() {
+        let annotations = Annotations::parse("[%cal Ge2e4,Rd1h5] [%csl Gd4,Bf7]");
+        assert_eq!(
+            annotations.arrows,
+            vec![
+                (AnnotationColor::Green, Square::E2, Square::E4),
+                (AnnotationColor::Red, Square::D1, Square::H5),
+            ]
+        );
+        assert_eq!(
+            annotations.circles,
+            vec![
+                (AnnotationColor::Green, Square::D4),
+                (AnnotationColor::Blue, Square::F7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_annotations_with_clock() {
+        let annotations = Annotations::parse("[%clk 0:02:59.5] [%csl Yc4][%cal Yg1f3]");
+        assert_eq!(
+            annotations.circles,
+            vec![(AnnotationColor::Yellow, Square::C4)]
+        );
+        assert_eq!(
+            annotations.arrows,
+            vec![(AnnotationColor::Yellow, Square::G1, Square::F3)]
+        );
+    }
+
+    #[test]
+    fn test_parse_annotations_unknown_color() {
+        let annotations = Annotations::parse("[%cal Xe2e4,e7e5] [%csl Qa1]");
+        assert_eq!(
+            annotations.arrows,
+            vec![
+                (AnnotationColor::Green, Square::E2, Square::E4),
+                (AnnotationColor::Green, Square::E7, Square::E5),
+            ]
+        );
+        assert_eq!(
+            annotations.circles,
+            vec![(AnnotationColor::Green, Square::A1)]
+        );
+    }
+
+    #[test]
+    fn test_parse_annotations_invalid() {
+        let annotations = Annotations::parse("[%cal Gz9e4,Ge2] [%csl] Just a comment");
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn test_move_assessment_from_nag() {
+        let glyph = |nag: u8| MoveAssessment::from_nag(Nag(nag)).map(|a| a.to_string());
+        assert_eq!(glyph(1).as_deref(), Some("!"));
+        assert_eq!(glyph(4).as_deref(), Some("??"));
+        assert_eq!(glyph(6).as_deref(), Some("?!"));
+        assert_eq!(glyph(0), None);
+        assert_eq!(glyph(18), None);
+    }
+
+    #[test]
+    fn test_comment_text() {
+        assert_eq!(
+            comment_text(" A bold\n sacrifice! [%clk 0:02:59] [%cal Rd1h5]").as_deref(),
+            Some("A bold sacrifice!")
+        );
+        assert_eq!(
+            comment_text("Threatening [%csl Gd4] mate").as_deref(),
+            Some("Threatening mate")
+        );
+        assert_eq!(comment_text(" [%clk 0:02:59] [%eval 0.17] "), None);
+        assert_eq!(comment_text(""), None);
+    }
+}
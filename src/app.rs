@@ -1,8 +1,38 @@
 use pgn_reader::BufferedReader;
 
 use crate::config::Config;
+use crate::drawer::SVGForest;
 use crate::error::C2GError;
-use crate::giffer::PGNGiffer;
+use crate::giffer::{GameMetadata, PGNGiffer};
+
+/// Byte order mark some editors prepend to UTF-8 files.
+const BOM: char = '\u{feff}';
+
+/// Decode PGN bytes into a String. PGNs that are not valid UTF-8 are assumed to be
+/// Latin-1, which is what most older tools export. A leading BOM is removed.
+pub fn decode_pgn(bytes: Vec<u8>) -> String {
+    let pgn = match String::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => {
+            log::debug!("PGN is not valid UTF-8, decoding as Latin-1: {}", e);
+            e.into_bytes().into_iter().map(char::from).collect()
+        }
+    };
+
+    match pgn.strip_prefix(BOM) {
+        Some(s) => s.to_owned(),
+        None => pgn,
+    }
+}
+
+/// Everything produced by rendering a game.
+#[derive(Debug)]
+pub struct RenderReport {
+    /// The GIF, if the output was a buffer.
+    pub gif: Option<Vec<u8>>,
+    /// Information about the game and the frames written. None if no game was found.
+    pub metadata: Option<GameMetadata>,
+}
 
 /// The main c2g app.
 #[derive(Debug)]
@@ -13,27 +43,78 @@ pub struct Chess2Gif {
 
 impl Chess2Gif {
     pub fn new(pgn: String, config: Config) -> Result<Self, C2GError> {
-        Ok(Chess2Gif {
+        Ok(Chess2Gif::from_giffer(pgn, PGNGiffer::new(config)?))
+    }
+
+    /// Like new, but draws with a pre-built SVGForest, like one made with
+    /// SVGForest::from_sources.
+    pub fn with_svgs(pgn: String, config: Config, svgs: SVGForest) -> Result<Self, C2GError> {
+        Ok(Chess2Gif::from_giffer(
             pgn,
-            giffer: PGNGiffer::new(config)?,
-        })
+            PGNGiffer::with_svgs(config, svgs)?,
+        ))
+    }
+
+    fn from_giffer(pgn: String, giffer: PGNGiffer) -> Self {
+        Chess2Gif {
+            pgn: decode_pgn(pgn.into_bytes()),
+            giffer,
+        }
     }
 
     /// Runs the main c2g app by reading the PGN game provided.
-    pub fn run(mut self) -> Result<Option<Vec<u8>>, C2GError> {
+    pub fn run(self) -> Result<Option<Vec<u8>>, C2GError> {
+        self.render().map(|report| report.gif)
+    }
+
+    /// Same as run, but also returns metadata about the game rendered.
+    pub fn render(mut self) -> Result<RenderReport, C2GError> {
         log::info!("Reading PGN");
         let mut reader = BufferedReader::new_cursor(&self.pgn[..]);
 
-        match reader.read_game(&mut self.giffer) {
+        let gif = match reader.read_game(&mut self.giffer) {
             Ok(result) => match result {
-                // result contains Option<Result<(), C2GError>>
-                Some(r) => match r {
-                    Ok(Some(v)) => Ok(Some(v)),
-                    Ok(None) | Err(_) => Ok(None),
-                },
-                None => Ok(None),
+                // result holds what the giffer returned, if a game was read
+                Some(r) => r?,
+                None => None,
             },
-            Err(e) => Err(C2GError::ReadGame { source: e }),
-        }
+            Err(e) => return Err(C2GError::ReadGame { source: e }),
+        };
+
+        Ok(RenderReport {
+            gif,
+            metadata: self.giffer.metadata().cloned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_pgn_utf8() {
+        let pgn = decode_pgn("[White \"Müller\"]".as_bytes().to_vec());
+        assert_eq!(pgn, "[White \"Müller\"]");
+    }
+
+    #[test]
+    fn test_decode_pgn_latin1() {
+        let bytes = vec![b'M', 0xfc, b'l', b'l', b'e', b'r'];
+        assert_eq!(decode_pgn(bytes), "Müller");
+    }
+
+    #[test]
+    fn test_decode_pgn_strips_bom() {
+        let mut bytes = vec![0xef, 0xbb, 0xbf];
+        bytes.extend_from_slice(b"[Event \"Live Chess\"]");
+        assert_eq!(decode_pgn(bytes), "[Event \"Live Chess\"]");
+    }
+
+    #[test]
+    fn test_new_strips_bom() {
+        let pgn = "\u{feff}[Event \"Live Chess\"]".to_string();
+        let app = Chess2Gif::new(pgn, Config::default()).unwrap();
+        assert_eq!(app.pgn, "[Event \"Live Chess\"]");
     }
 }
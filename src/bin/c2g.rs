@@ -1,19 +1,33 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
+use std::fs;
 use std::io::{self, Read};
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 use clap::{App, Arg};
 
-use c2g::app::Chess2Gif;
-use c2g::config::{Colors, Config, Output};
-use c2g::delay::{Delay, Delays};
+use c2g::app::{decode_pgn, Chess2Gif};
+use c2g::config::{
+    load_termination_map, parse_canvas, parse_termination_svg, BarLayout, BarStyle, ClockFormat,
+    Color, Colors, Config, CoordSize, CoordinateFont, CoordinateMode, CoordinateSides, Corner,
+    InitialFrame, NameFit, Orientation, Output, TerminationAnchor, TextOverlay, Watermark,
+};
+use c2g::delay::{
+    ClockSimulation, Delay, DelayRamp, Delays, KeyMomentFactors, Pace, RealDelayFallback,
+};
+use c2g::drawer::{utils::HIGHLIGHT_ALPHA, FontWeight};
 use c2g::error::C2GError;
+#[cfg(feature = "net")]
+use c2g::net::{chess_com::ChessComGame, lichess::LichessGame};
+use c2g::progress::{Progress, ProgressHook};
 use c2g::style::{StyleComponent, StyleComponents};
 
 #[derive(Debug)]
 pub struct Chess2GifCli {
     app: Chess2Gif,
+    metadata_json: Option<String>,
 }
 
 impl Chess2GifCli {
@@ -36,6 +50,14 @@ impl Chess2GifCli {
                     .required(false)
                     .help("A PGN string for a chess game"),
             )
+            .arg(
+                Arg::with_name("pgn-path")
+                    .long("pgn-path")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with("PGN")
+                    .help("Path to a file containing a PGN chess game"),
+            )
             .arg(
                 Arg::with_name("output")
                     .short("o")
@@ -48,8 +70,120 @@ impl Chess2GifCli {
                 Arg::with_name("flip")
                     .long("flip")
                     .takes_value(false)
+                    .conflicts_with("orientation")
                     .help("By default, white appears at the bottom, use this flag to flip the board"),
             )
+            .arg(
+                Arg::with_name("orientation")
+                    .long("orientation")
+                    .takes_value(true)
+                    .possible_values(&["white", "black", "follow-turn"])
+                    .help("Side of the board at the bottom: follow-turn flips the board every ply so the side to move is at the bottom [default: white]"),
+            )
+            .arg(
+                Arg::with_name("pov")
+                    .long("pov")
+                    .takes_value(true)
+                    .help("Name of a player to see the board from: the board is flipped if any part of the Black header matches it, ignoring case. Overrides --flip"),
+            )
+            .arg(
+                Arg::with_name("anonymous-name")
+                    .long("anonymous-name")
+                    .takes_value(true)
+                    .default_value("Anonymous")
+                    .help("Name shown in the player bar of a player without a header, when the other player has one"),
+            )
+            .arg(
+                Arg::with_name("force-player-bars")
+                    .long("force-player-bars")
+                    .takes_value(false)
+                    .help("Draw player bars even if the PGN has no player headers, with players named White and Black"),
+            )
+            .arg(
+                Arg::with_name("name-fit")
+                    .long("name-fit")
+                    .takes_value(true)
+                    .possible_values(&["truncate", "shrink"])
+                    .default_value("truncate")
+                    .help("How to fit player names too long for their bar: truncate cuts them short with an ellipsis, and shrink draws them smaller first"),
+            )
+            .arg(
+                Arg::with_name("bar-layout")
+                    .long("bar-layout")
+                    .takes_value(true)
+                    .possible_values(&["double", "single"])
+                    .default_value("double")
+                    .help("Draw a player bar above and below the board, or a single bar below it split between both players, for tall formats"),
+            )
+            .arg(
+                Arg::with_name("reverse")
+                    .long("reverse")
+                    .takes_value(false)
+                    .help("Play the game backwards, from the final position to the initial one"),
+            )
+            .arg(
+                Arg::with_name("initial-position")
+                    .long("initial-position")
+                    .takes_value(true)
+                    .default_value("show")
+                    .help("Use 'show' or 'hide' to show the initial position before the first move or not, or a number of times to show it"),
+            )
+            .arg(
+                Arg::with_name("coordinates")
+                    .long("coordinates")
+                    .takes_value(true)
+                    .possible_values(&["inside", "border", "none"])
+                    .default_value("inside")
+                    .help("Draw coordinates inside the board squares, in a border around the board, or not at all"),
+            )
+            .arg(
+                Arg::with_name("coordinate-sides")
+                    .long("coordinate-sides")
+                    .takes_value(true)
+                    .default_value("bottom,left")
+                    .validator(|val| {
+                        CoordinateSides::from_str(&val)
+                            .map(|_| ())
+                            .map_err(|e| e.to_string())
+                    })
+                    .help("Comma-separated list of sides to draw coordinates on (top, bottom, left, right), or 'all'"),
+            )
+            .arg(
+                Arg::with_name("coordinate-size")
+                    .long("coordinate-size")
+                    .takes_value(true)
+                    .default_value("auto")
+                    .validator(|val| CoordSize::from_str(&val).map(|_| ()).map_err(|e| e.to_string()))
+                    .help("Size of coordinates: 'auto' to scale with the board, pixels (e.g. 12px), or a percentage of a square (e.g. 25%)"),
+            )
+            .arg(
+                Arg::with_name("coordinate-weight")
+                    .long("coordinate-weight")
+                    .takes_value(true)
+                    .default_value("bold")
+                    .validator(|val| FontWeight::from_str(&val).map(|_| ()).map_err(|e| e.to_string()))
+                    .help("Font weight of coordinates: normal, bold, bolder, lighter, or a number between 1 and 1000"),
+            )
+            .arg(
+                Arg::with_name("title-card")
+                    .long("title-card")
+                    .takes_value(false)
+                    .help("Show a title card with player and event information before the game"),
+            )
+            .arg(
+                Arg::with_name("title-card-delay")
+                    .long("title-card-delay")
+                    .takes_value(true)
+                    .default_value("3000")
+                    .help("Delay for the title card in ms"),
+            )
+            .arg(
+                Arg::with_name("heatmap-delay")
+                    .long("heatmap-delay")
+                    .takes_value(true)
+                    .default_value("3000")
+                    .help("Delay for the heatmap frame in ms, drawn with the heatmap style"),
+            )
             .arg(
                 Arg::with_name("size")
                     .short("s")
@@ -79,6 +213,77 @@ impl Chess2GifCli {
                     .default_value("5000")
                     .help("Delay for the last frame in ms, before the GIF loops back around"),
             )
+            .arg(
+                Arg::with_name("delay-ramp")
+                    .long("delay-ramp")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Interpolate the delay between frames from start to end ms over the course of the game, \
+                           formatted as start_ms:end_ms[:easing]. Easing may be linear (default), ease-in, ease-out or ease-in-out"),
+            )
+            .arg(
+                Arg::with_name("total-duration")
+                    .long("total-duration")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Scale all frame delays so the whole GIF lasts this many seconds, or until the last frame with --play-once"),
+            )
+            .arg(
+                Arg::with_name("play-once")
+                    .long("play-once")
+                    .takes_value(false)
+                    .help("Play the GIF once and hold the last frame instead of looping"),
+            )
+            .arg(
+                Arg::with_name("metadata-json")
+                    .long("metadata-json")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Write players, result, termination, and frame delays of the game to a JSON file"),
+            )
+            .arg(
+                Arg::with_name("progress")
+                    .long("progress")
+                    .takes_value(false)
+                    .help("Print rendering progress to stderr"),
+            )
+            .arg(
+                Arg::with_name("pace")
+                    .long("pace")
+                    .takes_value(true)
+                    .default_value("flat")
+                    .possible_values(&["flat", "smart"])
+                    .help("Use 'smart' to hold frames longer after captures, checks, promotions, and mates"),
+            )
+            .arg(
+                Arg::with_name("real-delay-fallback")
+                    .long("real-delay-fallback")
+                    .takes_value(true)
+                    .default_value("first-frame")
+                    .possible_values(&["opponent", "average", "first-frame"])
+                    .help("Delay of moves without clocks to tell how long they took with real delays, like those of a player without clock comments: the delay of the opponent's move, the average of all known delays, or the first frame delay"),
+            )
+            .arg(
+                Arg::with_name("simulate-clocks")
+                    .long("simulate-clocks")
+                    .takes_value(true)
+                    .possible_values(&["even", "curve"])
+                    .help("With real delays, spend the starting time from the TimeControl header over the moves of games without clock comments: evenly, or faster in the opening and slower in the endgame"),
+            )
+            .arg(
+                Arg::with_name("encoder-speed")
+                    .long("encoder-speed")
+                    .takes_value(true)
+                    .default_value("10")
+                    .help("Speed of the GIF encoder, from 1 (slowest, best colors) to 30 (fastest)"),
+            )
+            .arg(
+                Arg::with_name("supersample")
+                    .long("supersample")
+                    .takes_value(true)
+                    .default_value("1")
+                    .help("Draw boards this many times bigger, from 1 to 4, and downscale them for smoother edges"),
+            )
             .arg(
                 Arg::with_name("style")
                     .long("style")
@@ -88,7 +293,7 @@ impl Chess2GifCli {
                     .validator(|val| {
                         let mut invalid_vals = val.split(',').filter(|style| {
                             !&[
-                                "full", "plain", "player-bars", "ranks", "files", "coordinates", "terminations",
+                                "full", "plain", "player-bars", "ranks", "files", "coordinates", "terminations", "end-card", "arrows", "move-badges", "material-balance", "termination-text", "winner-crown", "heatmap", "move-highlights", "event-bar", "opening", "clocks", "move-times", "move-list", "captions",
                             ]
                                 .contains(style)
                         });
@@ -100,7 +305,7 @@ impl Chess2GifCli {
                     })
                     .help(
                         "Comma-separated list of style elements to display \
-                         (*full*, plain, player-bars, ranks, files, terminations).",
+                         (*full*, plain, player-bars, ranks, files, terminations, end-card, arrows, move-badges, material-balance, termination-text, winner-crown, heatmap, move-highlights, event-bar, opening, clocks, move-times, move-list, captions).",
                     )
                     .long_help(
                         "Configure which elements (ranks, files, player-bars, ...)
@@ -113,9 +318,35 @@ impl Chess2GifCli {
                          * ranks: show rank numbers.\n  \
                          * files: show file lettrs.\n  \
                          * coordintes: show both ranks and files. Same as 'ranks,files'.\n  \
-                         * player-bars: draw bars with player information like names and ELO.",
+                         * player-bars: draw bars with player information like names and ELO.\n  \
+                         * clocks: draw the clocks of %clk comments, in the player bars or, without them, in small boxes at the corners of the board.\n  \
+                         * end-card: add a closing frame with the result of the game. Not included in full.\n  \
+                         * arrows: draw an arrow for every move. Not included in full.\n  \
+                         * move-badges: mark blunders (??), mistakes (?), and great moves (!) using %eval comments. Not included in full.\n  \
+                         * material-balance: show the material advantage (e.g. +3) next to the leading player's clock. Requires player-bars. Not included in full.\n  \
+                         * termination-text: write how the game ended (e.g. Checkmate — White wins) across the bottom of the last frame. Not included in full.\n  \
+                         * winner-crown: put a crown on the winning king in the last frame of decisive games. Not included in full.\n  \
+                         * heatmap: add a frame after the game tinting squares by how many times a piece landed on them. Not included in full.\n  \
+                         * move-highlights: tint the squares of the last move, and the square of a pawn taken en passant, in the highlight color. Not included in full.\n  \
+                         * event-bar: add a strip above the player bars with the event, round, site, and date of the game. Not included in full.\n  \
+                         * opening: show the ECO code and name of the opening in the strip above the player bars for the first moves, see --opening-moves. Not included in full.\n  \
+                         * move-times: show the time spent on the last move (e.g. +0:14.0) next to the mover's clock. Requires player-bars. Not included in full.\n  \
+                         * move-list: add a panel right of the board listing the moves played so far, with the last one highlighted. Not included in full.\n  \
+                         * captions: write the text of each move's comments, like annotations in a study, in a strip under the board, see --caption-length. Not included in full.",
                     ),
             )
+            .arg(
+                Arg::with_name("end-card")
+                    .long("end-card")
+                    .takes_value(false)
+                    .help("Add a closing frame with the result of the game. Same as adding end-card to style."),
+            )
+            .arg(
+                Arg::with_name("arrows")
+                    .long("arrows")
+                    .takes_value(false)
+                    .help("Draw an arrow from the origin to the destination square of every move. Same as adding arrows to style."),
+            )
             .arg(
                 Arg::with_name("plain")
                     .long("plain")
@@ -132,7 +363,7 @@ impl Chess2GifCli {
                     .takes_value(true)
                     .default_value("118,150,86")
                     .multiple(false)
-                    .help("RGB or HEX color to use for the dark squares"),
+                    .help("RGB(A), HEX or CSS named color to use for the dark squares. Colors with an alpha below 255 are blended over the board texture or background"),
             )
             .arg(
                 Arg::with_name("light")
@@ -141,14 +372,290 @@ impl Chess2GifCli {
                     .takes_value(true)
                     .default_value("238,238,210")
                     .multiple(false)
-                    .help("RGB or HEX color to use for the light squares"),
+                    .help("RGB(A), HEX or CSS named color to use for the light squares, blended like the dark ones"),
+            )
+            .arg(
+                Arg::with_name("auto-light")
+                    .long("auto-light")
+                    .takes_value(false)
+                    .help("Derive the light square color from --dark. An explicit --light takes precedence"),
+            )
+            .arg(
+                Arg::with_name("accessible")
+                    .long("accessible")
+                    .takes_value(false)
+                    .help("Use a high-contrast palette, hatch dark squares, and mark the winner and loser with a check and a cross, so nothing relies on hue alone. Explicit --dark and --light take precedence over the palette"),
+            )
+            .arg(
+                Arg::with_name("grayscale")
+                    .long("grayscale")
+                    .takes_value(false)
+                    .help("Render the GIF in grays only, for print or e-ink displays"),
+            )
+            .arg(
+                Arg::with_name("check-color")
+                    .long("check-color")
+                    .takes_value(true)
+                    .help("RGB or HEX color to tint the square of a king in check with, instead of the pieces' check artwork"),
+            )
+            .arg(
+                Arg::with_name("highlight-color")
+                    .long("highlight-color")
+                    .takes_value(true)
+                    .help("RGB or HEX color to use for move highlights, like the trail of recent moves"),
+            )
+            .arg(
+                Arg::with_name("title-color")
+                    .long("title-color")
+                    .takes_value(true)
+                    .help("RGB or HEX color to draw player titles with in player bars, like GM. By default, titles are drawn like player names"),
+            )
+            .arg(
+                Arg::with_name("bar-height")
+                    .long("bar-height")
+                    .takes_value(true)
+                    .help("Height of player bars as a fraction of a square, greater than 0 and at most 2 [default: 1]"),
+            )
+            .arg(
+                Arg::with_name("white-bar-color")
+                    .long("white-bar-color")
+                    .takes_value(true)
+                    .help("RGB or HEX color for the background of White's player bar. By default, the light square color"),
+            )
+            .arg(
+                Arg::with_name("black-bar-color")
+                    .long("black-bar-color")
+                    .takes_value(true)
+                    .help("RGB or HEX color for the background of Black's player bar. By default, the dark square color"),
+            )
+            .arg(
+                Arg::with_name("bar-text-color")
+                    .long("bar-text-color")
+                    .takes_value(true)
+                    .help("RGB or HEX color for player names in player bars. Clocks are drawn with their bar's colors swapped. By default, the square color of the other bar"),
+            )
+            .arg(
+                Arg::with_name("low-time")
+                    .long("low-time")
+                    .takes_value(true)
+                    .default_value("20")
+                    .validator(|val| match val.parse::<f64>() {
+                        Ok(secs) if secs >= 0.0 && secs.is_finite() => Ok(()),
+                        _ => Err(format!("Low time must be a number of seconds, got {}", val)),
+                    })
+                    .help("Draw clocks with less than this many seconds left in red. Set to 0 to never warn about low time"),
+            )
+            .arg(
+                Arg::with_name("clock-format")
+                    .long("clock-format")
+                    .takes_value(true)
+                    .possible_values(&["smart", "compact", "full"])
+                    .default_value("smart")
+                    .help("How to write clocks: smart leaves out hours if there are none and tenths of a second above a minute, like 3:00 or 0:59.9, compact only writes minutes and seconds, like 3:00, and full writes everything, like 0:03:00.0"),
+            )
+            .arg(
+                Arg::with_name("move-overlay")
+                    .long("move-overlay")
+                    .takes_value(true)
+                    .possible_values(&["top-left", "top-right", "bottom-left", "bottom-right"])
+                    .help("Write the last move, like 23... Qxh2+, in this corner of the board"),
+            )
+            .arg(
+                Arg::with_name("low-time-bold")
+                    .long("low-time-bold")
+                    .takes_value(false)
+                    .help("Draw clocks low on time in the heaviest weight of the font as well as in red"),
+            )
+            .arg(
+                Arg::with_name("animate-moves")
+                    .long("animate-moves")
+                    .takes_value(true)
+                    .default_value("0")
+                    .help("Number of frames of pieces sliding to their squares drawn before each move, at most 10. Makes GIFs bigger"),
+            )
+            .arg(
+                Arg::with_name("trail")
+                    .long("trail")
+                    .takes_value(true)
+                    .default_value("0")
+                    .help("Number of recent moves to highlight in the highlight color, fading out from the newest one, at most 16"),
+            )
+            .arg(
+                Arg::with_name("opening-moves")
+                    .long("opening-moves")
+                    .takes_value(true)
+                    .default_value("10")
+                    .validator(|val| {
+                        val.parse::<u32>()
+                            .map(|_| ())
+                            .map_err(|_| format!("Opening moves must be a number of moves, got {}", val))
+                    })
+                    .help("Number of moves the opening is shown for with the opening style component"),
+            )
+            .arg(
+                Arg::with_name("caption-length")
+                    .long("caption-length")
+                    .takes_value(true)
+                    .default_value("200")
+                    .validator(|val| {
+                        val.parse::<usize>()
+                            .map(|_| ())
+                            .map_err(|_| format!("Caption length must be a number of characters, got {}", val))
+                    })
+                    .help("Most characters of a comment written with the captions style component. Longer comments are cut short"),
+            )
+            .arg(
+                Arg::with_name("border")
+                    .long("border")
+                    .takes_value(true)
+                    .default_value("0")
+                    .validator(|val| {
+                        val.parse::<u32>()
+                            .map(|_| ())
+                            .map_err(|_| format!("Border must be a number of pixels, got {}", val))
+                    })
+                    .help("Width in pixels of a frame around the board"),
+            )
+            .arg(
+                Arg::with_name("border-color")
+                    .long("border-color")
+                    .takes_value(true)
+                    .default_value("40,26,13")
+                    .help("RGB or HEX color to use for the frame around the board"),
+            )
+            .arg(
+                Arg::with_name("canvas")
+                    .long("canvas")
+                    .takes_value(true)
+                    .help("Center the board on a canvas of WIDTHxHEIGHT pixels, like 1080x1920"),
+            )
+            .arg(
+                Arg::with_name("background")
+                    .long("background")
+                    .takes_value(true)
+                    .default_value("22,21,18")
+                    .help("RGB or HEX color of the canvas around the board, also seen through transparent squares"),
+            )
+            .arg(
+                Arg::with_name("piece-scale")
+                    .long("piece-scale")
+                    .takes_value(true)
+                    .help("Size of pieces as a fraction of a square, greater than 0 and at most 1 [default: 1]"),
+            )
+            .arg(
+                Arg::with_name("board-texture")
+                    .long("board-texture")
+                    .takes_value(true)
+                    .help("Image or SVG file to draw under the squares, like wood or marble. Pass colors with an alpha to --dark and --light to control how much of it shows through"),
+            )
+            .arg(
+                Arg::with_name("white-avatar")
+                    .long("white-avatar")
+                    .takes_value(true)
+                    .help("Image or SVG file to draw at the left edge of White's player bar, cropped to a square"),
+            )
+            .arg(
+                Arg::with_name("black-avatar")
+                    .long("black-avatar")
+                    .takes_value(true)
+                    .help("Image or SVG file to draw at the left edge of Black's player bar, cropped to a square"),
+            )
+            .arg(
+                Arg::with_name("watermark")
+                    .long("watermark")
+                    .takes_value(true)
+                    .help("PNG or SVG file, like a logo, to draw over a corner of every frame"),
+            )
+            .arg(
+                Arg::with_name("watermark-corner")
+                    .long("watermark-corner")
+                    .takes_value(true)
+                    .possible_values(&["top-left", "top-right", "bottom-left", "bottom-right"])
+                    .default_value("bottom-right")
+                    .help("Corner of the whole image, including player bars and the canvas, the watermark is drawn in"),
+            )
+            .arg(
+                Arg::with_name("watermark-opacity")
+                    .long("watermark-opacity")
+                    .takes_value(true)
+                    .default_value("0.5")
+                    .help("How opaque the watermark is drawn, between 0 and 1"),
+            )
+            .arg(
+                Arg::with_name("watermark-scale")
+                    .long("watermark-scale")
+                    .takes_value(true)
+                    .default_value("0.2")
+                    .help("Width of the watermark as a fraction of the width of the image, greater than 0 and at most 1"),
+            )
+            .arg(
+                Arg::with_name("caption")
+                    .long("caption")
+                    .takes_value(true)
+                    .help("Text to draw in the top-left corner of every frame, unlike the captions style component which shows PGN comments"),
+            )
+            .arg(
+                Arg::with_name("caption-color")
+                    .long("caption-color")
+                    .takes_value(true)
+                    .default_value("255,255,255")
+                    .help("RGB or HEX color of the --caption text"),
+            )
+            .arg(
+                Arg::with_name("white-country-header")
+                    .long("white-country-header")
+                    .takes_value(true)
+                    .default_value("WhiteCountry")
+                    .help("PGN header with the country code of White, like NOR, drawn as a flag next to their name or as text if there is no flag for it"),
+            )
+            .arg(
+                Arg::with_name("black-country-header")
+                    .long("black-country-header")
+                    .takes_value(true)
+                    .default_value("BlackCountry")
+                    .help("PGN header with the country code of Black, like NOR, drawn as a flag next to their name or as text if there is no flag for it"),
+            )
+            .arg(
+                Arg::with_name("termination-scale")
+                    .long("termination-scale")
+                    .takes_value(true)
+                    .help("Size of termination circles as a fraction of a square, greater than 0 and at most 1 [default: 0.33]"),
+            )
+            .arg(
+                Arg::with_name("termination-anchor")
+                    .long("termination-anchor")
+                    .takes_value(true)
+                    .possible_values(&["corner", "center", "above-piece"])
+                    .default_value("corner")
+                    .help("Where to place termination circles on the kings' squares"),
+            )
+            .arg(
+                Arg::with_name("termination-fade")
+                    .long("termination-fade")
+                    .takes_value(true)
+                    .default_value("0")
+                    .help("Number of frames over which termination circles fade in before the last frame, at most 10"),
+            )
+            .arg(
+                Arg::with_name("termination-svg")
+                    .long("termination-svg")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help("Draw a termination reason with an SVG file from its path without extension, e.g. checkmate=icons/skull. Reasons are checkmate, draw, resignation, timeout, and win. Can be repeated."),
+            )
+            .arg(
+                Arg::with_name("termination-map")
+                    .long("termination-map")
+                    .takes_value(true)
+                    .help("Path to a TOML file mapping termination reasons to SVG files, like --termination-svg. Entries passed with --termination-svg take precedence."),
             )
             .arg(
                 Arg::with_name("svgs-path")
                     .long("svgs-path")
                     .takes_value(true)
                     .required(false)
-                    .help("Path to directory containing SVGs of chess pieces and other effects. If compiled with include-svgs (default), this argument is ignored."),
+                    .help("Path to directory containing SVGs of chess pieces and other effects. If compiled with include-svgs (default), SVGs not found here are taken from the ones included."),
             )
             .arg(
                 Arg::with_name("pieces")
@@ -158,12 +665,34 @@ impl Chess2GifCli {
                     .required(false)
                     .help("Family of SVG pieces to use. Should be a directory inside svgs-path."),
             )
+            .arg(
+                Arg::with_name("pieces-white")
+                    .long("pieces-white")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Family of SVG pieces to use for white, instead of --pieces."),
+            )
+            .arg(
+                Arg::with_name("pieces-black")
+                    .long("pieces-black")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Family of SVG pieces to use for black, instead of --pieces."),
+            )
+            .arg(
+                Arg::with_name("pieces-sprite")
+                    .long("pieces-sprite")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with_all(&["pieces", "pieces-white", "pieces-black"])
+                    .help("A single SVG file with all pieces, in groups with ids like white-knight or black-king. Used instead of --pieces."),
+            )
             .arg(
                 Arg::with_name("fonts-path")
                     .long("fonts-path")
                     .takes_value(true)
                     .required(false)
-                    .help("Path to directory containing desired coordinates font. If compiled with include-fonts (default), this argument is ignored."),
+                    .help("Path to directory containing desired coordinates font. If compiled with include-fonts (default), the included fonts are also available."),
             )
             .arg(
                 Arg::with_name("font-family")
@@ -171,31 +700,88 @@ impl Chess2GifCli {
                     .takes_value(true)
                     .default_value("Roboto")
                     .required(false)
-                    .help("Font family to use for coordinates. Should be a file inside fonts-path."),
+                    .help("Font family to use for coordinates and text. Should be a file inside fonts-path. A comma separated list like \"Roboto,Noto Sans CJK\" renders characters missing from a family with the next ones."),
+            )
+            .arg(
+                Arg::with_name("font")
+                    .long("font")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help("Path to a font file or directory to render characters missing from the fonts in fonts-path, like names in other scripts. Can be repeated."),
+            )
+            .arg(
+                Arg::with_name("system-fonts")
+                    .long("system-fonts")
+                    .takes_value(false)
+                    .help("Render characters missing from the given fonts with the fonts installed in the system."),
             );
 
+        #[cfg(feature = "net")]
+        let app = app.arg(
+            Arg::with_name("lichess")
+                .long("lichess")
+                .takes_value(true)
+                .required(false)
+                .conflicts_with_all(&["PGN", "pgn-path"])
+                .help("A lichess.org game ID or URL to fetch the PGN from. Flips the board if the URL ends in /black, unless --orientation or --flip are given."),
+        )
+        .arg(
+            Arg::with_name("chess-com")
+                .long("chess-com")
+                .takes_value(true)
+                .required(false)
+                .conflicts_with_all(&["PGN", "pgn-path", "lichess"])
+                .help("A chess.com game URL, or a username/YYYY/MM[/N] reference to the Nth (default: last) game in a player's monthly archive, to fetch the PGN from."),
+        );
+
         let matches = app.get_matches_from_safe(args)?;
 
         let size = Self::get_valid_size(matches.value_of("size").expect("Size must be defined"))?;
 
-        let pgn = Self::pgn_or_read_stdin(matches.value_of("PGN"), &mut io::stdin())?;
+        #[allow(unused_mut)]
+        let mut orientation = match matches.value_of("orientation") {
+            Some(s) => Orientation::from_str(s)?,
+            None if matches.is_present("flip") => Orientation::Black,
+            None => Orientation::White,
+        };
 
-        let svgs_path = if cfg!(feature = "include-svgs") {
-            "svgs/"
-        } else {
-            match matches.value_of("svgs-path") {
-                Some(p) => p,
-                None => "svgs/",
+        #[cfg(feature = "net")]
+        let fetched = match matches.value_of("lichess") {
+            Some(s) => {
+                let game = LichessGame::parse(s)?;
+                // A /black URL only changes the default, not an orientation asked for
+                let oriented = matches.is_present("orientation") || matches.is_present("flip");
+                if game.black && !oriented {
+                    orientation = Orientation::Black;
+                }
+                Some(game.fetch_pgn()?)
             }
+            None => match matches.value_of("chess-com") {
+                Some(s) => Some(ChessComGame::parse(s)?.fetch_pgn()?),
+                None => None,
+            },
         };
+        #[cfg(not(feature = "net"))]
+        let fetched: Option<String> = None;
 
-        let font_path = if cfg!(feature = "include-fonts") {
-            "fonts/"
-        } else {
-            match matches.value_of("font-path") {
-                Some(p) => p,
-                None => "fonts/",
-            }
+        let pgn = match (fetched, matches.value_of("pgn-path")) {
+            (Some(pgn), _) => pgn,
+            (None, Some(path)) => Self::read_pgn_file(path)?,
+            (None, None) => Self::pgn_or_read_stdin(matches.value_of("PGN"), &mut io::stdin())?,
+        };
+
+        // Included SVGs and fonts are used for anything not found in the paths given
+        let svgs_path = match matches.value_of("svgs-path") {
+            Some(p) => p,
+            None if cfg!(feature = "include-svgs") => "",
+            None => "svgs/",
+        };
+
+        let font_path = match matches.value_of("fonts-path") {
+            Some(p) => p,
+            None if cfg!(feature = "include-fonts") => "",
+            None => "fonts/",
         };
 
         let font_family = matches
@@ -219,7 +805,171 @@ impl Chess2GifCli {
             .value_of("light")
             .expect("Light must be defined or default value is used");
 
-        let colors = Colors::from_strs(dark, light)?;
+        // Colors without an alpha let some of the texture or square under them show through
+        let square_alpha = if matches.is_present("board-texture") {
+            HIGHLIGHT_ALPHA
+        } else {
+            255
+        };
+        let accessible = matches.is_present("accessible");
+        let palette = match accessible {
+            true => Colors::accessible(),
+            false => Colors::default(),
+        };
+        let dark = match matches.occurrences_of("dark") {
+            0 if accessible => palette.dark,
+            _ => Color::from_str_or_alpha(dark, square_alpha)?,
+        };
+        let mut colors = match matches.occurrences_of("light") {
+            0 if matches.is_present("auto-light") => Colors::derive_from_dark(dark),
+            0 if accessible => Colors::new(dark, palette.light),
+            _ => Colors::new(dark, Color::from_str_or_alpha(light, square_alpha)?),
+        };
+        if let Some(s) = matches.value_of("check-color") {
+            colors.check_highlight = Some(Color::from_str_or_alpha(s, HIGHLIGHT_ALPHA)?);
+        }
+        if let Some(s) = matches.value_of("highlight-color") {
+            colors.highlight = Some(Color::from_str_or_alpha(s, HIGHLIGHT_ALPHA)?);
+        }
+        if let Some(s) = matches.value_of("title-color") {
+            colors.title = Some(Color::from_str(s)?);
+        }
+
+        let bar_style = BarStyle {
+            height_fraction: match matches.value_of("bar-height") {
+                Some(s) => Self::get_valid_bar_height(s)?,
+                None => BarStyle::default().height_fraction,
+            },
+            white_bg: matches
+                .value_of("white-bar-color")
+                .map(Color::from_str)
+                .transpose()?,
+            black_bg: matches
+                .value_of("black-bar-color")
+                .map(Color::from_str)
+                .transpose()?,
+            text: matches
+                .value_of("bar-text-color")
+                .map(Color::from_str)
+                .transpose()?,
+        };
+
+        let low_time = match matches
+            .value_of("low-time")
+            .expect("Low time must be defined as it has a default value")
+        {
+            s if s.parse::<f64>() == Ok(0.0) => None,
+            s => Some(Self::get_valid_duration(s)?),
+        };
+
+        let border_width = matches
+            .value_of("border")
+            .expect("Border must be defined as it has a default value")
+            .parse::<u32>()
+            .expect("Border must be a positive number");
+        let border_color = Color::from_str(
+            matches
+                .value_of("border-color")
+                .expect("Border color must be defined as it has a default value"),
+        )?;
+
+        let canvas = match matches.value_of("canvas") {
+            Some(s) => Some(parse_canvas(s)?),
+            None => None,
+        };
+        let background = Color::from_str(
+            matches
+                .value_of("background")
+                .expect("Background must be defined as it has a default value"),
+        )?;
+
+        let piece_scale = match matches.value_of("piece-scale") {
+            Some(s) => Self::get_valid_piece_scale(s)?,
+            None => Config::default().piece_scale,
+        };
+        let board_texture = matches.value_of("board-texture").map(PathBuf::from);
+        let watermark = match matches.value_of("watermark") {
+            Some(path) => Some(Watermark::new(
+                PathBuf::from(path),
+                Corner::from_str(
+                    matches
+                        .value_of("watermark-corner")
+                        .expect("Watermark corner must be defined as it has a default value"),
+                )?,
+                Self::get_valid_watermark_opacity(
+                    matches
+                        .value_of("watermark-opacity")
+                        .expect("Watermark opacity must be defined as it has a default value"),
+                )?,
+                Self::get_valid_watermark_scale(
+                    matches
+                        .value_of("watermark-scale")
+                        .expect("Watermark scale must be defined as it has a default value"),
+                )?,
+            )?),
+            None => None,
+        };
+
+        let overlays = match matches.value_of("caption") {
+            Some(text) => vec![TextOverlay {
+                text: text.to_string(),
+                x: size / 40,
+                y: size / 40,
+                size: size as f32 / 20.0,
+                color: Color::from_str(
+                    matches
+                        .value_of("caption-color")
+                        .expect("Caption color must be defined as it has a default value"),
+                )?,
+                frames: None,
+            }],
+            None => Vec::new(),
+        };
+
+        let termination_scale = match matches.value_of("termination-scale") {
+            Some(s) => Self::get_valid_termination_scale(s)?,
+            None => Config::default().termination_scale,
+        };
+        let termination_anchor = match matches.value_of("termination-anchor") {
+            Some(s) => TerminationAnchor::from_str(s)?,
+            None => panic!("Termination anchor must be defined as it has a default value"),
+        };
+
+        let animate_moves = match matches.value_of("animate-moves") {
+            Some(s) => Self::get_valid_animate_moves(s)?,
+            None => panic!("Animate moves must be defined as it has a default value"),
+        };
+
+        let trail = match matches.value_of("trail") {
+            Some(s) => Self::get_valid_trail(s)?,
+            None => panic!("Trail must be defined as it has a default value"),
+        };
+
+        let opening_moves = matches
+            .value_of("opening-moves")
+            .expect("Opening moves must be defined as it has a default value")
+            .parse::<u32>()
+            .expect("Opening moves must be a positive number");
+
+        let caption_length = matches
+            .value_of("caption-length")
+            .expect("Caption length must be defined as it has a default value")
+            .parse::<usize>()
+            .expect("Caption length must be a positive number");
+
+        let termination_fade = match matches.value_of("termination-fade") {
+            Some(s) => Self::get_valid_termination_fade(s)?,
+            None => panic!("Termination fade must be defined as it has a default value"),
+        };
+
+        let mut termination_map = match matches.value_of("termination-map") {
+            Some(path) => load_termination_map(path)?,
+            None => HashMap::new(),
+        };
+        for s in matches.values_of("termination-svg").into_iter().flatten() {
+            let (reason, stem) = parse_termination_svg(s)?;
+            termination_map.insert(reason, stem);
+        }
 
         let delay = match matches.value_of("delay") {
             Some(s) => Delay::from_str(s).expect("Invalid delay value"),
@@ -236,7 +986,84 @@ impl Chess2GifCli {
             None => panic!("First frame delay must be defined as it has a default value"),
         };
 
-        let flip = matches.is_present("flip");
+        let pace = match matches.value_of("pace") {
+            Some(s) => Pace::from_str(s)?,
+            None => panic!("Pace must be defined as it has a default value"),
+        };
+
+        let total_duration = match matches.value_of("total-duration") {
+            Some(s) => Some(Self::get_valid_duration(s)?),
+            None => None,
+        };
+
+        let play_once = matches.is_present("play-once");
+
+        let on_progress = if matches.is_present("progress") {
+            Some(ProgressHook::new(Self::print_progress))
+        } else {
+            None
+        };
+
+        let title_card = if matches.is_present("title-card") {
+            match matches.value_of("title-card-delay") {
+                Some(s) => s
+                    .parse::<u16>()
+                    .map(Some)
+                    .map_err(|_| C2GError::CannotParseDuration(s.to_string()))?,
+                None => panic!("Title card delay must be defined as it has a default value"),
+            }
+        } else {
+            None
+        };
+
+        let heatmap_delay = matches
+            .value_of("heatmap-delay")
+            .expect("Heatmap delay must be defined as it has a default value");
+        let heatmap_delay = heatmap_delay
+            .parse::<u16>()
+            .map_err(|_| C2GError::CannotParseDuration(heatmap_delay.to_string()))?;
+
+        let initial_position = match matches.value_of("initial-position") {
+            Some(s) => InitialFrame::from_str(s)?,
+            None => panic!("Initial position must be defined as it has a default value"),
+        };
+
+        let coordinates = match matches.value_of("coordinates") {
+            Some(s) => CoordinateMode::from_str(s)?,
+            None => panic!("Coordinates must be defined as it has a default value"),
+        };
+
+        let coordinate_sides = match matches.value_of("coordinate-sides") {
+            Some(s) => CoordinateSides::from_str(s)?,
+            None => panic!("Coordinate sides must be defined as it has a default value"),
+        };
+
+        let coordinate_font = CoordinateFont {
+            size: CoordSize::from_str(
+                matches
+                    .value_of("coordinate-size")
+                    .expect("Coordinate size must be defined as it has a default value"),
+            )?,
+            weight: FontWeight::from_str(
+                matches
+                    .value_of("coordinate-weight")
+                    .expect("Coordinate weight must be defined as it has a default value"),
+            )?,
+        };
+
+        let encoder_speed = Self::get_valid_encoder_speed(
+            matches
+                .value_of("encoder-speed")
+                .expect("Encoder speed must be defined as it has a default value"),
+        )?;
+
+        let supersample = Self::get_valid_supersample(
+            matches
+                .value_of("supersample")
+                .expect("Supersample must be defined as it has a default value"),
+        )?;
+
+        let reverse = matches.is_present("reverse");
 
         let styles = if matches.is_present("plain") {
             [StyleComponent::Plain].iter().cloned().collect()
@@ -259,50 +1086,274 @@ impl Chess2GifCli {
                 })
         };
 
-        let style_components = StyleComponents(styles);
+        let mut style_components = StyleComponents(styles);
+        if matches.is_present("end-card") {
+            style_components.0.insert(StyleComponent::EndCard);
+        }
+        if matches.is_present("arrows") {
+            style_components.0.insert(StyleComponent::Arrows);
+        }
 
-        let delays = Delays::new(&delay, &first_frame_delay, &last_frame_delay);
+        let mut delays = Delays::new(&delay, &first_frame_delay, &last_frame_delay);
+        if let Some(s) = matches.value_of("delay-ramp") {
+            delays.ramp = Some(DelayRamp::from_str(s)?);
+        }
 
         let config = Config {
             output: output,
             svgs_path: svgs_path.to_string(),
             font_path: font_path.to_string(),
             font_family: font_family.to_string(),
+            fallback_fonts: matches
+                .values_of("font")
+                .into_iter()
+                .flatten()
+                .map(str::to_string)
+                .collect(),
+            system_fonts: matches.is_present("system-fonts"),
             pieces_family: pieces.to_string(),
+            pieces_family_white: matches.value_of("pieces-white").map(str::to_string),
+            pieces_family_black: matches.value_of("pieces-black").map(str::to_string),
+            pieces_sprite: matches.value_of("pieces-sprite").map(PathBuf::from),
+            canvas,
+            background,
             size,
             colors,
-            flip,
+            orientation,
+            pov: matches.value_of("pov").map(str::to_string),
+            anonymous_name: matches
+                .value_of("anonymous-name")
+                .expect("Anonymous name must be defined as it has a default value")
+                .to_string(),
+            force_player_bars: matches.is_present("force-player-bars"),
+            name_fit: NameFit::from_str(
+                matches
+                    .value_of("name-fit")
+                    .expect("Name fit must be defined as it has a default value"),
+            )?,
+            bar_style,
+            bar_layout: BarLayout::from_str(
+                matches
+                    .value_of("bar-layout")
+                    .expect("Bar layout must be defined as it has a default value"),
+            )?,
+            low_time,
+            low_time_bold: matches.is_present("low-time-bold"),
+            clock_format: ClockFormat::from_str(
+                matches
+                    .value_of("clock-format")
+                    .expect("Clock format must be defined as it has a default value"),
+            )?,
             delays,
             style_components,
+            move_overlay: matches
+                .value_of("move-overlay")
+                .map(Corner::from_str)
+                .transpose()?,
+            reverse,
+            pace,
+            real_delay_fallback: RealDelayFallback::from_str(
+                matches
+                    .value_of("real-delay-fallback")
+                    .expect("Real delay fallback must be defined as it has a default value"),
+            )?,
+            clock_simulation: matches
+                .value_of("simulate-clocks")
+                .map(ClockSimulation::from_str)
+                .transpose()?,
+            key_moment_factors: KeyMomentFactors::default(),
+            total_duration,
+            play_once,
+            encoder_speed,
+            supersample,
+            initial_position,
+            title_card,
+            heatmap_delay,
+            on_progress,
+            coordinates,
+            coordinate_sides,
+            coordinate_font,
+            border_width,
+            border_color,
+            piece_scale,
+            board_texture,
+            white_avatar: matches.value_of("white-avatar").map(PathBuf::from),
+            black_avatar: matches.value_of("black-avatar").map(PathBuf::from),
+            watermark,
+            overlays,
+            white_country_header: matches
+                .value_of("white-country-header")
+                .expect("White country header must be defined as it has a default value")
+                .to_string(),
+            black_country_header: matches
+                .value_of("black-country-header")
+                .expect("Black country header must be defined as it has a default value")
+                .to_string(),
+            termination_map,
+            termination_scale,
+            termination_anchor,
+            termination_fade,
+            animate_moves,
+            trail,
+            opening_moves,
+            caption_length,
+            accessible,
+            grayscale: matches.is_present("grayscale"),
+            ..Config::default()
         };
 
         let app = Chess2Gif::new(pgn, config)?;
 
-        Ok(Self { app })
+        Ok(Self {
+            app,
+            metadata_json: matches.value_of("metadata-json").map(|s| s.to_string()),
+        })
     }
 
     fn pgn_or_read_stdin(pgn: Option<&str>, mut input: impl Read) -> Result<String, C2GError> {
         if let Some(s) = pgn {
             Ok(s.to_owned())
         } else {
-            let mut buffer = String::new();
-            input.read_to_string(&mut buffer)?;
-            Ok(buffer)
+            let mut buffer = Vec::new();
+            input.read_to_end(&mut buffer)?;
+            Ok(decode_pgn(buffer))
+        }
+    }
+
+    fn print_progress(progress: Progress) {
+        match progress.percent() {
+            Some(percent) => eprint!("\r{}: {:>3.0}%  ", progress.stage, percent),
+            None => eprint!("\r{}: move {}", progress.stage, progress.current),
+        }
+        if Some(progress.current) == progress.total {
+            eprintln!();
         }
     }
 
+    fn read_pgn_file(path: &str) -> Result<String, C2GError> {
+        let bytes = fs::read(path).map_err(|source| match source.kind() {
+            io::ErrorKind::NotFound => C2GError::PgnFileNotFound(path.to_string()),
+            _ => C2GError::ReadPgnFile {
+                path: path.to_string(),
+                source,
+            },
+        })?;
+
+        if bytes.contains(&0) {
+            return Err(C2GError::InvalidPgnFile {
+                path: path.to_string(),
+                reason: "file contains binary data".to_string(),
+            });
+        }
+
+        let pgn = decode_pgn(bytes);
+        if pgn.trim().is_empty() {
+            return Err(C2GError::InvalidPgnFile {
+                path: path.to_string(),
+                reason: "file is empty".to_string(),
+            });
+        }
+
+        Ok(pgn)
+    }
+
     fn get_valid_size(s: &str) -> Result<u32, C2GError> {
         let size = u32::from_str_radix(s, 10).expect("Size must be a positive number");
 
-        if size % 8 != 0 {
-            return Err(C2GError::NotDivisibleBy8);
+        if size < 64 {
+            return Err(C2GError::SizeTooSmall(size));
         }
 
         Ok(size)
     }
 
+    fn get_valid_duration(s: &str) -> Result<Duration, C2GError> {
+        match s.parse::<f64>() {
+            Ok(secs) if secs > 0.0 && secs.is_finite() => Ok(Duration::from_secs_f64(secs)),
+            _ => Err(C2GError::CannotParseDuration(s.to_string())),
+        }
+    }
+
+    fn get_valid_termination_scale(s: &str) -> Result<f32, C2GError> {
+        match s.parse::<f32>() {
+            Ok(scale) if scale > 0.0 && scale <= 1.0 => Ok(scale),
+            _ => Err(C2GError::InvalidTerminationScale(s.to_string())),
+        }
+    }
+
+    fn get_valid_bar_height(s: &str) -> Result<f32, C2GError> {
+        match s.parse::<f32>() {
+            Ok(fraction) if fraction > 0.0 && fraction <= 2.0 => Ok(fraction),
+            _ => Err(C2GError::InvalidBarHeight(s.to_string())),
+        }
+    }
+
+    fn get_valid_piece_scale(s: &str) -> Result<f32, C2GError> {
+        match s.parse::<f32>() {
+            Ok(scale) if scale > 0.0 && scale <= 1.0 => Ok(scale),
+            _ => Err(C2GError::InvalidPieceScale(s.to_string())),
+        }
+    }
+
+    fn get_valid_watermark_opacity(s: &str) -> Result<f32, C2GError> {
+        match s.parse::<f32>() {
+            Ok(opacity) if (0.0..=1.0).contains(&opacity) => Ok(opacity),
+            _ => Err(C2GError::InvalidWatermarkOpacity(s.to_string())),
+        }
+    }
+
+    fn get_valid_watermark_scale(s: &str) -> Result<f32, C2GError> {
+        match s.parse::<f32>() {
+            Ok(scale) if scale > 0.0 && scale <= 1.0 => Ok(scale),
+            _ => Err(C2GError::InvalidWatermarkScale(s.to_string())),
+        }
+    }
+
+    fn get_valid_termination_fade(s: &str) -> Result<u32, C2GError> {
+        match s.parse::<u32>() {
+            Ok(frames) if frames <= 10 => Ok(frames),
+            _ => Err(C2GError::InvalidTerminationFade(s.to_string())),
+        }
+    }
+
+    fn get_valid_animate_moves(s: &str) -> Result<u32, C2GError> {
+        match s.parse::<u32>() {
+            Ok(frames) if frames <= 10 => Ok(frames),
+            _ => Err(C2GError::InvalidAnimateMoves(s.to_string())),
+        }
+    }
+
+    fn get_valid_trail(s: &str) -> Result<u32, C2GError> {
+        match s.parse::<u32>() {
+            Ok(moves) if moves <= 16 => Ok(moves),
+            _ => Err(C2GError::InvalidTrail(s.to_string())),
+        }
+    }
+
+    fn get_valid_encoder_speed(s: &str) -> Result<i32, C2GError> {
+        match s.parse::<i32>() {
+            Ok(speed) if (1..=30).contains(&speed) => Ok(speed),
+            _ => Err(C2GError::InvalidEncoderSpeed(s.to_string())),
+        }
+    }
+
+    fn get_valid_supersample(s: &str) -> Result<u8, C2GError> {
+        match s.parse::<u8>() {
+            Ok(factor) if (1..=4).contains(&factor) => Ok(factor),
+            _ => Err(C2GError::InvalidSupersample(s.to_string())),
+        }
+    }
+
     fn run(self) -> Result<Option<Vec<u8>>, C2GError> {
-        self.app.run()
+        let report = self.app.render()?;
+
+        if let (Some(path), Some(metadata)) = (self.metadata_json, &report.metadata) {
+            log::info!("Writing metadata to {}", path);
+            let json = serde_json::to_string_pretty(metadata).expect("Metadata is serializable");
+            fs::write(&path, json).map_err(|source| C2GError::WriteMetadata { path, source })?;
+        }
+
+        Ok(report.gif)
     }
 }
 
@@ -322,19 +1373,19 @@ mod tests {
 
     #[test]
     fn test_get_valid_size() -> Result<(), String> {
-        let result = Chess2GifCli::get_valid_size("16");
+        let result = Chess2GifCli::get_valid_size("500");
 
         match result {
             Ok(v) => {
-                if v == 16 {
+                if v == 500 {
                     Ok(())
                 } else {
                     Err(String::from(
-                        "Value returned by get_valid_size did not match 16",
+                        "Value returned by get_valid_size did not match 500",
                     ))
                 }
             }
-            Err(_) => Err(String::from("Returned error despite being divisible by 8")),
+            Err(_) => Err(String::from("Returned error despite being at least 64")),
         }
     }
 
@@ -345,9 +1396,7 @@ mod tests {
         match result {
             Ok(v) => {
                 if v == 20 {
-                    Err(String::from(
-                        "Return value despite not being divisible by 8",
-                    ))
+                    Err(String::from("Return value despite being smaller than 64"))
                 } else {
                     Err(String::from("Returned unexpected value"))
                 }
@@ -363,6 +1412,21 @@ mod tests {
         let _ = Chess2GifCli::get_valid_size("-20");
     }
 
+    #[test]
+    fn test_get_valid_duration() {
+        assert_eq!(
+            Chess2GifCli::get_valid_duration("30").unwrap(),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            Chess2GifCli::get_valid_duration("2.5").unwrap(),
+            Duration::from_millis(2500)
+        );
+        assert!(Chess2GifCli::get_valid_duration("0").is_err());
+        assert!(Chess2GifCli::get_valid_duration("-3").is_err());
+        assert!(Chess2GifCli::get_valid_duration("thirty").is_err());
+    }
+
     #[test]
     fn test_pgn_or_read_stdin_with_none_pgn() -> Result<(), String> {
         use std::io::Cursor;
@@ -408,4 +1472,29 @@ mod tests {
             Err(_) => Err(String::from("Error reading buffer")),
         }
     }
+
+    #[test]
+    fn test_read_pgn_file() {
+        let pgn = Chess2GifCli::read_pgn_file("example/example_latin1.pgn").unwrap();
+        assert!(pgn.contains("[White \"Müller\"]"));
+    }
+
+    #[test]
+    fn test_read_pgn_file_not_found() {
+        let result = Chess2GifCli::read_pgn_file("example/does_not_exist.pgn");
+        assert!(matches!(result, Err(C2GError::PgnFileNotFound(_))));
+    }
+
+    #[test]
+    fn test_read_pgn_file_invalid() {
+        let result = Chess2GifCli::read_pgn_file("example/chess.gif");
+        assert!(matches!(result, Err(C2GError::InvalidPgnFile { .. })));
+    }
+
+    #[test]
+    fn test_pgn_path_conflicts_with_pgn() {
+        let args = vec!["c2g", "--pgn-path", "example/example.pgn", "1. e4 e5"];
+        let result = Chess2GifCli::new_from(args.into_iter());
+        assert!(matches!(result, Err(C2GError::ClapError { .. })));
+    }
 }
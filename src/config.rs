@@ -1,35 +1,67 @@
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
-use crate::delay::Delays;
+use image::{Pixel, Rgb};
+
+use crate::css_colors;
+use crate::delay::{ClockSimulation, Delays, KeyMomentFactors, Pace, RealDelayFallback};
+use crate::drawer::FontWeight;
 use crate::error::C2GError;
+use crate::progress::ProgressHook;
 use crate::style::StyleComponents;
 
+/// An RGBA color. Alpha goes from 0, transparent, to 255, opaque.
 #[derive(Debug, Clone)]
 pub struct Color(pub [u8; 4]);
 
 impl FromStr for Color {
     type Err = C2GError;
 
+    /// Parse a CSS color name, "r,g,b", "r,g,b,a", "#RGB", "#RRGGBB", "#RRGGBBAA",
+    /// "hsl(h,s%,l%)" or "hsla(h,s%,l%,a)". Colors without an alpha are opaque.
     fn from_str(s: &str) -> Result<Self, C2GError> {
-        let parse_result = if s.starts_with("#") || !s.contains(",") {
+        Color::from_str_or_alpha(s, 255)
+    }
+}
+
+impl Color {
+    /// Parse a color like from_str, using alpha if the string doesn't have one.
+    pub fn from_str_or_alpha(s: &str, alpha: u8) -> Result<Self, C2GError> {
+        let lowercase = s.trim().to_ascii_lowercase();
+        let parse_result = if lowercase.starts_with("hsl(") || lowercase.starts_with("hsla(") {
+            from_hsl_str(s)
+        } else if let Some(rgb) = css_colors::lookup(s) {
+            Ok(rgb.to_vec())
+        } else if s
+            .chars()
+            .any(|c| c.is_ascii_alphabetic() && !c.is_ascii_hexdigit())
+            && s.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            let matches = css_colors::close_matches(s, 3);
+            let reason = if matches.is_empty() {
+                "Unknown color name".to_string()
+            } else {
+                format!("Unknown color name, did you mean {}?", matches.join(", "))
+            };
+            Err(C2GError::CannotParseColor {
+                color: s.to_string(),
+                reason,
+            })
+        } else if s.starts_with("#") || !s.contains(",") {
             from_hex_str(s)
         } else {
             from_rgba_str(s)
         };
 
-        let mut vec_color = match parse_result {
-            Ok(v) => v,
-            Err(e) => {
-                return Err(C2GError::CannotParseColor {
-                    color: s.to_string(),
-                    reason: format!("{}", e),
-                })
-            }
-        };
+        let mut vec_color = parse_result?;
 
         if vec_color.len() == 3 {
-            vec_color.push(1)
+            vec_color.push(alpha)
         } else if vec_color.len() != 4 {
             return Err(C2GError::CannotParseColor {
                 color: s.to_string(),
@@ -46,6 +78,125 @@ impl FromStr for Color {
             }),
         }
     }
+
+    pub fn to_arr(&self) -> [u8; 4] {
+        self.0
+    }
+
+    /// The color's luma as a gray, keeping its alpha.
+    pub fn grayscale(&self) -> Color {
+        let [r, g, b, a] = self.0;
+        let [luma] = Rgb([r, g, b]).to_luma().0;
+        Color([luma, luma, luma, a])
+    }
+}
+
+/// Parse a CSS HSL color string like "hsl(120,40%,60%)" or "hsla(120,40%,60%,0.5)". The hue is
+/// in degrees, and the alpha either a number from 0 to 1 or a percentage.
+fn from_hsl_str(s: &str) -> Result<Vec<u8>, C2GError> {
+    let error = |reason: String| C2GError::CannotParseColor {
+        color: s.to_string(),
+        reason,
+    };
+
+    let trimmed = s.trim();
+    let open = trimmed
+        .find('(')
+        .expect("HSL colors start with hsl( or hsla(");
+    let inner = trimmed[open + 1..]
+        .strip_suffix(')')
+        .ok_or_else(|| error("Missing closing parenthesis".to_string()))?;
+    let components: Vec<&str> = inner.split(',').map(|c| c.trim()).collect();
+    if components.len() != 3 && components.len() != 4 {
+        return Err(error(format!(
+            "Expected 3 or 4 components but found {}",
+            components.len()
+        )));
+    }
+
+    let hue = components[0]
+        .strip_suffix("deg")
+        .unwrap_or(components[0])
+        .parse::<f32>()
+        .ok()
+        .filter(|h| h.is_finite())
+        .ok_or_else(|| error(format!("Invalid hue {:?}", components[0])))?;
+    let percentage = |name: &str, component: &str| {
+        component
+            .strip_suffix('%')
+            .and_then(|p| p.parse::<f32>().ok())
+            .filter(|p| (0.0..=100.0).contains(p))
+            .map(|p| p / 100.0)
+            .ok_or_else(|| {
+                error(format!(
+                    "Invalid {} {:?}, expected a percentage from 0% to 100%",
+                    name, component
+                ))
+            })
+    };
+    let saturation = percentage("saturation", components[1])?;
+    let lightness = percentage("lightness", components[2])?;
+
+    let mut rgba: Vec<u8> = hsl_to_rgb(hue, saturation, lightness).to_vec();
+    if let Some(component) = components.get(3) {
+        let alpha = match component.strip_suffix('%') {
+            Some(p) => p.parse::<f32>().map(|p| p / 100.0),
+            None => component.parse::<f32>(),
+        }
+        .ok()
+        .filter(|a| (0.0..=1.0).contains(a))
+        .ok_or_else(|| {
+            error(format!(
+                "Invalid alpha {:?}, expected a number from 0 to 1 or a percentage",
+                component
+            ))
+        })?;
+        rgba.push((alpha * 255.0).round() as u8);
+    }
+
+    Ok(rgba)
+}
+
+/// Convert a hue in degrees, and a saturation and lightness from 0 to 1 to RGB.
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [u8; 3] {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+
+    [r, g, b].map(|c| ((c + m) * 255.0).round() as u8)
+}
+
+/// Convert RGB to a hue in degrees, and a saturation and lightness from 0 to 1.
+fn rgb_to_hsl(rgb: [u8; 3]) -> (f32, f32, f32) {
+    let [r, g, b] = rgb.map(|c| c as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+
+    (hue * 60.0, saturation.min(1.0), lightness)
 }
 
 /// Parse an RGBA color string
@@ -67,56 +218,83 @@ fn from_rgba_str(s: &str) -> Result<Vec<u8>, C2GError> {
     Ok(tmp)
 }
 
-/// Parse a HEX color string
+/// Parse a HEX color string like "#RGB", "#RRGGBB" or "#RRGGBBAA". The "#" is optional.
 fn from_hex_str(s: &str) -> Result<Vec<u8>, C2GError> {
-    let mut tmp = Vec::with_capacity(4);
-
-    let s = match s.strip_prefix("#") {
-        Some(stripped) => stripped,
-        None => s,
+    let hex = s.strip_prefix("#").unwrap_or(s);
+    let error = |reason: String| C2GError::CannotParseColor {
+        color: s.to_string(),
+        reason,
     };
 
-    let bytes = s.as_bytes();
-
-    for (n, b) in bytes.iter().step_by(2).enumerate() {
-        // We are stepping by 2.
-        let hex_bytes = &[*b, bytes[n * 2 + 1]];
-
-        let hex_number =
-            std::str::from_utf8(hex_bytes).map_err(|e| C2GError::CannotParseColor {
-                color: s.to_string(),
-                reason: format!("{}", e),
-            })?;
-
-        let parsed =
-            u8::from_str_radix(&hex_number, 16).map_err(|e| C2GError::CannotParseColor {
-                color: s.to_string(),
-                reason: format!("{}", e),
-            })?;
-        tmp.push(parsed)
+    if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(error(format!("{:?} is not a hex digit", c)));
     }
 
-    Ok(tmp)
-}
+    let digits: Vec<u8> = hex
+        .chars()
+        .filter_map(|c| c.to_digit(16))
+        .map(|d| d as u8)
+        .collect();
 
-impl Color {
-    pub fn to_arr(&self) -> [u8; 4] {
-        self.0
+    match digits.len() {
+        // Each digit is repeated, so "#F80" is "#FF8800"
+        3 => Ok(digits.iter().map(|d| d * 17).collect()),
+        6 | 8 => Ok(digits
+            .chunks(2)
+            .map(|pair| pair[0] * 16 + pair[1])
+            .collect()),
+        n => Err(error(format!(
+            "Expected 3, 6 or 8 hex digits but found {}",
+            n
+        ))),
     }
 }
 
+/// Hue that derived light square colors are turned towards.
+const LIGHT_HUE: f32 = 60.0;
+
+/// Maximum number of degrees the hue of derived light square colors is turned.
+const MAX_LIGHT_HUE_SHIFT: f32 = 30.0;
+
+/// Derived light square colors are this many times more saturated than the dark one.
+const LIGHT_SATURATION_FACTOR: f32 = 1.7;
+
+/// Fraction of the way from the dark square's lightness to white for derived light squares.
+const LIGHT_LIGHTNESS_FACTOR: f32 = 0.77;
+
+/// Minimum difference in lightness between a dark square color and a derived light one.
+const MIN_LIGHTNESS_CONTRAST: f32 = 0.2;
+
 #[derive(Debug, Clone)]
 pub struct Colors {
-    /// The board's dark square color.
+    /// The board's dark square color. Transparent colors are blended over the board texture,
+    /// or the background if there is no texture.
     pub dark: Color,
 
-    /// The board's light square color.
+    /// The board's light square color, blended like the dark one.
     pub light: Color,
+
+    /// Color to tint the square of a king in check with. The check SVG asset of the pieces
+    /// family is used if None. Colors with an alpha below 255 let the square show through.
+    pub check_highlight: Option<Color>,
+
+    /// Color for the trail of recent moves. Older moves are drawn with less of its alpha.
+    pub highlight: Option<Color>,
+
+    /// Color for player titles in player bars, like GM. Titles are drawn like the rest of the
+    /// player's name if None.
+    pub title: Option<Color>,
 }
 
 impl Colors {
     pub fn new(dark: Color, light: Color) -> Colors {
-        Colors { dark, light }
+        Colors {
+            dark,
+            light,
+            check_highlight: None,
+            highlight: None,
+            title: None,
+        }
     }
 
     pub fn from_strs(dark: &str, light: &str) -> Result<Self, C2GError> {
@@ -124,13 +302,497 @@ impl Colors {
         let light = Color::from_str(light)?;
         Ok(Colors::new(dark, light))
     }
+
+    /// All colors converted to grays.
+    pub fn grayscale(&self) -> Colors {
+        Colors {
+            dark: self.dark.grayscale(),
+            light: self.light.grayscale(),
+            check_highlight: self.check_highlight.as_ref().map(Color::grayscale),
+            highlight: self.highlight.as_ref().map(Color::grayscale),
+            title: self.title.as_ref().map(Color::grayscale),
+        }
+    }
+
+    /// A high-contrast palette from the Okabe-Ito colors, which stay distinct for the common
+    /// types of color blindness: a deep blue for dark squares, and a near white for light ones.
+    pub fn accessible() -> Colors {
+        Colors::new(Color([0, 114, 178, 255]), Color([240, 240, 240, 255]))
+    }
+
+    /// Colors with a light square color derived from the dark one. The light color is much
+    /// lighter and more saturated, with its hue turned towards a warm yellow, which turns the
+    /// default green into the default cream. Dark colors too light to leave room for a lighter
+    /// one get a darker light color instead, so squares can still be told apart.
+    pub fn derive_from_dark(dark: Color) -> Colors {
+        let [r, g, b, a] = dark.to_arr();
+        let (hue, saturation, lightness) = rgb_to_hsl([r, g, b]);
+
+        let hue_shift = (LIGHT_HUE - hue + 180.0).rem_euclid(360.0) - 180.0;
+        let hue = hue + hue_shift.clamp(-MAX_LIGHT_HUE_SHIFT, MAX_LIGHT_HUE_SHIFT);
+        let saturation = (saturation * LIGHT_SATURATION_FACTOR).min(1.0);
+        let mut light_lightness = lightness + (1.0 - lightness) * LIGHT_LIGHTNESS_FACTOR;
+        if light_lightness - lightness < MIN_LIGHTNESS_CONTRAST {
+            light_lightness = lightness - MIN_LIGHTNESS_CONTRAST;
+        }
+
+        let [r, g, b] = hsl_to_rgb(hue, saturation, light_lightness);
+        Colors::new(dark, Color([r, g, b, a]))
+    }
 }
 
 impl Default for Colors {
     fn default() -> Self {
         Colors {
-            dark: Color([118, 150, 86, 1]),
-            light: Color([238, 238, 210, 1]),
+            dark: Color([118, 150, 86, 255]),
+            light: Color([238, 238, 210, 255]),
+            check_highlight: None,
+            highlight: None,
+            title: None,
+        }
+    }
+}
+
+/// How to show the initial position before the first move is played.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum InitialFrame {
+    /// Show the initial position once.
+    #[default]
+    Show,
+    /// Start directly on the first move.
+    Hide,
+    /// Show the initial position this many times.
+    Repeat(u8),
+}
+
+impl FromStr for InitialFrame {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "show" => Ok(InitialFrame::Show),
+            "hide" => Ok(InitialFrame::Hide),
+            n => match n.parse::<u8>() {
+                Ok(0) => Ok(InitialFrame::Hide),
+                Ok(1) => Ok(InitialFrame::Show),
+                Ok(times) => Ok(InitialFrame::Repeat(times)),
+                Err(_) => Err(C2GError::UnknownInitialFrame(n.to_string())),
+            },
+        }
+    }
+}
+
+/// Where to draw file letters and rank numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CoordinateMode {
+    /// Inside the squares of the first file and rank.
+    #[default]
+    Inside,
+    /// In a border around the board, half a square wide.
+    Border,
+    /// Don't draw coordinates.
+    None,
+}
+
+impl FromStr for CoordinateMode {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "inside" => Ok(CoordinateMode::Inside),
+            "border" => Ok(CoordinateMode::Border),
+            "none" => Ok(CoordinateMode::None),
+            _ => Err(C2GError::UnknownCoordinateMode(s.to_string())),
+        }
+    }
+}
+
+/// Where to place termination circles on the kings' squares.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TerminationAnchor {
+    /// Inside the top left corner of the square.
+    #[default]
+    Corner,
+    /// In the center of the square, over the piece.
+    Center,
+    /// Centered horizontally on the top edge of the square, above the piece.
+    AbovePiece,
+}
+
+impl FromStr for TerminationAnchor {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "corner" => Ok(TerminationAnchor::Corner),
+            "center" => Ok(TerminationAnchor::Center),
+            "above-piece" => Ok(TerminationAnchor::AbovePiece),
+            _ => Err(C2GError::UnknownTerminationAnchor(s.to_string())),
+        }
+    }
+}
+
+/// How to fit player names too long for their bar, which would run into the clock.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NameFit {
+    /// Cut the name short with an ellipsis.
+    #[default]
+    Truncate,
+    /// Draw the name smaller, down to a minimum size, and truncate it if it still doesn't fit.
+    Shrink,
+}
+
+impl FromStr for NameFit {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "truncate" => Ok(NameFit::Truncate),
+            "shrink" => Ok(NameFit::Shrink),
+            _ => Err(C2GError::UnknownNameFit(s.to_string())),
+        }
+    }
+}
+
+/// Where player bars are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BarLayout {
+    /// A bar for each player, above and below the board, on their side.
+    #[default]
+    Double,
+    /// A single bar below the board, split in two with White on the left and Black on the
+    /// right, with each clock under the name. Leaves more room for the board in tall formats.
+    Single,
+}
+
+impl FromStr for BarLayout {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "double" => Ok(BarLayout::Double),
+            "single" => Ok(BarLayout::Single),
+            _ => Err(C2GError::UnknownBarLayout(s.to_string())),
+        }
+    }
+}
+
+/// How clocks are written in player bars.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ClockFormat {
+    /// Hours only if there are any, and tenths of a second only under a minute, like 3:00,
+    /// 1:05:00 or 0:59.9.
+    #[default]
+    Smart,
+    /// Minutes and seconds, without hours or tenths, like 3:00 or 65:00.
+    Compact,
+    /// Hours, minutes, seconds and tenths of a second, like 0:03:00.0.
+    Full,
+}
+
+impl FromStr for ClockFormat {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "smart" => Ok(ClockFormat::Smart),
+            "compact" => Ok(ClockFormat::Compact),
+            "full" => Ok(ClockFormat::Full),
+            _ => Err(C2GError::UnknownClockFormat(s.to_string())),
+        }
+    }
+}
+
+/// Corner of the board, or of the whole image, to draw something in.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Corner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    /// Position of the top left corner of something of a size, as width and height, placed in
+    /// this corner of an outer area, inset from its edges.
+    pub fn origin(&self, outer: (u32, u32), size: (u32, u32), inset: u32) -> (u32, u32) {
+        let (start_x, start_y) = (inset, inset);
+        let end_x = outer.0.saturating_sub(size.0 + inset);
+        let end_y = outer.1.saturating_sub(size.1 + inset);
+        match self {
+            Corner::TopLeft => (start_x, start_y),
+            Corner::TopRight => (end_x, start_y),
+            Corner::BottomLeft => (start_x, end_y),
+            Corner::BottomRight => (end_x, end_y),
+        }
+    }
+}
+
+impl FromStr for Corner {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "top-left" => Ok(Corner::TopLeft),
+            "top-right" => Ok(Corner::TopRight),
+            "bottom-left" => Ok(Corner::BottomLeft),
+            "bottom-right" => Ok(Corner::BottomRight),
+            _ => Err(C2GError::UnknownCorner(s.to_string())),
+        }
+    }
+}
+
+/// A logo or other image drawn over a corner of every frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watermark {
+    /// PNG or SVG file with the watermark.
+    pub path: PathBuf,
+    /// Corner of the whole image, including bars and the canvas, the watermark is drawn in.
+    pub corner: Corner,
+    /// How opaque the watermark is drawn, between 0.0 and 1.0.
+    pub opacity: f32,
+    /// Width of the watermark as a fraction of the width of the image.
+    pub scale: f32,
+}
+
+impl Watermark {
+    /// A watermark from a file, checked to be a PNG or SVG file that can be read so a bad path
+    /// fails before rendering anything.
+    pub fn new(path: PathBuf, corner: Corner, opacity: f32, scale: f32) -> Result<Self, C2GError> {
+        let error = |reason: &str| C2GError::ReadWatermark {
+            path: path.display().to_string(),
+            reason: reason.to_string(),
+        };
+        let supported = path.extension().is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("png") || extension.eq_ignore_ascii_case("svg")
+        });
+        if !supported {
+            return Err(error("expected a PNG or SVG file"));
+        }
+        fs::File::open(&path).map_err(|e| error(&e.to_string()))?;
+
+        Ok(Watermark {
+            path,
+            corner,
+            opacity,
+            scale,
+        })
+    }
+}
+
+/// Text drawn over frames, like "Game of the Day #12". Overlays outside of the image, or with
+/// an empty range of frames, are rejected before anything is rendered.
+#[derive(Debug, Clone)]
+pub struct TextOverlay {
+    pub text: String,
+    /// Top left corner of the text, in pixels of the image with the board and its bars, before
+    /// it's centered on a canvas.
+    pub x: u32,
+    pub y: u32,
+    /// Font size in pixels.
+    pub size: f32,
+    pub color: Color,
+    /// Frames the text is drawn on, counting from the first frame, like the title card if
+    /// there is one. The text is drawn on every frame if None.
+    pub frames: Option<Range<usize>>,
+}
+
+/// Side of the board seen at the bottom of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Orientation {
+    /// White at the bottom.
+    #[default]
+    White,
+    /// Black at the bottom, i.e. a flipped board.
+    Black,
+    /// The side to move at the bottom, flipping the board every ply.
+    FollowTurn,
+}
+
+impl Orientation {
+    /// Whether the board is flipped when it's color's turn to move.
+    pub fn flip(&self, turn: shakmaty::Color) -> bool {
+        match self {
+            Orientation::White => false,
+            Orientation::Black => true,
+            Orientation::FollowTurn => turn == shakmaty::Color::Black,
+        }
+    }
+}
+
+impl FromStr for Orientation {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "white" => Ok(Orientation::White),
+            "black" => Ok(Orientation::Black),
+            "follow-turn" => Ok(Orientation::FollowTurn),
+            _ => Err(C2GError::UnknownOrientation(s.to_string())),
+        }
+    }
+}
+
+/// Sides of the final image to draw coordinates on. Files go on the top and bottom, and ranks
+/// on the left and right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordinateSides {
+    pub top: bool,
+    pub bottom: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+impl CoordinateSides {
+    pub fn all() -> Self {
+        CoordinateSides {
+            top: true,
+            bottom: true,
+            left: true,
+            right: true,
+        }
+    }
+}
+
+impl Default for CoordinateSides {
+    fn default() -> Self {
+        CoordinateSides {
+            top: false,
+            bottom: true,
+            left: true,
+            right: false,
+        }
+    }
+}
+
+impl FromStr for CoordinateSides {
+    type Err = C2GError;
+
+    /// Parse "all" or a comma-separated list of sides, like "bottom,left".
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        if s == "all" {
+            return Ok(CoordinateSides::all());
+        }
+
+        let mut sides = CoordinateSides {
+            top: false,
+            bottom: false,
+            left: false,
+            right: false,
+        };
+        for side in s.split(',').map(|side| side.trim()) {
+            match side {
+                "top" => sides.top = true,
+                "bottom" => sides.bottom = true,
+                "left" => sides.left = true,
+                "right" => sides.right = true,
+                _ => return Err(C2GError::UnknownCoordinateSide(side.to_string())),
+            }
+        }
+        Ok(sides)
+    }
+}
+
+/// Size of coordinate labels.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CoordSize {
+    /// Scale with the size of the board.
+    #[default]
+    Auto,
+    /// A size in pixels.
+    Px(f32),
+    /// A fraction of the size of a square.
+    Fraction(f32),
+}
+
+impl CoordSize {
+    /// Size of the labels of a board drawn factor times bigger. Only sizes in pixels change.
+    pub fn scaled(&self, factor: f32) -> Self {
+        match self {
+            CoordSize::Px(px) => CoordSize::Px(px * factor),
+            size => *size,
+        }
+    }
+}
+
+impl FromStr for CoordSize {
+    type Err = C2GError;
+
+    /// Parse "auto", a size in pixels like "12" or "12px", or a percentage of a square like
+    /// "25%".
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        let parse = |n: &str| {
+            n.parse::<f32>()
+                .ok()
+                .filter(|n| *n > 0.0)
+                .ok_or_else(|| C2GError::CannotParseCoordinateSize(s.to_string()))
+        };
+
+        if s == "auto" {
+            Ok(CoordSize::Auto)
+        } else if let Some(percent) = s.strip_suffix('%') {
+            Ok(CoordSize::Fraction(parse(percent)? / 100.0))
+        } else {
+            Ok(CoordSize::Px(parse(s.strip_suffix("px").unwrap_or(s))?))
+        }
+    }
+}
+
+/// Font options for coordinate labels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordinateFont {
+    pub size: CoordSize,
+    pub weight: FontWeight,
+}
+
+impl Default for CoordinateFont {
+    fn default() -> Self {
+        CoordinateFont {
+            size: CoordSize::default(),
+            weight: FontWeight::Bold,
+        }
+    }
+}
+
+/// Size and colors of player bars.
+#[derive(Debug, Clone)]
+pub struct BarStyle {
+    /// Height of player bars as a fraction of a square, greater than 0 and at most 2. Text,
+    /// clocks, and captured pieces are scaled with it.
+    pub height_fraction: f32,
+
+    /// Background color of White's bar. The light square color is used if None.
+    pub white_bg: Option<Color>,
+
+    /// Background color of Black's bar. The dark square color is used if None.
+    pub black_bg: Option<Color>,
+
+    /// Color of names in both bars. Names are drawn in the dark square color in White's bar,
+    /// and in the light one in Black's, if None. Clocks are drawn with their bar's colors
+    /// swapped.
+    pub text: Option<Color>,
+}
+
+impl BarStyle {
+    /// All colors converted to grays.
+    pub fn grayscale(&self) -> BarStyle {
+        BarStyle {
+            height_fraction: self.height_fraction,
+            white_bg: self.white_bg.as_ref().map(Color::grayscale),
+            black_bg: self.black_bg.as_ref().map(Color::grayscale),
+            text: self.text.as_ref().map(Color::grayscale),
+        }
+    }
+}
+
+impl Default for BarStyle {
+    fn default() -> Self {
+        BarStyle {
+            height_fraction: 1.0,
+            white_bg: None,
+            black_bg: None,
+            text: None,
         }
     }
 }
@@ -152,26 +814,216 @@ pub struct Config {
     /// Path to font files used to render coordinates.
     pub font_path: String,
 
-    /// Font family name to render coordinates.
+    /// Font family name to render coordinates. May be a comma separated list of families in
+    /// priority order, to render glyphs missing from the first one.
     pub font_family: String,
 
+    /// Font files or directories to look up glyphs missing from font_path.
+    pub fallback_fonts: Vec<String>,
+
+    /// Whether to look up missing glyphs in the fonts installed in the system.
+    pub system_fonts: bool,
+
     /// Family of SVG pieces to use.
     pub pieces_family: String,
 
-    /// Size of one side of the board in pixels. Must be multiple of 8.
+    /// Family of SVG pieces to use for white, instead of pieces_family.
+    pub pieces_family_white: Option<String>,
+
+    /// Family of SVG pieces to use for black, instead of pieces_family.
+    pub pieces_family_black: Option<String>,
+
+    /// A single SVG with all pieces in groups with ids like white-knight, used instead of the
+    /// pieces families.
+    pub pieces_sprite: Option<PathBuf>,
+
+    /// Width and height in pixels of a canvas to center frames on, like 1080x1920 for stories.
+    /// Frames are as big as the board, with its player bars, if None.
+    pub canvas: Option<(u32, u32)>,
+
+    /// Color of the canvas around the board, also seen through transparent square colors when
+    /// there is no board texture.
+    pub background: Color,
+
+    /// Size of one side of the board in pixels. Boards are drawn at the next multiple of 8, so
+    /// all squares are the same size, and resized to this size if it isn't one.
     pub size: u32,
 
     /// Board colors.
     pub colors: Colors,
 
-    /// Indicate whether to flip the board or not.
-    pub flip: bool,
+    /// Side of the board seen at the bottom.
+    pub orientation: Orientation,
+
+    /// Name of a player to see the board from, matched ignoring case against any part of the
+    /// White and Black headers. The board is flipped if they play black, and not if they play
+    /// white, regardless of orientation. orientation is used if no player matches, and
+    /// FollowTurn ignores pov.
+    pub pov: Option<String>,
+
+    /// Name shown for players without a header, like Black in a PGN with only a White header.
+    pub anonymous_name: String,
+
+    /// Draw player bars even if the PGN has no player headers, with players named White and
+    /// Black, so boards are the same size as those of games with players.
+    pub force_player_bars: bool,
+
+    /// How to fit player names too long for their bar.
+    pub name_fit: NameFit,
+
+    /// Size and colors of player bars.
+    pub bar_style: BarStyle,
+
+    /// Whether to draw a bar for each player, or a single bar below the board for both.
+    pub bar_layout: BarLayout,
+
+    /// Clocks with less time left than this are drawn in a warning color. Clocks are never
+    /// drawn as low on time if None.
+    pub low_time: Option<Duration>,
+
+    /// Draw clocks low on time in the heaviest weight of the font as well.
+    pub low_time_bold: bool,
+
+    /// How clocks are written.
+    pub clock_format: ClockFormat,
 
     /// Settings for delays between GIF frames.
     pub delays: Delays,
 
     /// Style elements like rank and file coordinates, player bars, etc ...
     pub style_components: StyleComponents,
+
+    /// Corner of the board to write the last move in, like 23... Qxh2+. No move is written if
+    /// None.
+    pub move_overlay: Option<Corner>,
+
+    /// Play the game backwards, from the final position to the initial one.
+    pub reverse: bool,
+
+    /// Pacing of frame delays: flat or holding key moments longer.
+    pub pace: Pace,
+
+    /// Delay of moves without clocks to tell how long they took, when delays are real.
+    pub real_delay_fallback: RealDelayFallback,
+
+    /// Spend the starting clock from the TimeControl header over the moves of games without
+    /// clock comments, when delays are real. Moves take the first frame delay if None.
+    pub clock_simulation: Option<ClockSimulation>,
+
+    /// Factors applied to the delay of key moment frames when pace is smart.
+    pub key_moment_factors: KeyMomentFactors,
+
+    /// Scale all frame delays so the whole GIF lasts this long.
+    pub total_duration: Option<Duration>,
+
+    /// Play the GIF once and hold the last frame instead of looping back to the first one. The
+    /// last frame is held for as long as a GIF frame can be, so total_duration only counts the
+    /// time until it's reached.
+    pub play_once: bool,
+
+    /// Speed of the GIF encoder color quantization, from 1 (slowest, best quality) to 30.
+    pub encoder_speed: i32,
+
+    /// Draw boards at this many times the size, from 1 to 4, and downscale every frame before
+    /// encoding it for smoother edges. Uses the square of this factor times more memory while
+    /// drawing. Boards are drawn at their size if 1.
+    pub supersample: u8,
+
+    /// Capacity in bytes of the buffer used when writing the GIF to a file.
+    pub write_buffer_capacity: usize,
+
+    /// Show, hide, or repeat the initial position frame.
+    pub initial_position: InitialFrame,
+
+    /// Delay in ms to hold a title card with player and event information before the game.
+    /// No title card is drawn if None.
+    pub title_card: Option<u16>,
+
+    /// Delay in ms to hold the heatmap of square activity drawn after the game, if the heatmap
+    /// style component is enabled.
+    pub heatmap_delay: u16,
+
+    /// Hook called with progress reports while drawing, encoding, and writing frames.
+    pub on_progress: Option<ProgressHook>,
+
+    /// Where to draw coordinates. Coordinates are not drawn if the style has neither ranks
+    /// nor files.
+    pub coordinates: CoordinateMode,
+
+    /// Sides of the board to draw coordinates on.
+    pub coordinate_sides: CoordinateSides,
+
+    /// Size and weight of coordinate labels.
+    pub coordinate_font: CoordinateFont,
+
+    /// Width in pixels of a solid frame around the board. No frame is drawn if 0.
+    pub border_width: u32,
+
+    /// Color of the frame around the board.
+    pub border_color: Color,
+
+    /// Size of pieces as a fraction of a square, greater than 0 and at most 1. Smaller pieces
+    /// are centered in their square.
+    pub piece_scale: f32,
+
+    /// Image or SVG file drawn under the squares, scaled to fill the board. The alpha of the
+    /// square colors controls how much of it shows through.
+    pub board_texture: Option<PathBuf>,
+
+    /// Image or SVG files drawn at the left edge of the white and black player bars, cropped
+    /// to a square. Files that can't be loaded are left out.
+    pub white_avatar: Option<PathBuf>,
+    pub black_avatar: Option<PathBuf>,
+
+    /// Logo or other image drawn over a corner of every frame, if any.
+    pub watermark: Option<Watermark>,
+
+    /// Texts drawn over frames, in order, under the watermark if there is one.
+    pub overlays: Vec<TextOverlay>,
+
+    /// Headers with the country code of the white and black players, like NOR, drawn as a
+    /// flag next to their name, or as text if there is no flag for it.
+    pub white_country_header: String,
+    pub black_country_header: String,
+
+    /// Termination reasons, like "checkmate", mapped to the stem of an SVG file to draw instead
+    /// of the built-in circle. Draw circles still get a _w or _b suffix for each color.
+    pub termination_map: HashMap<String, String>,
+
+    /// Size of termination circles as a fraction of a square, greater than 0 and at most 1.
+    pub termination_scale: f32,
+
+    /// Where to place termination circles on the kings' squares.
+    pub termination_anchor: TerminationAnchor,
+
+    /// Number of frames over which termination circles fade in before the last frame. Circles
+    /// are drawn at once if 0.
+    pub termination_fade: u32,
+
+    /// Number of frames of pieces sliding to their squares drawn before each move. Pieces
+    /// jump to their squares if 0.
+    pub animate_moves: u32,
+
+    /// Number of recent moves to highlight, fading out from the newest one. No moves are
+    /// highlighted if 0.
+    pub trail: u32,
+
+    /// Number of moves the opening from the ECO and Opening headers is shown for in the event
+    /// bar, when the opening style component is on. It fades out over the last few plies.
+    pub opening_moves: u32,
+
+    /// Most characters of comment text written in the caption strip, when the captions style
+    /// component is on. Longer comments are cut short with an ellipsis.
+    pub caption_length: usize,
+
+    /// Make the board readable without relying on hue: dark squares are hatched, and the
+    /// winner's and loser's circles get a check mark and a cross. Pair with
+    /// Colors::accessible for a high-contrast palette.
+    pub accessible: bool,
+
+    /// Render frames in grays only, for print or e-ink displays. Configured colors are
+    /// desaturated too, so edges blended with them are gray as well.
+    pub grayscale: bool,
 }
 
 impl Default for Config {
@@ -181,16 +1033,105 @@ impl Default for Config {
             svgs_path: "".to_string(),
             font_path: "".to_string(),
             font_family: "roboto".to_string(),
+            fallback_fonts: Vec::new(),
+            system_fonts: false,
             pieces_family: "cburnett".to_string(),
+            pieces_family_white: None,
+            pieces_family_black: None,
+            pieces_sprite: None,
+            canvas: None,
+            background: Color([22, 21, 18, 255]),
             size: 640,
             colors: Colors::default(),
-            flip: false,
+            orientation: Orientation::White,
+            pov: None,
+            anonymous_name: "Anonymous".to_string(),
+            force_player_bars: false,
+            name_fit: NameFit::Truncate,
+            bar_style: BarStyle::default(),
+            bar_layout: BarLayout::default(),
+            low_time: Some(Duration::from_secs(20)),
+            low_time_bold: false,
+            clock_format: ClockFormat::default(),
             delays: Delays::default(),
             style_components: StyleComponents::default(),
+            move_overlay: None,
+            reverse: false,
+            pace: Pace::default(),
+            real_delay_fallback: RealDelayFallback::default(),
+            clock_simulation: None,
+            key_moment_factors: KeyMomentFactors::default(),
+            total_duration: None,
+            play_once: false,
+            encoder_speed: 10,
+            supersample: 1,
+            write_buffer_capacity: 64 * 1024,
+            initial_position: InitialFrame::default(),
+            title_card: None,
+            heatmap_delay: 3000,
+            on_progress: None,
+            coordinates: CoordinateMode::default(),
+            coordinate_sides: CoordinateSides::default(),
+            coordinate_font: CoordinateFont::default(),
+            border_width: 0,
+            border_color: Color([40, 26, 13, 255]),
+            piece_scale: 1.0,
+            board_texture: None,
+            white_avatar: None,
+            black_avatar: None,
+            watermark: None,
+            overlays: Vec::new(),
+            white_country_header: "WhiteCountry".to_string(),
+            black_country_header: "BlackCountry".to_string(),
+            termination_map: HashMap::new(),
+            termination_scale: 1.0 / 3.0,
+            termination_anchor: TerminationAnchor::default(),
+            termination_fade: 0,
+            animate_moves: 0,
+            trail: 0,
+            opening_moves: 10,
+            caption_length: 200,
+            accessible: false,
+            grayscale: false,
+        }
+    }
+}
+
+/// Parse a termination SVG mapping like "checkmate=icons/skull" into a reason and a file stem.
+pub fn parse_termination_svg(s: &str) -> Result<(String, String), C2GError> {
+    match s.split_once('=') {
+        Some((reason, stem)) if !reason.trim().is_empty() && !stem.trim().is_empty() => {
+            Ok((reason.trim().to_string(), stem.trim().to_string()))
         }
+        _ => Err(C2GError::CannotParseTerminationSvg(s.to_string())),
+    }
+}
+
+/// Parse a canvas size like "1080x1920" into a width and a height.
+pub fn parse_canvas(s: &str) -> Result<(u32, u32), C2GError> {
+    let parse = |n: &str| n.trim().parse::<u32>().ok().filter(|n| *n > 0);
+    match s.split_once('x') {
+        Some((width, height)) => match (parse(width), parse(height)) {
+            (Some(width), Some(height)) => Ok((width, height)),
+            _ => Err(C2GError::CannotParseCanvas(s.to_string())),
+        },
+        None => Err(C2GError::CannotParseCanvas(s.to_string())),
     }
 }
 
+/// Load termination SVG mappings from a TOML file of reason and file stem pairs, like:
+/// checkmate = "icons/skull"
+pub fn load_termination_map(path: &str) -> Result<HashMap<String, String>, C2GError> {
+    let contents = fs::read_to_string(path).map_err(|e| C2GError::ReadTerminationMap {
+        path: path.to_string(),
+        reason: e.to_string(),
+    })?;
+    toml::from_str(&contents).map_err(|e| C2GError::ReadTerminationMap {
+        path: path.to_string(),
+        reason: e.to_string(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,12 +1139,386 @@ mod tests {
     #[test]
     fn test_color_from_str() {
         let color = Color::from_str("#B83B26").unwrap();
-        assert_eq!(color.to_arr(), [184, 59, 38, 1]);
+        assert_eq!(color.to_arr(), [184, 59, 38, 255]);
 
         let color = Color::from_str("B83B26").unwrap();
-        assert_eq!(color.to_arr(), [184, 59, 38, 1]);
+        assert_eq!(color.to_arr(), [184, 59, 38, 255]);
 
         let color = Color::from_str("184,59,38").unwrap();
-        assert_eq!(color.to_arr(), [184, 59, 38, 1]);
+        assert_eq!(color.to_arr(), [184, 59, 38, 255]);
+    }
+
+    #[test]
+    fn test_color_from_str_with_alpha() {
+        let color = Color::from_str("184,59,38,128").unwrap();
+        assert_eq!(color.to_arr(), [184, 59, 38, 128]);
+
+        let color = Color::from_str("#B83B2680").unwrap();
+        assert_eq!(color.to_arr(), [184, 59, 38, 128]);
+
+        let color = Color::from_str_or_alpha("184,59,38", 160).unwrap();
+        assert_eq!(color.to_arr(), [184, 59, 38, 160]);
+        let color = Color::from_str_or_alpha("184,59,38,0", 160).unwrap();
+        assert_eq!(color.to_arr(), [184, 59, 38, 0]);
+
+        assert!(Color::from_str("184,59,38,256").is_err());
+    }
+
+    #[test]
+    fn test_color_from_hex_str() {
+        let color = Color::from_str("#F80").unwrap();
+        assert_eq!(color.to_arr(), [255, 136, 0, 255]);
+
+        let color = Color::from_str("#b83b26").unwrap();
+        assert_eq!(color.to_arr(), [184, 59, 38, 255]);
+
+        let color = Color::from_str("#B83B2600").unwrap();
+        assert_eq!(color.to_arr(), [184, 59, 38, 0]);
+
+        let color = Color::from_str_or_alpha("#F80", 128).unwrap();
+        assert_eq!(color.to_arr(), [255, 136, 0, 128]);
+    }
+
+    #[test]
+    fn test_color_from_name() {
+        let color = Color::from_str("ivory").unwrap();
+        assert_eq!(color.to_arr(), [255, 255, 240, 255]);
+        let color = Color::from_str("DarkSlateGray").unwrap();
+        assert_eq!(color.to_arr(), [47, 79, 79, 255]);
+        let color = Color::from_str("TAN").unwrap();
+        assert_eq!(color.to_arr(), [210, 180, 140, 255]);
+        let color = Color::from_str_or_alpha("cornflowerblue", 160).unwrap();
+        assert_eq!(color.to_arr(), [100, 149, 237, 160]);
+
+        match Color::from_str("darkslategra") {
+            Err(C2GError::CannotParseColor { color, reason }) => {
+                assert_eq!(color, "darkslategra");
+                assert!(reason.contains("darkslategray, darkslategrey"));
+            }
+            other => panic!("Expected CannotParseColor, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_hsl_to_rgb() {
+        assert_eq!(hsl_to_rgb(0.0, 1.0, 0.5), [255, 0, 0]);
+        assert_eq!(hsl_to_rgb(360.0, 1.0, 0.5), [255, 0, 0]);
+        assert_eq!(hsl_to_rgb(0.0, 0.0, 0.5), [128, 128, 128]);
+        assert_eq!(hsl_to_rgb(0.0, 0.0, 1.0), [255, 255, 255]);
+        assert_eq!(hsl_to_rgb(30.0, 1.0, 0.5), [255, 128, 0]);
+        assert_eq!(hsl_to_rgb(60.0, 1.0, 0.5), [255, 255, 0]);
+        assert_eq!(hsl_to_rgb(120.0, 1.0, 0.25), [0, 128, 0]);
+        assert_eq!(hsl_to_rgb(240.0, 1.0, 0.5), [0, 0, 255]);
+        assert_eq!(hsl_to_rgb(300.0, 1.0, 0.5), [255, 0, 255]);
+        assert_eq!(hsl_to_rgb(-60.0, 1.0, 0.5), [255, 0, 255]);
+    }
+
+    #[test]
+    fn test_color_grayscale() {
+        assert_eq!(
+            Color([255, 0, 0, 255]).grayscale().to_arr(),
+            [54, 54, 54, 255]
+        );
+        assert_eq!(
+            Color([118, 150, 86, 128]).grayscale().to_arr(),
+            [138, 138, 138, 128]
+        );
+        assert_eq!(Color([20, 20, 20, 0]).grayscale().to_arr(), [20, 20, 20, 0]);
+    }
+
+    #[test]
+    fn test_color_from_hsl_str() {
+        let color = Color::from_str("hsl(120,40%,60%)").unwrap();
+        assert_eq!(color.to_arr(), [112, 194, 112, 255]);
+
+        let color = Color::from_str("HSL(0deg, 100%, 50%)").unwrap();
+        assert_eq!(color.to_arr(), [255, 0, 0, 255]);
+
+        let color = Color::from_str("hsla(210, 50%, 40%, 0.5)").unwrap();
+        assert_eq!(color.to_arr(), [51, 102, 153, 128]);
+
+        let color = Color::from_str("hsla(210, 50%, 40%, 25%)").unwrap();
+        assert_eq!(color.to_arr(), [51, 102, 153, 64]);
+
+        let color = Color::from_str_or_alpha("hsl(210, 50%, 40%)", 160).unwrap();
+        assert_eq!(color.to_arr(), [51, 102, 153, 160]);
+    }
+
+    #[test]
+    fn test_rgb_to_hsl_round_trip() {
+        for rgb in [[255, 0, 0], [128, 128, 128], [118, 150, 86], [51, 102, 153]] {
+            let (h, s, l) = rgb_to_hsl(rgb);
+            assert_eq!(hsl_to_rgb(h, s, l), rgb);
+        }
+        let (h, s, l) = rgb_to_hsl([51, 102, 153]);
+        assert!((h - 210.0).abs() < 0.01);
+        assert!((s - 0.5).abs() < 0.01);
+        assert!((l - 0.4).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_accessible_colors_contrast() {
+        // WCAG relative luminance and contrast ratio
+        let luminance = |color: &Color| {
+            let [r, g, b, _] = color.to_arr().map(|c| {
+                let c = c as f32 / 255.0;
+                match c <= 0.03928 {
+                    true => c / 12.92,
+                    false => ((c + 0.055) / 1.055).powf(2.4),
+                }
+            });
+            0.2126 * r + 0.7152 * g + 0.0722 * b
+        };
+        let colors = Colors::accessible();
+        let contrast = (luminance(&colors.light) + 0.05) / (luminance(&colors.dark) + 0.05);
+        assert!(contrast >= 4.5, "{}", contrast);
+    }
+
+    #[test]
+    fn test_derive_from_dark_default() {
+        let default = Colors::default();
+        let colors = Colors::derive_from_dark(default.dark.clone());
+        assert_eq!(colors.dark.to_arr(), default.dark.to_arr());
+        for (derived, expected) in colors.light.to_arr().iter().zip(default.light.to_arr()) {
+            assert!(derived.abs_diff(expected) <= 3, "{:?}", colors.light);
+        }
+    }
+
+    #[test]
+    fn test_derive_from_dark_very_dark() {
+        let colors = Colors::derive_from_dark(Color([0, 0, 0, 255]));
+        assert_eq!(colors.light.to_arr(), [196, 196, 196, 255]);
+
+        // Alpha is kept, so derived colors blend the same over textures
+        let colors = Colors::derive_from_dark(Color([10, 20, 60, 160]));
+        let [r, g, b, a] = colors.light.to_arr();
+        assert_eq!(a, 160);
+        assert!(rgb_to_hsl([r, g, b]).2 > 0.75);
+    }
+
+    #[test]
+    fn test_derive_from_dark_very_light() {
+        let colors = Colors::derive_from_dark(Color([255, 255, 255, 255]));
+        assert_eq!(colors.light.to_arr(), [204, 204, 204, 255]);
+
+        let colors = Colors::derive_from_dark(Color([240, 250, 240, 255]));
+        let [r, g, b, _] = colors.light.to_arr();
+        let (_, _, dark_lightness) = rgb_to_hsl([240, 250, 240]);
+        let (_, _, light_lightness) = rgb_to_hsl([r, g, b]);
+        assert!((dark_lightness - light_lightness - MIN_LIGHTNESS_CONTRAST).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_color_from_invalid_hsl_str() {
+        for (color, component) in [
+            ("hsl(red,40%,60%)", "\"red\""),
+            ("hsl(120,40,60%)", "saturation \"40\""),
+            ("hsl(120,40%,160%)", "lightness \"160%\""),
+            ("hsla(120,40%,60%,2)", "alpha \"2\""),
+            ("hsl(120,40%)", "found 2"),
+            ("hsl(120,40%,60%", "parenthesis"),
+        ] {
+            match Color::from_str(color) {
+                Err(C2GError::CannotParseColor { color: c, reason }) => {
+                    assert_eq!(c, color);
+                    assert!(reason.contains(component), "{}", reason);
+                }
+                other => panic!("Expected CannotParseColor for {}, got {:?}", color, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_color_from_invalid_hex_str() {
+        for color in [
+            "#12345",
+            "#GGGGGG",
+            "#",
+            "#1234",
+            "#B83B26800",
+            "#+F+F+F",
+            "#é1234",
+        ] {
+            match Color::from_str(color) {
+                Err(C2GError::CannotParseColor {
+                    color: c,
+                    reason: _,
+                }) => assert_eq!(c, color),
+                other => panic!("Expected CannotParseColor for {}, got {:?}", color, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_initial_frame_from_str() {
+        assert_eq!(InitialFrame::from_str("show").unwrap(), InitialFrame::Show);
+        assert_eq!(InitialFrame::from_str("hide").unwrap(), InitialFrame::Hide);
+        assert_eq!(InitialFrame::from_str("0").unwrap(), InitialFrame::Hide);
+        assert_eq!(InitialFrame::from_str("1").unwrap(), InitialFrame::Show);
+        assert_eq!(
+            InitialFrame::from_str("3").unwrap(),
+            InitialFrame::Repeat(3)
+        );
+        assert!(InitialFrame::from_str("twice").is_err());
+    }
+
+    #[test]
+    fn test_coordinate_mode_from_str() {
+        assert_eq!(
+            CoordinateMode::from_str("inside").unwrap(),
+            CoordinateMode::Inside
+        );
+        assert_eq!(
+            CoordinateMode::from_str("border").unwrap(),
+            CoordinateMode::Border
+        );
+        assert_eq!(
+            CoordinateMode::from_str("none").unwrap(),
+            CoordinateMode::None
+        );
+        assert!(CoordinateMode::from_str("outside").is_err());
+    }
+
+    #[test]
+    fn test_coordinate_sides_from_str() {
+        assert_eq!(
+            CoordinateSides::from_str("all").unwrap(),
+            CoordinateSides::all()
+        );
+        assert_eq!(
+            CoordinateSides::from_str("bottom,left").unwrap(),
+            CoordinateSides::default()
+        );
+        let sides = CoordinateSides::from_str("top, right").unwrap();
+        assert!(sides.top && sides.right && !sides.bottom && !sides.left);
+        assert!(CoordinateSides::from_str("bottom,middle").is_err());
+    }
+
+    #[test]
+    fn test_coord_size_from_str() {
+        assert_eq!(CoordSize::from_str("auto").unwrap(), CoordSize::Auto);
+        assert_eq!(CoordSize::from_str("12").unwrap(), CoordSize::Px(12.0));
+        assert_eq!(CoordSize::from_str("12px").unwrap(), CoordSize::Px(12.0));
+        assert_eq!(
+            CoordSize::from_str("25%").unwrap(),
+            CoordSize::Fraction(0.25)
+        );
+        assert!(CoordSize::from_str("-3px").is_err());
+        assert!(CoordSize::from_str("big").is_err());
+    }
+
+    #[test]
+    fn test_termination_anchor_from_str() {
+        assert_eq!(
+            TerminationAnchor::from_str("corner").unwrap(),
+            TerminationAnchor::Corner
+        );
+        assert_eq!(
+            TerminationAnchor::from_str("above-piece").unwrap(),
+            TerminationAnchor::AbovePiece
+        );
+        assert!(TerminationAnchor::from_str("middle").is_err());
+    }
+
+    #[test]
+    fn test_name_fit_from_str() {
+        assert_eq!(NameFit::from_str("truncate").unwrap(), NameFit::Truncate);
+        assert_eq!(NameFit::from_str("shrink").unwrap(), NameFit::Shrink);
+        assert!(NameFit::from_str("wrap").is_err());
+    }
+
+    #[test]
+    fn test_clock_format_from_str() {
+        assert_eq!(ClockFormat::from_str("smart").unwrap(), ClockFormat::Smart);
+        assert_eq!(
+            ClockFormat::from_str("compact").unwrap(),
+            ClockFormat::Compact
+        );
+        assert_eq!(ClockFormat::from_str("full").unwrap(), ClockFormat::Full);
+        assert!(ClockFormat::from_str("digital").is_err());
+    }
+
+    #[test]
+    fn test_corner_from_str() {
+        assert_eq!(Corner::from_str("top-left").unwrap(), Corner::TopLeft);
+        assert_eq!(
+            Corner::from_str("bottom-right").unwrap(),
+            Corner::BottomRight
+        );
+        assert!(Corner::from_str("center").is_err());
+    }
+
+    #[test]
+    fn test_corner_origin() {
+        let origin = |corner: Corner| corner.origin((320, 400), (60, 20), 5);
+        assert_eq!(origin(Corner::TopLeft), (5, 5));
+        assert_eq!(origin(Corner::TopRight), (255, 5));
+        assert_eq!(origin(Corner::BottomLeft), (5, 375));
+        assert_eq!(origin(Corner::BottomRight), (255, 375));
+    }
+
+    #[test]
+    fn test_watermark_new() {
+        let watermark =
+            |path: &str| Watermark::new(PathBuf::from(path), Corner::default(), 0.5, 0.2);
+        assert!(watermark("svgs/flags/NOR.svg").is_ok());
+        assert!(watermark("svgs/flags/XXX.svg").is_err());
+        assert!(watermark("README.md").is_err());
+    }
+
+    #[test]
+    fn test_bar_layout_from_str() {
+        assert_eq!(BarLayout::from_str("double").unwrap(), BarLayout::Double);
+        assert_eq!(BarLayout::from_str("single").unwrap(), BarLayout::Single);
+        assert!(BarLayout::from_str("triple").is_err());
+    }
+
+    #[test]
+    fn test_orientation() {
+        let follow_turn = Orientation::from_str("follow-turn").unwrap();
+        assert_eq!(follow_turn, Orientation::FollowTurn);
+        assert!(!follow_turn.flip(shakmaty::Color::White));
+        assert!(follow_turn.flip(shakmaty::Color::Black));
+        assert!(Orientation::from_str("black")
+            .unwrap()
+            .flip(shakmaty::Color::White));
+        assert!(!Orientation::White.flip(shakmaty::Color::Black));
+        assert!(Orientation::from_str("flipped").is_err());
+    }
+
+    #[test]
+    fn test_parse_canvas() {
+        assert_eq!(parse_canvas("1080x1920").unwrap(), (1080, 1920));
+        assert_eq!(parse_canvas("1280 x 720").unwrap(), (1280, 720));
+        assert!(parse_canvas("1080").is_err());
+        assert!(parse_canvas("0x1080").is_err());
+        assert!(parse_canvas("widex720").is_err());
+    }
+
+    #[test]
+    fn test_parse_termination_svg() {
+        assert_eq!(
+            parse_termination_svg("checkmate=icons/skull").unwrap(),
+            ("checkmate".to_string(), "icons/skull".to_string())
+        );
+        assert_eq!(
+            parse_termination_svg(" draw = shake ").unwrap(),
+            ("draw".to_string(), "shake".to_string())
+        );
+        assert!(parse_termination_svg("checkmate").is_err());
+        assert!(parse_termination_svg("=skull").is_err());
+    }
+
+    #[test]
+    fn test_load_termination_map() {
+        let path = std::env::temp_dir().join("c2g_test_termination_map.toml");
+        fs::write(&path, "checkmate = \"skull\"\ndraw = \"shake\"\n").unwrap();
+        let map = load_termination_map(path.to_str().unwrap()).unwrap();
+        assert_eq!(map.get("checkmate"), Some(&"skull".to_string()));
+        assert_eq!(map.get("draw"), Some(&"shake".to_string()));
+
+        fs::write(&path, "checkmate = 1").unwrap();
+        assert!(load_termination_map(path.to_str().unwrap()).is_err());
+        fs::remove_file(&path).unwrap();
     }
 }
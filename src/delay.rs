@@ -33,6 +33,9 @@ pub struct Delays {
 
     /// Delay after the last frame of the game. Must be set separately as otherwise there is no delay after game ends to digest a position.
     pub last_frame: Delay,
+
+    /// Interpolate the delay between frames from a start to an end value, instead of using frame.
+    pub ramp: Option<DelayRamp>,
 }
 
 impl Delays {
@@ -41,6 +44,7 @@ impl Delays {
             frame: frame.clone(),
             last_frame: last_frame.clone(),
             first_frame: first_frame.clone(),
+            ramp: None,
         }
     }
 
@@ -66,7 +70,7 @@ impl Delays {
     }
 
     pub fn first_frame_delay(&self) -> Option<u16> {
-        match self.last_frame {
+        match self.first_frame {
             Delay::Real => None,
             Delay::Duration(d) => Some(d),
         }
@@ -79,3 +83,353 @@ impl Default for Delays {
         Delays::new(&delay, &delay, &delay)
     }
 }
+
+/// Easing curves to interpolate a DelayRamp.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Apply the easing curve to a progress value between 0.0 and 1.0
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Easing {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "linear" => Ok(Easing::Linear),
+            "ease-in" => Ok(Easing::EaseIn),
+            "ease-out" => Ok(Easing::EaseOut),
+            "ease-in-out" => Ok(Easing::EaseInOut),
+            _ => Err(C2GError::CannotParseDelayRamp {
+                ramp: s.to_string(),
+                reason: "unknown easing, expected one of linear, ease-in, ease-out, ease-in-out"
+                    .to_string(),
+            }),
+        }
+    }
+}
+
+/// A delay between GIF frames that changes over the course of the game, going from start to end ms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelayRamp {
+    pub start: u16,
+    pub end: u16,
+    pub easing: Easing,
+}
+
+impl DelayRamp {
+    /// Calculate the delay for a step out of a total number of steps
+    pub fn delay_at(&self, step: usize, steps: usize) -> u16 {
+        let t = if steps <= 1 {
+            0.0
+        } else {
+            step as f32 / (steps - 1) as f32
+        };
+        let progress = self.easing.apply(t);
+        let delay = self.start as f32 + (self.end as f32 - self.start as f32) * progress;
+
+        delay.round() as u16
+    }
+}
+
+/// Parse a delay ramp string formatted as start_ms:end_ms, optionally followed by :easing.
+impl FromStr for DelayRamp {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        let error = |reason: &str| C2GError::CannotParseDelayRamp {
+            ramp: s.to_string(),
+            reason: reason.to_string(),
+        };
+        let parts: Vec<&str> = s.split(':').collect();
+        if parts.len() < 2 || parts.len() > 3 {
+            return Err(error("expected start_ms:end_ms[:easing]"));
+        }
+
+        let start = parts[0].parse::<u16>().map_err(|e| error(&e.to_string()))?;
+        let end = parts[1].parse::<u16>().map_err(|e| error(&e.to_string()))?;
+        if start == 0 || end == 0 {
+            return Err(error("start and end delays must be greater than 0"));
+        }
+
+        let easing = match parts.get(2) {
+            Some(e) => Easing::from_str(e)?,
+            None => Easing::default(),
+        };
+
+        Ok(DelayRamp { start, end, easing })
+    }
+}
+
+/// Represents how delays between GIF frames are paced.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Pace {
+    /// Every frame uses the frame delay.
+    #[default]
+    Flat,
+    /// Frames for key moments, like captures or checks, are held longer than the frame delay.
+    Smart,
+}
+
+impl FromStr for Pace {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "flat" => Ok(Pace::Flat),
+            "smart" => Ok(Pace::Smart),
+            _ => Err(C2GError::UnknownPace(s.to_string())),
+        }
+    }
+}
+
+/// Delay used for moves without a known real delay, like those of a player without clock
+/// comments when only their opponent has them.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RealDelayFallback {
+    /// The delay of the opponent's move next to it.
+    Opponent,
+    /// The average delay of all moves with a known delay.
+    Average,
+    /// The first frame delay, which is what moves without clocks always had before fallbacks.
+    #[default]
+    FirstFrame,
+}
+
+impl FromStr for RealDelayFallback {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "opponent" => Ok(RealDelayFallback::Opponent),
+            "average" => Ok(RealDelayFallback::Average),
+            "first-frame" => Ok(RealDelayFallback::FirstFrame),
+            _ => Err(C2GError::UnknownRealDelayFallback(s.to_string())),
+        }
+    }
+}
+
+/// How to spend the starting clock over the moves of a game without clock comments, to pace
+/// real delays by the length of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ClockSimulation {
+    /// Every move takes the same time.
+    #[default]
+    Even,
+    /// Moves take longer as the game goes on, with the last moves taking three times as long
+    /// as the first ones.
+    Curve,
+}
+
+impl ClockSimulation {
+    /// Share of a player's time spent on each of their moves, adding up to 1.
+    pub fn shares(&self, moves: usize) -> Vec<f64> {
+        let weights: Vec<f64> = match self {
+            ClockSimulation::Even => vec![1.0; moves],
+            ClockSimulation::Curve if moves == 1 => vec![1.0],
+            ClockSimulation::Curve => (0..moves)
+                .map(|i| 1.0 + 2.0 * i as f64 / (moves - 1) as f64)
+                .collect(),
+        };
+        let total: f64 = weights.iter().sum();
+        weights.iter().map(|w| w / total).collect()
+    }
+}
+
+impl FromStr for ClockSimulation {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "even" => Ok(ClockSimulation::Even),
+            "curve" => Ok(ClockSimulation::Curve),
+            _ => Err(C2GError::UnknownClockSimulation(s.to_string())),
+        }
+    }
+}
+
+/// Factors to multiply the delay of frames for key moments when using smart pace.
+/// If a move is more than one kind of key moment, the largest factor is used.
+#[derive(Debug, Clone)]
+pub struct KeyMomentFactors {
+    /// Factor for moves that capture a piece.
+    pub capture: f32,
+
+    /// Factor for moves that give check.
+    pub check: f32,
+
+    /// Factor for moves that promote a pawn.
+    pub promotion: f32,
+
+    /// Factor for moves that give checkmate.
+    pub mate: f32,
+}
+
+impl Default for KeyMomentFactors {
+    fn default() -> Self {
+        KeyMomentFactors {
+            capture: 1.5,
+            check: 1.5,
+            promotion: 2.0,
+            mate: 2.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_frame_delay() {
+        let delays = Delays::new(
+            &Delay::Duration(1000),
+            &Delay::Duration(500),
+            &Delay::Duration(3000),
+        );
+        assert_eq!(delays.first_frame_delay(), Some(500));
+        assert_eq!(delays.last_frame_delay(), Some(3000));
+    }
+
+    #[test]
+    fn test_delay_ramp_from_str() {
+        let ramp = DelayRamp::from_str("2000:500").unwrap();
+        assert_eq!(
+            ramp,
+            DelayRamp {
+                start: 2000,
+                end: 500,
+                easing: Easing::Linear
+            }
+        );
+
+        let ramp = DelayRamp::from_str("500:2000:ease-in").unwrap();
+        assert_eq!(ramp.easing, Easing::EaseIn);
+
+        assert!(DelayRamp::from_str("500:0").is_err());
+        assert!(DelayRamp::from_str("0:500").is_err());
+        assert!(DelayRamp::from_str("500").is_err());
+        assert!(DelayRamp::from_str("500:1000:bounce").is_err());
+        assert!(DelayRamp::from_str("500:-1").is_err());
+    }
+
+    #[test]
+    fn test_delay_ramp_linear() {
+        let ramp = DelayRamp {
+            start: 1000,
+            end: 2000,
+            easing: Easing::Linear,
+        };
+        assert_eq!(ramp.delay_at(0, 11), 1000);
+        assert_eq!(ramp.delay_at(5, 11), 1500);
+        assert_eq!(ramp.delay_at(10, 11), 2000);
+
+        let ramp = DelayRamp {
+            start: 2000,
+            end: 1000,
+            easing: Easing::Linear,
+        };
+        assert_eq!(ramp.delay_at(1, 5), 1750);
+
+        // A single step takes the start value
+        assert_eq!(ramp.delay_at(0, 1), 2000);
+    }
+
+    #[test]
+    fn test_delay_ramp_easing() {
+        let ramp = DelayRamp {
+            start: 1000,
+            end: 2000,
+            easing: Easing::EaseIn,
+        };
+        assert_eq!(ramp.delay_at(5, 11), 1250);
+
+        let ramp = DelayRamp {
+            easing: Easing::EaseOut,
+            ..ramp
+        };
+        assert_eq!(ramp.delay_at(5, 11), 1750);
+
+        let ramp = DelayRamp {
+            easing: Easing::EaseInOut,
+            ..ramp
+        };
+        assert_eq!(ramp.delay_at(0, 11), 1000);
+        assert_eq!(ramp.delay_at(5, 11), 1500);
+        assert_eq!(ramp.delay_at(10, 11), 2000);
+    }
+
+    #[test]
+    fn test_pace_from_str() {
+        assert_eq!(Pace::from_str("flat").unwrap(), Pace::Flat);
+        assert_eq!(Pace::from_str("smart").unwrap(), Pace::Smart);
+        assert!(Pace::from_str("fast").is_err());
+    }
+
+    #[test]
+    fn test_real_delay_fallback_from_str() {
+        assert_eq!(
+            RealDelayFallback::from_str("opponent").unwrap(),
+            RealDelayFallback::Opponent
+        );
+        assert_eq!(
+            RealDelayFallback::from_str("average").unwrap(),
+            RealDelayFallback::Average
+        );
+        assert_eq!(
+            RealDelayFallback::from_str("first-frame").unwrap(),
+            RealDelayFallback::FirstFrame
+        );
+        assert!(RealDelayFallback::from_str("median").is_err());
+    }
+
+    #[test]
+    fn test_clock_simulation_shares() {
+        assert_eq!(ClockSimulation::Even.shares(4), [0.25; 4]);
+        assert_eq!(ClockSimulation::Curve.shares(1), [1.0]);
+        assert!(ClockSimulation::Curve.shares(0).is_empty());
+
+        // The last move takes three times as long as the first one
+        let shares = ClockSimulation::Curve.shares(3);
+        assert_eq!(shares, [1.0 / 6.0, 2.0 / 6.0, 3.0 / 6.0]);
+        let shares = ClockSimulation::Curve.shares(40);
+        assert!((shares.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!((shares[39] / shares[0] - 3.0).abs() < 1e-9);
+        assert!(shares.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_clock_simulation_from_str() {
+        assert_eq!(
+            ClockSimulation::from_str("even").unwrap(),
+            ClockSimulation::Even
+        );
+        assert_eq!(
+            ClockSimulation::from_str("curve").unwrap(),
+            ClockSimulation::Curve
+        );
+        assert!(ClockSimulation::from_str("random").is_err());
+    }
+}
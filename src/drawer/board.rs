@@ -1,43 +1,403 @@
-use image::{imageops, ImageBuffer, Rgba, RgbaImage};
-use shakmaty::{self, Chess, File, Move, Position, Rank, Role, Square};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::Path;
+
+use image::{imageops, DynamicImage, ImageBuffer, Rgba, RgbaImage};
+use shakmaty::{self, attacks, ByColor, ByRole, Chess, File, Move, Position, Rank, Role, Square};
 use tiny_skia::{self, Pixmap, PixmapPaint, Transform};
-use usvg::FitTo;
+use usvg::{FitTo, NodeExt};
 
 use super::error::DrawerError;
-use super::svgs::{FontSize, FontWeight, SVGForest, SVGTree};
+use super::svgs::{FontSize, FontWeight, PieceSource, SVGForest, SVGTree, TextAnchor, TextStyle};
 use super::utils;
 
-use crate::config::Color;
+use crate::config::{
+    BarLayout, BarStyle, Color, CoordSize, CoordinateFont, CoordinateMode, CoordinateSides, Corner,
+    NameFit,
+};
+
+/// How to draw a player's clock.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ClockStyle {
+    #[default]
+    Normal,
+    /// The player is low on time: the clock is drawn in a warning color.
+    LowTime,
+    /// The player lost on time: the clock is drawn in a warning color next to a flag.
+    FlagFall,
+}
+
+/// Text of a player bar: an optional title, like GM, the player's name and rating, and how
+/// much their rating changed with the game and their country, drawn after the name.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PlayerLabel {
+    pub title: Option<String>,
+    pub name: String,
+    pub rating: Option<u32>,
+    pub rating_diff: Option<i32>,
+    /// Country code, like NOR, drawn as a flag if there is one for it.
+    pub country: Option<String>,
+}
+
+impl PlayerLabel {
+    /// The player's name followed by their rating, without their title.
+    fn name_with_rating(&self) -> String {
+        match self.rating {
+            Some(rating) => format!("{} ({})", self.name, rating),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for PlayerLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.title {
+            Some(title) => write!(f, "{} {}", title, self.name_with_rating()),
+            None => write!(f, "{}", self.name_with_rating()),
+        }
+    }
+}
+
+/// Where and how str_pixmap draws a line of text: bold, half as high as a pixmap of width by
+/// height, with its anchor at x, y percent of the pixmap.
+#[derive(Debug, Clone, Copy)]
+pub struct TextBox {
+    pub height: u32,
+    pub width: u32,
+    pub x: u32,
+    pub y: u32,
+    pub color: Rgba<u8>,
+    pub background_color: Rgba<u8>,
+    pub text_anchor: TextAnchor,
+}
 
 #[derive(Debug)]
 pub struct BoardDrawer {
     size: u32,
     flip: bool,
+    // Square colors as seen, blended over the background, and as given, blended over textures
     dark: Rgba<u8>,
     light: Rgba<u8>,
+    dark_overlay: Rgba<u8>,
+    light_overlay: Rgba<u8>,
+    background: Rgba<u8>,
+    material_balance: bool,
+    move_times: bool,
+    event_bar: bool,
+    move_list: bool,
+    captions: bool,
+    coordinates: CoordinateMode,
+    coordinate_sides: CoordinateSides,
+    coordinate_font: CoordinateFont,
+    frame_width: u32,
+    frame_color: Rgba<u8>,
+    check_highlight: Option<Rgba<u8>>,
+    title_color: Option<Rgba<u8>>,
+    name_fit: NameFit,
+    bar_height_fraction: f32,
+    bar_backgrounds: ByColor<Option<Rgba<u8>>>,
+    bar_text: Option<Rgba<u8>>,
+    bar_layout: BarLayout,
+    low_time_bold: bool,
+    move_highlight: Option<Rgba<u8>>,
+    texture: Option<RgbaImage>,
+    hatch: bool,
+    piece_scale: f32,
+    piece_cache: HashMap<PieceKey, Pixmap>,
+    /// Cells of the move list by text and whether they're highlighted, as the same moves are
+    /// drawn in every frame.
+    move_list_cache: HashMap<(String, bool), RgbaImage>,
+    coordinate_metrics: Option<(f32, f32)>,
+}
+
+/// Characters used as coordinate labels, measured to lay them out.
+const COORDINATE_GLYPHS: &str = "abcdefgh12345678";
+
+/// Space left around coordinate labels, as a fraction of their height.
+const COORDINATE_MARGIN: f32 = 0.05;
+
+/// Smallest space in pixels left around coordinate labels. Their position is rounded to whole
+/// pixels, which moves them by up to half a pixel, so this keeps glyphs from being clipped.
+const MIN_COORDINATE_MARGIN: f32 = 1.5;
+
+/// Characters in a clock like 0:03:00.0, the longest clock drawn at full size. Longer ones,
+/// like those of correspondence games, are drawn smaller to fit their box.
+const CLOCK_LENGTH: usize = 9;
+
+/// Color of the lines hatching dark squares, drawn over the square color.
+const HATCH_COLOR: Rgba<u8> = Rgba([0, 0, 0, 32]);
+
+/// Number of hatch lines crossing each side of a dark square. Squares hold a whole number of
+/// lines, so the hatch is continuous across the board.
+const HATCH_LINES: u32 = 4;
+
+/// Height of the baseline of player bar text, as a percentage of the bar's height from the top.
+const BAR_TEXT_Y: u32 = 65;
+
+/// Smallest size long player names are shrunk to, as a fraction of their size, before they
+/// are truncated.
+const MIN_NAME_SCALE: f32 = 0.6;
+
+/// Colors of rating changes in player bars, for rating gained and lost.
+const RATING_GAIN_COLOR: Rgba<u8> = Rgba([46, 160, 67, 255]);
+const RATING_LOSS_COLOR: Rgba<u8> = Rgba([202, 52, 49, 255]);
+
+/// Heatmap colors for the least and most visited squares, with their opacity.
+const HEATMAP_COLD: Rgba<u8> = Rgba([255, 235, 59, 64]);
+const HEATMAP_HOT: Rgba<u8> = Rgba([211, 47, 47, 192]);
+
+/// Color of a square in the heatmap, t going from 0 for the least visited to 1 for the most.
+fn heatmap_color(t: f32) -> Rgba<u8> {
+    let mut color = HEATMAP_COLD;
+    for (channel, hot) in color.0.iter_mut().zip(HEATMAP_HOT.0) {
+        *channel = (*channel as f32 + (hot as f32 - *channel as f32) * t).round() as u8;
+    }
+    color
 }
 
+/// A piece, with its effect like "check", rendered at a width and height in pixels.
+type PieceKey = (Role, shakmaty::Color, Option<String>, u32, u32);
+
 impl BoardDrawer {
     pub fn new(flip: bool, size: u32, dark: Color, light: Color) -> Result<Self, DrawerError> {
+        let (dark, light) = (image::Rgba(dark.to_arr()), image::Rgba(light.to_arr()));
+        let background = Rgba([255, 255, 255, 255]);
         Ok(BoardDrawer {
             size,
             flip,
-            dark: image::Rgba(dark.to_arr()),
-            light: image::Rgba(light.to_arr()),
+            dark: utils::composite(dark, background),
+            light: utils::composite(light, background),
+            dark_overlay: dark,
+            light_overlay: light,
+            background,
+            material_balance: false,
+            move_times: false,
+            event_bar: false,
+            move_list: false,
+            captions: false,
+            coordinates: CoordinateMode::default(),
+            coordinate_sides: CoordinateSides::default(),
+            coordinate_font: CoordinateFont::default(),
+            frame_width: 0,
+            frame_color: Rgba([0, 0, 0, 0]),
+            check_highlight: None,
+            title_color: None,
+            name_fit: NameFit::default(),
+            bar_height_fraction: 1.0,
+            bar_backgrounds: ByColor::default(),
+            bar_text: None,
+            bar_layout: BarLayout::default(),
+            low_time_bold: false,
+            move_highlight: None,
+            texture: None,
+            hatch: false,
+            piece_scale: 1.0,
+            piece_cache: HashMap::new(),
+            move_list_cache: HashMap::new(),
+            coordinate_metrics: None,
         })
     }
 
+    /// Set where coordinates are drawn and on which sides of the board. A border changes the
+    /// size of the images drawn, so this must be called before drawing anything.
+    pub fn set_coordinates(&mut self, coordinates: CoordinateMode, sides: CoordinateSides) {
+        self.coordinates = coordinates;
+        self.coordinate_sides = sides;
+    }
+
+    /// Leave space for the material balance in player bars, so captured pieces don't overlap it.
+    pub fn set_material_balance(&mut self, material_balance: bool) {
+        self.material_balance = material_balance;
+    }
+
+    /// Leave space for the time spent on the last move left of the clock in player bars.
+    pub fn set_move_times(&mut self, move_times: bool) {
+        self.move_times = move_times;
+    }
+
+    /// Leave space for an event bar above the player bars. Like coordinates, this changes the
+    /// size of the images drawn so it must be called before drawing anything.
+    pub fn set_event_bar(&mut self, event_bar: bool) {
+        self.event_bar = event_bar;
+    }
+
+    /// Leave space for a panel with the list of moves right of the board. Like the event bar,
+    /// this changes the size of the images drawn so it must be called before drawing anything.
+    pub fn set_move_list(&mut self, move_list: bool) {
+        self.move_list = move_list;
+    }
+
+    /// Leave space for a strip with captions, like comments on moves, at the bottom. Like the
+    /// event bar, this changes the size of the images drawn so it must be called before
+    /// drawing anything.
+    pub fn set_captions(&mut self, captions: bool) {
+        self.captions = captions;
+    }
+
+    /// Color seen through transparent square colors when there is no texture. White by
+    /// default. The background is always opaque, so its alpha is ignored.
+    pub fn set_background(&mut self, color: Color) {
+        let [r, g, b, _] = color.to_arr();
+        self.background = Rgba([r, g, b, 255]);
+        self.dark = utils::composite(self.dark_overlay, self.background);
+        self.light = utils::composite(self.light_overlay, self.background);
+    }
+
+    /// Set a solid frame around the board. Like coordinates, this changes the size of the
+    /// images drawn so it must be called before drawing anything.
+    pub fn set_frame(&mut self, width: u32, color: Color) {
+        self.frame_width = width;
+        self.frame_color = image::Rgba(color.to_arr());
+    }
+
+    /// Tint the square of a king in check with a color instead of using the check SVG asset.
+    pub fn set_check_highlight(&mut self, color: Option<Color>) {
+        self.check_highlight = color.map(|c| image::Rgba(c.to_arr()));
+    }
+
+    /// Draw player titles in player bars with a color, apart from the rest of the player's
+    /// name. Titles are drawn like names if None.
+    pub fn set_title_color(&mut self, color: Option<Color>) {
+        self.title_color = color.map(|c| image::Rgba(c.to_arr()));
+    }
+
+    /// Fit player names too long for their bar by truncating them, or by shrinking them first.
+    pub fn set_name_fit(&mut self, name_fit: NameFit) {
+        self.name_fit = name_fit;
+    }
+
+    /// Set the height of player bars, as a fraction of a square, and their colors. Like
+    /// coordinates, this changes the size of the images drawn so it must be called before
+    /// drawing anything.
+    pub fn set_bar_style(&mut self, style: &BarStyle) {
+        self.bar_height_fraction = style.height_fraction;
+        self.bar_backgrounds = ByColor {
+            white: style.white_bg.as_ref().map(|c| image::Rgba(c.to_arr())),
+            black: style.black_bg.as_ref().map(|c| image::Rgba(c.to_arr())),
+        };
+        self.bar_text = style.text.as_ref().map(|c| image::Rgba(c.to_arr()));
+    }
+
+    /// Draw a bar for each player, or a single bar below the board for both. Like
+    /// coordinates, this changes the size of the images drawn so it must be called before
+    /// drawing anything.
+    pub fn set_bar_layout(&mut self, bar_layout: BarLayout) {
+        self.bar_layout = bar_layout;
+    }
+
+    /// Draw clocks of players low on time in the heaviest weight of the font, on top of the
+    /// warning color.
+    pub fn set_low_time_bold(&mut self, bold: bool) {
+        self.low_time_bold = bold;
+    }
+
+    /// Mark squares moves leave behind, like the square of a pawn taken en passant, by tinting
+    /// them with a color. They are drawn empty if None.
+    pub fn set_move_highlight(&mut self, color: Option<Color>) {
+        self.move_highlight = color.map(|c| image::Rgba(c.to_arr()));
+    }
+
+    /// Draw squares on a texture, like wood or marble, loaded from an image or SVG file and
+    /// scaled to fill the board. Square colors are blended over it, so their alpha controls how
+    /// much of the texture shows through.
+    pub fn set_texture(&mut self, path: &Path, svgs: &SVGForest) -> Result<(), DrawerError> {
+        self.texture = Some(load_image_to_fill(path, self.size, svgs)?);
+        Ok(())
+    }
+
+    /// Side of the square avatars drawn at the left edge of player bars.
+    pub fn avatar_size(&self) -> u32 {
+        self.bar_height() * 3 / 4
+    }
+
+    /// Load an avatar from an image or SVG file, scaled and cropped to the size of avatars.
+    pub fn load_avatar(&self, path: &Path, svgs: &SVGForest) -> Result<RgbaImage, DrawerError> {
+        load_image_to_fill(path, self.avatar_size(), svgs)
+    }
+
+    /// Load a watermark from an image or SVG file, scaled to width pixels wide and made
+    /// translucent by an opacity between 0.0 and 1.0.
+    pub fn load_watermark(
+        &self,
+        path: &Path,
+        width: u32,
+        opacity: f32,
+        svgs: &SVGForest,
+    ) -> Result<RgbaImage, DrawerError> {
+        let mut watermark = load_image_file(path, width, svgs)?
+            .resize(width, u32::MAX, imageops::FilterType::Triangle)
+            .to_rgba8();
+        let opacity = opacity.clamp(0.0, 1.0);
+        for pixel in watermark.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+        }
+        Ok(watermark)
+    }
+
+    /// Render text, like "Game of the Day #12", in a font size in pixels over a transparent
+    /// image at most width pixels wide. The text starts at the left edge of the image, with
+    /// the top of its capitals about the top edge.
+    pub fn render_text_overlay(
+        &mut self,
+        text: &str,
+        size: f32,
+        color: Rgba<u8>,
+        width: u32,
+        svgs: &SVGForest,
+    ) -> Result<RgbaImage, DrawerError> {
+        // Text is drawn at half the height of str_pixmap, with its baseline at 40% of it
+        let height = (size * 2.0).ceil().max(1.0) as u32;
+        let pixmap = self.str_pixmap(
+            text,
+            TextBox {
+                height,
+                width: width.max(1),
+                x: 0,
+                y: 40,
+                color,
+                background_color: color,
+                text_anchor: TextAnchor::Start,
+            },
+            svgs,
+        )?;
+        Ok(utils::pixmap_to_image(&pixmap))
+    }
+
+    /// Hatch dark squares with diagonal lines, so they can be told apart from light squares
+    /// without relying on color.
+    pub fn set_hatch(&mut self, hatch: bool) {
+        self.hatch = hatch;
+    }
+
+    /// Shrink pieces to a fraction of a square, centered in it.
+    pub fn set_piece_scale(&mut self, piece_scale: f32) {
+        self.piece_scale = piece_scale;
+        self.piece_cache.clear();
+    }
+
+    pub fn set_coordinate_font(&mut self, coordinate_font: CoordinateFont) {
+        self.coordinate_font = coordinate_font;
+        self.coordinate_metrics = None;
+    }
+
+    /// Height of coordinate labels in pixels. Auto grows slower than the board, so labels are
+    /// readable in small boards without taking over the squares of large ones. Capped at half
+    /// a square.
+    pub fn coordinate_size(&self) -> u32 {
+        let size = match self.coordinate_font.size {
+            CoordSize::Auto => 0.7 * (self.size as f32).sqrt(),
+            CoordSize::Px(px) => px,
+            CoordSize::Fraction(fraction) => fraction * self.square_size() as f32,
+        };
+        (size.round() as u32).clamp(1, self.square_size() / 2)
+    }
+
     pub fn dark_color(&mut self) -> tiny_skia::Color {
-        tiny_skia::Color::from_rgba8(self.dark[0], self.dark[1], self.dark[2], self.dark[3] * 255)
+        tiny_skia::Color::from_rgba8(self.dark[0], self.dark[1], self.dark[2], self.dark[3])
     }
 
     pub fn light_color(&mut self) -> tiny_skia::Color {
-        tiny_skia::Color::from_rgba8(
-            self.light[0],
-            self.light[1],
-            self.light[2],
-            self.light[3] * 255,
-        )
+        tiny_skia::Color::from_rgba8(self.light[0], self.light[1], self.light[2], self.light[3])
     }
 
     pub fn size(&self) -> u32 {
@@ -48,21 +408,100 @@ impl BoardDrawer {
         self.flip
     }
 
+    /// See the board from black's side if flip is true. Boards already drawn are not flipped, so
+    /// they must be drawn again.
+    pub fn set_flip(&mut self, flip: bool) {
+        self.flip = flip;
+    }
+
     pub fn image_buffer(&self) -> RgbaImage {
-        ImageBuffer::new(self.size, self.size)
+        ImageBuffer::new(self.canvas_size(), self.canvas_size())
+    }
+
+    /// Width of the border around the board: the frame and, if coordinates are drawn in the
+    /// border, space for them.
+    pub fn border_size(&self) -> u32 {
+        self.frame_width + self.coordinates_border_size()
+    }
+
+    /// Width of the part of the border next to the board where coordinates are drawn.
+    fn coordinates_border_size(&self) -> u32 {
+        match self.coordinates {
+            CoordinateMode::Border => self.square_size() / 2,
+            _ => 0,
+        }
+    }
+
+    /// Size of one side of the board including its border.
+    pub fn canvas_size(&self) -> u32 {
+        self.size + self.border_size() * 2
     }
 
     pub fn square_size(&self) -> u32 {
         self.size / 8
     }
 
+    /// Height of player bars, a fraction of a square set with set_bar_style.
+    pub fn bar_height(&self) -> u32 {
+        ((self.square_size() as f32 * self.bar_height_fraction).round() as u32).max(1)
+    }
+
+    /// Text and background colors of a player's bar, as seen over the background. Clocks are
+    /// drawn with them swapped.
+    fn bar_colors(&self, player_color: shakmaty::Color) -> (Rgba<u8>, Rgba<u8>) {
+        let (text, background) = match player_color {
+            shakmaty::Color::White => (self.dark, self.light),
+            shakmaty::Color::Black => (self.light, self.dark),
+        };
+        let background = match self.bar_backgrounds.get(player_color) {
+            Some(color) => utils::composite(*color, self.background),
+            None => background,
+        };
+        let text = match self.bar_text {
+            Some(color) => utils::composite(color, background),
+            None => text,
+        };
+        (text, background)
+    }
+
     pub fn square_image(&mut self, square: &Square) -> RgbaImage {
+        if let Some(textured) = self.textured_square(square) {
+            return textured;
+        }
         match square.is_dark() {
             true => self.dark_square(),
             false => self.light_square(),
         }
     }
 
+    /// Texture under a square as seen in the final image, with the square color blended over
+    /// it. None if there is no texture.
+    fn textured_square(&self, display: &Square) -> Option<RgbaImage> {
+        let texture = self.texture.as_ref()?;
+        let size = self.square_size();
+        let color = match display.is_dark() {
+            true => self.dark_overlay,
+            false => self.light_overlay,
+        };
+
+        // Textures may have transparent parts, which show the square color instead
+        let mut square =
+            RgbaImage::from_pixel(size, size, Rgba([color[0], color[1], color[2], 255]));
+        let x = size * u32::from(display.file());
+        let y = self.size - size * (u32::from(display.rank()) + 1);
+        utils::blend(
+            &mut square,
+            &imageops::crop_imm(texture, x, y, size, size).to_image(),
+            0,
+            0,
+        );
+
+        for pixel in square.pixels_mut() {
+            utils::blend_pixel(pixel, color);
+        }
+        Some(square)
+    }
+
     pub fn dark_square(&self) -> RgbaImage {
         ImageBuffer::from_pixel(self.square_size(), self.square_size(), self.dark)
     }
@@ -88,9 +527,15 @@ impl BoardDrawer {
             }
         });
 
-        let mut board_img = ImageBuffer::new(self.size, self.size);
-        for n in 0..9 {
-            imageops::replace(&mut board_img, &column, (n * self.square_size()).into(), 0);
+        let border = self.border_size();
+        let mut board_img = self.image_buffer();
+        for n in 0..8 {
+            imageops::replace(
+                &mut board_img,
+                &column,
+                (border + n * self.square_size()).into(),
+                border.into(),
+            );
             imageops::flip_vertical_in_place(&mut column)
         }
 
@@ -99,16 +544,7 @@ impl BoardDrawer {
                 let square = Square::from_coords(file, rank);
                 if let Some(piece) = position.board().piece_at(square) {
                     log::debug!("Drawing {:?} in {:?}", piece, square);
-                    self.draw_piece(
-                        &square,
-                        &piece.role,
-                        piece.color,
-                        false,
-                        &mut board_img,
-                        None,
-                        svgs,
-                        false,
-                    )?;
+                    self.draw_piece(&square, piece, false, &mut board_img, None, svgs)?;
                 } else {
                     self.draw_square(&square, &mut board_img, svgs)?;
                 }
@@ -117,14 +553,202 @@ impl BoardDrawer {
 
         self.draw_ranks(2, 6, &mut board_img, svgs)?;
 
-        if self.flip == true {
-            imageops::flip_horizontal_in_place(&mut board_img);
-            imageops::flip_vertical_in_place(&mut board_img);
+        if self.border_size() > 0 {
+            self.draw_border(&mut board_img, svgs)?;
         }
 
         Ok(board_img)
     }
 
+    /// Draw a border around the board, in the frame color if there is a frame. Files and ranks
+    /// are drawn on the configured sides, next to the board, if coordinates go in the border.
+    pub fn draw_border(
+        &mut self,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let border = self.border_size();
+        let canvas = self.canvas_size();
+        let color = if self.frame_width > 0 {
+            self.frame_color
+        } else {
+            self.dark
+        };
+        for (x, y, width, height) in [
+            (0, 0, canvas, border),
+            (0, canvas - border, canvas, border),
+            (0, border, border, self.size),
+            (canvas - border, border, border, self.size),
+        ] {
+            let strip = ImageBuffer::from_pixel(width, height, color);
+            imageops::replace(img, &strip, x.into(), y.into());
+        }
+
+        if self.coordinates != CoordinateMode::Border {
+            return Ok(());
+        }
+
+        let frame = self.frame_width;
+        let coordinates_border = self.coordinates_border_size();
+
+        let sides = self.coordinate_sides;
+        for n in 0..8 {
+            let (file, rank) = if self.flip {
+                (File::new(7 - n), Rank::new(n))
+            } else {
+                (File::new(n), Rank::new(7 - n))
+            };
+            let offset = border + n * self.square_size();
+            let file_offset = (coordinates_border - self.coordinate_size()) / 2;
+            let rank_offset = offset + (self.square_size() - self.coordinate_size()) / 2;
+
+            let file_pixmap =
+                self.border_coordinate_pixmap(file.char(), self.square_size(), svgs)?;
+            let rank_pixmap =
+                self.border_coordinate_pixmap(rank.char(), coordinates_border, svgs)?;
+            if sides.bottom {
+                imageops::overlay(
+                    img,
+                    &file_pixmap,
+                    offset.into(),
+                    (canvas - border + file_offset).into(),
+                );
+            }
+            if sides.top {
+                imageops::overlay(
+                    img,
+                    &file_pixmap,
+                    offset.into(),
+                    (frame + file_offset).into(),
+                );
+            }
+            if sides.left {
+                imageops::overlay(img, &rank_pixmap, frame.into(), rank_offset.into());
+            }
+            if sides.right {
+                imageops::overlay(
+                    img,
+                    &rank_pixmap,
+                    (canvas - border).into(),
+                    rank_offset.into(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A coordinate centered horizontally in an image as tall as the coordinate font.
+    fn border_coordinate_pixmap(
+        &mut self,
+        coordinate: char,
+        width: u32,
+        svgs: &SVGForest,
+    ) -> Result<RgbaImage, DrawerError> {
+        let height = self.coordinate_size();
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        self.draw_coordinate(coordinate, self.light, true, &mut pixmap, svgs)?;
+
+        ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take()).ok_or(
+            DrawerError::ImageTooBig {
+                image: coordinate.to_string(),
+            },
+        )
+    }
+
+    /// How far coordinate labels reach above and below the point text is placed at, as
+    /// fractions of the font size. Measured on every label, so they all share a baseline and
+    /// none of them is clipped, whatever the font.
+    fn coordinate_metrics(&mut self, svgs: &SVGForest) -> Result<(f32, f32), DrawerError> {
+        if let Some(metrics) = self.coordinate_metrics {
+            return Ok(metrics);
+        }
+
+        let font_size = 100.0;
+        let tree = SVGTree::Str {
+            s: COORDINATE_GLYPHS.to_string(),
+            height: font_size as u32,
+            width: font_size as u32,
+            x: 0,
+            y: 0,
+            style: TextStyle {
+                color: self.dark,
+                background_color: self.light,
+                font_weight: self.coordinate_font.weight,
+                font_size: FontSize::Unit(font_size, "px".to_string()),
+                text_anchor: TextAnchor::Start,
+            },
+        };
+        let metrics = match svgs.load_svg_tree(&tree)?.root().calculate_bbox() {
+            Some(bbox) => (
+                -bbox.y() as f32 / font_size,
+                bbox.bottom() as f32 / font_size,
+            ),
+            None => {
+                log::warn!("Failed to measure coordinates, the font may be missing glyphs");
+                (0.75, 0.25)
+            }
+        };
+        self.coordinate_metrics = Some(metrics);
+        Ok(metrics)
+    }
+
+    /// Draw a coordinate label filling the height of a pixmap, on the left side of it or
+    /// centered. The font size and position are derived from the measured labels, and the
+    /// position is rounded once, so glyphs keep the same margin at any size.
+    fn draw_coordinate(
+        &mut self,
+        coordinate: char,
+        color: Rgba<u8>,
+        centered: bool,
+        pixmap: &mut Pixmap,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let (ascent, descent) = self.coordinate_metrics(svgs)?;
+        let height = pixmap.height() as f32;
+        let margin = (COORDINATE_MARGIN * height).max(MIN_COORDINATE_MARGIN);
+        let font_size = (height - 2.0 * margin) / (ascent + descent);
+
+        let coordinate_tree = SVGTree::Str {
+            s: coordinate.to_string(),
+            height: pixmap.height(),
+            width: pixmap.width(),
+            x: 0,
+            y: 0,
+            style: TextStyle {
+                color,
+                background_color: color,
+                font_weight: self.coordinate_font.weight,
+                font_size: FontSize::Unit(font_size, "px".to_string()),
+                text_anchor: TextAnchor::Start,
+            },
+        };
+        let rtree = svgs.load_svg_tree(&coordinate_tree)?;
+        let bbox = rtree
+            .root()
+            .calculate_bbox()
+            .ok_or(DrawerError::SVGRenderError {
+                svg: coordinate.to_string(),
+            })?;
+
+        let left = if centered {
+            (pixmap.width() as f32 - bbox.width() as f32) / 2.0
+        } else {
+            margin
+        };
+        let x = (left - bbox.x() as f32).round();
+        let y = (margin + ascent * font_size).round();
+        resvg::render(
+            &rtree,
+            FitTo::Original,
+            Transform::from_translate(x, y),
+            pixmap.as_mut(),
+        )
+        .ok_or(DrawerError::SVGRenderError {
+            svg: coordinate.to_string(),
+        })
+    }
+
     pub fn draw_initial_position(&mut self, svgs: &SVGForest) -> Result<RgbaImage, DrawerError> {
         log::debug!("Drawing initial position");
         let position = Chess::default();
@@ -168,9 +792,9 @@ impl BoardDrawer {
                 let blank_to_square = if capture.is_some() { true } else { false };
 
                 if let Some(promoted) = promotion {
-                    self.draw_piece(to, promoted, color, blank_to_square, img, None, svgs, false)?;
+                    self.draw_promoted_piece(to, *promoted, color, blank_to_square, img, svgs)?;
                 } else {
-                    self.draw_piece(to, role, color, blank_to_square, img, None, svgs, false)?;
+                    self.draw_piece(to, role.of(color), blank_to_square, img, None, svgs)?;
                 }
             }
             Move::EnPassant { from, to } => {
@@ -179,181 +803,472 @@ impl BoardDrawer {
                 // This pawn is in the same Rank as from
                 // And the same File as to
                 let taken_pawn = Square::from_coords(to.file(), from.rank());
-                self.draw_square(&taken_pawn, img, svgs)?;
+                match self.move_highlight {
+                    Some(highlight) => {
+                        self.draw_highlighted_square(&taken_pawn, highlight, img, svgs)?
+                    }
+                    None => self.draw_square(&taken_pawn, img, svgs)?,
+                }
 
-                self.draw_piece(to, &Role::Pawn, color, true, img, None, svgs, false)?;
+                self.draw_piece(to, Role::Pawn.of(color), true, img, None, svgs)?;
             }
             Move::Castle { king, rook } => {
-                // King and Rook initial squares, e.g. E1 and H1 respectively.
-                // Need to calculate where the pieces end up before drawing.
-                let offset = if rook.file() > king.file() { 1 } else { -1 };
-
+                // King and Rook initial squares, e.g. E1 and H1 respectively. Both are cleared
+                // before drawing, as in Chess960 either piece may end up on the other's square.
                 self.draw_square(king, img, svgs)?;
                 self.draw_square(rook, img, svgs)?;
 
-                let rook_square = king.offset(offset * 1).unwrap();
-                let king_square = king.offset(offset * 2).unwrap();
-                self.draw_piece(
-                    &king_square,
-                    &Role::King,
-                    color,
-                    true,
-                    img,
-                    None,
-                    svgs,
-                    false,
-                )?;
-                self.draw_piece(
-                    &rook_square,
-                    &Role::Rook,
-                    color,
-                    true,
-                    img,
-                    None,
-                    svgs,
-                    false,
-                )?;
+                let (king_square, rook_square) = utils::castling_destinations(*king, *rook);
+                self.draw_piece(&king_square, Role::King.of(color), true, img, None, svgs)?;
+                self.draw_piece(&rook_square, Role::Rook.of(color), true, img, None, svgs)?;
             }
             Move::Put { role, to } => {
-                self.draw_piece(to, role, color, true, img, None, svgs, false)?;
+                self.draw_piece(to, role.of(color), true, img, None, svgs)?;
             }
         };
 
-        if self.flip == true {
-            imageops::flip_horizontal_in_place(img);
-            imageops::flip_vertical_in_place(img);
-        }
-
         Ok(())
     }
 
-    pub fn draw_checked_king(
+    /// Draw a step of pieces sliding from one square to another, t going from 0 on the first
+    /// square to 1 on the second. pieces is the board without the sliding pieces, which is
+    /// drawn under them on the squares they cover and on the squares in redraw. Returns the
+    /// squares covered by the sliding pieces, so they can be drawn again in the next step.
+    pub fn draw_sliding_pieces(
         &mut self,
-        mut piece: utils::PieceInBoard,
+        slides: &[(shakmaty::Piece, Square, Square)],
+        t: f32,
+        pieces: &shakmaty::Board,
+        redraw: &[Square],
         img: &mut RgbaImage,
         svgs: &SVGForest,
-    ) -> Result<(), DrawerError> {
-        if self.flip == true {
-            piece.flip_both()
-        };
+    ) -> Result<Vec<Square>, DrawerError> {
+        let positions: Vec<(shakmaty::Piece, u32, u32)> = slides
+            .iter()
+            .map(|(piece, from, to)| {
+                let (from_x, from_y) = self.square_position(from);
+                let (to_x, to_y) = self.square_position(to);
+                let lerp = |a: u32, b: u32| (a as f32 + (b as f32 - a as f32) * t).round() as u32;
+                (*piece, lerp(from_x, to_x), lerp(from_y, to_y))
+            })
+            .collect();
 
-        self.draw_piece(
-            &piece.square,
-            &Role::King,
-            piece.color,
-            true,
-            img,
-            Some("check".to_string()),
-            svgs,
-            true,
-        )
+        let mut covered = Vec::new();
+        for (_, x, y) in positions.iter() {
+            for square in self.squares_under(*x, *y) {
+                if !covered.contains(&square) {
+                    covered.push(square);
+                }
+            }
+        }
+
+        for square in redraw.iter().chain(covered.iter()) {
+            match pieces.piece_at(*square) {
+                Some(piece) => self.draw_piece(square, piece, true, img, None, svgs)?,
+                None => self.draw_square(square, img, svgs)?,
+            }
+        }
+        for (piece, x, y) in positions {
+            self.draw_piece_at(piece, x, y, img, svgs)?;
+        }
+
+        Ok(covered)
     }
 
-    pub fn draw_win_king(
+    /// Draw a piece the size of a square with its top left corner at any pixel, blended over
+    /// the image instead of replacing a square.
+    pub fn draw_piece_at(
         &mut self,
-        square: &Square,
-        color: shakmaty::Color,
+        piece: shakmaty::Piece,
+        x: u32,
+        y: u32,
         img: &mut RgbaImage,
         svgs: &SVGForest,
     ) -> Result<(), DrawerError> {
-        self.draw_piece(
-            square,
-            &Role::King,
-            color,
-            true,
-            img,
-            Some("win".to_string()),
-            svgs,
-            false,
-        )
+        let size = self.square_size();
+        let pixmap = self.cached_piece(piece.color, piece.role, None, size, size, svgs)?;
+        utils::blend(img, &utils::pixmap_to_image(pixmap), x, y);
+        Ok(())
     }
 
-    pub fn draw_square(
+    /// Squares covered, even partly, by a square sized area with its top left corner at x, y.
+    pub fn squares_under(&self, x: u32, y: u32) -> Vec<Square> {
+        let size = self.square_size();
+        Square::ALL
+            .iter()
+            .filter(|square| {
+                let (square_x, square_y) = self.square_position(square);
+                square_x < x + size
+                    && x < square_x + size
+                    && square_y < y + size
+                    && y < square_y + size
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Draw a piece a pawn promoted to with the pieces family's promotion artwork. Families
+    /// without it draw the plain piece.
+    fn draw_promoted_piece(
         &mut self,
         square: &Square,
+        role: Role,
+        color: shakmaty::Color,
+        blank_target: bool,
         img: &mut RgbaImage,
         svgs: &SVGForest,
     ) -> Result<(), DrawerError> {
-        log::debug!("Drawing square: {}", square);
-        let pixmap =
-            self.square_pixmap(self.square_size(), self.square_size(), square, svgs, false)?;
-        let mut square_img = ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take())
-            .ok_or(DrawerError::ImageTooBig {
-                image: format!("{}x{} square", self.square_size(), self.square_size()),
-            })?;
-
-        let x = self.square_size() * u32::from(square.file());
-        let y = self.size - self.square_size() * (u32::from(square.rank()) + 1);
-
-        if self.flip == true {
-            imageops::flip_vertical_in_place(&mut square_img);
-            imageops::flip_horizontal_in_place(&mut square_img);
+        let promo = Some("promo".to_string());
+        match self.draw_piece(square, role.of(color), blank_target, img, promo, svgs) {
+            Err(DrawerError::SVGNotFound { svg }) => {
+                log::debug!("{} not found, drawing the promoted piece instead", svg);
+                self.draw_piece(square, role.of(color), blank_target, img, None, svgs)
+            }
+            result => result,
         }
-
-        imageops::overlay(img, &square_img, x.into(), y.into());
-
-        Ok(())
     }
 
-    pub fn draw_piece(
+    /// Draw a king in check with the pieces family's check artwork, or on a tinted square if a
+    /// check highlight is set or the family has no such artwork.
+    pub fn draw_checked_king(
         &mut self,
-        square: &Square,
-        role: &Role,
-        color: shakmaty::Color,
-        blank_target: bool,
+        piece: utils::PieceInBoard,
         img: &mut RgbaImage,
-        additional: Option<String>,
         svgs: &SVGForest,
-        skip_flip: bool,
     ) -> Result<(), DrawerError> {
-        log::debug!("Drawing {:?} {:?} on {:?}", color, role, square);
+        let king = shakmaty::Piece {
+            color: piece.color,
+            role: Role::King,
+        };
+        match self.check_highlight {
+            Some(highlight) => {
+                self.draw_highlighted_piece(&piece.square, king, highlight, img, svgs)
+            }
+            None => match self.draw_piece(
+                &piece.square,
+                Role::King.of(piece.color),
+                true,
+                img,
+                Some("check".to_string()),
+                svgs,
+            ) {
+                // Sprites usually only have the 12 pieces, so tint the king's square instead
+                Err(DrawerError::SVGNotFound { svg }) => {
+                    log::debug!("{} not found, tinting the king's square instead", svg);
+                    self.draw_highlighted_piece(
+                        &piece.square,
+                        king,
+                        utils::CHECK_RAY_COLOR,
+                        img,
+                        svgs,
+                    )
+                }
+                result => result,
+            },
+        }
+    }
+
+    /// Draw a checkmated king with the pieces family's mate artwork. Families without it fall
+    /// back to drawing the king like draw_checked_king.
+    pub fn draw_mated_king(
+        &mut self,
+        piece: utils::PieceInBoard,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        match self.draw_piece(
+            &piece.square,
+            Role::King.of(piece.color),
+            true,
+            img,
+            Some("mate".to_string()),
+            svgs,
+        ) {
+            Err(DrawerError::SVGNotFound { svg }) => {
+                log::debug!("{} not found, drawing king in check instead", svg);
+                self.draw_checked_king(piece, img, svgs)
+            }
+            result => result,
+        }
+    }
+
+    /// Tint the squares between a piece giving check and the checked king. Knights and pawns
+    /// give check from squares next to the king or without a line to it, so nothing is drawn
+    /// for them.
+    pub fn draw_check_ray(
+        &mut self,
+        checker: Square,
+        king: Square,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let highlight = self.check_highlight.unwrap_or(utils::CHECK_RAY_COLOR);
+        for square in attacks::between(checker, king) {
+            log::debug!("Drawing check ray on {:?}", square);
+            self.draw_highlighted_square(&square, highlight, img, svgs)?;
+        }
+        Ok(())
+    }
+
+    /// Draw a piece giving check on its square tinted like the check ray.
+    pub fn draw_checker(
+        &mut self,
+        square: Square,
+        piece: shakmaty::Piece,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let highlight = self.check_highlight.unwrap_or(utils::CHECK_RAY_COLOR);
+        self.draw_highlighted_piece(&square, piece, highlight, img, svgs)
+    }
+
+    /// Draw an empty square tinted with a highlight color.
+    fn draw_highlighted_square(
+        &mut self,
+        square: &Square,
+        highlight: Rgba<u8>,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let size = self.square_size();
+        let pixmap = self.square_pixmap(size, size, square, svgs)?;
+        let mut square_img = utils::pixmap_to_image(&pixmap);
+        utils::blend(
+            &mut square_img,
+            &ImageBuffer::from_pixel(size, size, highlight),
+            0,
+            0,
+        );
+
+        let (x, y) = self.square_position(square);
+        imageops::replace(img, &square_img, x.into(), y.into());
+
+        Ok(())
+    }
+
+    /// Draw a piece on a square tinted with a highlight color, blended with its alpha so the
+    /// square's color can show through.
+    pub fn draw_highlighted_piece(
+        &mut self,
+        square: &Square,
+        piece: shakmaty::Piece,
+        highlight: Rgba<u8>,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let shakmaty::Piece { color, role } = piece;
+        log::debug!("Drawing highlighted {:?} {:?} on {:?}", color, role, square);
+        let size = self.square_size();
+        let square_pixmap = self.square_pixmap(size, size, square, svgs)?;
+        let mut piece_img = utils::pixmap_to_image(&square_pixmap);
+        utils::blend(
+            &mut piece_img,
+            &ImageBuffer::from_pixel(size, size, highlight),
+            0,
+            0,
+        );
+
+        let pixmap = self.cached_piece(color, role, None, size, size, svgs)?;
+        utils::blend(&mut piece_img, &utils::pixmap_to_image(pixmap), 0, 0);
+
+        let (x, y) = self.square_position(square);
+        imageops::replace(img, &piece_img, x.into(), y.into());
+
+        Ok(())
+    }
+
+    /// Tint the squares of recent moves, given oldest first, with the pieces on them drawn over
+    /// the tint. The newest move gets the full alpha of color and each older one a length-th
+    /// less, so the trail fades out. Squares shared by several moves are tinted for the newest.
+    pub fn draw_trail(
+        &mut self,
+        trail: &VecDeque<Vec<(Square, Square)>>,
+        length: usize,
+        color: Rgba<u8>,
+        pieces: &shakmaty::Board,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let mut drawn = Vec::new();
+        for (age, paths) in trail.iter().rev().enumerate().take(length) {
+            let alpha = u32::from(color[3]) * (length - age) as u32 / length as u32;
+            let highlight = Rgba([color[0], color[1], color[2], alpha as u8]);
+            for square in paths.iter().flat_map(|(from, to)| [*to, *from]) {
+                if drawn.contains(&square) {
+                    continue;
+                }
+                match pieces.piece_at(square) {
+                    Some(piece) => {
+                        self.draw_highlighted_piece(&square, piece, highlight, img, svgs)?
+                    }
+                    None => self.draw_highlighted_square(&square, highlight, img, svgs)?,
+                }
+                drawn.push(square);
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the winning king with the pieces family's win artwork. Families without it keep the
+    /// king as it is.
+    pub fn draw_win_king(
+        &mut self,
+        piece: utils::PieceInBoard,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        match self.draw_piece(
+            &piece.square,
+            Role::King.of(piece.color),
+            true,
+            img,
+            Some("win".to_string()),
+            svgs,
+        ) {
+            Err(DrawerError::SVGNotFound { svg }) => {
+                log::debug!("{} not found, drawing the king instead", svg);
+                self.redraw_piece(piece, img, svgs)
+            }
+            result => result,
+        }
+    }
+
+    /// Redraw a piece over its square, like the winning king of a board that only contains the
+    /// squares that changed.
+    pub fn redraw_piece(
+        &mut self,
+        piece: utils::PieceInBoard,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        self.draw_piece(
+            &piece.square,
+            piece.role.of(piece.color),
+            true,
+            img,
+            None,
+            svgs,
+        )
+    }
+
+    /// Draw a decoration SVG, like crown.svg, scaled to a square and on top of its piece. The
+    /// square must already be drawn, as the decoration is mostly transparent.
+    pub fn draw_piece_decoration(
+        &mut self,
+        square: Square,
+        name: &str,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let decoration_tree = SVGTree::Termination {
+            reason: name.to_string(),
+            color: None,
+        };
+        let rtree = svgs.load_svg_tree(&decoration_tree)?;
+        let size = self.square_size();
+        let mut pixmap = Pixmap::new(size, size).unwrap();
+        resvg::render(
+            &rtree,
+            FitTo::Height(size),
+            Transform::identity(),
+            pixmap.as_mut(),
+        )
+        .ok_or(DrawerError::SVGRenderError {
+            svg: name.to_string(),
+        })?;
+
+        let (x, y) = self.square_position(&square);
+        utils::blend(img, &utils::pixmap_to_image(&pixmap), x, y);
+
+        Ok(())
+    }
+
+    pub fn draw_square(
+        &mut self,
+        square: &Square,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        log::debug!("Drawing square: {}", square);
+        let pixmap = self.square_pixmap(self.square_size(), self.square_size(), square, svgs)?;
+        let square_img = ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take())
+            .ok_or(DrawerError::ImageTooBig {
+                image: format!("{}x{} square", self.square_size(), self.square_size()),
+            })?;
+
+        let (x, y) = self.square_position(square);
+        imageops::overlay(img, &square_img, x.into(), y.into());
+
+        Ok(())
+    }
+
+    /// Top left corner of a square in the final image. Flipped boards are seen from black's
+    /// side, so only where squares are drawn changes, never what is drawn in them.
+    pub fn square_position(&self, square: &Square) -> (u32, u32) {
+        let display = self.display_square(square);
+        let size = self.square_size();
+        (
+            self.border_size() + size * u32::from(display.file()),
+            self.border_size() + self.size - size * (u32::from(display.rank()) + 1),
+        )
+    }
+
+    /// Square found at the position of square in an unflipped board, e.g. H8 for A1 if flipped.
+    fn display_square(&self, square: &Square) -> Square {
+        if self.flip {
+            square.flip_vertical().flip_horizontal()
+        } else {
+            *square
+        }
+    }
+
+    pub fn draw_piece(
+        &mut self,
+        square: &Square,
+        piece: shakmaty::Piece,
+        blank_target: bool,
+        img: &mut RgbaImage,
+        additional: Option<String>,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        log::debug!("Drawing {:?} on {:?}", piece, square);
         if blank_target {
             self.draw_square(square, img, svgs)?;
         }
 
-        let x = self.square_size() * u32::from(square.file());
-        let y = self.size - self.square_size() * (u32::from(square.rank()) + 1);
+        let (x, y) = self.square_position(square);
         log::debug!("Piece coordinates: ({}, {})", x, y);
 
         let height = self.square_size();
-        let mut resized_piece = self.piece_image(
-            color, square, role, height, height, additional, svgs, skip_flip,
-        )?;
-
-        if self.flip == true && skip_flip == false {
-            imageops::flip_vertical_in_place(&mut resized_piece);
-            imageops::flip_horizontal_in_place(&mut resized_piece);
-        }
-
-        imageops::replace(img, &resized_piece, x.into(), y.into());
+        let image = self.piece_image(piece, square, height, height, additional, svgs)?;
+        imageops::replace(img, &image, x.into(), y.into());
 
         Ok(())
     }
 
-    pub fn piece_image<'a>(
-        &'a mut self,
-        piece_color: shakmaty::Color,
-        square: &'a Square,
-        role: &'a Role,
+    pub fn piece_image(
+        &mut self,
+        piece: shakmaty::Piece,
+        square: &Square,
         height: u32,
         width: u32,
         additional: Option<String>,
         svgs: &SVGForest,
-        skip_flip: bool,
     ) -> Result<RgbaImage, DrawerError> {
-        let fit_to = FitTo::Height(height);
-        let piece_tree = SVGTree::Piece {
-            role: *role,
+        let shakmaty::Piece {
             color: piece_color,
-            additional: additional,
-        };
-        let rtree = svgs.load_svg_tree(&piece_tree)?;
-        let mut pixmap = self.square_pixmap(height, width, square, svgs, skip_flip)?;
-        resvg::render(&rtree, fit_to, Transform::identity(), pixmap.as_mut()).ok_or(
-            DrawerError::SVGRenderError {
-                svg: format!("{}_{}.svg", piece_color.char(), role.char()),
-            },
-        )?;
+            role,
+        } = piece;
+        let mut pixmap = self.square_pixmap(height, width, square, svgs)?;
+        let piece = self.cached_piece(piece_color, role, additional, height, width, svgs)?;
+        pixmap.draw_pixmap(
+            0,
+            0,
+            piece.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            None,
+        );
 
         ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take()).ok_or(
             DrawerError::ImageTooBig {
@@ -362,48 +1277,70 @@ impl BoardDrawer {
         )
     }
 
+    /// Render a piece on a transparent pixmap, or reuse the one rendered for an earlier frame.
+    /// A game only needs a few pieces at a single size, so pieces are parsed and rendered once.
+    fn cached_piece(
+        &mut self,
+        color: shakmaty::Color,
+        role: Role,
+        additional: Option<String>,
+        height: u32,
+        width: u32,
+        svgs: &SVGForest,
+    ) -> Result<&Pixmap, DrawerError> {
+        let key = (role, color, additional, height, width);
+        if !self.piece_cache.contains_key(&key) {
+            let piece_tree = SVGTree::Piece {
+                role,
+                color,
+                additional: key.2.clone(),
+            };
+            let piece = svgs.load_piece(&piece_tree)?;
+            let mut pixmap = Pixmap::new(width, height).ok_or(DrawerError::ImageTooBig {
+                image: format!("{}_{}.svg", color.char(), role.char()),
+            })?;
+            self.render_piece(&piece, &mut pixmap)
+                .ok_or(DrawerError::SVGRenderError {
+                    svg: format!("{}_{}.svg", color.char(), role.char()),
+                })?;
+            self.piece_cache.insert(key.clone(), pixmap);
+        }
+
+        Ok(&self.piece_cache[&key])
+    }
+
+    /// Render a piece on a square's pixmap, scaled to the piece scale and centered.
+    fn render_piece(&self, piece: &PieceSource, pixmap: &mut Pixmap) -> Option<()> {
+        let height = ((pixmap.height() as f32 * self.piece_scale).round() as u32).max(1);
+        let width = piece.aspect_ratio() * height as f64;
+        let x = (pixmap.width() as f64 - width) / 2.0;
+        let y = (pixmap.height() - height) as f64 / 2.0;
+
+        render_piece_at(piece, height, x as f32, y as f32, pixmap)
+    }
+
     pub fn coordinate_pixmap(
         &mut self,
         coordinate: char,
         square: &Square,
         height: u32,
         width: u32,
-        x: u32,
-        y: u32,
         svgs: &SVGForest,
     ) -> Result<Pixmap, DrawerError> {
         log::debug!("Generating svg text: {}", coordinate);
         let mut pixmap = Pixmap::new(width, height).unwrap();
-        let (square_color, coord_color) = match square.is_dark() {
-            true => {
-                pixmap.fill(self.dark_color());
-                (self.dark, self.light)
-            }
-            false => {
-                pixmap.fill(self.light_color());
-                (self.light, self.dark)
-            }
-        };
-        let coordinate_tree = SVGTree::Str {
-            s: coordinate.to_string(),
-            string_color: coord_color,
-            background_color: square_color,
-            height,
-            width,
-            x,
-            y,
-            font_weight: FontWeight::Bold,
-            font_size: FontSize::Unit(height as f32, "px".to_string()),
+        let coord_color = match square.is_dark() {
+            true => self.light,
+            false => self.dark,
         };
-
-        let rtree = svgs.load_svg_tree(&coordinate_tree)?;
-
-        let fit_to = FitTo::Height(height);
-        resvg::render(&rtree, fit_to, Transform::identity(), pixmap.as_mut()).ok_or(
-            DrawerError::SVGRenderError {
-                svg: coordinate.to_string(),
-            },
-        )?;
+        // Labels are drawn on top of their square, so a texture can show through around them
+        if self.texture.is_none() {
+            match square.is_dark() {
+                true => pixmap.fill(self.dark_color()),
+                false => pixmap.fill(self.light_color()),
+            }
+        }
+        self.draw_coordinate(coordinate, coord_color, false, &mut pixmap, svgs)?;
 
         Ok(pixmap)
     }
@@ -414,55 +1351,78 @@ impl BoardDrawer {
         width: u32,
         square: &Square,
         svgs: &SVGForest,
-        skip_flip: bool,
     ) -> Result<Pixmap, DrawerError> {
         let mut pixmap = Pixmap::new(width, height).unwrap();
-        match square.is_dark() {
-            true => pixmap.fill(self.dark_color()),
-            false => pixmap.fill(self.light_color()),
-        };
-        let flip = self.flip && !skip_flip;
-        if utils::has_coordinate(square, flip) {
-            if (square.rank() == Rank::First && self.flip == false)
-                || (square.rank() == Rank::Eighth && self.flip == true)
-            {
-                let file_pixmap = self.coordinate_pixmap(
-                    square.file().char(),
+        // Coordinates go on the squares at the edges of the final image
+        let display = self.display_square(square);
+        let label = *square;
+        match self.textured_square(&display) {
+            Some(textured) if textured.dimensions() == (width, height) => {
+                // Textured squares are opaque, so they don't need to be premultiplied
+                pixmap.data_mut().copy_from_slice(textured.as_raw());
+            }
+            _ => match square.is_dark() {
+                true => pixmap.fill(self.dark_color()),
+                false => pixmap.fill(self.light_color()),
+            },
+        };
+        if self.hatch && square.is_dark() {
+            let square_size = self.square_size() as f32;
+            let hatch_tree = SVGTree::Hatch {
+                spacing: square_size / HATCH_LINES as f32,
+                width: (square_size * 0.04).max(1.0),
+                size: width.max(height),
+                color: HATCH_COLOR,
+            };
+            let rtree = svgs.load_svg_tree(&hatch_tree)?;
+            resvg::render(
+                &rtree,
+                FitTo::Original,
+                Transform::identity(),
+                pixmap.as_mut(),
+            )
+            .ok_or(DrawerError::SVGRenderError {
+                svg: format!("{} hatch", square),
+            })?;
+        }
+        if self.coordinates == CoordinateMode::Inside {
+            let coordinate_size = self.coordinate_size();
+            let far = self.square_size() - coordinate_size;
+            let center = (self.square_size() - coordinate_size) / 2;
+
+            let mut labels = Vec::new();
+            if self.coordinate_sides.bottom && display.rank() == Rank::First {
+                labels.push((label.file().char(), far, far));
+            }
+            if self.coordinate_sides.top && display.rank() == Rank::Eighth {
+                labels.push((label.file().char(), center, 0));
+            }
+            if self.coordinate_sides.left && display.file() == File::A {
+                labels.push((label.rank().char(), 0, 0));
+            }
+            if self.coordinate_sides.right && display.file() == File::H {
+                labels.push((label.rank().char(), far, 0));
+            }
+
+            let paint = PixmapPaint::default();
+            let transform = Transform::default();
+            for (coordinate, x, y) in labels {
+                let coordinate_pixmap = self.coordinate_pixmap(
+                    coordinate,
                     square,
-                    self.size / 32,
-                    self.size / 32,
-                    5,
-                    75,
+                    coordinate_size,
+                    coordinate_size,
                     svgs,
                 )?;
-                let paint = PixmapPaint::default();
-                let transform = Transform::default();
                 pixmap.draw_pixmap(
-                    (self.square_size() - self.size / 32) as i32,
-                    (self.square_size() - self.size / 32) as i32,
-                    file_pixmap.as_ref(),
+                    x as i32,
+                    y as i32,
+                    coordinate_pixmap.as_ref(),
                     &paint,
                     transform,
                     None,
                 );
             }
-
-            if (square.file() == File::A && self.flip == false)
-                || (square.file() == File::H && self.flip == true)
-            {
-                let rank_pixmap = self.coordinate_pixmap(
-                    square.rank().char(),
-                    square,
-                    self.size / 32,
-                    self.size / 32,
-                    5,
-                    75,
-                    svgs,
-                )?;
-                let paint = PixmapPaint::default();
-                let transform = Transform::default();
-                pixmap.draw_pixmap(0, 0, rank_pixmap.as_ref(), &paint, transform, None);
-            }
         }
 
         Ok(pixmap)
@@ -470,250 +1430,3207 @@ impl BoardDrawer {
 
     pub fn str_pixmap(
         &mut self,
-        height: u32,
-        width: u32,
-        x: u32,
-        y: u32,
         s: &str,
-        str_color: Rgba<u8>,
-        background_color: Rgba<u8>,
+        text_box: TextBox,
         svgs: &SVGForest,
     ) -> Result<Pixmap, DrawerError> {
-        let mut pixmap = Pixmap::new(width, height).unwrap();
-
+        let TextBox {
+            height,
+            width,
+            x,
+            y,
+            color,
+            background_color,
+            text_anchor,
+        } = text_box;
         let str_tree = SVGTree::Str {
             s: s.to_string(),
-            string_color: str_color,
-            background_color: background_color,
             height,
             width,
             x,
             y,
-            font_weight: FontWeight::Bold,
-            font_size: FontSize::Unit(height as f32 * 0.5, "px".to_string()),
+            style: TextStyle {
+                color,
+                background_color,
+                font_weight: FontWeight::Bold,
+                font_size: FontSize::Unit(height as f32 * 0.5, "px".to_string()),
+                text_anchor,
+            },
         };
 
-        let rtree = svgs.load_svg_tree(&str_tree)?;
-
-        let fit_to = FitTo::Height(height);
-        resvg::render(&rtree, fit_to, Transform::identity(), pixmap.as_mut())
-            .ok_or(DrawerError::SVGRenderError { svg: s.to_string() })?;
-
-        Ok(pixmap)
+        render_str_tree(&str_tree, width, height, svgs)
     }
 
     pub fn draw_player_bar(
         &mut self,
-        player: &str,
+        player: &PlayerLabel,
         player_color: shakmaty::Color,
-        bottom: bool,
+        captured: &ByRole<u8>,
+        avatar: Option<&RgbaImage>,
         img: &mut RgbaImage,
         svgs: &SVGForest,
     ) -> Result<(), DrawerError> {
-        let mut pixmap = Pixmap::new(self.size, self.square_size()).unwrap();
-        let (color, background_color, y) = match player_color {
-            shakmaty::Color::White => {
-                pixmap.fill(self.light_color());
-                (self.dark, self.light, 65)
-            }
-            shakmaty::Color::Black => {
-                pixmap.fill(self.dark_color());
-                (self.light, self.dark, 65)
-            }
-        };
-
-        let player_pixmap = self.str_pixmap(
-            self.square_size(),
-            self.size,
-            2,
-            y,
-            player,
-            color,
-            background_color,
-            svgs,
-        )?;
+        let bar_height = self.bar_height();
+        let mut pixmap = Pixmap::new(self.bar_width(player_color), bar_height).unwrap();
+        let (color, background_color) = self.bar_colors(player_color);
+        pixmap.fill(skia_color(background_color));
 
+        // Avatars are drawn in a square at the start of the bar, and labels after them. In a
+        // shared bar, names have a row of their own above the clock
+        let label_x = match avatar {
+            Some(_) => bar_height,
+            None => 0,
+        };
+        let limit = match self.bar_layout {
+            BarLayout::Double => self.bar_text_limit(player_color),
+            BarLayout::Single => self.bar_width(player_color) - bar_height / 8,
+        };
+        let (label_pixmap, height, text_end) =
+            self.fitted_label_pixmap(player, limit - label_x, color, background_color, svgs)?;
+        let text_end = text_end + label_x;
+        // Shrunk labels keep the baseline of full size ones
         let paint = PixmapPaint::default();
         let transform = Transform::default();
-        pixmap.draw_pixmap(0, 0, player_pixmap.as_ref(), &paint, transform, None);
-
-        let player_image = ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take())
-            .ok_or(DrawerError::ImageTooBig {
-                image: format!("{}.svg", player),
-            })?;
+        pixmap.draw_pixmap(
+            label_x as i32,
+            ((self.label_height() - height) * BAR_TEXT_Y / 100) as i32,
+            label_pixmap.as_ref(),
+            &paint,
+            transform,
+            None,
+        );
 
-        let y = if bottom == true {
-            self.size + self.square_size()
-        } else {
-            0
+        // Captured pieces follow the name, or go under it, left of the clock, in a shared bar
+        let captured_x = match self.bar_layout {
+            BarLayout::Double => text_end + bar_height / 4,
+            BarLayout::Single => label_x + bar_height / 8,
         };
+        self.draw_captured_pieces(captured, !player_color, captured_x, &mut pixmap, svgs)?;
+
+        let mut player_image =
+            ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take()).ok_or(
+                DrawerError::ImageTooBig {
+                    image: format!("{}.svg", player),
+                },
+            )?;
+
+        if let Some(avatar) = avatar {
+            let size = self.avatar_size();
+            let margin = (bar_height - size) / 2;
+            if avatar.dimensions() == (size, size) {
+                imageops::overlay(&mut player_image, avatar, margin.into(), margin.into());
+            } else {
+                let avatar = DynamicImage::ImageRgba8(avatar.clone())
+                    .resize_to_fill(size, size, imageops::FilterType::Triangle)
+                    .to_rgba8();
+                imageops::overlay(&mut player_image, &avatar, margin.into(), margin.into());
+            }
+        }
 
-        log::debug!("Bottom: {:?}, y: {}", bottom, y);
-        imageops::overlay(img, &player_image, 0, y.into());
+        let (x, y) = self.bar_origin(player_color);
+        log::debug!("Player bar of {:?} at x: {}, y: {}", player_color, x, y);
+        imageops::overlay(img, &player_image, x.into(), y.into());
 
         Ok(())
     }
 
-    pub fn draw_player_clock(
-        &mut self,
-        clock: &str,
-        player_color: shakmaty::Color,
-        bottom: bool,
-        img: &mut RgbaImage,
-        svgs: &SVGForest,
-    ) -> Result<(), DrawerError> {
-        let mut pixmap = Pixmap::new(self.square_size() * 2, self.square_size() * 3 / 4).unwrap();
-        let (color, background_color) = match player_color {
-            shakmaty::Color::White => {
-                pixmap.fill(self.dark_color());
-                (self.light, self.dark)
+    /// Top left corner of a player's bar in images laid out by add_bar_space.
+    fn bar_origin(&self, player_color: shakmaty::Color) -> (u32, u32) {
+        match self.bar_layout {
+            BarLayout::Double => {
+                let bottom = match player_color {
+                    shakmaty::Color::White => !self.flip,
+                    shakmaty::Color::Black => self.flip,
+                };
+                (0, self.bar_y(bottom))
             }
-            shakmaty::Color::Black => {
-                pixmap.fill(self.light_color());
-                (self.dark, self.light)
+            BarLayout::Single => {
+                let x = match player_color {
+                    shakmaty::Color::White => 0,
+                    shakmaty::Color::Black => self.canvas_size() / 2,
+                };
+                (x, self.event_bar_height() + self.canvas_size())
             }
-        };
-
-        let clock_pixmap = self.str_pixmap(
-            self.square_size() * 3 / 4,
-            self.square_size() * 2,
-            10,
-            65,
-            clock,
-            color,
-            background_color,
-            svgs,
-        )?;
+        }
+    }
 
-        let paint = PixmapPaint::default();
-        let transform = Transform::default();
-        pixmap.draw_pixmap(0, 0, clock_pixmap.as_ref(), &paint, transform, None);
+    /// Width of a player's bar: the whole image, or one half of it in a shared bar.
+    fn bar_width(&self, player_color: shakmaty::Color) -> u32 {
+        match (self.bar_layout, player_color) {
+            (BarLayout::Double, _) => self.canvas_size(),
+            (BarLayout::Single, shakmaty::Color::White) => self.canvas_size() / 2,
+            (BarLayout::Single, shakmaty::Color::Black) => {
+                self.canvas_size() - self.canvas_size() / 2
+            }
+        }
+    }
 
-        let player_image = ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take())
-            .ok_or(DrawerError::ImageTooBig {
-                image: format!("{}.svg", clock),
-            })?;
+    /// Height of the labels with player names: the whole bar, or its top half in a shared bar.
+    fn label_height(&self) -> u32 {
+        match self.bar_layout {
+            BarLayout::Double => self.bar_height(),
+            BarLayout::Single => self.bar_height() / 2,
+        }
+    }
+
+    /// Top left corner of the box a player's clock is drawn in, relative to their bar, and its
+    /// width and height. Clocks are at the right end of the bar, and in a shared bar, under the
+    /// name.
+    fn clock_rect(&self, player_color: shakmaty::Color) -> (u32, u32, u32, u32) {
+        let bar_height = self.bar_height();
+        match self.bar_layout {
+            BarLayout::Double => (
+                self.canvas_size() - bar_height * 17 / 8,
+                bar_height / 8,
+                bar_height * 2,
+                bar_height * 3 / 4,
+            ),
+            BarLayout::Single => {
+                let width = bar_height * 4 / 3;
+                (
+                    self.bar_width(player_color) - bar_height / 8 - width,
+                    bar_height / 2,
+                    width,
+                    bar_height * 7 / 16,
+                )
+            }
+        }
+    }
+
+    /// Top left corner of the box left of a player's clock where the time spent on their last
+    /// move is drawn, relative to their bar, and its width and height.
+    fn move_time_rect(&self, player_color: shakmaty::Color) -> (u32, u32, u32, u32) {
+        let (x, y, _, height) = self.clock_rect(player_color);
+        let width = height * 5 / 3;
+        (x - width, y, width, height)
+    }
+
+    /// Top left corner of the square slot left of a player's clock, or of their move time if
+    /// drawn, relative to their bar, and its side. The material balance, or the flag of a
+    /// fallen clock, is drawn in it.
+    fn clock_slot(&self, player_color: shakmaty::Color) -> (u32, u32, u32) {
+        let (x, y, _, height) = self.clock_rect(player_color);
+        let x = match self.move_times {
+            true => self.move_time_rect(player_color).0,
+            false => x,
+        };
+        match self.bar_layout {
+            BarLayout::Double => (x - self.bar_height() * 3 / 4, y, height),
+            BarLayout::Single => (x - height, y, height),
+        }
+    }
 
-        let y = if bottom == true {
-            self.size + self.square_size()
+    /// Row where a player bar starts, under the event bar if there is one.
+    fn bar_y(&self, bottom: bool) -> u32 {
+        if bottom {
+            self.event_bar_height() + self.canvas_size() + self.bar_height()
         } else {
-            0
+            self.event_bar_height()
+        }
+    }
+
+    /// Column of a player bar where the clock, the move time, or the material balance starts.
+    /// Names and captured pieces next to them must end before it.
+    fn bar_text_limit(&self, player_color: shakmaty::Color) -> u32 {
+        if self.material_balance {
+            self.clock_slot(player_color).0
+        } else if self.move_times {
+            self.move_time_rect(player_color).0
+        } else {
+            self.clock_rect(player_color).0
+        }
+    }
+
+    /// Draw a player's label so it ends before limit: long labels are shrunk down to
+    /// MIN_NAME_SCALE of their size first if the name fit is Shrink, and their names are
+    /// truncated with an ellipsis if they still don't fit. Returns the label's pixmap, its
+    /// height, and the column right after its text.
+    fn fitted_label_pixmap(
+        &mut self,
+        player: &PlayerLabel,
+        limit: u32,
+        color: Rgba<u8>,
+        background_color: Rgba<u8>,
+        svgs: &SVGForest,
+    ) -> Result<(Pixmap, u32, u32), DrawerError> {
+        let full_height = self.label_height();
+        let min_height = (full_height as f32 * MIN_NAME_SCALE) as u32;
+        let mut height = full_height;
+        loop {
+            let (pixmap, end) = self.label_pixmap(player, height, color, background_color, svgs)?;
+            if end <= limit {
+                return Ok((pixmap, height, end));
+            }
+            if self.name_fit != NameFit::Shrink || height <= min_height {
+                break;
+            }
+            height = (height - (full_height / 20).max(1)).max(min_height);
+        }
+
+        // The longest prefix of the name that fits, found with a binary search. Titles and
+        // ratings are kept
+        let chars: Vec<char> = player.name.chars().collect();
+        let truncated = |n: usize| PlayerLabel {
+            name: format!("{}…", chars[..n].iter().collect::<String>().trim_end()),
+            ..player.clone()
         };
+        let n = longest_fitting(chars.len(), |n| {
+            let (_, end) =
+                self.label_pixmap(&truncated(n), height, color, background_color, svgs)?;
+            Ok(end <= limit)
+        })?;
+        log::debug!("Truncating {} to {} characters", player.name, n);
+        let (pixmap, end) =
+            self.label_pixmap(&truncated(n), height, color, background_color, svgs)?;
+        Ok((pixmap, height, end))
+    }
 
-        log::debug!("Bottom: {:?}, y: {}", bottom, y);
-        imageops::overlay(
-            img,
-            &player_image,
-            (self.size - (self.square_size() * 17 / 8)).into(), // This leaves a 1 / 8 * square_size margin on the right side
-            (y + self.square_size() / 8).into(),
-        );
+    /// Draw a player's title, name, and rating change on a pixmap height pixels tall and as
+    /// wide as the board. Returns the pixmap and the column right after the text.
+    fn label_pixmap(
+        &mut self,
+        player: &PlayerLabel,
+        height: u32,
+        color: Rgba<u8>,
+        background_color: Rgba<u8>,
+        svgs: &SVGForest,
+    ) -> Result<(Pixmap, u32), DrawerError> {
+        let y = BAR_TEXT_Y;
+        // A title with its own color is drawn first, and the name after it, a space apart
+        let mut pixmap = match (&player.title, self.title_color) {
+            (Some(title), Some(title_color)) => {
+                let mut pixmap = self.str_pixmap(
+                    title,
+                    TextBox {
+                        height,
+                        width: self.canvas_size(),
+                        x: 2,
+                        y,
+                        color: title_color,
+                        background_color,
+                        text_anchor: TextAnchor::Start,
+                    },
+                    svgs,
+                )?;
+                let title_end = utils::last_drawn_column(&pixmap, background_color);
+                let name_pixmap = self.str_pixmap(
+                    &player.name_with_rating(),
+                    TextBox {
+                        height,
+                        width: self.canvas_size(),
+                        x: 0,
+                        y,
+                        color,
+                        background_color,
+                        text_anchor: TextAnchor::Start,
+                    },
+                    svgs,
+                )?;
+                pixmap.draw_pixmap(
+                    (title_end + height / 6) as i32,
+                    0,
+                    name_pixmap.as_ref(),
+                    &PixmapPaint::default(),
+                    Transform::default(),
+                    None,
+                );
+                pixmap
+            }
+            _ => self.str_pixmap(
+                &player.to_string(),
+                TextBox {
+                    height,
+                    width: self.canvas_size(),
+                    x: 2,
+                    y,
+                    color,
+                    background_color,
+                    text_anchor: TextAnchor::Start,
+                },
+                svgs,
+            )?,
+        };
+        let mut text_end = utils::last_drawn_column(&pixmap, background_color);
 
-        Ok(())
+        // The rating change is smaller than the name, with its baseline close to the name's
+        if let Some(diff) = player.rating_diff {
+            let diff_color = match diff.signum() {
+                1 => RATING_GAIN_COLOR,
+                -1 => RATING_LOSS_COLOR,
+                _ => color,
+            };
+            let diff_pixmap = self.str_pixmap(
+                &format!("{:+}", diff),
+                TextBox {
+                    height: height * 3 / 4,
+                    width: height * 2,
+                    x: 0,
+                    y,
+                    color: diff_color,
+                    background_color,
+                    text_anchor: TextAnchor::Start,
+                },
+                svgs,
+            )?;
+            let x = text_end + height / 8;
+            pixmap.draw_pixmap(
+                x as i32,
+                (height / 6) as i32,
+                diff_pixmap.as_ref(),
+                &PixmapPaint::default(),
+                Transform::default(),
+                None,
+            );
+            text_end = x + utils::last_drawn_column(&diff_pixmap, background_color);
+        }
+
+        // Countries are drawn last, as a flag about as tall as capital letters, or as text like
+        // the rating change if there is no flag for the code
+        if let Some(country) = &player.country {
+            let x = text_end + height / 5;
+            let flag = SVGTree::Flag {
+                code: country.clone(),
+            };
+            match svgs.load_svg_tree(&flag) {
+                Ok(rtree) => {
+                    let flag_height = height * 3 / 8;
+                    let size = rtree.svg_node().size;
+                    let flag_width =
+                        (flag_height as f64 * size.width() / size.height()).ceil() as u32;
+                    resvg::render(
+                        &rtree,
+                        FitTo::Height(flag_height),
+                        Transform::from_translate(x as f32, (height * 2 / 7) as f32),
+                        pixmap.as_mut(),
+                    )
+                    .ok_or(DrawerError::SVGRenderError {
+                        svg: country.to_string(),
+                    })?;
+                    text_end = x + flag_width;
+                }
+                Err(DrawerError::SVGNotFound { .. }) => {
+                    let code_pixmap = self.str_pixmap(
+                        country,
+                        TextBox {
+                            height: height * 3 / 4,
+                            width: height * 2,
+                            x: 0,
+                            y,
+                            color,
+                            background_color,
+                            text_anchor: TextAnchor::Start,
+                        },
+                        svgs,
+                    )?;
+                    pixmap.draw_pixmap(
+                        x as i32,
+                        (height / 6) as i32,
+                        code_pixmap.as_ref(),
+                        &PixmapPaint::default(),
+                        Transform::default(),
+                        None,
+                    );
+                    text_end = x + utils::last_drawn_column(&code_pixmap, background_color);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok((pixmap, text_end))
     }
 
-    pub fn add_player_bar_space(&self, img: RgbaImage) -> RgbaImage {
-        let mut new_img = RgbaImage::new(self.size, self.size + self.square_size() * 2);
-        imageops::replace(&mut new_img, &img, 0, self.square_size().into());
-        new_img
+    /// Draw miniatures of captured pieces in a player bar pixmap, starting at x and grouped by
+    /// type. Pieces of the same type overlap each other. Stops before reaching the clock.
+    fn draw_captured_pieces(
+        &mut self,
+        captured: &ByRole<u8>,
+        color: shakmaty::Color,
+        x: u32,
+        pixmap: &mut Pixmap,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let bar_height = self.bar_height();
+        let (piece_size, y) = match self.bar_layout {
+            BarLayout::Double => (bar_height / 2, (bar_height - bar_height / 2) / 2),
+            BarLayout::Single => (bar_height * 3 / 8, bar_height / 2 + bar_height / 32),
+        };
+        let overlap = piece_size / 2;
+        let max_x = self.bar_text_limit(!color);
+        let paint = PixmapPaint::default();
+        let transform = Transform::default();
+
+        let mut x = x;
+        for role in [
+            Role::Pawn,
+            Role::Knight,
+            Role::Bishop,
+            Role::Rook,
+            Role::Queen,
+        ] {
+            let count = *captured.get(role) as u32;
+            if count == 0 {
+                continue;
+            }
+            if x + piece_size + overlap * (count - 1) > max_x {
+                log::debug!("No space left for captured pieces");
+                break;
+            }
+
+            let piece_tree = SVGTree::Piece {
+                role,
+                color,
+                additional: None,
+            };
+            let piece = svgs.load_piece(&piece_tree)?;
+            let mut piece_pixmap = Pixmap::new(piece_size, piece_size).unwrap();
+            render_piece_at(&piece, piece_size, 0.0, 0.0, &mut piece_pixmap).ok_or(
+                DrawerError::SVGRenderError {
+                    svg: format!("{}_{}.svg", color.char(), role.char()),
+                },
+            )?;
+
+            for _ in 0..count {
+                pixmap.draw_pixmap(
+                    x as i32,
+                    y as i32,
+                    piece_pixmap.as_ref(),
+                    &paint,
+                    transform,
+                    None,
+                );
+                x += overlap;
+            }
+            x += piece_size;
+        }
+
+        Ok(())
     }
 
-    pub fn draw_player_clocks(
+    pub fn draw_player_clock(
         &mut self,
-        white_clock: &str,
-        black_clock: &str,
+        clock: &str,
+        player_color: shakmaty::Color,
+        style: ClockStyle,
         img: &mut RgbaImage,
         svgs: &SVGForest,
     ) -> Result<(), DrawerError> {
-        self.draw_player_clock(white_clock, shakmaty::Color::White, !self.flip, img, svgs)?;
-        self.draw_player_clock(black_clock, shakmaty::Color::Black, self.flip, img, svgs)?;
+        let (bar_x, bar_y) = self.bar_origin(player_color);
+        let (x, y, width, height) = self.clock_rect(player_color);
+        let clock_image = self.clock_image(clock, player_color, style, width, height, svgs)?;
+        log::debug!(
+            "Clock of {:?} at x: {}, y: {}",
+            player_color,
+            bar_x + x,
+            bar_y + y
+        );
+        imageops::replace(img, &clock_image, (bar_x + x).into(), (bar_y + y).into());
+
+        if style == ClockStyle::FlagFall {
+            self.draw_clock_flag(player_color, img, svgs)?;
+        }
 
         Ok(())
     }
 
-    pub fn draw_one_player_clock(
+    /// Draw a player's clock in a small box at the right corner of the board on their side, for
+    /// boards without player bars. The box is drawn over the board, so it must be drawn again
+    /// after the squares under it.
+    pub fn draw_floating_clock(
         &mut self,
         clock: &str,
-        color: shakmaty::Color,
+        player_color: shakmaty::Color,
+        style: ClockStyle,
         img: &mut RgbaImage,
         svgs: &SVGForest,
     ) -> Result<(), DrawerError> {
-        match color {
-            shakmaty::Color::White => {
-                self.draw_player_clock(clock, color, !self.flip, img, svgs)?
-            }
-            shakmaty::Color::Black => self.draw_player_clock(clock, color, self.flip, img, svgs)?,
-        }
+        let (width, height) = (self.square_size() * 4 / 3, self.square_size() / 2);
+        let clock_image = self.clock_image(clock, player_color, style, width, height, svgs)?;
+
+        let bottom = match player_color {
+            shakmaty::Color::White => !self.flip,
+            shakmaty::Color::Black => self.flip,
+        };
+        let margin = self.square_size() / 16;
+        let board_top = self.event_bar_height() + self.border_size();
+        let x = self.border_size() + self.square_size() * 8 - margin - width;
+        let y = if bottom {
+            board_top + self.square_size() * 8 - margin - height
+        } else {
+            board_top + margin
+        };
+        imageops::replace(img, &clock_image, x.into(), y.into());
 
         Ok(())
     }
 
-    pub fn draw_player_bars(
+    /// Draw a player's clock in a box with their bar's colors swapped, and the text in a
+    /// warning color if they are low on time or their flag fell.
+    fn clock_image(
+        &mut self,
+        clock: &str,
+        player_color: shakmaty::Color,
+        style: ClockStyle,
+        width: u32,
+        height: u32,
+        svgs: &SVGForest,
+    ) -> Result<RgbaImage, DrawerError> {
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        let (background_color, color) = self.bar_colors(player_color);
+        pixmap.fill(skia_color(background_color));
+        let (color, font_weight) = match style {
+            ClockStyle::Normal => (color, FontWeight::Bold),
+            ClockStyle::LowTime if self.low_time_bold => {
+                (utils::CLOCK_WARNING_COLOR, FontWeight::Number(900.0))
+            }
+            ClockStyle::LowTime | ClockStyle::FlagFall => {
+                (utils::CLOCK_WARNING_COLOR, FontWeight::Bold)
+            }
+        };
+
+        let font_scale = (CLOCK_LENGTH as f32 / clock.chars().count() as f32).min(1.0);
+        let clock_tree = SVGTree::Str {
+            s: clock.to_string(),
+            height,
+            width,
+            x: 10,
+            y: 65,
+            style: TextStyle {
+                color,
+                background_color,
+                font_weight,
+                font_size: FontSize::Unit(height as f32 * 0.5 * font_scale, "px".to_string()),
+                text_anchor: TextAnchor::Start,
+            },
+        };
+        let clock_pixmap = render_str_tree(&clock_tree, width, height, svgs)?;
+        let paint = PixmapPaint::default();
+        let transform = Transform::default();
+        pixmap.draw_pixmap(0, 0, clock_pixmap.as_ref(), &paint, transform, None);
+
+        Ok(utils::pixmap_to_image(&pixmap))
+    }
+
+    /// Draw a flag to the left of the clock in a player bar, where the material balance would
+    /// be.
+    fn draw_clock_flag(
         &mut self,
-        white_player: &str,
-        black_player: &str,
+        player_color: shakmaty::Color,
         img: &mut RgbaImage,
         svgs: &SVGForest,
     ) -> Result<(), DrawerError> {
-        self.draw_player_bar(white_player, shakmaty::Color::White, !self.flip, img, svgs)?;
-        self.draw_player_bar(black_player, shakmaty::Color::Black, self.flip, img, svgs)?;
+        let (bar_x, bar_y) = self.bar_origin(player_color);
+        let (x, y, slot_size) = self.clock_slot(player_color);
+        let mut pixmap = Pixmap::new(slot_size, slot_size).unwrap();
+        pixmap.fill(skia_color(self.bar_colors(player_color).1));
+
+        let flag_tree = SVGTree::Termination {
+            reason: "flag".to_string(),
+            color: None,
+        };
+        let rtree = svgs.load_svg_tree(&flag_tree)?;
+        let flag_size = slot_size * 2 / 3;
+        let offset = ((slot_size - flag_size) / 2) as f32;
+        resvg::render(
+            &rtree,
+            FitTo::Height(flag_size),
+            Transform::from_translate(offset, offset),
+            pixmap.as_mut(),
+        )
+        .ok_or(DrawerError::SVGRenderError {
+            svg: "flag".to_string(),
+        })?;
+
+        imageops::replace(
+            img,
+            &utils::pixmap_to_image(&pixmap),
+            (bar_x + x).into(),
+            (bar_y + y).into(),
+        );
 
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::drawer::SVGFontConfig;
+    /// Draw a frame with lines of text centered vertically, to be shown before the game starts.
+    pub fn draw_title_card(
+        &mut self,
+        lines: &[String],
+        height: u32,
+        svgs: &SVGForest,
+    ) -> Result<RgbaImage, DrawerError> {
+        let mut pixmap = Pixmap::new(self.layout_width(), height).unwrap();
+        pixmap.fill(self.dark_color());
 
-    #[test]
-    fn test_square_image() {
-        let dark_arr: [u8; 4] = [249, 100, 100, 1];
-        let light_arr: [u8; 4] = [249, 100, 100, 1];
-        let dark: Color = Color(dark_arr);
-        let light: Color = Color(light_arr);
-        let mut drawer = BoardDrawer::new(false, 80, dark, light).unwrap();
+        let line_height = self.square_size();
+        let top = height.saturating_sub(line_height * lines.len() as u32) / 2;
+        let paint = PixmapPaint::default();
+        let transform = Transform::default();
 
-        let square = Square::new(0); // A1 is dark
-        let expected = ImageBuffer::from_pixel(10, 10, image::Rgba(dark_arr));
-        assert_eq!(expected, drawer.square_image(&square));
+        for (n, line) in lines.iter().enumerate() {
+            let line_pixmap = self.str_pixmap(
+                line,
+                TextBox {
+                    height: line_height,
+                    width: self.layout_width(),
+                    x: 5,
+                    y: 65,
+                    color: self.light,
+                    background_color: self.dark,
+                    text_anchor: TextAnchor::Start,
+                },
+                svgs,
+            )?;
+            pixmap.draw_pixmap(
+                0,
+                (top + line_height * n as u32) as i32,
+                line_pixmap.as_ref(),
+                &paint,
+                transform,
+                None,
+            );
+        }
 
-        let square = Square::new(7); // H1 is light
-        let expected = ImageBuffer::from_pixel(10, 10, image::Rgba(light_arr));
-        assert_eq!(expected, drawer.square_image(&square));
+        ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take()).ok_or(
+            DrawerError::ImageTooBig {
+                image: "title card".to_string(),
+            },
+        )
     }
 
-    #[test]
-    fn test_sizes() {
-        let dark: Color = Color([249, 100, 100, 1]);
-        let light: Color = Color([255, 253, 253, 1]);
-        let drawer = BoardDrawer::new(false, 80, dark, light).unwrap();
+    /// Dim an image and draw a banner with text, like the result of the game, across its center.
+    pub fn draw_result_banner(
+        &mut self,
+        text: &str,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        utils::dim(img, 0.4);
 
-        assert_eq!(drawer.size(), 80);
-        assert_eq!(drawer.square_size(), 10);
+        let banner_height = self.square_size() * 3 / 2;
+        let mut pixmap = Pixmap::new(self.canvas_size(), banner_height).unwrap();
+        pixmap.fill(self.dark_color());
+
+        let text_pixmap = self.str_pixmap(
+            text,
+            TextBox {
+                height: banner_height,
+                width: self.canvas_size(),
+                x: 50,
+                y: 65,
+                color: self.light,
+                background_color: self.dark,
+                text_anchor: TextAnchor::Middle,
+            },
+            svgs,
+        )?;
+        let paint = PixmapPaint::default();
+        let transform = Transform::default();
+        pixmap.draw_pixmap(0, 0, text_pixmap.as_ref(), &paint, transform, None);
+
+        let banner = ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take()).ok_or(
+            DrawerError::ImageTooBig {
+                image: format!("{} banner", text),
+            },
+        )?;
+
+        let y = img.height().saturating_sub(banner_height) / 2;
+        imageops::overlay(img, &banner, 0, y.into());
+
+        Ok(())
     }
 
-    #[test]
-    fn test_square_pixmap() {
-        let dark: Color = Color([249, 100, 100, 1]);
-        let light: Color = Color([255, 253, 253, 1]);
-        let mut drawer = BoardDrawer::new(false, 80, dark, light).unwrap();
+    /// Tint squares by how many times a piece landed on them, indexed like shakmaty's Square.
+    /// The busiest squares are the reddest, while squares no piece landed on are left as they
+    /// are. img must only contain the board, without player bars.
+    pub fn draw_heatmap(&self, counts: &[u32; 64], img: &mut RgbaImage) {
+        let max = match counts.iter().max() {
+            Some(&max) if max > 0 => max,
+            _ => return,
+        };
+        let size = self.square_size();
 
-        let mut pixmap = Pixmap::new(10, 10).unwrap();
-        let square = Square::new(9); // B2 is dark
-        pixmap.fill(tiny_skia::Color::from_rgba8(249, 100, 100, 255));
+        for (square, &count) in Square::ALL.iter().zip(counts.iter()) {
+            if count == 0 {
+                continue;
+            }
+            let color = heatmap_color(count as f32 / max as f32);
+            let (x, y) = self.square_position(square);
+            for pixel_y in y..(y + size).min(img.height()) {
+                for pixel_x in x..(x + size).min(img.width()) {
+                    utils::blend_pixel(img.get_pixel_mut(pixel_x, pixel_y), color);
+                }
+            }
+        }
+    }
 
-        let config = SVGFontConfig::default();
-        let svgs = SVGForest::new(config, "svgs", "cburnett", "terminations").unwrap();
-        let result = drawer.square_pixmap(10, 10, &square, &svgs, false).unwrap();
-        assert_eq!(pixmap, result);
+    /// Draw a strip with text, like how the game ended, across the bottom of the board. Text
+    /// that doesn't fit the width of the board is drawn smaller.
+    pub fn draw_termination_text(
+        &mut self,
+        text: &str,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let strip_height = self.square_size() * 3 / 4;
+        let available_width = self.size - self.square_size() / 2;
 
-        let square = Square::new(10); // C2 is dark
-        pixmap.fill(tiny_skia::Color::from_rgba8(255, 253, 253, 255));
+        // Text is measured at full size, and scaled down along with its height if too wide
+        let measure = self.str_pixmap(
+            text,
+            TextBox {
+                height: strip_height,
+                width: self.size * 4,
+                x: 0,
+                y: 65,
+                color: self.light,
+                background_color: self.dark,
+                text_anchor: TextAnchor::Start,
+            },
+            svgs,
+        )?;
+        let text_width = utils::last_drawn_column(&measure, self.dark);
+        let text_height = if text_width > available_width {
+            (strip_height * available_width / text_width).max(1)
+        } else {
+            strip_height
+        };
 
-        let config = SVGFontConfig::default();
-        let svgs = SVGForest::new(config, "svgs", "cburnett", "terminations").unwrap();
-        let result = drawer.square_pixmap(10, 10, &square, &svgs, false).unwrap();
-        assert_eq!(pixmap, result);
+        let mut pixmap = Pixmap::new(self.size, strip_height).unwrap();
+        pixmap.fill(self.dark_color());
+        let text_pixmap = self.str_pixmap(
+            text,
+            TextBox {
+                height: text_height,
+                width: self.size,
+                x: 50,
+                y: 65,
+                color: self.light,
+                background_color: self.dark,
+                text_anchor: TextAnchor::Middle,
+            },
+            svgs,
+        )?;
+        let paint = PixmapPaint::default();
+        let transform = Transform::default();
+        pixmap.draw_pixmap(
+            0,
+            ((strip_height - text_height) / 2) as i32,
+            text_pixmap.as_ref(),
+            &paint,
+            transform,
+            None,
+        );
+
+        let strip = ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take()).ok_or(
+            DrawerError::ImageTooBig {
+                image: format!("{} strip", text),
+            },
+        )?;
+
+        let x = self.border_size();
+        let y = self.border_size() + self.size - strip_height;
+        imageops::replace(img, &strip, x.into(), y.into());
+
+        Ok(())
+    }
+
+    /// Center of a square in pixels, as seen in the final image: takes flip into account.
+    pub fn square_center(&self, square: Square) -> (f32, f32) {
+        let (file, rank) = if self.flip {
+            (7 - u32::from(square.file()), u32::from(square.rank()))
+        } else {
+            (u32::from(square.file()), 7 - u32::from(square.rank()))
+        };
+        let square_size = self.square_size() as f32;
+        let border = self.border_size() as f32;
+        (
+            border + (file as f32 + 0.5) * square_size,
+            border + (rank as f32 + 0.5) * square_size,
+        )
+    }
+
+    /// Draw a semi-transparent arrow between the centers of two squares. Only pixels that
+    /// have already been drawn in img are painted, so the squares under the arrow must be drawn
+    /// before calling this.
+    pub fn draw_arrow(
+        &mut self,
+        from: Square,
+        to: Square,
+        color: Rgba<u8>,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        log::debug!("Drawing arrow: {} -> {}", from, to);
+        let arrow_tree = SVGTree::Arrow {
+            from: self.square_center(from),
+            to: self.square_center(to),
+            width: self.square_size() as f32 * 0.25,
+            size: self.canvas_size(),
+            color,
+        };
+        self.blend_svg_tree(&arrow_tree, &format!("{}-{} arrow", from, to), img, svgs)
+    }
+
+    /// Draw a semi-transparent circle around the edge of a square. Like draw_arrow, the square
+    /// must be drawn before calling this.
+    pub fn draw_circle(
+        &mut self,
+        square: Square,
+        color: Rgba<u8>,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        log::debug!("Drawing circle: {}", square);
+        let square_size = self.square_size() as f32;
+        let circle_tree = SVGTree::Circle {
+            center: self.square_center(square),
+            radius: square_size * 0.45,
+            width: square_size * 0.08,
+            size: self.canvas_size(),
+            color,
+        };
+        self.blend_svg_tree(&circle_tree, &format!("{} circle", square), img, svgs)
+    }
+
+    /// Draw a small badge with a short text, like ?? for a blunder, on the top right corner of
+    /// a square. Like draw_arrow, the square must be drawn before calling this.
+    pub fn draw_badge(
+        &mut self,
+        square: Square,
+        text: &str,
+        color: Rgba<u8>,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        log::debug!("Drawing badge {} on {}", text, square);
+        let square_size = self.square_size() as f32;
+        let radius = square_size * 0.2;
+        let (x, y) = self.square_center(square);
+        let badge_tree = SVGTree::Badge {
+            text: text.to_string(),
+            center: (
+                x + square_size / 2.0 - radius - 1.0,
+                y - square_size / 2.0 + radius + 1.0,
+            ),
+            radius,
+            size: self.canvas_size(),
+            color,
+        };
+        self.blend_svg_tree(&badge_tree, &format!("{} badge", text), img, svgs)
+    }
+
+    /// Render an SVG tree the size of the board and blend it over the pixels of img that have
+    /// already been drawn.
+    fn blend_svg_tree(
+        &self,
+        svg_tree: &SVGTree,
+        name: &str,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let rtree = svgs.load_svg_tree(svg_tree)?;
+        let mut pixmap = Pixmap::new(self.canvas_size(), self.canvas_size()).unwrap();
+        resvg::render(
+            &rtree,
+            FitTo::Original,
+            Transform::identity(),
+            pixmap.as_mut(),
+        )
+        .ok_or(DrawerError::SVGRenderError {
+            svg: name.to_string(),
+        })?;
+        let overlay = utils::pixmap_to_image(&pixmap);
+
+        for (pixel, overlay_pixel) in img.pixels_mut().zip(overlay.pixels()) {
+            if pixel[3] != 0 {
+                utils::blend_pixel(pixel, *overlay_pixel);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Height of the event bar, or 0 if there is none.
+    pub fn event_bar_height(&self) -> u32 {
+        if self.event_bar {
+            self.square_size() / 2
+        } else {
+            0
+        }
+    }
+
+    /// Row where the board starts in images laid out by add_bar_space.
+    pub fn board_offset(&self, player_bars: bool) -> u32 {
+        if player_bars && self.bar_layout == BarLayout::Double {
+            self.event_bar_height() + self.bar_height()
+        } else {
+            self.event_bar_height()
+        }
+    }
+
+    /// Width of the move list panel, three squares wide, or 0 if there is none.
+    fn move_list_width(&self) -> u32 {
+        if self.move_list {
+            self.square_size() * 3
+        } else {
+            0
+        }
+    }
+
+    /// Height of a line of text in the caption strip.
+    fn caption_line_height(&self) -> u32 {
+        self.square_size() * 2 / 5
+    }
+
+    /// Height of the caption strip, two lines of text high, or 0 if there is none.
+    pub fn caption_height(&self) -> u32 {
+        if self.captions {
+            self.caption_line_height() * 2
+        } else {
+            0
+        }
+    }
+
+    /// Width of images laid out by add_bar_space.
+    pub fn layout_width(&self) -> u32 {
+        self.canvas_size() + self.move_list_width()
+    }
+
+    /// Height of images laid out by add_bar_space.
+    pub fn layout_height(&self, player_bars: bool) -> u32 {
+        if player_bars {
+            let bars = match self.bar_layout {
+                BarLayout::Double => 2,
+                BarLayout::Single => 1,
+            };
+            self.event_bar_height()
+                + self.canvas_size()
+                + self.bar_height() * bars
+                + self.caption_height()
+        } else {
+            self.event_bar_height() + self.canvas_size() + self.caption_height()
+        }
+    }
+
+    /// Lay out a board with space above it for the event bar, if there is one, and with space
+    /// above and below it for player bars if player_bars, or only below it with the single
+    /// bar layout. The caption strip, if any, goes at the bottom, and the move list panel
+    /// right of all of them. The board is returned as is if there is nothing to make space
+    /// for.
+    pub fn add_bar_space(&self, img: RgbaImage, player_bars: bool) -> RgbaImage {
+        let (width, height) = (self.layout_width(), self.layout_height(player_bars));
+        if (width, height) == img.dimensions() {
+            return img;
+        }
+        let mut new_img = RgbaImage::new(width, height);
+        imageops::replace(&mut new_img, &img, 0, self.board_offset(player_bars).into());
+        new_img
+    }
+
+    /// Draw the event bar at the top of an image laid out by add_bar_space, with text about
+    /// the event like its name and round. Text too long for the bar is cut short with an
+    /// ellipsis.
+    pub fn draw_event_bar(
+        &mut self,
+        text: &str,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        self.draw_faded_event_bar(text, 1.0, img, svgs)
+    }
+
+    /// Like draw_event_bar, but with the text faded into the bar by an opacity between 0.0
+    /// and 1.0.
+    pub fn draw_faded_event_bar(
+        &mut self,
+        text: &str,
+        opacity: f32,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let height = self.event_bar_height();
+        let mut pixmap = Pixmap::new(self.canvas_size(), height).unwrap();
+        pixmap.fill(self.dark_color());
+
+        let alpha = (opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let light = utils::composite(
+            Rgba([self.light[0], self.light[1], self.light[2], alpha]),
+            self.dark,
+        );
+        let dark = self.dark;
+        let limit = self.canvas_size() - self.square_size() / 8;
+        let chars: Vec<char> = text.chars().collect();
+        let truncated = |n: usize| match n == chars.len() {
+            true => text.to_string(),
+            false => format!("{}…", chars[..n].iter().collect::<String>().trim_end()),
+        };
+        let mut text_pixmap = |n: usize| {
+            self.str_pixmap(
+                &truncated(n),
+                TextBox {
+                    height,
+                    width: self.canvas_size(),
+                    x: 2,
+                    y: BAR_TEXT_Y,
+                    color: light,
+                    background_color: dark,
+                    text_anchor: TextAnchor::Start,
+                },
+                svgs,
+            )
+        };
+        let n = longest_fitting(chars.len(), |n| {
+            let pixmap = text_pixmap(n)?;
+            Ok(utils::last_drawn_column(&pixmap, dark) <= limit)
+        })?;
+        pixmap.draw_pixmap(
+            0,
+            0,
+            text_pixmap(n)?.as_ref(),
+            &PixmapPaint::default(),
+            Transform::default(),
+            None,
+        );
+
+        let event_image = ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take())
+            .ok_or(DrawerError::ImageTooBig {
+                image: format!("{} event bar", text),
+            })?;
+        imageops::overlay(img, &event_image, 0, 0);
+        Ok(())
+    }
+
+    /// Draw the caption strip at the bottom of an image laid out by add_bar_space, with text
+    /// like a comment on the last move wrapped over two lines. Text too long for both is cut
+    /// short with an ellipsis, and an empty text clears the strip.
+    pub fn draw_caption(
+        &mut self,
+        text: &str,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let line_height = self.caption_line_height();
+        let mut pixmap = Pixmap::new(self.canvas_size(), line_height * 2).unwrap();
+        pixmap.fill(self.dark_color());
+
+        // Lines are filled with as many words as fit, and what's left of the text after the
+        // last line is cut short. A first word too long for a line is cut short as well.
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let first = longest_fitting(words.len(), |n| {
+            self.caption_line_fits(&words[..n].join(" "), svgs)
+        })?;
+        let lines = if first == words.len() {
+            vec![words.join(" ")]
+        } else if first == 0 {
+            vec![self.truncate_caption_line(&words.join(" "), svgs)?]
+        } else {
+            let rest = words[first..].join(" ");
+            vec![
+                words[..first].join(" "),
+                self.truncate_caption_line(&rest, svgs)?,
+            ]
+        };
+
+        for (n, line) in lines.iter().enumerate() {
+            let line_pixmap = self.caption_line_pixmap(line, svgs)?;
+            pixmap.draw_pixmap(
+                0,
+                (line_height * n as u32) as i32,
+                line_pixmap.as_ref(),
+                &PixmapPaint::default(),
+                Transform::default(),
+                None,
+            );
+        }
+
+        let caption = utils::pixmap_to_image(&pixmap);
+        let y = img.height() - caption.height();
+        imageops::replace(img, &caption, 0, y.into());
+        Ok(())
+    }
+
+    /// A line of text of the caption strip, over the dark color.
+    fn caption_line_pixmap(&mut self, line: &str, svgs: &SVGForest) -> Result<Pixmap, DrawerError> {
+        self.str_pixmap(
+            line,
+            TextBox {
+                height: self.caption_line_height(),
+                width: self.canvas_size(),
+                x: 2,
+                y: BAR_TEXT_Y,
+                color: self.light,
+                background_color: self.dark,
+                text_anchor: TextAnchor::Start,
+            },
+            svgs,
+        )
+    }
+
+    /// Whether a line of text fits the width of the caption strip.
+    fn caption_line_fits(&mut self, line: &str, svgs: &SVGForest) -> Result<bool, DrawerError> {
+        let limit = self.canvas_size() - self.square_size() / 8;
+        let pixmap = self.caption_line_pixmap(line, svgs)?;
+        Ok(utils::last_drawn_column(&pixmap, self.dark) <= limit)
+    }
+
+    /// Cut a line of the caption strip short with an ellipsis if it doesn't fit.
+    fn truncate_caption_line(
+        &mut self,
+        line: &str,
+        svgs: &SVGForest,
+    ) -> Result<String, DrawerError> {
+        let chars: Vec<char> = line.chars().collect();
+        let truncated = |n: usize| match n == chars.len() {
+            true => line.to_string(),
+            false => format!("{}…", chars[..n].iter().collect::<String>().trim_end()),
+        };
+        let n = longest_fitting(chars.len(), |n| self.caption_line_fits(&truncated(n), svgs))?;
+        Ok(truncated(n))
+    }
+
+    pub fn draw_player_clocks(
+        &mut self,
+        white_clock: &str,
+        black_clock: &str,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        self.draw_player_clock(
+            white_clock,
+            shakmaty::Color::White,
+            ClockStyle::Normal,
+            img,
+            svgs,
+        )?;
+        self.draw_player_clock(
+            black_clock,
+            shakmaty::Color::Black,
+            ClockStyle::Normal,
+            img,
+            svgs,
+        )?;
+
+        Ok(())
+    }
+
+    /// Draw the material balance to the left of the leading player's clock, e.g. "+3", and
+    /// clear it from the other player's bar. Nothing is shown when the balance is zero.
+    pub fn draw_material_balance(
+        &mut self,
+        balance: i32,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        for player_color in [shakmaty::Color::White, shakmaty::Color::Black] {
+            let (x, y, slot_size) = self.clock_slot(player_color);
+            let mut pixmap = Pixmap::new(slot_size, slot_size).unwrap();
+            let (color, background_color) = self.bar_colors(player_color);
+            pixmap.fill(skia_color(background_color));
+
+            let leading = match player_color {
+                shakmaty::Color::White => balance > 0,
+                shakmaty::Color::Black => balance < 0,
+            };
+            if leading {
+                let balance_pixmap = self.str_pixmap(
+                    &format!("+{}", balance.abs()),
+                    TextBox {
+                        height: slot_size,
+                        width: slot_size,
+                        x: 95,
+                        y: 65,
+                        color,
+                        background_color,
+                        text_anchor: TextAnchor::End,
+                    },
+                    svgs,
+                )?;
+                let paint = PixmapPaint::default();
+                let transform = Transform::default();
+                pixmap.draw_pixmap(0, 0, balance_pixmap.as_ref(), &paint, transform, None);
+            }
+
+            let balance_image =
+                ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take()).ok_or(
+                    DrawerError::ImageTooBig {
+                        image: format!("{}.svg", balance),
+                    },
+                )?;
+
+            let (bar_x, bar_y) = self.bar_origin(player_color);
+            imageops::overlay(img, &balance_image, (bar_x + x).into(), (bar_y + y).into());
+        }
+
+        Ok(())
+    }
+
+    /// Draw the time a player spent on their last move, e.g. "+0:14", left of their clock in
+    /// smaller text. An empty move time clears it.
+    pub fn draw_move_time(
+        &mut self,
+        move_time: &str,
+        player_color: shakmaty::Color,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let (x, y, width, height) = self.move_time_rect(player_color);
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        let (color, background_color) = self.bar_colors(player_color);
+        pixmap.fill(skia_color(background_color));
+
+        if !move_time.is_empty() {
+            let move_time_tree = SVGTree::Str {
+                s: move_time.to_string(),
+                height,
+                width,
+                x: 90,
+                y: 65,
+                style: TextStyle {
+                    color,
+                    background_color,
+                    font_weight: FontWeight::Normal,
+                    font_size: FontSize::Unit(height as f32 * 0.4, "px".to_string()),
+                    text_anchor: TextAnchor::End,
+                },
+            };
+            let move_time_pixmap = render_str_tree(&move_time_tree, width, height, svgs)?;
+            let paint = PixmapPaint::default();
+            let transform = Transform::default();
+            pixmap.draw_pixmap(0, 0, move_time_pixmap.as_ref(), &paint, transform, None);
+        }
+
+        let (bar_x, bar_y) = self.bar_origin(player_color);
+        imageops::replace(
+            img,
+            &utils::pixmap_to_image(&pixmap),
+            (bar_x + x).into(),
+            (bar_y + y).into(),
+        );
+
+        Ok(())
+    }
+
+    /// Draw the move list panel right of an image laid out by add_bar_space: moves in SAN in
+    /// numbered rows of two, with the move at index current highlighted in the panel's colors
+    /// swapped. Rows scroll up to keep the current move in view.
+    pub fn draw_move_list(
+        &mut self,
+        moves: &[String],
+        current: usize,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let (width, height) = (self.move_list_width(), img.height());
+        let mut panel = RgbaImage::from_pixel(width, height, self.dark);
+
+        let row_height = self.square_size() * 2 / 5;
+        let margin = row_height / 4;
+        let rows = ((height - margin) / row_height).max(1) as usize;
+        let number_width = self.square_size() * 3 / 4;
+        let move_width = (width - number_width) / 2;
+        let first_row = (current / 2 + 1).saturating_sub(rows);
+        for (row, pair) in moves.chunks(2).enumerate().skip(first_row).take(rows) {
+            let y = margin + (row - first_row) as u32 * row_height;
+            let number = format!("{}.", row + 1);
+            let cell = self.move_list_cell(&number, false, number_width, row_height, svgs)?;
+            imageops::replace(&mut panel, &cell, 0, y.into());
+            for (n, san) in pair.iter().enumerate() {
+                let highlight = row * 2 + n == current;
+                let cell = self.move_list_cell(san, highlight, move_width, row_height, svgs)?;
+                let x = number_width + move_width * n as u32;
+                imageops::replace(&mut panel, &cell, x.into(), y.into());
+            }
+        }
+
+        imageops::replace(img, &panel, self.canvas_size().into(), 0);
+        Ok(())
+    }
+
+    /// A cell of the move list with text in the light color over the dark one, or the other
+    /// way around if highlighted.
+    fn move_list_cell(
+        &mut self,
+        text: &str,
+        highlight: bool,
+        width: u32,
+        height: u32,
+        svgs: &SVGForest,
+    ) -> Result<RgbaImage, DrawerError> {
+        let key = (text.to_string(), highlight);
+        if let Some(cell) = self.move_list_cache.get(&key) {
+            return Ok(cell.clone());
+        }
+
+        let (color, background_color) = match highlight {
+            true => (self.dark, self.light),
+            false => (self.light, self.dark),
+        };
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        pixmap.fill(skia_color(background_color));
+        let text_tree = SVGTree::Str {
+            s: text.to_string(),
+            height,
+            width,
+            x: 8,
+            y: 72,
+            style: TextStyle {
+                color,
+                background_color,
+                font_weight: FontWeight::Bold,
+                font_size: FontSize::Unit(height as f32 * 0.6, "px".to_string()),
+                text_anchor: TextAnchor::Start,
+            },
+        };
+        let text_pixmap = render_str_tree(&text_tree, width, height, svgs)?;
+        let paint = PixmapPaint::default();
+        let transform = Transform::default();
+        pixmap.draw_pixmap(0, 0, text_pixmap.as_ref(), &paint, transform, None);
+
+        let cell = utils::pixmap_to_image(&pixmap);
+        self.move_list_cache.insert(key, cell.clone());
+        Ok(cell)
+    }
+
+    /// Size of the box the last move is written in by draw_move_overlay.
+    fn move_overlay_size(&self) -> (u32, u32) {
+        (self.square_size() * 5 / 2, self.square_size() / 2)
+    }
+
+    /// Top left corner of the box the last move is written in, inset a little from the corner
+    /// of the board.
+    fn move_overlay_origin(&self, corner: Corner) -> (u32, u32) {
+        let inset = self.square_size() / 16;
+        let (x, y) = corner.origin((self.size, self.size), self.move_overlay_size(), inset);
+        (self.border_size() + x, self.border_size() + y)
+    }
+
+    /// Write the last move, like 23... Qxh2+, in a box in a corner of the board. The box is
+    /// drawn over the squares under it, so it must be drawn after them.
+    pub fn draw_move_overlay(
+        &mut self,
+        text: &str,
+        corner: Corner,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        let (width, height) = self.move_overlay_size();
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        pixmap.fill(self.dark_color());
+
+        let text_pixmap = self.str_pixmap(
+            text,
+            TextBox {
+                height,
+                width,
+                x: 6,
+                y: 68,
+                color: self.light,
+                background_color: self.dark,
+                text_anchor: TextAnchor::Start,
+            },
+            svgs,
+        )?;
+        let paint = PixmapPaint::default();
+        let transform = Transform::default();
+        pixmap.draw_pixmap(0, 0, text_pixmap.as_ref(), &paint, transform, None);
+
+        let (x, y) = self.move_overlay_origin(corner);
+        imageops::replace(img, &utils::pixmap_to_image(&pixmap), x.into(), y.into());
+        Ok(())
+    }
+
+    pub fn draw_one_player_bar(
+        &mut self,
+        player: &PlayerLabel,
+        color: shakmaty::Color,
+        captured: &ByRole<u8>,
+        avatar: Option<&RgbaImage>,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        self.draw_player_bar(player, color, captured, avatar, img, svgs)
+    }
+
+    pub fn draw_one_player_clock(
+        &mut self,
+        clock: &str,
+        color: shakmaty::Color,
+        style: ClockStyle,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        self.draw_player_clock(clock, color, style, img, svgs)
+    }
+
+    pub fn draw_player_bars(
+        &mut self,
+        white_player: &PlayerLabel,
+        black_player: &PlayerLabel,
+        captured: &ByColor<ByRole<u8>>,
+        avatars: &ByColor<Option<RgbaImage>>,
+        img: &mut RgbaImage,
+        svgs: &SVGForest,
+    ) -> Result<(), DrawerError> {
+        self.draw_player_bar(
+            white_player,
+            shakmaty::Color::White,
+            &captured.white,
+            avatars.white.as_ref(),
+            img,
+            svgs,
+        )?;
+        self.draw_player_bar(
+            black_player,
+            shakmaty::Color::Black,
+            &captured.black,
+            avatars.black.as_ref(),
+            img,
+            svgs,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Render an SVGTree::Str on a pixmap of the given size.
+fn render_str_tree(
+    str_tree: &SVGTree,
+    width: u32,
+    height: u32,
+    svgs: &SVGForest,
+) -> Result<Pixmap, DrawerError> {
+    let mut pixmap = Pixmap::new(width, height).unwrap();
+    let rtree = svgs.load_svg_tree(str_tree)?;
+
+    let fit_to = FitTo::Height(height);
+    resvg::render(&rtree, fit_to, Transform::identity(), pixmap.as_mut()).ok_or_else(|| {
+        let svg = match str_tree {
+            SVGTree::Str { s, .. } => s.to_string(),
+            _ => String::new(),
+        };
+        DrawerError::SVGRenderError { svg }
+    })?;
+
+    Ok(pixmap)
+}
+
+/// Draw a piece on a pixmap with its top left corner at (x, y), fit to a height in pixels. Raster
+/// pieces are resized with a high quality filter and blended over the pixmap, like SVGs are.
+fn render_piece_at(
+    piece: &PieceSource,
+    height: u32,
+    x: f32,
+    y: f32,
+    pixmap: &mut Pixmap,
+) -> Option<()> {
+    match piece {
+        PieceSource::Svg(rtree) => resvg::render(
+            rtree,
+            FitTo::Height(height),
+            Transform::from_translate(x, y),
+            pixmap.as_mut(),
+        ),
+        PieceSource::Raster(image) => {
+            let width = ((piece.aspect_ratio() * height as f64).round() as u32).max(1);
+            let resized = imageops::resize(image, width, height, imageops::FilterType::Lanczos3);
+            pixmap.draw_pixmap(
+                x.round() as i32,
+                y.round() as i32,
+                utils::image_to_pixmap(&resized)?.as_ref(),
+                &PixmapPaint::default(),
+                Transform::identity(),
+                None,
+            );
+            Some(())
+        }
+        PieceSource::Sprite { tree, node, cell } => {
+            let bbox = node.calculate_bbox()?;
+            let zoom = height as f64 / cell;
+            let x = x as f64 + (*cell - bbox.width()) * zoom / 2.0;
+            let y = y as f64 + (*cell - bbox.height()) * zoom / 2.0;
+            resvg::render_node(
+                tree,
+                node,
+                FitTo::Zoom(zoom as f32),
+                Transform::from_translate(x as f32, y as f32),
+                pixmap.as_mut(),
+            )
+        }
+    }
+}
+
+/// Convert a color to the one used to fill pixmaps.
+fn skia_color(color: Rgba<u8>) -> tiny_skia::Color {
+    tiny_skia::Color::from_rgba8(color[0], color[1], color[2], color[3])
+}
+
+/// Largest n up to len for which fits(n) is true, found with a binary search, like the number of
+/// characters of a text that fit somewhere. fits(0) is assumed to be true.
+fn longest_fitting<F>(len: usize, mut fits: F) -> Result<usize, DrawerError>
+where
+    F: FnMut(usize) -> Result<bool, DrawerError>,
+{
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if fits(mid)? {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+/// Load an image or SVG file, like a texture or an avatar, scaled and cropped to fill a square
+/// of size pixels.
+fn load_image_to_fill(path: &Path, size: u32, svgs: &SVGForest) -> Result<RgbaImage, DrawerError> {
+    Ok(load_image_file(path, size, svgs)?
+        .resize_to_fill(size, size, imageops::FilterType::Triangle)
+        .to_rgba8())
+}
+
+/// Load an image or SVG file. SVGs are rendered with their shortest side size pixels long, so
+/// they can be scaled to about that size without losing detail.
+fn load_image_file(path: &Path, size: u32, svgs: &SVGForest) -> Result<DynamicImage, DrawerError> {
+    let is_svg = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+
+    if is_svg {
+        let rtree = svgs.load_svg_file(path)?;
+        let svg_size = rtree.svg_node().size;
+        let zoom = size as f64 / svg_size.width().min(svg_size.height());
+        let mut pixmap = Pixmap::new(
+            (svg_size.width() * zoom).ceil() as u32,
+            (svg_size.height() * zoom).ceil() as u32,
+        )
+        .ok_or(DrawerError::ImageTooBig {
+            image: path.display().to_string(),
+        })?;
+        resvg::render(
+            &rtree,
+            FitTo::Zoom(zoom as f32),
+            Transform::identity(),
+            pixmap.as_mut(),
+        )
+        .ok_or(DrawerError::SVGRenderError {
+            svg: path.display().to_string(),
+        })?;
+        Ok(DynamicImage::ImageRgba8(utils::pixmap_to_image(&pixmap)))
+    } else {
+        image::open(path).map_err(|e| DrawerError::LoadImage {
+            path: path.display().to_string(),
+            reason: e.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawer::SVGFontConfig;
+
+    #[test]
+    fn test_square_image() {
+        let dark_arr: [u8; 4] = [249, 100, 100, 255];
+        let light_arr: [u8; 4] = [249, 100, 100, 255];
+        let dark: Color = Color(dark_arr);
+        let light: Color = Color(light_arr);
+        let mut drawer = BoardDrawer::new(false, 80, dark, light).unwrap();
+
+        let square = Square::new(0); // A1 is dark
+        let expected = ImageBuffer::from_pixel(10, 10, image::Rgba(dark_arr));
+        assert_eq!(expected, drawer.square_image(&square));
+
+        let square = Square::new(7); // H1 is light
+        let expected = ImageBuffer::from_pixel(10, 10, image::Rgba(light_arr));
+        assert_eq!(expected, drawer.square_image(&square));
+    }
+
+    #[test]
+    fn test_transparent_square_colors() {
+        let dark: Color = Color([200, 100, 50, 128]);
+        let light: Color = Color([250, 250, 250, 128]);
+        let mut drawer = BoardDrawer::new(false, 80, dark, light).unwrap();
+        drawer.set_background(Color([0, 0, 0, 255]));
+
+        // Half of the background shows through each square
+        let a1 = drawer.square_image(&Square::A1);
+        assert_eq!(*a1.get_pixel(5, 5), Rgba([100, 50, 25, 255]));
+        let h1 = drawer.square_image(&Square::H1);
+        assert_eq!(*h1.get_pixel(5, 5), Rgba([125, 125, 125, 255]));
+    }
+
+    #[test]
+    fn test_transparent_square_colors_board() {
+        let dark: Color = Color([200, 100, 50, 128]);
+        let light: Color = Color([250, 250, 250, 128]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        drawer.set_coordinates(CoordinateMode::None, CoordinateSides::default());
+        drawer.set_background(Color([0, 0, 255, 255]));
+        let svgs = roboto_svgs();
+
+        let img = drawer.draw_initial_position(&svgs).unwrap();
+        // A3 is dark and H3 is light, both are empty and blended over the background
+        assert_eq!(*img.get_pixel(20, 5 * 40 + 20), Rgba([100, 50, 152, 255]));
+        assert_eq!(
+            *img.get_pixel(7 * 40 + 20, 5 * 40 + 20),
+            Rgba([125, 125, 252, 255])
+        );
+        // Squares under pieces are blended too
+        assert_eq!(*img.get_pixel(1, 7 * 40 + 1), Rgba([100, 50, 152, 255]));
+    }
+
+    #[test]
+    fn test_hatched_dark_squares() {
+        let mut drawer = BoardDrawer::new(
+            false,
+            320,
+            Color([0, 114, 178, 255]),
+            Color([240, 240, 240, 255]),
+        )
+        .unwrap();
+        drawer.set_coordinates(CoordinateMode::None, CoordinateSides::default());
+        drawer.set_hatch(true);
+        let svgs = roboto_svgs();
+
+        let a1 = square_image(&mut drawer, Square::A1, &svgs);
+        let h1 = square_image(&mut drawer, Square::H1, &svgs);
+        assert!(h1.pixels().all(|p| *p == Rgba([240, 240, 240, 255])));
+        assert!(a1.pixels().any(|p| *p == Rgba([0, 114, 178, 255])));
+        assert!(a1.pixels().any(|p| p[2] < 170));
+
+        // Squares hold a whole number of lines, so the hatch repeats across the board
+        for y in 0..40 {
+            for x in 0..30 {
+                assert_eq!(a1.get_pixel(x, y), a1.get_pixel(x + 10, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_heatmap_color() {
+        assert_eq!(heatmap_color(0.0), HEATMAP_COLD);
+        assert_eq!(heatmap_color(1.0), HEATMAP_HOT);
+        assert_eq!(heatmap_color(0.5), Rgba([233, 141, 53, 128]));
+    }
+
+    #[test]
+    fn test_draw_heatmap() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(true, 320, dark, light).unwrap();
+        drawer.set_coordinates(CoordinateMode::None, CoordinateSides::default());
+        let svgs = roboto_svgs();
+        let board = drawer.draw_initial_position(&svgs).unwrap();
+
+        let mut counts = [0; 64];
+        counts[Square::E4 as usize] = 4;
+        counts[Square::E5 as usize] = 1;
+        let mut heatmap = board.clone();
+        drawer.draw_heatmap(&counts, &mut heatmap);
+
+        // The board is flipped, so e4 is where d5 would be, on the fourth row from the top
+        let e4 = heatmap.get_pixel(3 * 40 + 20, 3 * 40 + 20);
+        let e5 = heatmap.get_pixel(3 * 40 + 20, 4 * 40 + 20);
+        let mut expected = *board.get_pixel(3 * 40 + 20, 3 * 40 + 20);
+        utils::blend_pixel(&mut expected, HEATMAP_HOT);
+        assert_eq!(*e4, expected);
+        assert!(e5[0] > e5[2] && e4[1] < e5[1]);
+
+        // Squares no piece landed on are untouched
+        assert_eq!(heatmap.get_pixel(20, 20), board.get_pixel(20, 20));
+    }
+
+    #[test]
+    fn test_textured_square_image() {
+        let dark: Color = Color([100, 100, 100, 128]);
+        let light: Color = Color([200, 200, 200, 128]);
+        let mut drawer = BoardDrawer::new(false, 80, dark, light).unwrap();
+        let svgs = roboto_svgs();
+
+        // Red on the queen side and blue on the king side
+        let texture = RgbaImage::from_fn(40, 40, |x, _| match x < 20 {
+            true => image::Rgba([255, 0, 0, 255]),
+            false => image::Rgba([0, 0, 255, 255]),
+        });
+        let path = std::env::temp_dir().join("c2g_test_texture.png");
+        texture.save(&path).unwrap();
+        drawer.set_texture(&path, &svgs).unwrap();
+
+        let a1 = *drawer.square_image(&Square::A1).get_pixel(5, 5);
+        let h2 = *drawer.square_image(&Square::H2).get_pixel(5, 5);
+        assert!(a1[0] > h2[0] && a1[2] < h2[2]);
+        // Half of the texture shows through the dark square color
+        assert!((a1[0] as i32 - 177).abs() <= 1);
+        assert_eq!(a1[3], 255);
+    }
+
+    #[test]
+    fn test_load_watermark() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let drawer = BoardDrawer::new(false, 80, dark, light).unwrap();
+        let svgs = roboto_svgs();
+
+        // The aspect ratio of the file is kept, and its alpha multiplied by the opacity
+        let logo = RgbaImage::from_pixel(40, 20, image::Rgba([255, 0, 0, 255]));
+        let path = std::env::temp_dir().join("c2g_test_watermark.png");
+        logo.save(&path).unwrap();
+        let watermark = drawer.load_watermark(&path, 60, 0.5, &svgs).unwrap();
+        assert_eq!(watermark.dimensions(), (60, 30));
+        assert_eq!(*watermark.get_pixel(30, 15), image::Rgba([255, 0, 0, 128]));
+
+        let path = Path::new("svgs/flags/NOR.svg");
+        let watermark = drawer.load_watermark(path, 60, 1.0, &svgs).unwrap();
+        assert_eq!(watermark.width(), 60);
+    }
+
+    #[test]
+    fn test_piece_cache() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        let mut knight = |square| {
+            drawer
+                .piece_image(
+                    Role::Knight.of(shakmaty::Color::Black),
+                    &square,
+                    40,
+                    40,
+                    None,
+                    &svgs,
+                )
+                .unwrap()
+        };
+
+        let on_dark = knight(Square::D4);
+        let on_light = knight(Square::E4);
+        // The cached knight is drawn over each square's color
+        assert_eq!(*on_dark.get_pixel(1, 1), image::Rgba([249, 100, 100, 255]));
+        assert_eq!(*on_light.get_pixel(1, 1), image::Rgba([255, 253, 253, 255]));
+        assert_eq!(on_dark.get_pixel(20, 20), on_light.get_pixel(20, 20));
+        assert_eq!(drawer.piece_cache.len(), 1);
+    }
+
+    /// Check drawing pieces from the cache is faster than rendering them. Timings depend on the
+    /// machine, so run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn bench_piece_cache() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 1024, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        let size = drawer.square_size();
+
+        let mut elapsed = Vec::new();
+        for cached in [false, true] {
+            let start = std::time::Instant::now();
+            for square in Square::ALL {
+                if !cached {
+                    drawer.piece_cache.clear();
+                }
+                drawer
+                    .piece_image(
+                        Role::Queen.of(shakmaty::Color::White),
+                        &square,
+                        size,
+                        size,
+                        None,
+                        &svgs,
+                    )
+                    .unwrap();
+            }
+            elapsed.push(start.elapsed());
+        }
+        assert!(elapsed[1] < elapsed[0]);
+    }
+
+    #[test]
+    fn test_piece_scale() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        let square_color = image::Rgba([249, 100, 100, 255]);
+        let outer_quarter_untouched = |img: &RgbaImage| {
+            img.enumerate_pixels()
+                .filter(|(x, y, _)| *x < 10 || *x >= 30 || *y < 10 || *y >= 30)
+                .all(|(_, _, pixel)| *pixel == square_color)
+        };
+
+        let full = drawer
+            .piece_image(
+                Role::Queen.of(shakmaty::Color::White),
+                &Square::D4,
+                40,
+                40,
+                None,
+                &svgs,
+            )
+            .unwrap();
+        assert!(!outer_quarter_untouched(&full));
+
+        drawer.set_piece_scale(0.5);
+        let half = drawer
+            .piece_image(
+                Role::Queen.of(shakmaty::Color::White),
+                &Square::D4,
+                40,
+                40,
+                None,
+                &svgs,
+            )
+            .unwrap();
+        assert!(outer_quarter_untouched(&half));
+        // The piece is centered, so its middle is not the square color
+        assert_ne!(*half.get_pixel(20, 20), square_color);
+    }
+
+    #[test]
+    fn test_raster_piece_image() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = SVGForest::new(
+            SVGFontConfig::default(),
+            "tests/fixtures",
+            "pieces_png",
+            "terminations",
+        )
+        .unwrap();
+
+        let king = drawer
+            .piece_image(
+                Role::King.of(shakmaty::Color::White),
+                &Square::D4,
+                40,
+                40,
+                None,
+                &svgs,
+            )
+            .unwrap();
+
+        // The PNG is scaled up from 16 pixels and blended over the square
+        assert_eq!(*king.get_pixel(1, 1), image::Rgba([249, 100, 100, 255]));
+        let center = king.get_pixel(20, 20);
+        assert!(center[0] < 60 && center[3] == 255);
+        let body = king.get_pixel(20, 6);
+        assert!(body[0] > 200 && body[1] > 200);
+    }
+
+    #[test]
+    fn test_sizes() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let drawer = BoardDrawer::new(false, 80, dark, light).unwrap();
+
+        assert_eq!(drawer.size(), 80);
+        assert_eq!(drawer.square_size(), 10);
+    }
+
+    #[test]
+    fn test_coordinate_size() {
+        let drawer_with_size = |size, coordinate_size| {
+            let dark: Color = Color([249, 100, 100, 255]);
+            let light: Color = Color([255, 253, 253, 255]);
+            let mut drawer = BoardDrawer::new(false, size, dark, light).unwrap();
+            drawer.set_coordinate_font(CoordinateFont {
+                size: coordinate_size,
+                weight: FontWeight::Normal,
+            });
+            drawer
+        };
+
+        assert_eq!(drawer_with_size(160, CoordSize::Auto).coordinate_size(), 9);
+        assert_eq!(drawer_with_size(640, CoordSize::Auto).coordinate_size(), 18);
+        assert_eq!(
+            drawer_with_size(2160, CoordSize::Auto).coordinate_size(),
+            33
+        );
+        assert_eq!(
+            drawer_with_size(640, CoordSize::Px(12.0)).coordinate_size(),
+            12
+        );
+        assert_eq!(
+            drawer_with_size(640, CoordSize::Fraction(0.25)).coordinate_size(),
+            20
+        );
+        // Capped at half a square
+        assert_eq!(
+            drawer_with_size(160, CoordSize::Px(30.0)).coordinate_size(),
+            10
+        );
+    }
+
+    #[test]
+    fn test_border_sizes() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 80, dark, light).unwrap();
+        assert_eq!(drawer.canvas_size(), 80);
+
+        drawer.set_coordinates(CoordinateMode::Border, CoordinateSides::default());
+        assert_eq!(drawer.border_size(), 5);
+        assert_eq!(drawer.canvas_size(), 90);
+        assert_eq!(drawer.image_buffer().dimensions(), (90, 90));
+        assert_eq!(drawer.square_center(Square::A8), (10.0, 10.0));
+
+        drawer.set_coordinates(CoordinateMode::None, CoordinateSides::default());
+        assert_eq!(drawer.canvas_size(), 80);
+    }
+
+    #[test]
+    fn test_square_pixmap() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 80, dark, light).unwrap();
+
+        let mut pixmap = Pixmap::new(10, 10).unwrap();
+        let square = Square::new(9); // B2 is dark
+        pixmap.fill(tiny_skia::Color::from_rgba8(249, 100, 100, 255));
+
+        let config = SVGFontConfig::default();
+        let svgs = SVGForest::new(config, "svgs", "cburnett", "terminations").unwrap();
+        let result = drawer.square_pixmap(10, 10, &square, &svgs).unwrap();
+        assert_eq!(pixmap, result);
+
+        let square = Square::new(10); // C2 is dark
+        pixmap.fill(tiny_skia::Color::from_rgba8(255, 253, 253, 255));
+
+        let config = SVGFontConfig::default();
+        let svgs = SVGForest::new(config, "svgs", "cburnett", "terminations").unwrap();
+        let result = drawer.square_pixmap(10, 10, &square, &svgs).unwrap();
+        assert_eq!(pixmap, result);
+    }
+
+    /// Whether a region of an image has more than one color, like a label over a background.
+    fn has_label(img: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> bool {
+        let region = imageops::crop_imm(img, x, y, width, height).to_image();
+        let first = *region.get_pixel(0, 0);
+        region.pixels().any(|p| *p != first)
+    }
+
+    fn roboto_svgs() -> SVGForest {
+        let config = SVGFontConfig {
+            font_family: Some("Roboto".to_string()),
+            ..SVGFontConfig::default()
+        };
+        SVGForest::new(config, "svgs", "cburnett", "terminations").unwrap()
+    }
+
+    fn square_image(drawer: &mut BoardDrawer, square: Square, svgs: &SVGForest) -> RgbaImage {
+        let size = drawer.square_size();
+        let pixmap = drawer.square_pixmap(size, size, &square, svgs).unwrap();
+        ImageBuffer::from_raw(size, size, pixmap.take()).unwrap()
+    }
+
+    #[test]
+    fn test_frame() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        drawer.set_frame(16, Color([40, 26, 13, 255]));
+        assert_eq!(drawer.border_size(), 16);
+        assert_eq!(drawer.canvas_size(), 352);
+        assert_eq!(drawer.square_center(Square::A8), (36.0, 36.0));
+
+        let img = drawer.draw_initial_position(&svgs).unwrap();
+        assert_eq!(img.dimensions(), (352, 352));
+        assert_eq!(*img.get_pixel(0, 0), Rgba([40, 26, 13, 255]));
+        assert_eq!(*img.get_pixel(351, 200), Rgba([40, 26, 13, 255]));
+        // A3 is a dark square next to the frame
+        assert_eq!(
+            *img.get_pixel(20, 16 + 5 * 40 + 20),
+            Rgba([249, 100, 100, 255])
+        );
+
+        // Coordinates go between the frame and the board
+        drawer.set_coordinates(CoordinateMode::Border, CoordinateSides::default());
+        assert_eq!(drawer.border_size(), 36);
+        let img = drawer.draw_initial_position(&svgs).unwrap();
+        assert!(has_label(&img, 16, 36, 20, 40));
+        assert!(!has_label(&img, 0, 36, 16, 40));
+    }
+
+    #[test]
+    fn test_termination_text_fits() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        let text = "Timeout vs insufficient material — a very long way to end a game";
+
+        let mut img = drawer.image_buffer();
+        drawer.draw_termination_text(text, &mut img, &svgs).unwrap();
+
+        // The strip covers the bottom of the board, and the text is shrunk to fit inside it
+        assert_eq!(img.get_pixel(0, 319)[3], 255);
+        assert_eq!(img.get_pixel(0, 280)[3], 0);
+        let text_columns: Vec<u32> = (0..320)
+            .filter(|x| (290..320).any(|y| *img.get_pixel(*x, y) != Rgba([249, 100, 100, 255])))
+            .collect();
+        assert!(!text_columns.is_empty());
+        assert!(*text_columns.first().unwrap() > 0);
+        assert!(*text_columns.last().unwrap() < 319);
+    }
+
+    #[test]
+    fn test_check_highlight() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        drawer.set_check_highlight(Some(Color([0, 0, 255, 255])));
+
+        let mut img = drawer.image_buffer();
+        let king = utils::PieceInBoard::new_king(Square::E1, shakmaty::Color::White);
+        drawer.draw_checked_king(king, &mut img, &svgs).unwrap();
+
+        // The corner of E1 is blended with the highlight instead of the light square
+        let pixel = img.get_pixel(4 * 40 + 1, 7 * 40 + 1);
+        assert!(pixel[2] > pixel[0]);
+        assert_eq!(pixel[3], 255);
+    }
+
+    #[test]
+    fn test_draw_trail() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        drawer.set_coordinates(CoordinateMode::None, CoordinateSides::default());
+        let svgs = roboto_svgs();
+        let position = Chess::default();
+
+        let color = Rgba([0, 0, 255, 200]);
+        let trail = VecDeque::from([
+            vec![(Square::A2, Square::A3)],
+            vec![(Square::H2, Square::H3)],
+        ]);
+        let mut img = drawer.image_buffer();
+        drawer
+            .draw_trail(&trail, 4, color, position.board(), &mut img, &svgs)
+            .unwrap();
+
+        // The newest move gets the full alpha, and the one before it a quarter less
+        let mut expected = Rgba([238, 238, 210, 255]);
+        utils::blend_pixel(&mut expected, color);
+        assert_eq!(*img.get_pixel(7 * 40 + 1, 5 * 40 + 1), expected);
+        let mut expected = Rgba([118, 150, 86, 255]);
+        utils::blend_pixel(&mut expected, Rgba([0, 0, 255, 150]));
+        assert_eq!(*img.get_pixel(1, 5 * 40 + 1), expected);
+
+        // Pieces are drawn over the tint
+        assert_ne!(img.get_pixel(20, 6 * 40 + 20), img.get_pixel(1, 6 * 40 + 1));
+
+        // Squares outside of the trail are left as they are
+        assert_eq!(img.get_pixel(3 * 40 + 1, 5 * 40 + 1)[3], 0);
+    }
+
+    #[test]
+    fn test_draw_promotion() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark.clone(), light.clone()).unwrap();
+        drawer.set_coordinates(CoordinateMode::None, CoordinateSides::default());
+        let mut svgs = roboto_svgs();
+        let underpromotion = Move::Normal {
+            role: Role::Pawn,
+            from: Square::B7,
+            capture: Some(Role::Rook),
+            to: Square::A8,
+            promotion: Some(Role::Knight),
+        };
+        let a8 = |img: &RgbaImage| imageops::crop_imm(img, 0, 0, 40, 40).to_image();
+
+        let mut img = drawer.image_buffer();
+        drawer
+            .draw_move(&underpromotion, shakmaty::Color::White, &mut img, &svgs)
+            .unwrap();
+        let glowing = drawer
+            .piece_image(
+                Role::Knight.of(shakmaty::Color::White),
+                &Square::A8,
+                40,
+                40,
+                Some("promo".to_string()),
+                &svgs,
+            )
+            .unwrap();
+        assert_eq!(a8(&img), glowing);
+
+        // Sprites without promotion artwork draw the plain piece. Pieces are cached by the
+        // drawer, so a new one is needed
+        svgs.set_pieces_sprite(Path::new("tests/fixtures/pieces_sprite.svg"))
+            .unwrap();
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        drawer.set_coordinates(CoordinateMode::None, CoordinateSides::default());
+        let mut img = drawer.image_buffer();
+        drawer
+            .draw_move(&underpromotion, shakmaty::Color::White, &mut img, &svgs)
+            .unwrap();
+        let plain = drawer
+            .piece_image(
+                Role::Knight.of(shakmaty::Color::White),
+                &Square::A8,
+                40,
+                40,
+                None,
+                &svgs,
+            )
+            .unwrap();
+        assert_eq!(a8(&img), plain);
+        assert_ne!(glowing, plain);
+    }
+
+    #[test]
+    fn test_en_passant_move_highlight() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        drawer.set_coordinates(CoordinateMode::None, CoordinateSides::default());
+        let svgs = roboto_svgs();
+        let en_passant = Move::EnPassant {
+            from: Square::E5,
+            to: Square::D6,
+        };
+
+        let mut img = drawer.image_buffer();
+        drawer
+            .draw_move(&en_passant, shakmaty::Color::White, &mut img, &svgs)
+            .unwrap();
+        assert_eq!(
+            *img.get_pixel(3 * 40 + 1, 3 * 40 + 1),
+            Rgba([238, 238, 210, 255])
+        );
+
+        // The taken pawn's square is tinted with the move highlight
+        let highlight = Color([0, 0, 255, 128]);
+        drawer.set_move_highlight(Some(highlight.clone()));
+        drawer
+            .draw_move(&en_passant, shakmaty::Color::White, &mut img, &svgs)
+            .unwrap();
+        let mut expected = Rgba([238, 238, 210, 255]);
+        utils::blend_pixel(&mut expected, Rgba(highlight.to_arr()));
+        assert_eq!(*img.get_pixel(3 * 40 + 1, 3 * 40 + 1), expected);
+    }
+
+    #[test]
+    fn test_squares_under() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        assert_eq!(drawer.squares_under(4 * 40, 6 * 40), vec![Square::E2]);
+        assert_eq!(
+            drawer.squares_under(4 * 40, 5 * 40 + 20),
+            vec![Square::E2, Square::E3]
+        );
+        assert_eq!(
+            drawer.squares_under(10, 10),
+            vec![Square::A7, Square::B7, Square::A8, Square::B8]
+        );
+
+        drawer.flip = true;
+        assert_eq!(drawer.squares_under(0, 0), vec![Square::H1]);
+    }
+
+    #[test]
+    fn test_draw_sliding_pieces() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        drawer.set_coordinates(CoordinateMode::None, CoordinateSides::default());
+        let svgs = roboto_svgs();
+
+        let mut pieces = Chess::default().board().clone();
+        pieces.discard_piece_at(Square::E2);
+        let pawn = Role::Pawn.of(shakmaty::Color::White);
+        let mut img = drawer.image_buffer();
+        let covered = drawer
+            .draw_sliding_pieces(
+                &[(pawn, Square::E2, Square::E4)],
+                0.25,
+                &pieces,
+                &[Square::E2],
+                &mut img,
+                &svgs,
+            )
+            .unwrap();
+        assert_eq!(covered, vec![Square::E2, Square::E3]);
+
+        // The pawn is a quarter of the way up, half on e2 and half on e3
+        let mut expected = square_image(&mut drawer, Square::E3, &svgs);
+        let pixmap = drawer
+            .cached_piece(shakmaty::Color::White, Role::Pawn, None, 40, 40, &svgs)
+            .unwrap();
+        utils::blend(&mut expected, &utils::pixmap_to_image(pixmap), 0, 20);
+        let slid = imageops::crop_imm(&img, 4 * 40, 5 * 40, 40, 40).to_image();
+        assert_eq!(slid, expected);
+
+        // Squares that aren't covered or redrawn are left as they are
+        assert_eq!(img.get_pixel(4 * 40 + 20, 4 * 40 + 20)[3], 0);
+    }
+
+    #[test]
+    fn test_flip_keeps_pieces_upright() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let svgs = roboto_svgs();
+        let position = Chess::default();
+        let mut images = Vec::new();
+        for flip in [false, true] {
+            let mut drawer = BoardDrawer::new(flip, 320, dark.clone(), light.clone()).unwrap();
+            drawer.set_coordinates(CoordinateMode::None, CoordinateSides::default());
+            let img = drawer.draw_position(&position, &svgs).unwrap();
+            images.push((drawer, img));
+        }
+
+        // Every square looks the same in both boards, only its position changes
+        let (drawer, img) = &images[0];
+        let (flipped_drawer, flipped_img) = &images[1];
+        for square in Square::ALL {
+            let (x, y) = drawer.square_position(&square);
+            let (flipped_x, flipped_y) = flipped_drawer.square_position(&square);
+            assert_eq!((x, y), (280 - flipped_x, 280 - flipped_y));
+            assert_eq!(
+                imageops::crop_imm(img, x, y, 40, 40).to_image(),
+                imageops::crop_imm(flipped_img, flipped_x, flipped_y, 40, 40).to_image(),
+                "{} differs when flipped",
+                square
+            );
+        }
+    }
+
+    #[test]
+    fn test_piece_decoration_flipped() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(true, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        let is_gold = |p: &Rgba<u8>| p[0] > 200 && p[1] > 150 && p[2] < 100;
+
+        let mut img = drawer.image_buffer();
+        drawer
+            .draw_piece_decoration(Square::A1, "crown", &mut img, &svgs)
+            .unwrap();
+
+        // A flipped board has A1 at the top right corner
+        assert!(is_gold(img.get_pixel(7 * 40 + 16, 7)));
+        assert!(!is_gold(img.get_pixel(16, 7 * 40 + 7)));
+    }
+
+    #[test]
+    fn test_player_bar_title_color() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        let is_title = |p: &Rgba<u8>| p[0] > 150 && p[1] < 100 && p[2] < 100;
+        let player = PlayerLabel {
+            title: Some("GM".to_string()),
+            name: "Hikaru".to_string(),
+            rating: Some(3200),
+            rating_diff: None,
+            country: None,
+        };
+        assert_eq!(player.to_string(), "GM Hikaru (3200)");
+
+        let mut plain = RgbaImage::new(320, 400);
+        drawer
+            .draw_one_player_bar(
+                &player,
+                shakmaty::Color::White,
+                &ByRole::default(),
+                None,
+                &mut plain,
+                &svgs,
+            )
+            .unwrap();
+        drawer.set_title_color(Some(Color([200, 30, 30, 255])));
+        let mut titled = RgbaImage::new(320, 400);
+        drawer
+            .draw_one_player_bar(
+                &player,
+                shakmaty::Color::White,
+                &ByRole::default(),
+                None,
+                &mut titled,
+                &svgs,
+            )
+            .unwrap();
+
+        // Only the title is drawn in its color, at the start of the bottom bar
+        let title_columns = |img: &RgbaImage| {
+            (0..320)
+                .filter(|&x| (360..400).any(|y| is_title(img.get_pixel(x, y))))
+                .collect::<Vec<u32>>()
+        };
+        assert!(title_columns(&plain).is_empty());
+        let columns = title_columns(&titled);
+        assert!(!columns.is_empty());
+        assert!(*columns.last().unwrap() < 40);
+        // The name follows the title in the bar's text color
+        assert!(has_label(&titled, 40, 360, 80, 40));
+    }
+
+    #[test]
+    fn test_player_bar_rating_diff() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        let mut draw = |rating_diff: Option<i32>| {
+            let player = PlayerLabel {
+                name: "Anna".to_string(),
+                rating_diff,
+                ..PlayerLabel::default()
+            };
+            let mut img = RgbaImage::new(320, 400);
+            drawer
+                .draw_one_player_bar(
+                    &player,
+                    shakmaty::Color::White,
+                    &ByRole::default(),
+                    None,
+                    &mut img,
+                    &svgs,
+                )
+                .unwrap();
+            img
+        };
+        let count = |img: &RgbaImage, color: Rgba<u8>| {
+            img.pixels()
+                .filter(|p| p.0.iter().zip(color.0).all(|(a, b)| a.abs_diff(b) < 8))
+                .count()
+        };
+
+        let none = draw(None);
+        let gain = draw(Some(8));
+        let loss = draw(Some(-8));
+        assert_eq!(count(&none, RATING_GAIN_COLOR), 0);
+        assert!(count(&gain, RATING_GAIN_COLOR) > 0);
+        assert_eq!(count(&gain, RATING_LOSS_COLOR), 0);
+        assert!(count(&loss, RATING_LOSS_COLOR) > 0);
+        // The change is drawn after the name, which is left as is
+        let name = |img: &RgbaImage| imageops::crop_imm(img, 0, 360, 40, 40).to_image();
+        assert_eq!(name(&gain), name(&none));
+    }
+
+    #[test]
+    fn test_player_bar_font_fallback() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let config = SVGFontConfig {
+            font_family: Some("Oswald, Noto Sans CJK, Roboto".to_string()),
+            ..SVGFontConfig::default()
+        };
+        let svgs = SVGForest::new(config, "svgs", "cburnett", "terminations").unwrap();
+        let player = PlayerLabel {
+            name: "Непомнящий".to_string(),
+            ..PlayerLabel::default()
+        };
+
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let mut img = RgbaImage::new(320, 400);
+        drawer
+            .draw_one_player_bar(
+                &player,
+                shakmaty::Color::White,
+                &ByRole::default(),
+                None,
+                &mut img,
+                &svgs,
+            )
+            .unwrap();
+
+        assert!(has_label(&img, 0, 360, 200, 40));
+    }
+
+    #[test]
+    fn test_player_bar_avatar() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let svgs = roboto_svgs();
+        let player = PlayerLabel {
+            name: "Anna".to_string(),
+            ..PlayerLabel::default()
+        };
+        // Avatars that aren't square are cropped
+        let avatar = RgbaImage::from_pixel(64, 32, Rgba([200, 30, 30, 255]));
+
+        let draw = |avatar: Option<&RgbaImage>| {
+            let mut drawer = BoardDrawer::new(false, 320, dark.clone(), light.clone()).unwrap();
+            let mut img = RgbaImage::new(320, 400);
+            drawer
+                .draw_one_player_bar(
+                    &player,
+                    shakmaty::Color::White,
+                    &ByRole::default(),
+                    avatar,
+                    &mut img,
+                    &svgs,
+                )
+                .unwrap();
+            img
+        };
+        let plain = draw(None);
+        let with_avatar = draw(Some(&avatar));
+
+        // A 30px avatar is centered in the first square of the bar
+        assert_eq!(*with_avatar.get_pixel(5, 365), Rgba([200, 30, 30, 255]));
+        assert_eq!(*with_avatar.get_pixel(34, 394), Rgba([200, 30, 30, 255]));
+        assert_eq!(*with_avatar.get_pixel(35, 395), Rgba([238, 238, 210, 255]));
+        assert_eq!(*with_avatar.get_pixel(4, 364), Rgba([238, 238, 210, 255]));
+
+        // The name is drawn the same, a square to the right
+        assert!(has_label(&plain, 0, 360, 200, 40));
+        assert_eq!(
+            imageops::crop_imm(&plain, 0, 360, 200, 40).to_image(),
+            imageops::crop_imm(&with_avatar, 40, 360, 200, 40).to_image()
+        );
+    }
+
+    #[test]
+    fn test_player_bar_country() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let draw = |country: Option<&str>, svgs: &SVGForest| {
+            let player = PlayerLabel {
+                name: "Magnus".to_string(),
+                country: country.map(str::to_string),
+                ..PlayerLabel::default()
+            };
+            let mut drawer = BoardDrawer::new(false, 320, dark.clone(), light.clone()).unwrap();
+            let mut img = RgbaImage::new(320, 400);
+            drawer
+                .draw_one_player_bar(
+                    &player,
+                    shakmaty::Color::White,
+                    &ByRole::default(),
+                    None,
+                    &mut img,
+                    svgs,
+                )
+                .unwrap();
+            imageops::crop_imm(&img, 0, 360, 320, 40).to_image()
+        };
+        let has_color = |bar: &RgbaImage, color: Rgba<u8>| bar.pixels().any(|p| *p == color);
+        let svgs = roboto_svgs();
+
+        let plain = draw(None, &svgs);
+        let flag = draw(Some("NOR"), &svgs);
+        let code = draw(Some("XYZ"), &svgs);
+        // The name is drawn the same, followed by the flag or the code
+        let name_end = (0..320)
+            .filter(|&x| (0..40).any(|y| *plain.get_pixel(x, y) != Rgba([238, 238, 210, 255])))
+            .max()
+            .unwrap();
+        let name = |bar: &RgbaImage| imageops::crop_imm(bar, 0, 0, name_end + 1, 40).to_image();
+        assert_eq!(name(&flag), name(&plain));
+        assert_eq!(name(&code), name(&plain));
+        assert!(has_color(&flag, Rgba([186, 12, 47, 255])));
+        assert!(!has_color(&code, Rgba([186, 12, 47, 255])));
+        assert_ne!(code, plain);
+    }
+
+    #[test]
+    #[cfg(feature = "include-svgs")]
+    fn test_player_bar_flag_in_svgs_path() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let svgs_path = std::env::temp_dir().join("c2g_test_flags");
+        std::fs::create_dir_all(svgs_path.join("flags")).unwrap();
+        std::fs::write(
+            svgs_path.join("flags/XYZ.svg"),
+            "<svg width=\"3\" height=\"2\" xmlns=\"http://www.w3.org/2000/svg\"><rect width=\"3\" height=\"2\" fill=\"#0000ff\"/></svg>",
+        )
+        .unwrap();
+        // Flags are looked up in svgs_path before the included ones, and anything else is
+        // still included
+        let config = SVGFontConfig {
+            font_family: Some("Roboto".to_string()),
+            ..SVGFontConfig::default()
+        };
+        let svgs = SVGForest::new(
+            config,
+            svgs_path.to_str().unwrap(),
+            "cburnett",
+            "terminations",
+        )
+        .unwrap();
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let mut img = RgbaImage::new(320, 400);
+        for country in ["XYZ", "NOR"] {
+            let player = PlayerLabel {
+                name: "Magnus".to_string(),
+                country: Some(country.to_string()),
+                ..PlayerLabel::default()
+            };
+            drawer
+                .draw_one_player_bar(
+                    &player,
+                    shakmaty::Color::White,
+                    &ByRole::default(),
+                    None,
+                    &mut img,
+                    &svgs,
+                )
+                .unwrap();
+            let flag_color = match country {
+                "XYZ" => Rgba([0, 0, 255, 255]),
+                _ => Rgba([186, 12, 47, 255]),
+            };
+            assert!(img.pixels().any(|p| *p == flag_color));
+        }
+        std::fs::remove_dir_all(svgs_path).unwrap();
+    }
+
+    #[test]
+    fn test_long_player_names_fit() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let svgs = roboto_svgs();
+        let long = PlayerLabel {
+            title: Some("GM".to_string()),
+            name: "Abcdefghij".repeat(6),
+            rating: Some(2950),
+            rating_diff: Some(-12),
+            country: None,
+        };
+        let short = PlayerLabel {
+            name: "Anna".to_string(),
+            ..PlayerLabel::default()
+        };
+        let draw = |name_fit: NameFit, player: &PlayerLabel| {
+            let mut drawer = BoardDrawer::new(false, 320, dark.clone(), light.clone()).unwrap();
+            drawer.set_name_fit(name_fit);
+            let mut img = RgbaImage::new(320, 400);
+            drawer
+                .draw_one_player_bar(
+                    player,
+                    shakmaty::Color::White,
+                    &ByRole::default(),
+                    None,
+                    &mut img,
+                    &svgs,
+                )
+                .unwrap();
+            imageops::crop_imm(&img, 0, 360, 320, 40).to_image()
+        };
+        // Text must end before the clock, 17/8 squares from the right edge
+        let text_end = |bar: &RgbaImage| {
+            (0..320)
+                .filter(|&x| (0..40).any(|y| *bar.get_pixel(x, y) != Rgba([238, 238, 210, 255])))
+                .max()
+                .unwrap()
+        };
+
+        let truncated = draw(NameFit::Truncate, &long);
+        let shrunk = draw(NameFit::Shrink, &long);
+        assert!(text_end(&truncated) < 235);
+        assert!(text_end(&shrunk) < 235);
+        // Names are cut as late as possible
+        assert!(text_end(&truncated) > 200);
+        assert_ne!(truncated, shrunk);
+
+        // Names that fit are drawn as they are
+        assert_eq!(
+            draw(NameFit::Truncate, &short),
+            draw(NameFit::Shrink, &short)
+        );
+    }
+
+    #[test]
+    fn test_event_bar() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let svgs = roboto_svgs();
+        let mut drawer = BoardDrawer::new(false, 320, dark.clone(), light.clone()).unwrap();
+        assert_eq!(drawer.event_bar_height(), 0);
+        assert_eq!(drawer.layout_height(true), 400);
+
+        drawer.set_event_bar(true);
+        assert_eq!(drawer.event_bar_height(), 20);
+        assert_eq!(drawer.board_offset(false), 20);
+        assert_eq!(drawer.board_offset(true), 60);
+        assert_eq!(drawer.layout_height(false), 340);
+        assert_eq!(drawer.layout_height(true), 420);
+
+        let board = RgbaImage::from_pixel(320, 320, Rgba([1, 2, 3, 255]));
+        let laid_out = drawer.add_bar_space(board, true);
+        assert_eq!(laid_out.dimensions(), (320, 420));
+        assert_eq!(*laid_out.get_pixel(0, 60), Rgba([1, 2, 3, 255]));
+        assert_eq!(*laid_out.get_pixel(0, 59), Rgba([0, 0, 0, 0]));
+
+        let mut draw = |text: &str| {
+            let mut img = drawer.add_bar_space(RgbaImage::new(320, 320), true);
+            drawer.draw_event_bar(text, &mut img, &svgs).unwrap();
+            imageops::crop_imm(&img, 0, 0, 320, 20).to_image()
+        };
+        let text_end = |bar: &RgbaImage| {
+            (0..320)
+                .filter(|&x| (0..20).any(|y| *bar.get_pixel(x, y) != Rgba([118, 150, 86, 255])))
+                .max()
+        };
+        let short = draw("Tata Steel Masters 2023");
+        let long = draw(&"Tata Steel Masters 2023 · Round 6 · ".repeat(4));
+        assert!(text_end(&short).unwrap() < 200);
+        // Long text is cut short right before the edge
+        assert!(text_end(&long).unwrap() > 280);
+        assert!(text_end(&long).unwrap() < 316);
+        assert_eq!(text_end(&draw("")), None);
+
+        // Faded text is drawn dimmer, down to the color of the bar
+        let mut faded = |opacity: f32| {
+            let mut img = drawer.add_bar_space(RgbaImage::new(320, 320), true);
+            drawer
+                .draw_faded_event_bar("Tata Steel Masters 2023", opacity, &mut img, &svgs)
+                .unwrap();
+            imageops::crop_imm(&img, 0, 0, 320, 20).to_image()
+        };
+        let brightest = |bar: &RgbaImage| bar.pixels().map(|p| p[0]).max().unwrap();
+        assert_eq!(faded(1.0), short);
+        assert!(brightest(&faded(0.5)) < brightest(&short));
+        assert!(brightest(&faded(0.5)) > 150);
+        assert!(brightest(&faded(0.0)) <= 120);
+    }
+
+    #[test]
+    fn test_bar_style() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let svgs = roboto_svgs();
+        let player = PlayerLabel {
+            name: "Magnus".to_string(),
+            ..PlayerLabel::default()
+        };
+        let mut drawer = BoardDrawer::new(false, 320, dark.clone(), light.clone()).unwrap();
+        drawer.set_bar_style(&BarStyle {
+            height_fraction: 0.5,
+            white_bg: Some(Color([255, 255, 255, 255])),
+            black_bg: None,
+            text: Some(Color([200, 0, 0, 255])),
+        });
+        assert_eq!(drawer.bar_height(), 20);
+        assert_eq!(drawer.board_offset(true), 20);
+        assert_eq!(drawer.layout_height(true), 360);
+
+        let mut img = drawer.add_bar_space(RgbaImage::new(320, 320), true);
+        drawer
+            .draw_one_player_bar(
+                &player,
+                shakmaty::Color::White,
+                &ByRole::default(),
+                None,
+                &mut img,
+                &svgs,
+            )
+            .unwrap();
+        drawer
+            .draw_one_player_bar(
+                &player,
+                shakmaty::Color::Black,
+                &ByRole::default(),
+                None,
+                &mut img,
+                &svgs,
+            )
+            .unwrap();
+        drawer
+            .draw_one_player_clock(
+                "1:00",
+                shakmaty::Color::White,
+                ClockStyle::Normal,
+                &mut img,
+                &svgs,
+            )
+            .unwrap();
+        let bottom = imageops::crop_imm(&img, 0, 340, 320, 20).to_image();
+        let top = imageops::crop_imm(&img, 0, 0, 320, 20).to_image();
+        // Bars are filled with their background, or the square color if there is none
+        assert_eq!(*bottom.get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+        assert_eq!(*top.get_pixel(0, 0), Rgba([118, 150, 86, 255]));
+        // Names are drawn in the text color, and the clock with the bar's colors swapped
+        assert!(top.pixels().any(|p| *p == Rgba([200, 0, 0, 255])));
+        let clock = imageops::crop_imm(&bottom, 320 - 20 * 17 / 8, 2, 40, 15).to_image();
+        assert_eq!(*clock.get_pixel(0, 0), Rgba([200, 0, 0, 255]));
+        assert!(clock.pixels().any(|p| *p == Rgba([255, 255, 255, 255])));
+        // Nothing is drawn over the board
+        assert_eq!(*img.get_pixel(0, 20), Rgba([0, 0, 0, 0]));
+        assert_eq!(*img.get_pixel(0, 339), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_single_bar_layout() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let svgs = roboto_svgs();
+        let player = PlayerLabel {
+            name: "Magnus".to_string(),
+            ..PlayerLabel::default()
+        };
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        drawer.set_bar_layout(BarLayout::Single);
+        assert_eq!(drawer.board_offset(true), 0);
+        assert_eq!(drawer.layout_height(true), 360);
+
+        let mut img = drawer.add_bar_space(RgbaImage::new(320, 320), true);
+        for color in [shakmaty::Color::White, shakmaty::Color::Black] {
+            drawer
+                .draw_one_player_bar(&player, color, &ByRole::default(), None, &mut img, &svgs)
+                .unwrap();
+            drawer
+                .draw_one_player_clock("1:00", color, ClockStyle::Normal, &mut img, &svgs)
+                .unwrap();
+        }
+        // Both players share the bar below the board, white on the left half
+        assert_eq!(*img.get_pixel(0, 359), Rgba([238, 238, 210, 255]));
+        assert_eq!(*img.get_pixel(319, 359), Rgba([118, 150, 86, 255]));
+        // Clocks go in the lower row of each half, with the bar's colors swapped
+        let white_clock = imageops::crop_imm(&img, 100, 340, 60, 20).to_image();
+        let black_clock = imageops::crop_imm(&img, 260, 340, 60, 20).to_image();
+        assert!(white_clock
+            .pixels()
+            .any(|p| *p == Rgba([118, 150, 86, 255])));
+        assert!(black_clock
+            .pixels()
+            .any(|p| *p == Rgba([238, 238, 210, 255])));
+        let black_top = imageops::crop_imm(&img, 260, 320, 60, 10).to_image();
+        assert!(black_top.pixels().all(|p| *p != Rgba([238, 238, 210, 255])));
+        // Nothing is drawn over the board
+        assert_eq!(*img.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+        assert_eq!(*img.get_pixel(0, 319), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_flag_fall_clock() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        let is_warning = |p: &Rgba<u8>| p[0] > 150 && p[1] < 100 && p[2] < 100;
+
+        let mut normal = RgbaImage::new(320, 400);
+        drawer
+            .draw_one_player_clock(
+                "0:00:00",
+                shakmaty::Color::White,
+                ClockStyle::Normal,
+                &mut normal,
+                &svgs,
+            )
+            .unwrap();
+        let mut flag_fall = RgbaImage::new(320, 400);
+        drawer
+            .draw_one_player_clock(
+                "0:00:00",
+                shakmaty::Color::White,
+                ClockStyle::FlagFall,
+                &mut flag_fall,
+                &svgs,
+            )
+            .unwrap();
+
+        // The clock of the bottom bar starts at x = 320 - 85 and y = 360 + 5
+        let clock_pixels = |img: &RgbaImage| {
+            (235..315)
+                .flat_map(|x| (365..395).map(move |y| (x, y)))
+                .filter(|&(x, y)| is_warning(img.get_pixel(x, y)))
+                .count()
+        };
+        assert_eq!(clock_pixels(&normal), 0);
+        assert!(clock_pixels(&flag_fall) > 0);
+
+        // The flag is drawn to the left of the clock
+        assert_eq!(normal.get_pixel(218, 375)[3], 0);
+        assert!(is_warning(flag_fall.get_pixel(218, 375)));
+    }
+
+    #[test]
+    fn test_long_clock_fits() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+
+        // The clock box spans x = 235 to 315, and text is kept clear of its right edge
+        for clock in ["0:03:00.0", "68:00:00.0", "123:00:00.0"] {
+            let mut img = RgbaImage::new(320, 400);
+            drawer
+                .draw_one_player_clock(
+                    clock,
+                    shakmaty::Color::White,
+                    ClockStyle::Normal,
+                    &mut img,
+                    &svgs,
+                )
+                .unwrap();
+            let edge = imageops::crop_imm(&img, 312, 365, 3, 30).to_image();
+            assert!(edge.pixels().all(|p| *p == Rgba([118, 150, 86, 255])));
+        }
+    }
+
+    #[test]
+    fn test_move_time() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        drawer.set_move_times(true);
+        let svgs = roboto_svgs();
+        let is_text = |p: &Rgba<u8>| p[0] < 200;
+
+        // The move time box spans x = 185 to 235, left of the clock, in the bar's colors
+        let mut img = RgbaImage::new(320, 400);
+        drawer
+            .draw_move_time("+0:14.0", shakmaty::Color::White, &mut img, &svgs)
+            .unwrap();
+        let move_time = imageops::crop_imm(&img, 185, 365, 50, 30).to_image();
+        assert!(move_time.pixels().any(is_text));
+        assert_eq!(img.get_pixel(184, 375)[3], 0);
+        assert_eq!(img.get_pixel(236, 375)[3], 0);
+
+        drawer
+            .draw_move_time("", shakmaty::Color::White, &mut img, &svgs)
+            .unwrap();
+        let cleared = imageops::crop_imm(&img, 185, 365, 50, 30).to_image();
+        assert!(cleared.pixels().all(|p| *p == Rgba([238, 238, 210, 255])));
+    }
+
+    #[test]
+    fn test_move_list() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        drawer.set_move_list(true);
+        let svgs = roboto_svgs();
+        assert_eq!(drawer.layout_width(), 440);
+
+        let board = drawer.add_bar_space(RgbaImage::new(320, 320), false);
+        assert_eq!(board.dimensions(), (440, 320));
+
+        // Rows are 16 pixels high, so 19 of them fit and the first rows scroll out of view
+        let moves: Vec<String> = (0..60).map(|n| format!("Nf{}", n % 8 + 1)).collect();
+        let is_light = |p: &Rgba<u8>| *p == Rgba([238, 238, 210, 255]);
+        let highlighted_rows = |img: &RgbaImage| {
+            (0..img.height())
+                .filter(|&y| is_light(img.get_pixel(439, y)))
+                .collect::<Vec<u32>>()
+        };
+        let mut img = board.clone();
+        drawer.draw_move_list(&moves, 3, &mut img, &svgs).unwrap();
+        assert_eq!(highlighted_rows(&img), (20..36).collect::<Vec<u32>>());
+
+        let mut img = board;
+        drawer.draw_move_list(&moves, 59, &mut img, &svgs).unwrap();
+        assert_eq!(highlighted_rows(&img), (292..308).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_caption() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        drawer.set_captions(true);
+        let svgs = roboto_svgs();
+        assert_eq!(drawer.layout_height(false), 352);
+
+        // Lines are 16 pixels high, under the board
+        let board = drawer.add_bar_space(RgbaImage::new(320, 320), false);
+        let has_text = |img: &RgbaImage, rows: std::ops::Range<u32>| {
+            rows.flat_map(|y| (0..320).map(move |x| (x, y)))
+                .any(|(x, y)| *img.get_pixel(x, y) != Rgba([118, 150, 86, 255]))
+        };
+        let mut img = board.clone();
+        drawer
+            .draw_caption("Threatens mate", &mut img, &svgs)
+            .unwrap();
+        assert!(has_text(&img, 320..336));
+        assert!(!has_text(&img, 336..352));
+
+        let long = "An early queen sortie, threatening the pawn on e5 and eyeing f7 right away, \
+                    which is a lot for a single move to achieve this early in the game";
+        drawer.draw_caption(long, &mut img, &svgs).unwrap();
+        assert!(has_text(&img, 320..336));
+        assert!(has_text(&img, 336..352));
+
+        drawer.draw_caption("", &mut img, &svgs).unwrap();
+        assert!(!has_text(&img, 320..352));
+    }
+
+    #[test]
+    fn test_move_overlay() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        let drawn = |img: &RgbaImage| {
+            let (xs, ys): (Vec<u32>, Vec<u32>) = img
+                .enumerate_pixels()
+                .filter(|(_, _, p)| p[3] > 0)
+                .map(|(x, y, _)| (x, y))
+                .unzip();
+            (
+                *xs.iter().min().unwrap(),
+                *ys.iter().min().unwrap(),
+                *xs.iter().max().unwrap(),
+                *ys.iter().max().unwrap(),
+            )
+        };
+
+        // The box is two and a half squares wide and half a square high, inset 2 pixels
+        let mut img = RgbaImage::new(320, 320);
+        drawer
+            .draw_move_overlay("23... Qxh2+", Corner::TopLeft, &mut img, &svgs)
+            .unwrap();
+        assert_eq!(drawn(&img), (2, 2, 101, 21));
+        assert!(img
+            .enumerate_pixels()
+            .any(|(_, _, p)| *p == Rgba([238, 238, 210, 255])));
+
+        let mut img = RgbaImage::new(320, 320);
+        drawer
+            .draw_move_overlay("24. Kxh2", Corner::BottomRight, &mut img, &svgs)
+            .unwrap();
+        assert_eq!(drawn(&img), (218, 298, 317, 317));
+    }
+
+    #[test]
+    fn test_low_time_clock() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        let is_warning = |p: &Rgba<u8>| p[0] > 150 && p[1] < 100 && p[2] < 100;
+        let draw = |drawer: &mut BoardDrawer| {
+            let mut img = RgbaImage::new(320, 400);
+            drawer
+                .draw_one_player_clock(
+                    "0:00:15",
+                    shakmaty::Color::White,
+                    ClockStyle::LowTime,
+                    &mut img,
+                    &svgs,
+                )
+                .unwrap();
+            img
+        };
+
+        // The clock is drawn in the warning color, without a flag next to it
+        let low_time = draw(&mut drawer);
+        let clock = imageops::crop_imm(&low_time, 235, 365, 80, 30).to_image();
+        assert!(clock.pixels().any(is_warning));
+        assert_eq!(low_time.get_pixel(218, 375)[3], 0);
+
+        drawer.set_low_time_bold(true);
+        let bold = draw(&mut drawer);
+        let bold_clock = imageops::crop_imm(&bold, 235, 365, 80, 30).to_image();
+        assert!(bold_clock.pixels().any(is_warning));
+    }
+
+    #[test]
+    fn test_floating_clock() {
+        let dark: Color = Color([118, 150, 86, 255]);
+        let light: Color = Color([238, 238, 210, 255]);
+        let svgs = roboto_svgs();
+        let draw = |flip: bool| {
+            let mut drawer = BoardDrawer::new(flip, 320, dark.clone(), light.clone()).unwrap();
+            let mut img = RgbaImage::new(320, 320);
+            for color in [shakmaty::Color::White, shakmaty::Color::Black] {
+                drawer
+                    .draw_floating_clock("0:00:30", color, ClockStyle::Normal, &mut img, &svgs)
+                    .unwrap();
+            }
+            img
+        };
+
+        // Boxes are 4/3 of a square wide and half a square tall, 1/16 of a square from the
+        // right corners, with the bar colors swapped
+        let img = draw(false);
+        assert_eq!(*img.get_pixel(314, 2), Rgba([238, 238, 210, 255]));
+        assert_eq!(*img.get_pixel(265, 2), Rgba([238, 238, 210, 255]));
+        assert_eq!(*img.get_pixel(314, 317), Rgba([118, 150, 86, 255]));
+        assert_eq!(img.get_pixel(264, 2)[3], 0);
+        assert_eq!(img.get_pixel(314, 23)[3], 0);
+        assert_eq!(img.get_pixel(0, 0)[3], 0);
+        // The text is drawn inside
+        let box_pixels = |y| (265..318).map(move |x| (x, y));
+        assert!(box_pixels(12).any(|(x, y)| img.get_pixel(x, y)[0] != 238));
+
+        // White's clock is at the top when the board is flipped
+        let flipped = draw(true);
+        assert_eq!(*flipped.get_pixel(314, 2), Rgba([118, 150, 86, 255]));
+        assert_eq!(*flipped.get_pixel(314, 317), Rgba([238, 238, 210, 255]));
+    }
+
+    #[test]
+    fn test_mated_king_falls_back_to_check() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(true, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+
+        // cburnett has no mate artwork
+        let mut mated = drawer.image_buffer();
+        let king = utils::PieceInBoard::new_king(Square::H6, shakmaty::Color::White);
+        drawer.draw_mated_king(king, &mut mated, &svgs).unwrap();
+
+        let mut checked = drawer.image_buffer();
+        let king = utils::PieceInBoard::new_king(Square::H6, shakmaty::Color::White);
+        drawer.draw_checked_king(king, &mut checked, &svgs).unwrap();
+
+        assert_eq!(mated, checked);
+        // Flipped H6 is drawn on A3
+        assert_eq!(mated.get_pixel(0, 5 * 40)[3], 255);
+    }
+
+    #[test]
+    fn test_check_ray() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        let is_tinted = |img: &RgbaImage, file: u32, rank: u32| {
+            let pixel = img.get_pixel(file * 40 + 20, (7 - rank) * 40 + 20);
+            pixel[3] == 255 && *pixel != Rgba([249, 100, 100, 255])
+        };
+
+        // Only the squares between a rook on A1 and a king on A5 are tinted
+        let mut img = drawer.image_buffer();
+        drawer
+            .draw_check_ray(Square::A1, Square::A5, &mut img, &svgs)
+            .unwrap();
+        assert!(!is_tinted(&img, 0, 0));
+        assert!(is_tinted(&img, 0, 1));
+        assert!(is_tinted(&img, 0, 3));
+        assert!(!is_tinted(&img, 0, 4));
+
+        // Knight checks have no ray
+        let mut img = drawer.image_buffer();
+        drawer
+            .draw_check_ray(Square::F3, Square::E1, &mut img, &svgs)
+            .unwrap();
+        assert!(img.pixels().all(|p| p[3] == 0));
+
+        // Squares are drawn where a flipped board shows them
+        drawer.flip = true;
+        let mut img = drawer.image_buffer();
+        drawer
+            .draw_check_ray(Square::A1, Square::C3, &mut img, &svgs)
+            .unwrap();
+        assert!(is_tinted(&img, 6, 6));
+        assert!(!is_tinted(&img, 1, 1));
+    }
+
+    #[test]
+    fn test_inside_coordinate_sides() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        // Coordinates are 10x10 in a 40x40 square
+        let (near, center, far) = (0, 15, 30);
+
+        let a8 = square_image(&mut drawer, Square::A8, &svgs);
+        assert!(has_label(&a8, near, near, 10, 10));
+        assert!(!has_label(&a8, center, near, 10, 10));
+        let h1 = square_image(&mut drawer, Square::H1, &svgs);
+        assert!(has_label(&h1, far, far, 10, 10));
+        assert!(!has_label(&h1, far, near, 10, 10));
+
+        drawer.set_coordinates(CoordinateMode::Inside, CoordinateSides::all());
+        let a8 = square_image(&mut drawer, Square::A8, &svgs);
+        assert!(has_label(&a8, near, near, 10, 10));
+        assert!(has_label(&a8, center, near, 10, 10));
+        let h1 = square_image(&mut drawer, Square::H1, &svgs);
+        assert!(has_label(&h1, far, far, 10, 10));
+        assert!(has_label(&h1, far, near, 10, 10));
+        let d4 = square_image(&mut drawer, Square::D4, &svgs);
+        assert!(!has_label(&d4, 0, 0, 40, 40));
+    }
+
+    #[test]
+    fn test_coordinates_inside_corner_squares() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let svgs = roboto_svgs();
+        // Bounds of the pixels that clearly differ from the square color
+        let ink_bounds = |img: &RgbaImage| {
+            let background = *img.get_pixel(img.width() / 2, img.height() / 2);
+            img.enumerate_pixels()
+                .filter(|(_, _, p)| (0..3).any(|c| (p[c] as i32 - background[c] as i32).abs() > 32))
+                .fold((u32::MAX, u32::MAX, 0, 0), |(x0, y0, x1, y1), (x, y, _)| {
+                    (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+                })
+        };
+
+        for size in [328, 488, 1000, 1040] {
+            let mut drawer = BoardDrawer::new(false, size, dark.clone(), light.clone()).unwrap();
+            drawer.set_coordinates(CoordinateMode::Inside, CoordinateSides::default());
+            let last = drawer.square_size() - 1;
+
+            // The rank on A8 in the top left corner, and the file on H1 in the bottom right one
+            let (x0, y0, _, _) = ink_bounds(&square_image(&mut drawer, Square::A8, &svgs));
+            assert!(x0 > 0 && y0 > 0, "8 clipped at {}px: {:?}", size, (x0, y0));
+            let (_, _, x1, y1) = ink_bounds(&square_image(&mut drawer, Square::H1, &svgs));
+            assert!(
+                x1 < last && y1 < last,
+                "h clipped at {}px: {:?}",
+                size,
+                (x1, y1)
+            );
+
+            // Files share a baseline, so only descenders like g's reach lower than the others
+            let bottom = |drawer: &mut BoardDrawer, square| {
+                ink_bounds(&square_image(drawer, square, &svgs)).3
+            };
+            let a = bottom(&mut drawer, Square::A1);
+            assert!(a.abs_diff(bottom(&mut drawer, Square::C1)) <= 1);
+            let g = bottom(&mut drawer, Square::G1);
+            assert!(g > a + 1 && g < last);
+        }
+    }
+
+    #[test]
+    fn test_inside_coordinate_sides_flip() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(true, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        drawer.set_coordinates(CoordinateMode::Inside, CoordinateSides::default());
+
+        // H8 is shown in the bottom left corner when flipped
+        let h8 = square_image(&mut drawer, Square::H8, &svgs);
+        assert!(has_label(&h8, 0, 0, 10, 10));
+        assert!(has_label(&h8, 30, 30, 10, 10));
+        let a1 = square_image(&mut drawer, Square::A1, &svgs);
+        assert!(!has_label(&a1, 0, 0, 40, 40));
+    }
+
+    #[test]
+    fn test_border_coordinate_sides() {
+        let dark: Color = Color([249, 100, 100, 255]);
+        let light: Color = Color([255, 253, 253, 255]);
+        let mut drawer = BoardDrawer::new(false, 320, dark, light).unwrap();
+        let svgs = roboto_svgs();
+        // The border is 20 pixels wide and the board starts at (20, 20)
+        let (border, canvas) = (20, 360);
+
+        drawer.set_coordinates(CoordinateMode::Border, CoordinateSides::default());
+        let img = drawer.draw_initial_position(&svgs).unwrap();
+        assert_eq!(img.dimensions(), (canvas, canvas));
+        assert!(has_label(&img, border, canvas - border, 40, border));
+        assert!(has_label(&img, 0, border, border, 40));
+        assert!(!has_label(&img, border, 0, 40, border));
+        assert!(!has_label(&img, canvas - border, border, border, 40));
+
+        drawer.set_coordinates(CoordinateMode::Border, CoordinateSides::all());
+        let img = drawer.draw_initial_position(&svgs).unwrap();
+        assert!(has_label(&img, border, 0, 40, border));
+        assert!(has_label(&img, canvas - border, border, border, 40));
+        // Corners are left empty
+        assert!(!has_label(&img, 0, 0, border, border));
     }
 }
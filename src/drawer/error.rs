@@ -25,6 +25,12 @@ pub enum DrawerError {
     SVGRenderError { svg: String },
     #[error("A correct SVG for {s:?} could not be produced")]
     SVGTreeFromStrError { source: usvg::Error, s: String },
+    #[error("Piece image {image:?} could not be decoded: {reason}")]
+    DecodePieceImage { image: String, reason: String },
+    #[error("Image {path:?} could not be loaded: {reason}")]
+    LoadImage { path: String, reason: String },
     #[error("An SVGTree::{s:?} could not be loaded")]
     LoadSVGTree { s: String },
+    #[error("SVGs could not be loaded: {}", failures.join(", "))]
+    LoadAssets { failures: Vec<String> },
 }
@@ -4,8 +4,8 @@ pub mod svgs;
 pub mod termination;
 pub mod utils;
 
-pub use board::BoardDrawer;
+pub use board::{BoardDrawer, ClockStyle, PlayerLabel, TextBox};
 pub use error::DrawerError;
-pub use svgs::{FontSize, FontWeight, SVGFontConfig, SVGForest};
+pub use svgs::{FontSize, FontWeight, SVGFontConfig, SVGForest, TextAnchor, TextStyle};
 pub use termination::{TerminationDrawer, TerminationReason};
 pub use utils::PieceInBoard;
@@ -1,46 +1,69 @@
+use std::collections::HashMap;
 use std::fmt;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use image::Rgba;
+use image::{Rgba, RgbaImage};
 use include_dir::{include_dir, Dir};
-use shakmaty::{self, Role};
-use usvg::{self, fontdb, Options, Tree};
+use shakmaty::{self, ByColor, Role};
+use usvg::{self, fontdb, NodeExt, Options, Tree};
 
 use super::error::DrawerError;
+use super::termination::TERMINATION_SVGS;
+use crate::error::C2GError;
 
 #[cfg(feature = "include-svgs")]
 static SVGS_DIR: Dir = include_dir!("svgs/");
 
+/// Read a file given its path relative to the SVGs directory, like cburnett/w_k.svg. Files in
+/// svgs_path take precedence over the ones included in the binary, which are only used if
+/// svgs_path is empty or doesn't have the file.
 #[cfg(feature = "include-svgs")]
-fn load_svg_string(svg_path: &str) -> Result<String, DrawerError> {
-    let svg_file = SVGS_DIR
-        .get_file(&svg_path)
+fn load_file_bytes(svgs_path: &Path, path: &Path) -> Result<Vec<u8>, DrawerError> {
+    if !svgs_path.as_os_str().is_empty() {
+        match fs::read(svgs_path.join(path)) {
+            Ok(bytes) => return Ok(bytes),
+            Err(e) => log::debug!(
+                "Failed to read {}, using the included file: {}",
+                svgs_path.join(path).display(),
+                e
+            ),
+        }
+    }
+
+    SVGS_DIR
+        .get_file(path)
+        .map(|file| file.contents().to_vec())
         .ok_or(DrawerError::SVGNotFound {
-            svg: svg_path.to_owned(),
-        })?;
-    Ok(svg_file
-        .contents_utf8()
-        .expect("Failed to parse file contents")
-        .to_owned())
+            svg: path.display().to_string(),
+        })
 }
 
 #[cfg(not(feature = "include-svgs"))]
-fn load_svg_string(svg_path: &str) -> Result<String, DrawerError> {
-    let mut f = fs::File::open(&svg_path).map_err(|_| DrawerError::SVGNotFound {
-        svg: svg_path.to_owned(),
-    })?;
-    let mut svg_str = String::new();
-    f.read_to_string(&mut svg_str)
-        .map_err(|source| DrawerError::LoadFile { source })?;
+fn load_file_bytes(svgs_path: &Path, path: &Path) -> Result<Vec<u8>, DrawerError> {
+    fs::read(svgs_path.join(path)).map_err(|_| DrawerError::SVGNotFound {
+        svg: path.display().to_string(),
+    })
+}
 
-    Ok(svg_str)
+fn load_svg_string(svgs_path: &Path, path: &Path) -> Result<String, DrawerError> {
+    let bytes = load_file_bytes(svgs_path, path)?;
+    String::from_utf8(bytes).map_err(|_| DrawerError::LoadPieceSVG {
+        source: usvg::Error::NotAnUtf8Str,
+    })
 }
 
 #[cfg(feature = "include-fonts")]
 static FONTS_DIR: Dir = include_dir!("fonts/");
 
+/// Load fonts from fonts_dir, if any, and then the ones included in the binary. Fonts are
+/// looked up in the order they were loaded, so the ones in fonts_dir take precedence.
 #[cfg(feature = "include-fonts")]
-fn load_fonts(fonts: &mut fontdb::Database, _fonts_dir: &str) {
+fn load_fonts(fonts: &mut fontdb::Database, fonts_dir: &str) {
+    if !fonts_dir.is_empty() {
+        fonts.load_fonts_dir(fonts_dir);
+    }
     for font_file in FONTS_DIR.files() {
         fonts.load_font_data(font_file.contents().to_vec())
     }
@@ -48,10 +71,49 @@ fn load_fonts(fonts: &mut fontdb::Database, _fonts_dir: &str) {
 
 #[cfg(not(feature = "include-fonts"))]
 fn load_fonts(fonts: &mut fontdb::Database, fonts_dir: &str) {
-    fonts.load_font_dir(fonts_dir);
+    fonts.load_fonts_dir(fonts_dir);
+}
+
+/// Load fallback fonts from a path, which may be a single font file or a directory of them.
+fn load_fallback_fonts(fonts: &mut fontdb::Database, path: &str) {
+    if Path::new(path).is_dir() {
+        fonts.load_fonts_dir(path);
+    } else if let Err(e) = fonts.load_font_file(path) {
+        log::warn!("Could not load fallback font {}: {}", path, e);
+    }
+}
+
+/// Split a font family list like "Roboto, 'Noto Sans CJK'" into family names.
+fn font_families(families: &str) -> Vec<String> {
+    families
+        .split(',')
+        .map(|family| family.trim().trim_matches(|c| c == '"' || c == '\'').trim())
+        .filter(|family| !family.is_empty())
+        .map(|family| family.to_owned())
+        .collect()
+}
+
+/// Reorder fonts so faces of the given families come first, in the order given. usvg looks up
+/// glyphs missing from the main font in faces by their order in the database, which makes the
+/// families a fallback chain.
+fn prioritize_families(fonts: &fontdb::Database, families: &[String]) -> fontdb::Database {
+    let mut faces = fonts.faces().to_vec();
+    faces.sort_by_key(|face| {
+        families
+            .iter()
+            .position(|family| family.eq_ignore_ascii_case(&face.family))
+            .unwrap_or(families.len())
+    });
+
+    let mut prioritized = fontdb::Database::new();
+    for face in faces {
+        prioritized.push_face_info(face);
+    }
+    prioritized
 }
 
 /// SVG font-weight attribute options
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FontWeight {
     Normal,
     Bold,
@@ -72,6 +134,23 @@ impl fmt::Display for FontWeight {
     }
 }
 
+impl FromStr for FontWeight {
+    type Err = C2GError;
+
+    fn from_str(s: &str) -> Result<Self, C2GError> {
+        match s {
+            "normal" => Ok(FontWeight::Normal),
+            "bold" => Ok(FontWeight::Bold),
+            "bolder" => Ok(FontWeight::Bolder),
+            "lighter" => Ok(FontWeight::Lighter),
+            n => match n.parse::<f32>() {
+                Ok(weight) if (1.0..=1000.0).contains(&weight) => Ok(FontWeight::Number(weight)),
+                _ => Err(C2GError::UnknownFontWeight(n.to_string())),
+            },
+        }
+    }
+}
+
 /// SVG font-size attribute options
 pub enum FontSize {
     XXSmall,
@@ -101,18 +180,127 @@ impl fmt::Display for FontSize {
     }
 }
 
+/// SVG text-anchor attribute options
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+impl fmt::Display for TextAnchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextAnchor::Start => write!(f, "start"),
+            TextAnchor::Middle => write!(f, "middle"),
+            TextAnchor::End => write!(f, "end"),
+        }
+    }
+}
+
+/// Escape characters that would otherwise be parsed as SVG markup, and drop control
+/// characters, which are not allowed in XML even when escaped.
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Build a semi-transparent arrow as an SVG polygon: a shaft of the given width that ends in
+/// a head twice as wide, with its tip at the to point.
+fn build_arrow_svg_string(
+    from: (f32, f32),
+    to: (f32, f32),
+    width: f32,
+    size: u32,
+    color: Rgba<u8>,
+) -> String {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+    // Unit vectors along and perpendicular to the arrow
+    let (ux, uy) = (dx / length, dy / length);
+    let (px, py) = (-uy, ux);
+
+    let head_length = (width * 2.0).min(length);
+    let neck = (to.0 - ux * head_length, to.1 - uy * head_length);
+    let point = |(x, y): (f32, f32), offset: f32| (x + px * offset, y + py * offset);
+
+    let points = [
+        point(from, width / 2.0),
+        point(neck, width / 2.0),
+        point(neck, width),
+        to,
+        point(neck, -width),
+        point(neck, -width / 2.0),
+        point(from, -width / 2.0),
+    ]
+    .iter()
+    .map(|(x, y)| format!("{:.2},{:.2}", x, y))
+    .collect::<Vec<String>>()
+    .join(" ");
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" height=\"{}\" width=\"{}\"><polygon points=\"{}\" fill=\"rgb({}, {}, {})\" fill-opacity=\"0.8\"/></svg>",
+        size, size, points, color[0], color[1], color[2],
+    )
+}
+
+/// A piece to draw: an SVG, or a PNG for piece sets that are only distributed as images.
+pub enum PieceSource {
+    Svg(Tree),
+    Raster(RgbaImage),
+    /// A node of a sprite SVG, fit in a cell the size of the sprite's largest piece.
+    Sprite {
+        tree: Tree,
+        node: usvg::Node,
+        cell: f64,
+    },
+}
+
+impl PieceSource {
+    /// Width of the piece divided by its height.
+    pub fn aspect_ratio(&self) -> f64 {
+        match self {
+            PieceSource::Svg(tree) => {
+                let size = tree.svg_node().size;
+                size.width() / size.height()
+            }
+            PieceSource::Raster(image) => image.width() as f64 / image.height() as f64,
+            PieceSource::Sprite { .. } => 1.0,
+        }
+    }
+}
+
+/// How the text of an SVGTree::Str is drawn: its colors, its font, and which end of it is
+/// placed at its position.
+pub struct TextStyle {
+    pub color: Rgba<u8>,
+    pub background_color: Rgba<u8>,
+    pub font_weight: FontWeight,
+    pub font_size: FontSize,
+    pub text_anchor: TextAnchor,
+}
+
 /// All SVG tree variants that can be loaded
 pub enum SVGTree {
     Str {
         s: String,
-        string_color: Rgba<u8>,
-        background_color: Rgba<u8>,
         height: u32,
         width: u32,
         x: u32,
         y: u32,
-        font_weight: FontWeight,
-        font_size: FontSize,
+        style: TextStyle,
     },
     Piece {
         role: Role,
@@ -123,13 +311,55 @@ pub enum SVGTree {
         reason: String,
         color: Option<shakmaty::Color>,
     },
+    /// Flag of a country by its code, like NOR.
+    Flag { code: String },
+    Arrow {
+        /// Start and end of the arrow in pixels, from the top left corner.
+        from: (f32, f32),
+        to: (f32, f32),
+        /// Width of the arrow shaft in pixels. The head is proportional to it.
+        width: f32,
+        /// Size of the square canvas the arrow is drawn in.
+        size: u32,
+        color: Rgba<u8>,
+    },
+    Badge {
+        text: String,
+        center: (f32, f32),
+        radius: f32,
+        /// Size of the square canvas the badge is drawn in.
+        size: u32,
+        color: Rgba<u8>,
+    },
+    Circle {
+        center: (f32, f32),
+        radius: f32,
+        /// Width of the circle stroke in pixels.
+        width: f32,
+        /// Size of the square canvas the circle is drawn in.
+        size: u32,
+        color: Rgba<u8>,
+    },
+    Hatch {
+        /// Distance between lines in pixels, measured along the sides of the canvas.
+        spacing: f32,
+        /// Width of the lines in pixels.
+        width: f32,
+        /// Size of the square canvas the hatch is drawn in.
+        size: u32,
+        color: Rgba<u8>,
+    },
 }
 
 impl SVGTree {
     /// Produce an expected file name for a given SVGTree
     pub fn svg_file(&self) -> Option<String> {
         match self {
-            SVGTree::Str { .. } => None,
+            SVGTree::Str { .. }
+            | SVGTree::Arrow { .. }
+            | SVGTree::Badge { .. }
+            | SVGTree::Circle { .. }
+            | SVGTree::Hatch { .. } => None,
             SVGTree::Piece {
                 role: r,
                 color: c,
@@ -141,23 +371,97 @@ impl SVGTree {
                 };
                 Some(s)
             }
-            SVGTree::Termination { reason: r, color } => {
-                let s = match color {
-                    Some(c) => format!("{}_{}.svg", r, c.char()),
-                    None => format!("{}.svg", r),
-                };
-                Some(s)
-            }
+            SVGTree::Termination { reason: r, color } => Some(termination_file(r, *color)),
+            SVGTree::Flag { code } => Some(format!("{}.svg", code)),
         }
     }
 }
 
+/// File name of a termination SVG given its stem, e.g. draw_w.svg for a draw circle.
+fn termination_file(stem: &str, color: Option<shakmaty::Color>) -> String {
+    match color {
+        Some(c) => format!("{}_{}.svg", stem, c.char()),
+        None => format!("{}.svg", stem),
+    }
+}
+
+/// Id of a piece in a sprite SVG, like white-knight or white-king-check for effects.
+fn sprite_id(role: Role, color: shakmaty::Color, additional: Option<&str>) -> String {
+    let color = match color {
+        shakmaty::Color::White => "white",
+        shakmaty::Color::Black => "black",
+    };
+    let role = match role {
+        Role::Pawn => "pawn",
+        Role::Knight => "knight",
+        Role::Bishop => "bishop",
+        Role::Rook => "rook",
+        Role::Queen => "queen",
+        Role::King => "king",
+    };
+    match additional {
+        Some(a) => format!("{}-{}-{}", color, role, a),
+        None => format!("{}-{}", color, role),
+    }
+}
+
+/// A single SVG with all pieces in groups named by sprite_id.
+struct PiecesSprite {
+    svg: String,
+    /// Largest width or height of the pieces, so all of them are drawn at the same scale.
+    cell: f64,
+}
+
+impl fmt::Debug for PiecesSprite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PiecesSprite")
+            .field("cell", &self.cell)
+            .finish()
+    }
+}
+
+/// A piece or termination read and checked when the forest is built. usvg trees can't be sent
+/// across threads, so only their sources are kept and trees are parsed when loaded.
+enum Asset {
+    Svg(String),
+    Raster(RgbaImage),
+}
+
+impl fmt::Debug for Asset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Asset::Svg(_) => write!(f, "Svg"),
+            Asset::Raster(image) => write!(f, "Raster({}x{})", image.width(), image.height()),
+        }
+    }
+}
+
+/// Termination SVGs drawn by c2g, besides the ones for each termination reason.
+const TERMINATION_DECORATIONS: [&str; 4] = ["crown", "flag", "win_mark", "loss_mark"];
+
+/// Effects drawn on kings, like w_k_check.svg. Families may not have all of them.
+const KING_EFFECTS: [&str; 3] = ["check", "mate", "win"];
+
+/// Effects drawn on pieces a pawn can promote to, like w_q_promo.svg. Families may not have
+/// them.
+const PROMOTION_EFFECTS: [&str; 1] = ["promo"];
+
+/// Directory of flag SVGs relative to svgs_path, named by country code like NOR.svg. Flags are
+/// only loaded when drawn, as most are never used.
+const FLAGS_DIR: &str = "flags";
+
 /// A struct to hold SVG font configuration options and provide a default
 /// configuration.
 pub struct SVGFontConfig {
     pub font_path: String,
+    /// A family or a comma separated list of them in priority order, like "Roboto, Noto Sans
+    /// CJK". Glyphs missing from the first family are looked up in the next ones.
     pub font_family: Option<String>,
     pub font_size: Option<f64>,
+    /// Font files or directories loaded after font_path, for glyphs missing from it.
+    pub fallback_paths: Vec<String>,
+    /// Whether to load fonts installed in the system, looked up last.
+    pub system_fonts: bool,
 }
 
 impl Default for SVGFontConfig {
@@ -168,6 +472,8 @@ impl Default for SVGFontConfig {
             // 16 works well with the default size of 640px but there should be a way
             // to calculate a proper default size given a board size.
             font_size: Some(16.0),
+            fallback_paths: Vec::new(),
+            system_fonts: false,
         }
     }
 }
@@ -176,8 +482,15 @@ impl Default for SVGFontConfig {
 /// methods to produce SVG trees for pieces, circles, and coordinates.
 #[derive(Debug)]
 pub struct SVGForest {
-    pieces_path: PathBuf,
-    terminations_path: PathBuf,
+    svgs_path: PathBuf,
+    /// Directories of pieces and terminations, relative to svgs_path.
+    pieces_dirs: ByColor<PathBuf>,
+    terminations_dir: PathBuf,
+    pieces_sprite: Option<PiecesSprite>,
+    /// Pieces and terminations by their path relative to svgs_path.
+    assets: HashMap<PathBuf, Asset>,
+    sources: HashMap<String, String>,
+    termination_map: HashMap<String, String>,
     svg_options: Options,
 }
 
@@ -193,9 +506,16 @@ impl SVGForest {
         // Load font for coordinates
         let mut fonts = fontdb::Database::new();
         load_fonts(&mut fonts, &font_config.font_path);
+        for path in &font_config.fallback_paths {
+            load_fallback_fonts(&mut fonts, path);
+        }
+        if font_config.system_fonts {
+            fonts.load_system_fonts();
+        }
+        let families = font_families(font_config.font_family.as_deref().unwrap_or(""));
 
         opt.keep_named_groups = true;
-        opt.fontdb = fonts;
+        opt.fontdb = prioritize_families(&fonts, &families);
 
         if let Some(s) = font_config.font_size {
             opt.font_size = s;
@@ -204,45 +524,355 @@ impl SVGForest {
             opt.font_size = 16.0;
         }
 
-        if let Some(f) = font_config.font_family {
+        if let Some(f) = families.first() {
             opt.font_family = f.to_string();
         } else {
             // If font_family is None, assume we will use the first font in DB
             opt.font_family = (*(opt.fontdb.faces())[0].family).to_owned();
         }
 
-        let (pieces_path, terminations_path) = if cfg!(feature = "include-svgs") {
-            (
-                Path::new(pieces_dir).to_path_buf(),
-                Path::new(terminations_dir).to_path_buf(),
-            )
+        let mut forest = SVGForest {
+            svgs_path: PathBuf::from(svgs_path),
+            pieces_dirs: ByColor {
+                white: PathBuf::from(pieces_dir),
+                black: PathBuf::from(pieces_dir),
+            },
+            terminations_dir: PathBuf::from(terminations_dir),
+            pieces_sprite: None,
+            assets: HashMap::new(),
+            sources: HashMap::new(),
+            termination_map: HashMap::new(),
+            svg_options: opt,
+        };
+
+        let mut failures = forest.load_pieces(Path::new(pieces_dir));
+        failures.extend(forest.load_terminations());
+        if !failures.is_empty() {
+            return Err(DrawerError::LoadAssets { failures });
+        }
+
+        Ok(forest)
+    }
+
+    /// Read and parse all pieces in a family, and the effects it has, so a broken piece set is
+    /// reported before drawing anything. Returns every file that failed to load.
+    fn load_pieces(&mut self, pieces_dir: &Path) -> Vec<String> {
+        let mut failures = Vec::new();
+        for color in shakmaty::Color::ALL {
+            for role in Role::ALL {
+                let effects = match role {
+                    Role::King => &KING_EFFECTS[..],
+                    Role::Pawn => &[],
+                    _ => &PROMOTION_EFFECTS[..],
+                };
+                for additional in [None].into_iter().chain(effects.iter().map(Some)) {
+                    let piece_tree = SVGTree::Piece {
+                        role,
+                        color,
+                        additional: additional.map(|a| a.to_string()),
+                    };
+                    let svg_file = piece_tree.svg_file().expect("Pieces have a file");
+                    if self.sources.contains_key(&svg_file) {
+                        continue;
+                    }
+
+                    let path = pieces_dir.join(svg_file);
+                    match self.read_asset(&path, true) {
+                        Ok(asset) => {
+                            self.assets.insert(path, asset);
+                        }
+                        // Effects are optional, drawers fall back to the plain piece
+                        Err(DrawerError::SVGNotFound { .. }) if additional.is_some() => (),
+                        Err(DrawerError::SVGNotFound { svg }) => {
+                            failures.push(format!("{} not found", svg))
+                        }
+                        Err(e) => failures.push(format!("{}: {}", path.display(), e)),
+                    }
+                }
+            }
+        }
+        failures
+    }
+
+    /// Read and parse all termination SVGs. Reasons only drawn for some colors, like draws,
+    /// may not have all variants, so only files that fail to parse are returned.
+    fn load_terminations(&mut self) -> Vec<String> {
+        let mut failures = Vec::new();
+        let stems = TERMINATION_SVGS
+            .iter()
+            .chain(TERMINATION_DECORATIONS.iter());
+        for stem in stems {
+            for color in [
+                None,
+                Some(shakmaty::Color::White),
+                Some(shakmaty::Color::Black),
+            ] {
+                let path = self.terminations_dir.join(termination_file(stem, color));
+                match self.read_asset(&path, false) {
+                    Ok(asset) => {
+                        self.assets.insert(path, asset);
+                    }
+                    Err(DrawerError::SVGNotFound { .. }) => (),
+                    Err(e) => failures.push(format!("{}: {}", path.display(), e)),
+                }
+            }
+        }
+        failures
+    }
+
+    /// Read an SVG and check that it parses. Pieces may also be a PNG with the same name.
+    fn read_asset(&self, path: &Path, raster: bool) -> Result<Asset, DrawerError> {
+        match load_svg_string(&self.svgs_path, path) {
+            Ok(svg) => {
+                Tree::from_str(&svg, &self.svg_options.to_ref())?;
+                Ok(Asset::Svg(svg))
+            }
+            Err(DrawerError::SVGNotFound { svg }) if raster => {
+                let png = path.with_extension("png");
+                // Report the SVG as missing, so callers can fall back from missing effects
+                let bytes = load_file_bytes(&self.svgs_path, &png)
+                    .map_err(|_| DrawerError::SVGNotFound { svg })?;
+                let image =
+                    image::load_from_memory(&bytes).map_err(|e| DrawerError::DecodePieceImage {
+                        image: png.display().to_string(),
+                        reason: e.to_string(),
+                    })?;
+                Ok(Asset::Raster(image.to_rgba8()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Build an SVGForest from SVGs already in memory, keyed by the file names given by
+    /// SVGTree::svg_file, like w_k.svg or checkmate.svg. SVGs missing from sources are still
+    /// looked up like in new, so only the ones to replace have to be given.
+    pub fn from_sources(
+        font_config: SVGFontConfig,
+        sources: HashMap<String, String>,
+    ) -> Result<Self, DrawerError> {
+        let svgs_path = if cfg!(feature = "include-svgs") {
+            ""
         } else {
-            (
-                Path::new(svgs_path).join(pieces_dir),
-                Path::new(svgs_path).join(terminations_dir),
-            )
+            "svgs"
         };
+        let mut forest = SVGForest::new(font_config, svgs_path, "cburnett", "terminations")?;
+        let failures: Vec<String> = sources
+            .iter()
+            .filter_map(|(file, svg)| {
+                let error = Tree::from_str(svg, &forest.svg_options.to_ref()).err()?;
+                Some(format!("{}: {}", file, DrawerError::from(error)))
+            })
+            .collect();
+        if !failures.is_empty() {
+            return Err(DrawerError::LoadAssets { failures });
+        }
 
-        Ok(SVGForest {
-            pieces_path: pieces_path,
-            terminations_path: terminations_path,
-            svg_options: opt,
+        forest.sources = sources;
+        Ok(forest)
+    }
+
+    /// Use a different family of pieces for one color, like "merida" for black. Effect SVGs,
+    /// like the checked king, are also looked up in that family. The family is loaded right
+    /// away, and kept unchanged if any of its pieces fail to load.
+    pub fn set_pieces_family(
+        &mut self,
+        color: shakmaty::Color,
+        pieces_dir: &str,
+    ) -> Result<(), DrawerError> {
+        let failures = self.load_pieces(Path::new(pieces_dir));
+        if !failures.is_empty() {
+            return Err(DrawerError::LoadAssets { failures });
+        }
+
+        *self.pieces_dirs.get_mut(color) = PathBuf::from(pieces_dir);
+        Ok(())
+    }
+
+    /// Load all pieces from a single sprite SVG instead of a family, like the ones used by
+    /// lichess. Pieces are groups with ids like white-knight, or white-king-check for effects.
+    pub fn set_pieces_sprite(&mut self, path: &Path) -> Result<(), DrawerError> {
+        let svg = fs::read_to_string(path)?;
+        let tree = Tree::from_str(&svg, &self.svg_options.to_ref()).map_err(|source| {
+            DrawerError::SVGTreeFromStrError {
+                source,
+                s: path.display().to_string(),
+            }
+        })?;
+        let cell = shakmaty::Color::ALL
+            .iter()
+            .flat_map(|color| {
+                Role::ALL
+                    .iter()
+                    .map(move |role| sprite_id(*role, *color, None))
+            })
+            .filter_map(|id| tree.node_by_id(&id)?.calculate_bbox())
+            .map(|bbox| bbox.width().max(bbox.height()))
+            .fold(0.0, f64::max);
+
+        if cell <= 0.0 {
+            return Err(DrawerError::SVGNotFound {
+                svg: sprite_id(Role::King, shakmaty::Color::White, None),
+            });
+        }
+        self.pieces_sprite = Some(PiecesSprite { svg, cell });
+        Ok(())
+    }
+
+    /// Map termination reasons to the stem of an SVG file in the file system to use instead
+    /// of the built-in one. Unknown reasons are never drawn, so they are warned about.
+    pub fn set_termination_map(&mut self, map: HashMap<String, String>) {
+        for reason in map.keys() {
+            if !TERMINATION_SVGS.contains(&reason.as_str()) {
+                log::warn!(
+                    "Unknown termination reason {} will be ignored, expected one of: {}",
+                    reason,
+                    TERMINATION_SVGS.join(", ")
+                );
+            }
+        }
+        self.termination_map = map;
+    }
+
+    /// Load an SVG file from the file system, like a board texture.
+    pub fn load_svg_file(&self, path: &Path) -> Result<Tree, DrawerError> {
+        let svg_string = fs::read_to_string(path)?;
+        Tree::from_str(&svg_string, &self.svg_options.to_ref()).map_err(|source| {
+            DrawerError::SVGTreeFromStrError {
+                source,
+                s: path.display().to_string(),
+            }
         })
     }
 
+    /// Load a piece from an SVGTree::Piece. If the family has no SVG for it, a PNG with the same
+    /// name is used instead, like w_k.png.
+    pub fn load_piece(&self, piece_tree: &SVGTree) -> Result<PieceSource, DrawerError> {
+        if let (
+            Some(sprite),
+            SVGTree::Piece {
+                role,
+                color,
+                additional,
+            },
+        ) = (&self.pieces_sprite, piece_tree)
+        {
+            let id = sprite_id(*role, *color, additional.as_deref());
+            let tree = Tree::from_str(&sprite.svg, &self.svg_options.to_ref())?;
+            let node = tree
+                .node_by_id(&id)
+                .ok_or(DrawerError::SVGNotFound { svg: id })?;
+            return Ok(PieceSource::Sprite {
+                tree,
+                node,
+                cell: sprite.cell,
+            });
+        }
+
+        let path = self.asset_path(piece_tree).expect("Pieces have a path");
+        let in_sources = piece_tree
+            .svg_file()
+            .is_some_and(|file| self.sources.contains_key(&file));
+        match self.assets.get(&path) {
+            Some(Asset::Raster(image)) if !in_sources => Ok(PieceSource::Raster(image.clone())),
+            Some(_) => self.load_svg_tree(piece_tree).map(PieceSource::Svg),
+            // Pieces missing when the forest was built, like effects the family doesn't have
+            None if in_sources => self.load_svg_tree(piece_tree).map(PieceSource::Svg),
+            None => match self.read_asset(&path, true)? {
+                Asset::Svg(svg) => Ok(PieceSource::Svg(Tree::from_str(
+                    &svg,
+                    &self.svg_options.to_ref(),
+                )?)),
+                Asset::Raster(image) => Ok(PieceSource::Raster(image)),
+            },
+        }
+    }
+
+    /// Path of a piece, termination, or flag SVG relative to svgs_path.
+    fn asset_path(&self, svg_tree: &SVGTree) -> Option<PathBuf> {
+        let svg_file = svg_tree.svg_file()?;
+        match svg_tree {
+            SVGTree::Piece {
+                role: _,
+                color,
+                additional: _,
+            } => Some(self.pieces_dirs.get(*color).join(svg_file)),
+            SVGTree::Termination {
+                reason: _,
+                color: _,
+            } => Some(self.terminations_dir.join(svg_file)),
+            SVGTree::Flag { code: _ } => Some(Path::new(FLAGS_DIR).join(svg_file)),
+            _ => None,
+        }
+    }
+
     pub fn load_svg_tree(&self, svg_tree: &SVGTree) -> Result<Tree, DrawerError> {
         let svg_string = match svg_tree {
             SVGTree::Str {
                 s,
-                height: h,
-                width: w,
+                height,
+                width,
                 x,
                 y,
-                background_color: b,
-                string_color: c,
-                font_weight: font_w,
-                font_size: font_s,
-            } => self.build_svg_string(s, *h, *w, *x, *y, *b, *c, font_w, font_s),
+                style,
+            } => self.build_svg_string(s, *height, *width, *x, *y, style),
+            SVGTree::Arrow {
+                from,
+                to,
+                width,
+                size,
+                color,
+            } => Ok(build_arrow_svg_string(*from, *to, *width, *size, *color)),
+            SVGTree::Badge {
+                text,
+                center,
+                radius,
+                size,
+                color,
+            } => {
+                // Texts longer than two characters, like ?!?, are shrunk to fit the circle
+                let font_size = radius * 1.3 * 2.0 / text.chars().count().max(2) as f32;
+                Ok(format!(
+                    "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" height=\"{}\" width=\"{}\"><circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"rgb({}, {}, {})\"/><text x=\"{:.2}\" y=\"{:.2}\" fill=\"rgb(255, 255, 255)\" font-weight=\"bold\" font-size=\"{:.2}px\" text-anchor=\"middle\">{}</text></svg>",
+                    size,
+                    size,
+                    center.0,
+                    center.1,
+                    radius,
+                    color[0],
+                    color[1],
+                    color[2],
+                    center.0,
+                    center.1 + font_size * 0.35,
+                    font_size,
+                    escape_xml(text),
+                ))
+            }
+            SVGTree::Circle {
+                center,
+                radius,
+                width,
+                size,
+                color,
+            } => Ok(format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" height=\"{}\" width=\"{}\"><circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"none\" stroke=\"rgb({}, {}, {})\" stroke-width=\"{:.2}\" stroke-opacity=\"0.8\"/></svg>",
+                size, size, center.0, center.1, radius, color[0], color[1], color[2], width,
+            )),
+            SVGTree::Hatch {
+                spacing,
+                width,
+                size,
+                color,
+            } => Ok(format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" height=\"{size}\" width=\"{size}\"><defs><pattern id=\"hatch\" patternUnits=\"userSpaceOnUse\" width=\"{spacing:.2}\" height=\"{spacing:.2}\"><path d=\"M{start:.2},{end:.2} L{end:.2},{start:.2} M0,{spacing:.2} L{spacing:.2},0 M{spacing_end:.2},{spacing_start:.2} L{spacing_start:.2},{spacing_end:.2}\" stroke=\"rgb({}, {}, {})\" stroke-opacity=\"{:.3}\" stroke-width=\"{width:.2}\"/></pattern></defs><rect width=\"{size}\" height=\"{size}\" fill=\"url(#hatch)\"/></svg>",
+                color[0],
+                color[1],
+                color[2],
+                color[3] as f32 / 255.0,
+                start = -spacing / 2.0,
+                end = spacing / 2.0,
+                spacing_start = spacing / 2.0,
+                spacing_end = spacing * 1.5,
+            )),
             s => self.load_svg_string_from_tree(s),
         }?;
         Tree::from_str(&svg_string, &self.svg_options.to_ref())
@@ -256,13 +886,11 @@ impl SVGForest {
         width: u32,
         x: u32,
         y: u32,
-        background_color: Rgba<u8>,
-        string_color: Rgba<u8>,
-        font_weight: &FontWeight,
-        font_size: &FontSize,
+        style: &TextStyle,
     ) -> Result<String, DrawerError> {
+        let (background_color, string_color) = (style.background_color, style.color);
         Ok(format!(
-            "<svg xmlns:svg=\"http://www.w3.org/2000/svg\" xmlns=\"http://www.w3.org/2000/svg\" version=\"1.0\" height=\"{}\" width=\"{}\" style=\"background-color:rgb({},{},{})\"> <text x=\"{}%\" y=\"{}%\" fill=\"rgb({}, {}, {})\" font-weight=\"{}\" font-size=\"{}\" dominant-baseline=\"text-bottom\" text-anchor=\"start\">{}</text></svg>",
+            "<svg xmlns:svg=\"http://www.w3.org/2000/svg\" xmlns=\"http://www.w3.org/2000/svg\" version=\"1.0\" height=\"{}\" width=\"{}\" style=\"background-color:rgb({},{},{})\"> <text x=\"{}%\" y=\"{}%\" fill=\"rgb({}, {}, {})\" font-weight=\"{}\" font-size=\"{}\" dominant-baseline=\"text-bottom\" text-anchor=\"{}\">{}</text></svg>",
             height,
             width,
             background_color[0],
@@ -273,33 +901,312 @@ impl SVGForest {
             string_color[0],
             string_color[1],
             string_color[2],
-            font_weight.to_string(),
-            font_size.to_string(),
-            s,
+            style.font_weight,
+            style.font_size,
+            style.text_anchor,
+            escape_xml(s),
         ))
     }
 
     pub fn load_svg_string_from_tree(&self, svg_tree: &SVGTree) -> Result<String, DrawerError> {
+        if let SVGTree::Termination { reason, color } = svg_tree {
+            if let Some(stem) = self.termination_map.get(reason) {
+                let path = termination_file(stem, *color);
+                match fs::read_to_string(&path) {
+                    Ok(s) => return Ok(s),
+                    Err(e) => log::warn!(
+                        "Failed to read termination SVG {}, using the built-in one: {}",
+                        path,
+                        e
+                    ),
+                }
+            }
+        }
+
         let svg_file = svg_tree.svg_file().expect("SVGTree variant not supported");
+        if let Some(s) = self.sources.get(&svg_file) {
+            return Ok(s.clone());
+        }
 
-        let full_path = match svg_tree {
-            SVGTree::Piece {
-                role: _,
-                color: _,
-                additional: _,
-            } => self.pieces_path.join(svg_file),
-            SVGTree::Termination {
-                reason: _,
-                color: _,
-            } => self.terminations_path.join(svg_file),
-            _ => {
-                return Err(DrawerError::LoadSVGTree {
-                    s: "Str".to_string(),
-                })
+        let path = self.asset_path(svg_tree).ok_or(DrawerError::LoadSVGTree {
+            s: "Str".to_string(),
+        })?;
+        match self.assets.get(&path) {
+            Some(Asset::Svg(svg)) => Ok(svg.clone()),
+            _ => load_svg_string(&self.svgs_path, &path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_font_weight_from_str() {
+        assert_eq!(FontWeight::from_str("normal").unwrap(), FontWeight::Normal);
+        assert_eq!(FontWeight::from_str("bold").unwrap(), FontWeight::Bold);
+        assert_eq!(
+            FontWeight::from_str("600").unwrap(),
+            FontWeight::Number(600.0)
+        );
+        assert!(FontWeight::from_str("heavy").is_err());
+        assert!(FontWeight::from_str("0").is_err());
+    }
+
+    #[test]
+    fn test_font_families() {
+        assert_eq!(
+            font_families("Roboto, 'Noto Sans CJK',\"Oswald\""),
+            vec!["Roboto", "Noto Sans CJK", "Oswald"]
+        );
+        assert_eq!(font_families("Roboto"), vec!["Roboto"]);
+        assert!(font_families(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_prioritize_families() {
+        let mut fonts = fontdb::Database::new();
+        fonts.load_fonts_dir("fonts/");
+
+        for first in ["Oswald", "Roboto"] {
+            let families = font_families(&format!("{}, Not Installed", first));
+            let prioritized = prioritize_families(&fonts, &families);
+            assert_eq!(prioritized.len(), fonts.len());
+            assert_eq!(prioritized.faces()[0].family, first);
+        }
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("Müller"), "Müller");
+        assert_eq!(escape_xml("Tom & Jerry <3"), "Tom &amp; Jerry &lt;3");
+        assert_eq!(
+            escape_xml("\"Bobby\" O'Brien>"),
+            "&quot;Bobby&quot; O&apos;Brien&gt;"
+        );
+        assert_eq!(escape_xml("Magnus\u{7}\u{1b} 🐴"), "Magnus 🐴");
+    }
+
+    #[test]
+    fn test_str_svg_with_hostile_text() {
+        let svgs =
+            SVGForest::new(SVGFontConfig::default(), "svgs", "cburnett", "terminations").unwrap();
+        for s in [
+            "Smith & Jones",
+            "</text><script>alert('x')</script>",
+            "\"quoted\" & 'single'",
+            "🐴 Horsey \u{0}",
+        ] {
+            let tree = SVGTree::Str {
+                s: s.to_string(),
+                height: 40,
+                width: 320,
+                x: 2,
+                y: 65,
+                style: TextStyle {
+                    color: Rgba([0, 0, 0, 255]),
+                    background_color: Rgba([255, 255, 255, 255]),
+                    font_weight: FontWeight::Bold,
+                    font_size: FontSize::Unit(20.0, "px".to_string()),
+                    text_anchor: TextAnchor::Start,
+                },
+            };
+            assert!(svgs.load_svg_tree(&tree).is_ok(), "Failed to parse {}", s);
+        }
+    }
+
+    #[test]
+    fn test_build_arrow_svg_string() {
+        let svg =
+            build_arrow_svg_string((10.0, 50.0), (90.0, 50.0), 10.0, 100, Rgba([1, 2, 3, 255]));
+        assert!(svg.contains("10.00,55.00 70.00,55.00 70.00,60.00 90.00,50.00"));
+        assert!(svg.contains("fill=\"rgb(1, 2, 3)\""));
+    }
+
+    #[test]
+    fn test_termination_map() {
+        let mut svgs =
+            SVGForest::new(SVGFontConfig::default(), "svgs", "cburnett", "terminations").unwrap();
+        let stem = std::env::temp_dir().join("c2g_test_skull");
+        let stem = stem.to_str().unwrap();
+        fs::write(format!("{}.svg", stem), "<svg>skull</svg>").unwrap();
+
+        let mut map = HashMap::new();
+        map.insert("checkmate".to_string(), stem.to_string());
+        map.insert("draw".to_string(), stem.to_string());
+        svgs.set_termination_map(map);
+
+        let checkmate = SVGTree::Termination {
+            reason: "checkmate".to_string(),
+            color: None,
+        };
+        assert_eq!(
+            svgs.load_svg_string_from_tree(&checkmate).unwrap(),
+            "<svg>skull</svg>"
+        );
+
+        // There is no skull_w.svg, so the built-in draw circle is used
+        let draw = SVGTree::Termination {
+            reason: "draw".to_string(),
+            color: Some(shakmaty::Color::White),
+        };
+        let built_in =
+            load_svg_string(Path::new("svgs"), Path::new("terminations/draw_w.svg")).unwrap();
+        assert_eq!(svgs.load_svg_string_from_tree(&draw).unwrap(), built_in);
+
+        fs::remove_file(format!("{}.svg", stem)).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "include-svgs")]
+    fn test_pieces_family_per_color() {
+        // cburnett is not in tests/fixtures, so the included one is used
+        let mut svgs = SVGForest::new(
+            SVGFontConfig::default(),
+            "tests/fixtures",
+            "cburnett",
+            "terminations",
+        )
+        .unwrap();
+        svgs.set_pieces_family(shakmaty::Color::Black, "pieces_png")
+            .unwrap();
+
+        let piece = |color, additional: Option<&str>| SVGTree::Piece {
+            role: Role::King,
+            color,
+            additional: additional.map(str::to_string),
+        };
+        assert!(matches!(
+            svgs.load_piece(&piece(shakmaty::Color::White, None)),
+            Ok(PieceSource::Svg(_))
+        ));
+        // Black pieces and their effects are only looked up in the black family
+        for additional in [None, Some("check")] {
+            assert!(matches!(
+                svgs.load_piece(&piece(shakmaty::Color::Black, additional)),
+                Ok(PieceSource::Raster(_))
+            ));
+        }
+
+        // A family that fails to load is not used
+        match svgs.set_pieces_family(shakmaty::Color::Black, "missing") {
+            Err(DrawerError::LoadAssets { failures }) => {
+                assert_eq!(failures.len(), 12);
+                assert!(failures[0].starts_with("missing/w_p.svg"));
+            }
+            other => panic!("Expected the missing family to fail, got {:?}", other),
+        }
+        assert!(matches!(
+            svgs.load_piece(&piece(shakmaty::Color::Black, None)),
+            Ok(PieceSource::Raster(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_assets_failures() {
+        let svgs_path = std::env::temp_dir().join("c2g_test_broken_family");
+        let family = svgs_path.join("broken");
+        fs::create_dir_all(&family).unwrap();
+        for file in fs::read_dir("svgs/cburnett").unwrap() {
+            let file = file.unwrap();
+            fs::copy(file.path(), family.join(file.file_name())).unwrap();
+        }
+        fs::write(family.join("w_q.svg"), "<svg>").unwrap();
+        fs::write(family.join("b_k_check.svg"), "not an svg").unwrap();
+
+        let result = SVGForest::new(
+            SVGFontConfig::default(),
+            svgs_path.to_str().unwrap(),
+            "broken",
+            "terminations",
+        );
+        match result {
+            Err(DrawerError::LoadAssets { failures }) => {
+                assert_eq!(failures.len(), 2);
+                assert!(failures[0].starts_with("broken/w_q.svg"));
+                assert!(failures[1].starts_with("broken/b_k_check.svg"));
+            }
+            other => panic!("Expected broken SVGs to fail, got {:?}", other),
+        }
+
+        fs::remove_dir_all(svgs_path).unwrap();
+    }
+
+    #[test]
+    fn test_forest_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SVGForest>();
+    }
+
+    #[test]
+    fn test_pieces_sprite() {
+        let mut svgs =
+            SVGForest::new(SVGFontConfig::default(), "svgs", "cburnett", "terminations").unwrap();
+        svgs.set_pieces_sprite(Path::new("tests/fixtures/pieces_sprite.svg"))
+            .unwrap();
+
+        let piece = |role, additional: Option<&str>| SVGTree::Piece {
+            role,
+            color: shakmaty::Color::Black,
+            additional: additional.map(str::to_string),
+        };
+        match svgs.load_piece(&piece(Role::Knight, None)).unwrap() {
+            PieceSource::Sprite { node, cell, .. } => {
+                assert_eq!(&*node.id(), "black-knight");
+                // The kings and queens are the largest pieces, with their stroke
+                assert_eq!(cell, 41.0);
             }
+            _ => panic!("Expected the knight to be loaded from the sprite"),
+        }
+
+        match svgs.load_piece(&piece(Role::King, Some("check"))) {
+            Err(DrawerError::SVGNotFound { svg }) => assert_eq!(svg, "black-king-check"),
+            _ => panic!("Expected the checked king not to be found"),
+        }
+    }
+
+    #[test]
+    fn test_from_sources() {
+        let king = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"45\" height=\"45\"/>";
+        let mut sources = HashMap::new();
+        sources.insert("w_k.svg".to_string(), king.to_string());
+        let svgs = SVGForest::from_sources(SVGFontConfig::default(), sources).unwrap();
+
+        let piece = |role| SVGTree::Piece {
+            role,
+            color: shakmaty::Color::White,
+            additional: None,
         };
+        assert_eq!(
+            svgs.load_svg_string_from_tree(&piece(Role::King)).unwrap(),
+            king
+        );
+        // Pieces not given are the built-in ones
+        assert_eq!(
+            svgs.load_svg_string_from_tree(&piece(Role::Queen)).unwrap(),
+            load_svg_string(Path::new(""), Path::new("cburnett/w_q.svg")).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "include-svgs")]
+    fn test_svgs_path_overrides_included() {
+        let svgs_path = std::env::temp_dir().join("c2g_test_svgs_path");
+        fs::create_dir_all(svgs_path.join("cburnett")).unwrap();
+        fs::write(svgs_path.join("cburnett/w_k.svg"), "<svg>king</svg>").unwrap();
+
+        assert_eq!(
+            load_svg_string(&svgs_path, Path::new("cburnett/w_k.svg")).unwrap(),
+            "<svg>king</svg>"
+        );
+        // Files missing from svgs_path are the included ones
+        let included = SVGS_DIR.get_file("cburnett/w_q.svg").unwrap();
+        assert_eq!(
+            load_svg_string(&svgs_path, Path::new("cburnett/w_q.svg")).unwrap(),
+            included.contents_utf8().unwrap()
+        );
 
-        let full_path_str = full_path.to_str().expect("Invalid SVG path");
-        load_svg_string(full_path_str)
+        fs::remove_dir_all(svgs_path).unwrap();
     }
 }
@@ -1,6 +1,6 @@
 use std::fmt;
 
-use image::{imageops, ImageBuffer, RgbaImage};
+use image::RgbaImage;
 use pgn_reader::Outcome;
 use shakmaty;
 use tiny_skia::{self, Pixmap, Transform};
@@ -8,10 +8,14 @@ use usvg::FitTo;
 
 use super::error::DrawerError;
 use super::svgs::{SVGForest, SVGTree};
-use super::utils::PieceInBoard;
+use super::utils::{self, PieceInBoard};
+use crate::config::TerminationAnchor;
+
+/// Stems of the termination SVGs that are drawn, which can be mapped to other files.
+pub const TERMINATION_SVGS: [&str; 5] = ["checkmate", "draw", "resignation", "timeout", "win"];
 
 /// All possible endings for a chess game
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TerminationReason {
     Checkmate { winner: shakmaty::Color },
     Stalemate,
@@ -48,6 +52,38 @@ impl TerminationReason {
         }
     }
 
+    /// A human readable description of the termination reason
+    pub fn description(&self) -> &'static str {
+        match self {
+            TerminationReason::Checkmate { winner: _ } => "Checkmate",
+            TerminationReason::Stalemate => "Stalemate",
+            TerminationReason::DrawAgreement => "Draw by agreement",
+            TerminationReason::DrawByRepetition => "Draw by repetition",
+            TerminationReason::Timeout { winner: _ } => "Timeout",
+            TerminationReason::Resignation { winner: _ } => "Resignation",
+            TerminationReason::InsufficientMaterial => "Insufficient material",
+            TerminationReason::DrawByTimeoutVsInsufficientMaterial => {
+                "Timeout vs insufficient material"
+            }
+        }
+    }
+
+    /// A short sentence describing how the game ended, e.g. "Checkmate — White wins".
+    pub fn summary(&self) -> String {
+        let winner = match self {
+            TerminationReason::Checkmate { winner }
+            | TerminationReason::Timeout { winner }
+            | TerminationReason::Resignation { winner } => Some(*winner),
+            _ => None,
+        };
+
+        match winner {
+            Some(shakmaty::Color::White) => format!("{} — White wins", self.description()),
+            Some(shakmaty::Color::Black) => format!("{} — Black wins", self.description()),
+            None => self.description().to_string(),
+        }
+    }
+
     pub fn is_draw(&self) -> bool {
         match self {
             TerminationReason::Stalemate
@@ -81,11 +117,71 @@ impl fmt::Display for TerminationReason {
 pub struct TerminationDrawer {
     width: u32,
     height: u32,
+    anchor: TerminationAnchor,
+    opacity: f32,
+    flip: bool,
+    marks: bool,
 }
 
 impl TerminationDrawer {
     pub fn new(width: u32, height: u32) -> Result<Self, DrawerError> {
-        Ok(TerminationDrawer { width, height })
+        Ok(TerminationDrawer {
+            width,
+            height,
+            anchor: TerminationAnchor::default(),
+            opacity: 1.0,
+            flip: false,
+            marks: false,
+        })
+    }
+
+    pub fn set_anchor(&mut self, anchor: TerminationAnchor) {
+        self.anchor = anchor;
+    }
+
+    /// Place circles as seen from black's side, like a flipped BoardDrawer.
+    pub fn set_flip(&mut self, flip: bool) {
+        self.flip = flip;
+    }
+
+    /// Draw a check mark on the winner's circle and a cross on the loser's for decisive games,
+    /// so they differ in shape and not only in color.
+    pub fn set_marks(&mut self, marks: bool) {
+        self.marks = marks;
+    }
+
+    /// Opacity of the circles drawn next, between 0.0 and 1.0. Used to fade them in.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    /// Top left corner of the circle drawn on a square of a board of board_size pixels. Circles
+    /// are kept inside the board, so they are never clipped on the edge files and ranks.
+    pub fn circle_position(&self, square: shakmaty::Square, board_size: u32) -> (u32, u32) {
+        let square = if self.flip {
+            square.flip_vertical().flip_horizontal()
+        } else {
+            square
+        };
+        let square_size = board_size / 8;
+        let square_x = square_size * u32::from(square.file());
+        let square_y = board_size - square_size * (u32::from(square.rank()) + 1);
+
+        let (x, y) = match self.anchor {
+            TerminationAnchor::Corner => (square_x as i64, square_y as i64),
+            TerminationAnchor::Center => (
+                square_x as i64 + (square_size as i64 - self.width as i64) / 2,
+                square_y as i64 + (square_size as i64 - self.height as i64) / 2,
+            ),
+            TerminationAnchor::AbovePiece => (
+                square_x as i64 + (square_size as i64 - self.width as i64) / 2,
+                square_y as i64 - self.height as i64 / 2,
+            ),
+        };
+
+        let max_x = board_size.saturating_sub(self.width) as i64;
+        let max_y = board_size.saturating_sub(self.height) as i64;
+        (x.clamp(0, max_x) as u32, y.clamp(0, max_y) as u32)
     }
 
     pub fn termination_circle_pixmap(
@@ -113,9 +209,22 @@ impl TerminationDrawer {
     }
 
     pub fn win_circle_pixmap(&self, svgs: &SVGForest) -> Result<Pixmap, DrawerError> {
+        let stem = match self.marks {
+            true => "win_mark",
+            false => "win",
+        };
+        self.circle_pixmap(stem, svgs)
+    }
+
+    /// Circle drawn on the loser's king when marks are drawn.
+    pub fn loss_mark_pixmap(&self, svgs: &SVGForest) -> Result<Pixmap, DrawerError> {
+        self.circle_pixmap("loss_mark", svgs)
+    }
+
+    fn circle_pixmap(&self, stem: &str, svgs: &SVGForest) -> Result<Pixmap, DrawerError> {
         let mut pixmap = Pixmap::new(self.width, self.height).unwrap();
         let svg_tree = SVGTree::Termination {
-            reason: "win".to_string(),
+            reason: stem.to_string(),
             color: None,
         };
         let rtree = svgs.load_svg_tree(&svg_tree)?;
@@ -123,7 +232,7 @@ impl TerminationDrawer {
         let fit_to = FitTo::Height(self.height);
         resvg::render(&rtree, fit_to, Transform::identity(), pixmap.as_mut()).ok_or(
             DrawerError::SVGRenderError {
-                svg: "win".to_string(),
+                svg: stem.to_string(),
             },
         )?;
 
@@ -137,24 +246,16 @@ impl TerminationDrawer {
         svgs: &SVGForest,
     ) -> Result<RgbaImage, DrawerError> {
         let pixmap = self.termination_circle_pixmap(color, reason, svgs)?;
-
-        ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take()).ok_or(
-            DrawerError::ImageTooBig {
-                image: format!("{}_{:?}.svg", reason, color),
-            },
-        )
+        Ok(utils::pixmap_to_image(&pixmap))
     }
 
     pub fn win_circle_image(&self, svgs: &SVGForest) -> Result<RgbaImage, DrawerError> {
         let pixmap = self.win_circle_pixmap(svgs)?;
-
-        ImageBuffer::from_raw(pixmap.width(), pixmap.height(), pixmap.take()).ok_or(
-            DrawerError::ImageTooBig {
-                image: "win.svg".to_string(),
-            },
-        )
+        Ok(utils::pixmap_to_image(&pixmap))
     }
 
+    /// Draw circles on the winner and loser kings' squares, placed by the anchor. img must only
+    /// contain the board, without player bars or border.
     pub fn draw_termination_circles(
         &mut self,
         reason: TerminationReason,
@@ -163,27 +264,30 @@ impl TerminationDrawer {
         img: &mut RgbaImage,
         svgs: &SVGForest,
     ) -> Result<(), DrawerError> {
-        let (circle_winner, circle_loser) = if reason.is_draw() {
+        let (mut circle_winner, mut circle_loser) = if reason.is_draw() {
             let c1 = self.termination_circle_image(Some(loser.color), &reason, svgs)?;
             let c2 = self.termination_circle_image(Some(winner.color), &reason, svgs)?;
             (c1, c2)
+        } else if self.marks {
+            let c1 = self.win_circle_image(svgs)?;
+            let c2 = utils::pixmap_to_image(&self.loss_mark_pixmap(svgs)?);
+            (c1, c2)
         } else {
             let c1 = self.win_circle_image(svgs)?;
             let c2 = self.termination_circle_image(None, &reason, svgs)?;
             (c1, c2)
         };
 
-        let height = img.height();
-        let width = img.width();
-
-        let winner_x = (width / 8) * u32::from(winner.square.file());
-        let winner_y = height - (width / 8) * (u32::from(winner.square.rank()) + 2);
+        if self.opacity < 1.0 {
+            utils::fade(&mut circle_winner, self.opacity);
+            utils::fade(&mut circle_loser, self.opacity);
+        }
 
-        let loser_x = (width / 8) * u32::from(loser.square.file());
-        let loser_y = height - (width / 8) * (u32::from(loser.square.rank()) + 2);
+        let (winner_x, winner_y) = self.circle_position(winner.square, img.width());
+        let (loser_x, loser_y) = self.circle_position(loser.square, img.width());
 
-        imageops::overlay(img, &circle_winner, winner_x.into(), winner_y.into());
-        imageops::overlay(img, &circle_loser, loser_x.into(), loser_y.into());
+        utils::blend(img, &circle_winner, winner_x, winner_y);
+        utils::blend(img, &circle_loser, loser_x, loser_y);
 
         Ok(())
     }
@@ -221,4 +325,78 @@ mod tests {
         assert_eq!(circle.width(), 16);
         assert_eq!(circle.height(), 16);
     }
+
+    #[test]
+    fn test_circle_pixmap_marks() {
+        let mut drawer = TerminationDrawer::new(25, 25).unwrap();
+        let config = SVGFontConfig::default();
+        let svgs = SVGForest::new(config, "svgs", "cburnett", "terminations").unwrap();
+        let win = drawer.win_circle_pixmap(&svgs).unwrap();
+
+        drawer.set_marks(true);
+        let win_mark = drawer.win_circle_pixmap(&svgs).unwrap();
+        let loss_mark = drawer.loss_mark_pixmap(&svgs).unwrap();
+
+        assert_ne!(win.data(), win_mark.data());
+        // The cross goes through the center of the circle, while the check mark is under it
+        assert_eq!(loss_mark.pixel(12, 12).unwrap().red(), 0);
+        assert_eq!(win_mark.pixel(12, 10).unwrap().red(), 255);
+    }
+
+    #[test]
+    fn test_circle_position_corner() {
+        let drawer = TerminationDrawer::new(16, 16).unwrap();
+        assert_eq!(drawer.circle_position(shakmaty::Square::A1, 320), (0, 280));
+        assert_eq!(drawer.circle_position(shakmaty::Square::H8, 320), (280, 0));
+    }
+
+    #[test]
+    fn test_circle_position_flipped() {
+        let mut drawer = TerminationDrawer::new(40, 40).unwrap();
+        drawer.set_anchor(TerminationAnchor::Corner);
+        drawer.set_flip(true);
+        assert_eq!(drawer.circle_position(shakmaty::Square::A1, 320), (280, 0));
+        assert_eq!(drawer.circle_position(shakmaty::Square::H8, 320), (0, 280));
+    }
+
+    #[test]
+    fn test_circle_position_center() {
+        let mut drawer = TerminationDrawer::new(20, 20).unwrap();
+        drawer.set_anchor(TerminationAnchor::Center);
+        assert_eq!(drawer.circle_position(shakmaty::Square::A1, 320), (10, 290));
+        assert_eq!(drawer.circle_position(shakmaty::Square::H8, 320), (290, 10));
+    }
+
+    #[test]
+    fn test_circle_position_above_piece() {
+        let mut drawer = TerminationDrawer::new(20, 20).unwrap();
+        drawer.set_anchor(TerminationAnchor::AbovePiece);
+        assert_eq!(drawer.circle_position(shakmaty::Square::A1, 320), (10, 270));
+        // Circles on the eighth rank would stick out of the board
+        assert_eq!(drawer.circle_position(shakmaty::Square::H8, 320), (290, 0));
+    }
+
+    #[test]
+    fn test_circle_position_full_square() {
+        let mut drawer = TerminationDrawer::new(40, 40).unwrap();
+        drawer.set_anchor(TerminationAnchor::AbovePiece);
+        assert_eq!(drawer.circle_position(shakmaty::Square::A1, 320), (0, 260));
+        assert_eq!(drawer.circle_position(shakmaty::Square::H8, 320), (280, 0));
+    }
+
+    #[test]
+    fn test_summary() {
+        let reason = TerminationReason::Checkmate {
+            winner: shakmaty::Color::White,
+        };
+        assert_eq!(reason.summary(), "Checkmate — White wins");
+        let reason = TerminationReason::Timeout {
+            winner: shakmaty::Color::Black,
+        };
+        assert_eq!(reason.summary(), "Timeout — Black wins");
+        assert_eq!(
+            TerminationReason::DrawByRepetition.summary(),
+            "Draw by repetition"
+        );
+    }
 }
@@ -1,7 +1,9 @@
-use shakmaty::{self, File, Rank, Role, Square};
+use image::{Rgba, RgbaImage};
+use shakmaty::{self, CastlingSide, File, Rank, Role, Square};
+use tiny_skia::Pixmap;
 
 /// A piece in a chess board
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct PieceInBoard {
     pub square: Square,
     pub role: Role,
@@ -29,6 +31,33 @@ impl PieceInBoard {
     }
 }
 
+/// Opacity used for highlight colors that don't set one.
+pub const HIGHLIGHT_ALPHA: u8 = 160;
+
+/// Color of the check ray and checking pieces when no check highlight color is set.
+pub const CHECK_RAY_COLOR: Rgba<u8> = Rgba([235, 97, 80, HIGHLIGHT_ALPHA]);
+
+/// Color of the trail of recent moves when no highlight color is set.
+pub const TRAIL_COLOR: Rgba<u8> = Rgba([255, 213, 79, HIGHLIGHT_ALPHA]);
+
+/// Color of the clock of a player that lost on time.
+pub const CLOCK_WARNING_COLOR: Rgba<u8> = Rgba([202, 52, 49, 255]);
+
+/// Squares the king and the rook end up on when castling, in that order. They only depend on
+/// the side castled to, so they are also right for Chess960, where the king and the rook may
+/// start anywhere on the back rank.
+pub fn castling_destinations(king: Square, rook: Square) -> (Square, Square) {
+    let side = if rook > king {
+        CastlingSide::KingSide
+    } else {
+        CastlingSide::QueenSide
+    };
+    (
+        Square::from_coords(side.king_to_file(), king.rank()),
+        Square::from_coords(side.rook_to_file(), king.rank()),
+    )
+}
+
 /// Check if a square contains a coordinate. Coordindates are found in the A file
 /// and first rank
 pub fn has_coordinate(s: &Square, flip: bool) -> bool {
@@ -41,10 +70,160 @@ pub fn has_coordinate(s: &Square, flip: bool) -> bool {
     }
 }
 
+/// Squares an arrow between the centers of two squares passes over, including the margin
+/// taken by the arrow head. Squares are returned in the order they are found, without repeats.
+pub fn arrow_squares(from: Square, to: Square) -> Vec<Square> {
+    let center = |s: Square| {
+        (
+            u32::from(s.file()) as f32 + 0.5,
+            u32::from(s.rank()) as f32 + 0.5,
+        )
+    };
+    let (start, end) = (center(from), center(to));
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+    let (px, py) = (-dy / length, dx / length);
+
+    let steps = (length * 8.0).ceil() as u32;
+    let mut squares = Vec::new();
+    for step in 0..=steps {
+        let t = step as f32 / steps.max(1) as f32;
+        for offset in [-0.3, 0.0, 0.3] {
+            let x = start.0 + dx * t + px * offset;
+            let y = start.1 + dy * t + py * offset;
+            if !(0.0..8.0).contains(&x) || !(0.0..8.0).contains(&y) {
+                continue;
+            }
+            let square = Square::from_coords(File::new(x as u32), Rank::new(y as u32));
+            if !squares.contains(&square) {
+                squares.push(square);
+            }
+        }
+    }
+    squares
+}
+
+/// Rightmost column of a pixmap with a pixel that isn't the background color, e.g. where a
+/// rendered text ends. Returns 0 if the pixmap only contains background.
+pub fn last_drawn_column(pixmap: &Pixmap, background: Rgba<u8>) -> u32 {
+    let width = pixmap.width() as usize;
+    pixmap
+        .pixels()
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| {
+            let c = p.demultiply();
+            p.alpha() > 0
+                && (c.red() != background[0]
+                    || c.green() != background[1]
+                    || c.blue() != background[2])
+        })
+        .map(|(i, _)| (i % width) as u32 + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Convert a rendered pixmap, which holds premultiplied colors, to an image with straight alpha
+/// that can be passed to blend.
+pub fn pixmap_to_image(pixmap: &Pixmap) -> RgbaImage {
+    let mut img = RgbaImage::new(pixmap.width(), pixmap.height());
+    for (pixel, p) in img.pixels_mut().zip(pixmap.pixels()) {
+        let c = p.demultiply();
+        *pixel = Rgba([c.red(), c.green(), c.blue(), c.alpha()]);
+    }
+    img
+}
+
+/// Blend a pixel over another one using source-over compositing with straight alpha.
+pub fn blend_pixel(bottom: &mut Rgba<u8>, top: Rgba<u8>) {
+    let top_alpha = top[3] as u32;
+    if top_alpha == 0 {
+        return;
+    }
+    // Work in units of 255 * 255 to avoid rounding the bottom's contribution twice
+    let top_weight = top_alpha * 255;
+    let bottom_weight = bottom[3] as u32 * (255 - top_alpha);
+    let alpha = top_weight + bottom_weight;
+    for channel in 0..3 {
+        bottom[channel] = ((top[channel] as u32 * top_weight
+            + bottom[channel] as u32 * bottom_weight
+            + alpha / 2)
+            / alpha) as u8;
+    }
+    bottom[3] = ((alpha + 127) / 255) as u8;
+}
+
+/// Color seen when a color is drawn over an opaque background.
+pub fn composite(color: Rgba<u8>, background: Rgba<u8>) -> Rgba<u8> {
+    let mut seen = background;
+    blend_pixel(&mut seen, color);
+    seen
+}
+
+/// Blend an overlay onto img with its top left corner at (x, y), using source-over compositing
+/// with the overlay's alpha. Parts of the overlay that fall outside of img are ignored.
+pub fn blend(img: &mut RgbaImage, overlay: &RgbaImage, x: u32, y: u32) {
+    for (overlay_x, overlay_y, pixel) in overlay.enumerate_pixels() {
+        let (img_x, img_y) = (x + overlay_x, y + overlay_y);
+        if img_x < img.width() && img_y < img.height() {
+            blend_pixel(img.get_pixel_mut(img_x, img_y), *pixel);
+        }
+    }
+}
+
+/// Dim an image in place by scaling its color channels by a factor between 0.0 and 1.0
+pub fn dim(img: &mut RgbaImage, factor: f32) {
+    let factor = factor.clamp(0.0, 1.0);
+    for pixel in img.pixels_mut() {
+        for channel in pixel.0[..3].iter_mut() {
+            *channel = (*channel as f32 * factor).round() as u8;
+        }
+    }
+}
+
+/// Convert an image to a pixmap, premultiplying its colors.
+pub fn image_to_pixmap(img: &RgbaImage) -> Option<Pixmap> {
+    let mut pixmap = Pixmap::new(img.width(), img.height())?;
+    for (pixel, color) in pixmap.pixels_mut().iter_mut().zip(img.pixels()) {
+        *pixel =
+            tiny_skia::ColorU8::from_rgba(color[0], color[1], color[2], color[3]).premultiply();
+    }
+    Some(pixmap)
+}
+
+/// Fade an image in place by scaling its alpha channel by an opacity between 0.0 and 1.0
+pub fn fade(img: &mut RgbaImage, opacity: f32) {
+    let opacity = opacity.clamp(0.0, 1.0);
+    for pixel in img.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_castling_destinations() {
+        assert_eq!(
+            castling_destinations(Square::E1, Square::H1),
+            (Square::G1, Square::F1)
+        );
+        assert_eq!(
+            castling_destinations(Square::E8, Square::A8),
+            (Square::C8, Square::D8)
+        );
+        // Chess960: the rook passes over the king's square, and the king doesn't move
+        assert_eq!(
+            castling_destinations(Square::B1, Square::A1),
+            (Square::C1, Square::D1)
+        );
+        assert_eq!(
+            castling_destinations(Square::G8, Square::H8),
+            (Square::G8, Square::F8)
+        );
+    }
+
     #[test]
     fn test_piece_in_board_new_king() {
         let piece = PieceInBoard::new_king(Square::new(0), shakmaty::Color::Black);
@@ -74,4 +253,100 @@ mod tests {
         let square = Square::new(63); // H8
         assert!(has_coordinate(&square, true));
     }
+
+    #[test]
+    fn test_dim() {
+        let mut img = RgbaImage::from_pixel(1, 1, image::Rgba([200, 100, 50, 255]));
+        dim(&mut img, 0.5);
+        assert_eq!(img.get_pixel(0, 0), &image::Rgba([100, 50, 25, 255]));
+    }
+
+    #[test]
+    fn test_composite() {
+        let background = image::Rgba([0, 0, 0, 255]);
+        assert_eq!(
+            composite(image::Rgba([200, 100, 50, 255]), background),
+            image::Rgba([200, 100, 50, 255])
+        );
+        assert_eq!(
+            composite(image::Rgba([200, 100, 50, 128]), background),
+            image::Rgba([100, 50, 25, 255])
+        );
+        assert_eq!(
+            composite(image::Rgba([200, 100, 50, 0]), background),
+            background
+        );
+    }
+
+    #[test]
+    fn test_blend_pixel() {
+        let mut pixel = image::Rgba([0, 0, 0, 255]);
+        blend_pixel(&mut pixel, image::Rgba([255, 255, 255, 128]));
+        assert_eq!(pixel, image::Rgba([128, 128, 128, 255]));
+
+        // Transparent overlays leave the pixel untouched, opaque ones replace it
+        blend_pixel(&mut pixel, image::Rgba([255, 0, 0, 0]));
+        assert_eq!(pixel, image::Rgba([128, 128, 128, 255]));
+        blend_pixel(&mut pixel, image::Rgba([255, 0, 0, 255]));
+        assert_eq!(pixel, image::Rgba([255, 0, 0, 255]));
+
+        // Over a transparent pixel the overlay keeps its own color and alpha
+        let mut pixel = image::Rgba([0, 0, 0, 0]);
+        blend_pixel(&mut pixel, image::Rgba([10, 20, 30, 100]));
+        assert_eq!(pixel, image::Rgba([10, 20, 30, 100]));
+    }
+
+    #[test]
+    fn test_blend_clips() {
+        let mut img = RgbaImage::from_pixel(4, 4, image::Rgba([0, 0, 0, 255]));
+        let overlay = RgbaImage::from_pixel(2, 2, image::Rgba([255, 255, 255, 255]));
+        blend(&mut img, &overlay, 3, 3);
+        assert_eq!(img.get_pixel(3, 3), &image::Rgba([255, 255, 255, 255]));
+        assert_eq!(img.get_pixel(2, 3), &image::Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_fade() {
+        let mut img = RgbaImage::from_pixel(1, 1, image::Rgba([255, 170, 0, 200]));
+        fade(&mut img, 0.5);
+        assert_eq!(img.get_pixel(0, 0), &image::Rgba([255, 170, 0, 100]));
+        fade(&mut img, 0.0);
+        assert_eq!(img.get_pixel(0, 0)[3], 0);
+    }
+
+    #[test]
+    fn test_image_to_pixmap() {
+        let img = RgbaImage::from_pixel(1, 1, image::Rgba([200, 100, 50, 128]));
+        let pixmap = image_to_pixmap(&img).unwrap();
+        let pixel = pixmap.pixels()[0];
+        assert_eq!(pixel.alpha(), 128);
+        assert_eq!(pixel.red(), 100);
+
+        let round_trip = pixmap_to_image(&pixmap);
+        assert!((round_trip.get_pixel(0, 0)[0] as i32 - 200).abs() <= 1);
+    }
+
+    #[test]
+    fn test_pixmap_to_image() {
+        let mut pixmap = Pixmap::new(1, 1).unwrap();
+        pixmap.fill(tiny_skia::Color::from_rgba8(200, 100, 50, 128));
+        let img = pixmap_to_image(&pixmap);
+        let pixel = img.get_pixel(0, 0);
+        assert_eq!(pixel[3], 128);
+        assert!((pixel[0] as i32 - 200).abs() <= 1);
+        assert!((pixel[2] as i32 - 50).abs() <= 1);
+    }
+
+    #[test]
+    fn test_arrow_squares() {
+        let squares = arrow_squares(Square::E2, Square::E4);
+        assert_eq!(squares, vec![Square::E2, Square::E3, Square::E4]);
+
+        // Knight moves cut through the corners of the squares in between
+        let squares = arrow_squares(Square::G1, Square::F3);
+        assert!(squares.contains(&Square::G1));
+        assert!(squares.contains(&Square::F3));
+        assert!(squares.contains(&Square::G2));
+        assert!(squares.contains(&Square::F2));
+    }
 }
@@ -11,18 +11,96 @@ pub enum C2GError {
         #[from]
         source: std::io::Error,
     },
+    #[error("PGN file not found: {0}")]
+    PgnFileNotFound(String),
+    #[error("Failed to read PGN file {path}")]
+    ReadPgnFile {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("File {path} is not a valid PGN: {reason}")]
+    InvalidPgnFile { path: String, reason: String },
+    #[cfg(feature = "net")]
+    #[error(transparent)]
+    NetError {
+        #[from]
+        source: crate::net::NetError,
+    },
+    #[error("Failed to write metadata to {path}")]
+    WriteMetadata {
+        path: String,
+        source: std::io::Error,
+    },
     #[error(transparent)]
     GIFRenderingError {
         #[from]
         source: GifferError,
     },
-    #[error("Size is not divisible by 8")]
-    NotDivisibleBy8,
+    #[error("Size must be at least 64 pixels, got {0}")]
+    SizeTooSmall(u32),
     #[error("Unknown style {0}")]
     UnknownStyle(String),
     #[error("Unable to parse duration {0}")]
     CannotParseDuration(String),
-    #[error("Unable to parse color string {color}")]
+    #[error("Encoder speed must be a number between 1 and 30, got {0}")]
+    InvalidEncoderSpeed(String),
+    #[error("Supersample must be a number between 1 and 4, got {0}")]
+    InvalidSupersample(String),
+    #[error("Unknown initial position option {0}")]
+    UnknownInitialFrame(String),
+    #[error("Unknown coordinates option {0}")]
+    UnknownCoordinateMode(String),
+    #[error("Unknown coordinate side {0}")]
+    UnknownCoordinateSide(String),
+    #[error("Unknown font weight {0}")]
+    UnknownFontWeight(String),
+    #[error("Unable to parse coordinate size {0}")]
+    CannotParseCoordinateSize(String),
+    #[error("Unknown pace {0}")]
+    UnknownPace(String),
+    #[error("Unknown real delay fallback {0}, expected opponent, average or first-frame")]
+    UnknownRealDelayFallback(String),
+    #[error("Unknown clock simulation {0}, expected even or curve")]
+    UnknownClockSimulation(String),
+    #[error("Unable to parse delay ramp {ramp}: {reason}")]
+    CannotParseDelayRamp { ramp: String, reason: String },
+    #[error("Termination scale must be a number greater than 0 and at most 1, got {0}")]
+    InvalidTerminationScale(String),
+    #[error("Piece scale must be a number greater than 0 and at most 1, got {0}")]
+    InvalidPieceScale(String),
+    #[error("Watermark opacity must be a number between 0 and 1, got {0}")]
+    InvalidWatermarkOpacity(String),
+    #[error("Watermark scale must be a number greater than 0 and at most 1, got {0}")]
+    InvalidWatermarkScale(String),
+    #[error("Bar height must be a fraction of a square greater than 0 and at most 2, got {0}")]
+    InvalidBarHeight(String),
+    #[error("Termination fade must be a number of frames between 0 and 10, got {0}")]
+    InvalidTerminationFade(String),
+    #[error("Animated moves must have between 0 and 10 frames, got {0}")]
+    InvalidAnimateMoves(String),
+    #[error("Trail must be a number of moves between 0 and 16, got {0}")]
+    InvalidTrail(String),
+    #[error("Unknown termination anchor {0}")]
+    UnknownTerminationAnchor(String),
+    #[error("Unknown orientation {0}")]
+    UnknownOrientation(String),
+    #[error("Unknown name fit {0}, expected truncate or shrink")]
+    UnknownNameFit(String),
+    #[error("Unknown bar layout {0}, expected double or single")]
+    UnknownBarLayout(String),
+    #[error("Unknown clock format {0}, expected smart, compact or full")]
+    UnknownClockFormat(String),
+    #[error("Unknown corner {0}, expected top-left, top-right, bottom-left or bottom-right")]
+    UnknownCorner(String),
+    #[error("Unable to parse canvas {0}, expected WIDTHxHEIGHT")]
+    CannotParseCanvas(String),
+    #[error("Unable to parse termination SVG {0}, expected reason=file")]
+    CannotParseTerminationSvg(String),
+    #[error("Failed to read termination SVG map {path}: {reason}")]
+    ReadTerminationMap { path: String, reason: String },
+    #[error("Failed to read watermark {path}: {reason}")]
+    ReadWatermark { path: String, reason: String },
+    #[error("Unable to parse color string {color}: {reason}")]
     CannotParseColor { color: String, reason: String },
     #[error("Clap failed")]
     ClapError {
@@ -35,11 +113,49 @@ impl C2GError {
     pub fn exit(&self) -> ! {
         match self {
             C2GError::ClapError { source: s } => s.exit(),
+            #[cfg(feature = "net")]
+            C2GError::NetError { source: _ } => {
+                eprintln!("Error: {}", self);
+                process::exit(1);
+            }
             C2GError::UnknownStyle(_)
             | C2GError::GIFRenderingError { source: _ }
             | C2GError::ReadGame { source: _ }
-            | C2GError::NotDivisibleBy8
+            | C2GError::PgnFileNotFound(_)
+            | C2GError::WriteMetadata { path: _, source: _ }
+            | C2GError::ReadPgnFile { path: _, source: _ }
+            | C2GError::InvalidPgnFile { path: _, reason: _ }
+            | C2GError::SizeTooSmall(_)
             | C2GError::CannotParseDuration(_)
+            | C2GError::UnknownPace(_)
+            | C2GError::UnknownRealDelayFallback(_)
+            | C2GError::UnknownClockSimulation(_)
+            | C2GError::UnknownInitialFrame(_)
+            | C2GError::UnknownCoordinateMode(_)
+            | C2GError::UnknownCoordinateSide(_)
+            | C2GError::UnknownFontWeight(_)
+            | C2GError::CannotParseCoordinateSize(_)
+            | C2GError::InvalidEncoderSpeed(_)
+            | C2GError::InvalidSupersample(_)
+            | C2GError::CannotParseDelayRamp { ramp: _, reason: _ }
+            | C2GError::InvalidTerminationScale(_)
+            | C2GError::InvalidTerminationFade(_)
+            | C2GError::InvalidAnimateMoves(_)
+            | C2GError::InvalidTrail(_)
+            | C2GError::InvalidPieceScale(_)
+            | C2GError::InvalidWatermarkOpacity(_)
+            | C2GError::InvalidWatermarkScale(_)
+            | C2GError::InvalidBarHeight(_)
+            | C2GError::UnknownTerminationAnchor(_)
+            | C2GError::UnknownOrientation(_)
+            | C2GError::UnknownNameFit(_)
+            | C2GError::UnknownBarLayout(_)
+            | C2GError::UnknownClockFormat(_)
+            | C2GError::UnknownCorner(_)
+            | C2GError::CannotParseCanvas(_)
+            | C2GError::CannotParseTerminationSvg(_)
+            | C2GError::ReadTerminationMap { path: _, reason: _ }
+            | C2GError::ReadWatermark { path: _, reason: _ }
             | C2GError::CannotParseColor {
                 color: _,
                 reason: _,
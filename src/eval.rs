@@ -0,0 +1,187 @@
+use std::fmt;
+use std::str::FromStr;
+
+use image::Rgba;
+use regex::Regex;
+use shakmaty::Color;
+
+/// Evaluations are capped at this many centipawns, so swings between already won positions
+/// don't count as mistakes. Mates count as the cap.
+const MAX_CENTIPAWNS: i32 = 1000;
+
+/// Losing more than this many centipawns is a blunder.
+pub const BLUNDER_THRESHOLD: i32 = 300;
+
+/// Losing at least this many centipawns is a mistake.
+pub const MISTAKE_THRESHOLD: i32 = 100;
+
+/// Gaining at least this many centipawns over the previous evaluation is a great move.
+pub const GREAT_MOVE_THRESHOLD: i32 = 100;
+
+/// An engine evaluation from white's point of view, as found in [%eval ...] comments.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Eval {
+    /// Advantage in centipawns, e.g. [%eval -1.25] is -125.
+    Centipawns(i32),
+    /// Mate in a number of moves, negative if black mates, e.g. [%eval #-3].
+    Mate(i32),
+}
+
+impl Eval {
+    /// Parse the first %eval command in a comment, if any.
+    pub fn from_comment(comment: &str) -> Option<Self> {
+        let re = Regex::new(r"\[%eval\s+([^\]\s,]+)").unwrap();
+        re.captures(comment)
+            .and_then(|caps| caps[1].parse::<Eval>().ok())
+    }
+
+    /// Evaluation in centipawns from white's point of view, capped to +/- MAX_CENTIPAWNS.
+    pub fn centipawns(&self) -> i32 {
+        match self {
+            Eval::Centipawns(cp) => (*cp).clamp(-MAX_CENTIPAWNS, MAX_CENTIPAWNS),
+            Eval::Mate(n) if *n < 0 => -MAX_CENTIPAWNS,
+            Eval::Mate(_) => MAX_CENTIPAWNS,
+        }
+    }
+}
+
+impl FromStr for Eval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('#') {
+            Some(n) => n
+                .parse::<i32>()
+                .map(Eval::Mate)
+                .map_err(|_| format!("Invalid mate evaluation: {}", s)),
+            None => s
+                .parse::<f32>()
+                .map(|pawns| Eval::Centipawns((pawns * 100.0).round() as i32))
+                .map_err(|_| format!("Invalid evaluation: {}", s)),
+        }
+    }
+}
+
+/// Classification of a move given how much it changed the evaluation for the side that moved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveClass {
+    Great,
+    Mistake,
+    Blunder,
+}
+
+impl MoveClass {
+    /// Classify a move by the evaluations before and after it was played. Returns None for moves
+    /// that are not worth highlighting.
+    pub fn classify(before: Eval, after: Eval, mover: Color) -> Option<Self> {
+        let sign = match mover {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+        let change = (after.centipawns() - before.centipawns()) * sign;
+
+        if -change > BLUNDER_THRESHOLD {
+            Some(MoveClass::Blunder)
+        } else if -change >= MISTAKE_THRESHOLD {
+            Some(MoveClass::Mistake)
+        } else if change >= GREAT_MOVE_THRESHOLD {
+            Some(MoveClass::Great)
+        } else {
+            None
+        }
+    }
+
+    /// Badge background color, similar to the ones used by chess sites.
+    pub fn color(&self) -> Rgba<u8> {
+        match self {
+            MoveClass::Great => Rgba([92, 139, 176, 255]),
+            MoveClass::Mistake => Rgba([230, 143, 0, 255]),
+            MoveClass::Blunder => Rgba([202, 52, 49, 255]),
+        }
+    }
+}
+
+impl fmt::Display for MoveClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveClass::Great => write!(f, "!"),
+            MoveClass::Mistake => write!(f, "?"),
+            MoveClass::Blunder => write!(f, "??"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_from_comment() {
+        assert_eq!(
+            Eval::from_comment("[%eval 0.17] [%clk 0:02:59]"),
+            Some(Eval::Centipawns(17))
+        );
+        assert_eq!(
+            Eval::from_comment("[%clk 0:02:59] [%eval -1.5]"),
+            Some(Eval::Centipawns(-150))
+        );
+        assert_eq!(Eval::from_comment("[%eval #-3]"), Some(Eval::Mate(-3)));
+        assert_eq!(
+            Eval::from_comment("[%eval 0.3,22]"),
+            Some(Eval::Centipawns(30))
+        );
+        assert_eq!(Eval::from_comment("[%clk 0:02:59]"), None);
+        assert_eq!(Eval::from_comment("[%eval ?]"), None);
+    }
+
+    #[test]
+    fn test_centipawns_capped() {
+        assert_eq!(Eval::Centipawns(2500).centipawns(), MAX_CENTIPAWNS);
+        assert_eq!(Eval::Mate(2).centipawns(), MAX_CENTIPAWNS);
+        assert_eq!(Eval::Mate(-1).centipawns(), -MAX_CENTIPAWNS);
+    }
+
+    #[test]
+    fn test_classify_thresholds() {
+        let classify = |before, after| {
+            MoveClass::classify(
+                Eval::Centipawns(before),
+                Eval::Centipawns(after),
+                Color::White,
+            )
+        };
+
+        assert_eq!(classify(0, -301), Some(MoveClass::Blunder));
+        assert_eq!(classify(0, -300), Some(MoveClass::Mistake));
+        assert_eq!(classify(0, -100), Some(MoveClass::Mistake));
+        assert_eq!(classify(0, -99), None);
+        assert_eq!(classify(0, 99), None);
+        assert_eq!(classify(0, 100), Some(MoveClass::Great));
+    }
+
+    #[test]
+    fn test_classify_black() {
+        let before = Eval::Centipawns(-50);
+        assert_eq!(
+            MoveClass::classify(before, Eval::Centipawns(300), Color::Black),
+            Some(MoveClass::Blunder)
+        );
+        assert_eq!(
+            MoveClass::classify(before, Eval::Mate(-2), Color::Black),
+            Some(MoveClass::Great)
+        );
+    }
+
+    #[test]
+    fn test_classify_already_winning() {
+        // Evaluations are capped, so only the drop from +10 counts
+        assert_eq!(
+            MoveClass::classify(Eval::Centipawns(1500), Eval::Centipawns(900), Color::White),
+            Some(MoveClass::Mistake)
+        );
+        assert_eq!(
+            MoveClass::classify(Eval::Mate(3), Eval::Centipawns(1200), Color::White),
+            None
+        );
+    }
+}
@@ -1,31 +1,45 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io::{self, BufWriter, Write};
-use std::ops::Sub;
+use std::ops::{Range, Sub};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
 use gif::{self, Encoder, Frame, Repeat};
-use image::RgbaImage;
-use pgn_reader::{Outcome, RawComment, RawHeader, SanPlus, Skip, Visitor};
+use image::{imageops, Pixel, RgbaImage};
+use pgn_reader::{Nag, Outcome, RawComment, RawHeader, SanPlus, Skip, Visitor};
 use rayon::iter::ParallelBridge;
 use rayon::iter::ParallelIterator;
 use regex::Regex;
-use shakmaty::{Chess, Color, Position, Role, Square};
+use serde::Serialize;
+use shakmaty::{Board, ByColor, ByRole, Chess, Color, Move, Position, Role, Square};
 use thiserror::Error;
 
-use crate::config::{Config, Output};
-use crate::delay::Delay;
+use crate::annotation::{comment_text, Annotations, MoveAssessment};
+use crate::config::{
+    ClockFormat, Config, CoordinateFont, CoordinateMode, Corner, InitialFrame, Orientation, Output,
+    TextOverlay,
+};
+use crate::delay::{ClockSimulation, Delay, KeyMomentFactors, Pace, RealDelayFallback};
 use crate::drawer::{
-    BoardDrawer, DrawerError, PieceInBoard, SVGFontConfig, SVGForest, TerminationDrawer,
-    TerminationReason,
+    utils, BoardDrawer, ClockStyle, DrawerError, PieceInBoard, PlayerLabel, SVGFontConfig,
+    SVGForest, TerminationDrawer, TerminationReason,
 };
+use crate::eval::{Eval, MoveClass};
+use crate::progress::{Progress, Stage};
+use crate::time_control::TimeControl;
 
 /// A player during a GIF frame. Used to add player bars at the top and the bottom of the GIF.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Player {
     name: Option<String>,
     title: Option<String>,
     elo: Option<u32>,
+    rating_diff: Option<i32>,
+    country: Option<String>,
 }
 
 impl Default for Player {
@@ -34,27 +48,50 @@ impl Default for Player {
             name: None,
             title: None,
             elo: None,
+            rating_diff: None,
+            country: None,
+        }
+    }
+}
+
+impl Player {
+    /// Text of the player's bar: their title, name, rating, rating change, and country.
+    /// placeholder is used as the name of players without one.
+    pub fn label(&self, placeholder: &str) -> PlayerLabel {
+        let name = self.name.as_deref().unwrap_or(placeholder);
+        PlayerLabel {
+            title: self.title.clone(),
+            name: name.to_string(),
+            rating: self.elo,
+            rating_diff: self.rating_diff,
+            country: self.country.clone(),
         }
     }
 }
 
 impl fmt::Display for Player {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let player_string = match &self.title {
-            Some(s) => format!(
-                "{} {}",
-                s,
-                self.name.as_ref().unwrap_or(&"Anonymous".to_string())
-            ),
-            None => format!("{}", self.name.as_ref().unwrap_or(&"Anonymous".to_string())),
-        };
-        match &self.elo {
-            Some(n) => write!(f, "{} ({})", player_string, n),
-            None => write!(f, "{}", player_string),
-        }
+        write!(f, "{}", self.label("Anonymous"))
     }
 }
 
+/// Information about a rendered game, like players and result, and the delays of every frame
+/// written to the GIF.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GameMetadata {
+    pub white: Option<Player>,
+    pub black: Option<Player>,
+    pub event: Option<String>,
+    pub date: Option<String>,
+    pub eco: Option<String>,
+    /// Result of the game like 1-0, 0-1, or 1/2-1/2. An asterisk if the game didn't end.
+    pub result: String,
+    pub termination: Option<String>,
+    pub ply_count: usize,
+    /// Delay of each frame in hundredths of a second, as written to the GIF.
+    pub frame_delays_cs: Vec<u16>,
+}
+
 /// Both players during a frame or turn in the game.
 #[derive(Clone, Debug)]
 pub struct Players {
@@ -72,10 +109,20 @@ impl Default for Players {
 }
 
 impl Players {
-    /// Convenience method to check if player headers were found
+    /// Convenience method to check if any player headers were found
     pub fn exist(&self) -> bool {
         log::debug!("White: {:?}, Black: {:?}", self.white, self.black);
-        self.white.is_some() && self.black.is_some()
+        self.white.is_some() || self.black.is_some()
+    }
+
+    /// Label of color's player bar. Players without headers are shown with placeholder as
+    /// their name.
+    pub fn label(&self, color: shakmaty::Color, placeholder: &str) -> PlayerLabel {
+        let player = match color {
+            shakmaty::Color::White => self.white.as_ref(),
+            shakmaty::Color::Black => self.black.as_ref(),
+        };
+        player.cloned().unwrap_or_default().label(placeholder)
     }
 
     pub fn update_player_name(&mut self, color: shakmaty::Color, name: &str) {
@@ -129,6 +176,42 @@ impl Players {
         };
     }
 
+    /// Set the rating change of color's player, creating them if missing.
+    pub fn update_player_rating_diff(&mut self, color: shakmaty::Color, rating_diff: i32) {
+        let player = match color {
+            shakmaty::Color::White => &mut self.white,
+            shakmaty::Color::Black => &mut self.black,
+        };
+        player.get_or_insert_with(Player::default).rating_diff = Some(rating_diff);
+    }
+
+    /// Set the country code of color's player, creating them if missing.
+    pub fn update_player_country(&mut self, color: shakmaty::Color, country: String) {
+        let player = match color {
+            shakmaty::Color::White => &mut self.white,
+            shakmaty::Color::Black => &mut self.black,
+        };
+        player.get_or_insert_with(Player::default).country = Some(country);
+    }
+
+    /// Color of the player whose name contains name, ignoring case. Black is checked first.
+    pub fn color_of(&self, name: &str) -> Option<shakmaty::Color> {
+        let name = name.to_lowercase();
+        let matches = |player: &Option<Player>| {
+            player
+                .as_ref()
+                .and_then(|p| p.name.as_ref())
+                .is_some_and(|n| n.to_lowercase().contains(&name))
+        };
+        if matches(&self.black) {
+            Some(shakmaty::Color::Black)
+        } else if matches(&self.white) {
+            Some(shakmaty::Color::White)
+        } else {
+            None
+        }
+    }
+
     pub fn create_player(
         &mut self,
         color: shakmaty::Color,
@@ -137,8 +220,22 @@ impl Players {
         elo: Option<u32>,
     ) {
         match color {
-            shakmaty::Color::White => self.white = Some(Player { name, title, elo }),
-            shakmaty::Color::Black => self.black = Some(Player { name, title, elo }),
+            shakmaty::Color::White => {
+                self.white = Some(Player {
+                    name,
+                    title,
+                    elo,
+                    ..Player::default()
+                })
+            }
+            shakmaty::Color::Black => {
+                self.black = Some(Player {
+                    name,
+                    title,
+                    elo,
+                    ..Player::default()
+                })
+            }
         };
     }
 }
@@ -160,9 +257,19 @@ impl Default for Clock {
 impl<'a, 'b> Sub<&'b Clock> for &'a Clock {
     type Output = Clock;
 
+    /// Subtract clocks, saturating at zero as clocks may go up between moves if the
+    /// increment is larger than the time spent, or if a clock was corrected.
     fn sub(self, other: &'b Clock) -> Self::Output {
+        if other.duration > self.duration {
+            log::debug!(
+                "Clock {:?} is larger than {:?}, saturating difference at zero",
+                other,
+                self
+            );
+        }
+
         Clock {
-            duration: self.duration - other.duration,
+            duration: self.duration.saturating_sub(other.duration),
         }
     }
 }
@@ -188,20 +295,76 @@ impl Clock {
         }
     }
 
-    /// Construct a clock from a time string
-    fn from_time_str(s: &str) -> Self {
-        let splitted: Vec<&str> = s.split(":").collect();
-        let hours_ms = splitted[0].parse::<u64>().unwrap() * 60 * 60 * 1000;
-        let minutes_ms = splitted[1].parse::<u64>().unwrap() * 60 * 1000;
-        let milliseconds = splitted[2].parse::<f64>().unwrap() * 1000.0;
-        let total_ms = milliseconds as u64 + minutes_ms + hours_ms;
+    /// Construct a clock from a time string like 0:03:00 or 1:10:45.1, with days before the
+    /// hours for correspondence games, like 2:03:15:00. Hours may go over 23, like 26:00:00.
+    fn from_time_str(s: &str) -> Result<Self, GifferError> {
+        let error = || GifferError::InvalidClock {
+            clock: s.to_string(),
+        };
+        let splitted: Vec<&str> = s.split(':').collect();
+        let (days, hours, minutes, seconds) = match splitted[..] {
+            [d, h, m, s] => (d, h, m, s),
+            [h, m, s] => ("0", h, m, s),
+            _ => return Err(error()),
+        };
+        let whole = |field: &str| field.parse::<u64>().map_err(|_| error());
+        let seconds = seconds.parse::<f64>().map_err(|_| error())?;
+        if !seconds.is_finite() || seconds < 0.0 || seconds * 1000.0 >= u64::MAX as f64 {
+            return Err(error());
+        }
+
+        let (days, hours, minutes) = (whole(days)?, whole(hours)?, whole(minutes)?);
+        // Clocks too long to fit in milliseconds are as malformed as any other
+        let total_ms = days
+            .checked_mul(24)
+            .and_then(|h| h.checked_add(hours))
+            .and_then(|h| h.checked_mul(60))
+            .and_then(|m| m.checked_add(minutes))
+            .and_then(|m| m.checked_mul(60 * 1000))
+            .and_then(|ms| ms.checked_add((seconds * 1000.0) as u64))
+            .ok_or_else(error)?;
+        Ok(Clock::from_millis(total_ms))
+    }
 
-        Clock::from_millis(total_ms)
+    /// Parse the time of the first command of a kind in a comment, like [%clk 0:02:59.5] for
+    /// clk or [%emt 0:00:07] for emt. Times outside of a command, or that can't be parsed, are
+    /// ignored.
+    fn from_comment(comment: &str, command: &str) -> Option<Self> {
+        let re = Regex::new(&format!(r"\[%{}\s+([^\]\s]+)\s*\]", command)).unwrap();
+        let time = re.captures(comment)?;
+        match Clock::from_time_str(&time[1]) {
+            Ok(clock) => Some(clock),
+            Err(e) => {
+                log::debug!("Ignoring %{} command: {}", command, e);
+                None
+            }
+        }
     }
 
     fn as_millis(&self) -> u128 {
         self.duration.as_millis()
     }
+
+    /// Write the clock in a format. Display writes it in full. Clocks of a day or more are
+    /// written with days and hours in the smart format, like 1d 2:03.
+    fn format(&self, format: ClockFormat) -> String {
+        let millis = self.duration.as_millis();
+        let tenth_secs = millis / 100 % 10;
+        let secs = millis / 1000 % 60;
+        let total_minutes = millis / 60_000;
+        let (hours, minutes) = (total_minutes / 60, total_minutes % 60);
+
+        match format {
+            ClockFormat::Full => self.to_string(),
+            ClockFormat::Compact => format!("{}:{:02}", total_minutes, secs),
+            ClockFormat::Smart if hours >= 24 => {
+                format!("{}d {}:{:02}", hours / 24, hours % 24, minutes)
+            }
+            ClockFormat::Smart if hours > 0 => format!("{}:{:02}:{:02}", hours, minutes, secs),
+            ClockFormat::Smart if millis >= 60_000 => format!("{}:{:02}", minutes, secs),
+            ClockFormat::Smart => format!("0:{:02}.{}", secs, tenth_secs),
+        }
+    }
 }
 
 impl fmt::Display for Clock {
@@ -225,7 +388,13 @@ impl fmt::Display for Clock {
 pub struct GameClocks {
     white: Vec<Clock>,
     black: Vec<Clock>,
-    increment: Option<u16>,
+    increment: Option<u32>,
+    /// Clock both players start with, from the TimeControl header, shown until their first
+    /// clock comment.
+    start: Option<Clock>,
+    /// Time each player spent on their moves, from %emt comments.
+    white_elapsed: Vec<Clock>,
+    black_elapsed: Vec<Clock>,
 }
 
 impl Default for GameClocks {
@@ -234,13 +403,16 @@ impl Default for GameClocks {
             white: Vec::new(),
             black: Vec::new(),
             increment: None,
+            start: None,
+            white_elapsed: Vec::new(),
+            black_elapsed: Vec::new(),
         }
     }
 }
 
 impl GameClocks {
     /// Calculate the delay between a turn and the previous one
-    fn turn_delay<U>(&self, turn: U, color: Color) -> Option<u16>
+    fn turn_delay<U>(&self, turn: U, color: Color) -> Option<u32>
     where
         U: Into<usize>,
     {
@@ -272,7 +444,105 @@ impl GameClocks {
             let curr = turn_clock.unwrap();
 
             let diff = &prev - curr;
-            Some(diff.as_millis() as u16)
+            Some(u32::try_from(diff.as_millis()).unwrap_or(u32::MAX))
+        }
+    }
+
+    /// Calculate the delay between a turn and the previous one from the time the player spent
+    /// on the move, if known, or from their clocks otherwise.
+    fn move_delay<U>(&self, turn: U, color: Color) -> Option<u32>
+    where
+        U: Into<usize>,
+    {
+        let turn = turn.into();
+        let elapsed = match color {
+            Color::White => self.white_elapsed.get(turn),
+            Color::Black => self.black_elapsed.get(turn),
+        };
+        match elapsed {
+            Some(elapsed) => Some(u32::try_from(elapsed.as_millis()).unwrap_or(u32::MAX)),
+            None => self.turn_delay(turn, color),
+        }
+    }
+
+    /// Calculate the delay of a turn like move_delay, falling back to another delay for turns
+    /// without one, like those of a player without clock comments.
+    fn real_delay(&self, turn: usize, color: Color, fallback: RealDelayFallback) -> Option<u32> {
+        self.move_delay(turn, color).or_else(|| match fallback {
+            RealDelayFallback::Opponent => self.move_delay(turn, !color),
+            RealDelayFallback::Average => self.average_delay(),
+            RealDelayFallback::FirstFrame => None,
+        })
+    }
+
+    /// Average delay of all turns with a known delay, if any.
+    fn average_delay(&self) -> Option<u32> {
+        let turns = [
+            self.white.len(),
+            self.black.len(),
+            self.white_elapsed.len(),
+            self.black_elapsed.len(),
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or(0);
+        let known: Vec<u64> = (0..turns)
+            .flat_map(|turn| [Color::White, Color::Black].map(|color| self.move_delay(turn, color)))
+            .flatten()
+            .map(u64::from)
+            .collect();
+        if known.is_empty() {
+            return None;
+        }
+        let average = known.iter().sum::<u64>() / known.len() as u64;
+        Some(u32::try_from(average).unwrap_or(u32::MAX))
+    }
+
+    /// Time a player spent on their last move with a clock, if known.
+    fn last_move_delay(&self, color: Color) -> Option<u32> {
+        let clocks = match color {
+            Color::White => self.white(),
+            Color::Black => self.black(),
+        };
+        clocks
+            .len()
+            .checked_sub(1)
+            .and_then(|turn| self.move_delay(turn, color))
+    }
+
+    /// Whether there are no clocks nor elapsed move times for either player.
+    fn is_empty(&self) -> bool {
+        self.white.is_empty()
+            && self.black.is_empty()
+            && self.white_elapsed.is_empty()
+            && self.black_elapsed.is_empty()
+    }
+
+    /// Make up elapsed move times for a game of a number of plies, with each player spending
+    /// their starting clock, and the increments of their moves, as the simulation says. Nothing
+    /// is made up without a starting clock, returning false.
+    fn simulate(&mut self, plies: usize, simulation: ClockSimulation) -> bool {
+        let start = match &self.start {
+            Some(start) => start.duration,
+            None => return false,
+        };
+        let increment = Duration::from_millis(self.increment.unwrap_or(0).into());
+        for (color, moves) in [(Color::White, plies.div_ceil(2)), (Color::Black, plies / 2)] {
+            let total = start + increment * moves as u32;
+            for share in simulation.shares(moves) {
+                let elapsed = Clock {
+                    duration: total.mul_f64(share),
+                };
+                self.append_elapsed(elapsed, color);
+            }
+        }
+        true
+    }
+
+    fn append_elapsed(&mut self, elapsed: Clock, color: Color) {
+        match color {
+            Color::White => self.white_elapsed.push(elapsed),
+            Color::Black => self.black_elapsed.push(elapsed),
         }
     }
 
@@ -297,11 +567,142 @@ impl GameClocks {
         &self.white
     }
 
+    /// The last clock of a player, or the starting clock if they have none yet.
+    fn last(&self, color: Color) -> Option<&Clock> {
+        let clocks = match color {
+            Color::White => self.white(),
+            Color::Black => self.black(),
+        };
+        clocks.last().or(self.start.as_ref())
+    }
+
+    /// The clocks shown at this point of the game: the last clock of each player, if any.
+    fn snapshot(&self) -> ByColor<Option<Clock>> {
+        ByColor::new_with(|color| self.last(color).cloned())
+    }
+
     fn black(&self) -> &Vec<Clock> {
         &self.black
     }
 }
 
+/// Key moments of the move that lead to a frame. Used to hold frames longer when pacing is smart.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyMoments {
+    capture: bool,
+    check: bool,
+    promotion: bool,
+    mate: bool,
+}
+
+impl KeyMoments {
+    /// Classify a move given the position after the move has been played
+    fn from_move(m: &Move, position: &Chess) -> Self {
+        let (capture, promotion) = match m {
+            Move::Normal {
+                capture, promotion, ..
+            } => (capture.is_some(), promotion.is_some()),
+            Move::EnPassant { .. } => (true, false),
+            Move::Castle { .. } | Move::Put { .. } => (false, false),
+        };
+
+        KeyMoments {
+            capture,
+            promotion,
+            check: position.is_check(),
+            mate: position.is_checkmate(),
+        }
+    }
+
+    /// The factor to multiply a frame's delay with. Largest factor of all key moments, or 1.0 if none.
+    fn factor(&self, factors: &KeyMomentFactors) -> f32 {
+        [
+            (self.capture, factors.capture),
+            (self.check, factors.check),
+            (self.promotion, factors.promotion),
+            (self.mate, factors.mate),
+        ]
+        .iter()
+        .filter(|(is_moment, _)| *is_moment)
+        .fold(1.0, |acc, (_, factor)| f32::max(acc, *factor))
+    }
+}
+
+/// Information about a board collected while reading the game, used when encoding its frame.
+#[derive(Clone, Debug, Default)]
+pub struct FrameInfo {
+    /// Key moments of the move that lead to the frame.
+    key_moments: KeyMoments,
+    /// Keep the frame even if it's identical to the previous one, as it was duplicated on purpose.
+    keep: bool,
+    /// Arrows and circles from %cal and %csl comments, drawn on this frame only.
+    annotations: Annotations,
+    /// Evaluation of the position after the move, from %eval comments.
+    eval: Option<Eval>,
+    /// The frame fades in termination circles, so it's only held briefly.
+    fade: bool,
+    /// Hold the frame for this many ms instead of the usual delay, like the heatmap.
+    hold: Option<u32>,
+    /// The frame shows pieces sliding to their squares before a move's frame, so it doesn't
+    /// count as a move when assigning delays.
+    animation: bool,
+    /// Number of plies played in the position shown, for the initial position and move frames.
+    ply: Option<usize>,
+    /// Clocks shown on the frame, including the clocks commented after its move.
+    clocks: ByColor<Option<Clock>>,
+    /// Text of the comments on the move, without commands like [%clk ...].
+    caption: Option<String>,
+}
+
+/// A player bar rendered by the drawer, cropped to the area it covers in a frame.
+#[derive(Clone, Debug)]
+struct CachedBar {
+    /// Pieces captured by the player when the bar was rendered.
+    captured: ByRole<u8>,
+    x: u32,
+    y: u32,
+    image: RgbaImage,
+}
+
+/// Player bars rendered once and overlaid on every frame that needs them, as rendering their
+/// labels is slow. Bars are kept for each player and side of the board, and only rendered again
+/// when the player captures a piece.
+#[derive(Clone, Debug, Default)]
+struct BarCache {
+    bars: HashMap<(Color, bool), CachedBar>,
+}
+
+impl BarCache {
+    /// The bar of a player on a flipped or unflipped board, if it was rendered with the same
+    /// captured pieces.
+    fn get(&self, color: Color, flip: bool, captured: &ByRole<u8>) -> Option<&CachedBar> {
+        self.bars
+            .get(&(color, flip))
+            .filter(|bar| bar.captured == *captured)
+    }
+
+    /// Keep the bar drawn on layer, an otherwise transparent image, replacing the player's
+    /// previous bar on the same side of the board.
+    fn insert(
+        &mut self,
+        color: Color,
+        flip: bool,
+        captured: ByRole<u8>,
+        layer: &RgbaImage,
+    ) -> &CachedBar {
+        let (x, y, width, height) = drawn_bounds(layer);
+        let image = imageops::crop_imm(layer, x, y, width, height).to_image();
+        let bar = CachedBar {
+            captured,
+            x,
+            y,
+            image,
+        };
+        self.bars.insert((color, flip), bar);
+        &self.bars[&(color, flip)]
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum GifferError {
     #[error(transparent)]
@@ -311,7 +712,7 @@ pub enum GifferError {
     },
     #[error("A GIF encoder could not be initialized")]
     InitializeEncoder { source: gif::EncodingError },
-    #[error("A GIF encoder could not be initialized due to an invalid configuration")]
+    #[error("A GIF encoder could not be initialized due to an invalid configuration: {reason}")]
     InvalidConfig { reason: String },
     #[error("A GIF frame could not be encoded")]
     FrameEncoding { source: gif::EncodingError },
@@ -322,6 +723,8 @@ pub enum GifferError {
     },
     #[error("Invalid writer variant")]
     InvalidGifWriterVariant,
+    #[error("Unable to parse clock {clock}")]
+    InvalidClock { clock: String },
 }
 
 /// A GifWriter that supports writing to a file or to an in-memory buffer.
@@ -331,13 +734,13 @@ pub enum GifWriter {
 }
 
 impl GifWriter {
-    pub fn from_output(output: &Output) -> Result<GifWriter, GifferError> {
+    pub fn from_output(output: &Output, capacity: usize) -> Result<GifWriter, GifferError> {
         match output {
             Output::Path(s) => {
                 let file =
                     fs::File::create(&s).map_err(|source| GifferError::CreateOutput { source })?;
 
-                Ok(GifWriter::File(BufWriter::new(file)))
+                Ok(GifWriter::File(BufWriter::with_capacity(capacity, file)))
             }
             Output::Buffer => {
                 let v = Vec::new();
@@ -388,38 +791,235 @@ pub struct PGNGiffer {
     position: Chess,
     config: Config,
     termination: Option<String>,
+    event: Option<String>,
+    site: Option<String>,
+    round: Option<String>,
+    date: Option<String>,
+    time_control: Option<TimeControl>,
+    eco: Option<String>,
+    opening: Option<String>,
+    result: Option<(Outcome, TerminationReason)>,
+    metadata: Option<GameMetadata>,
+    title_card: Option<RgbaImage>,
     players: Players,
     boards: Vec<RgbaImage>,
+    frames: Vec<FrameInfo>,
+    moves: usize,
+    /// Moves played so far in SAN, for the move list.
+    san_moves: Vec<String>,
+    /// Assessments given with NAGs to the last move, like ?? for $4, in the order given.
+    assessments: Vec<MoveAssessment>,
+    /// Whether the caption strip of the last board has text, so it's cleared on the next move.
+    caption_shown: bool,
     clocks: GameClocks,
+    captures: ByColor<ByRole<u8>>,
+    material_balance: i32,
     to_clear: Vec<(Square, Role, Color)>,
+    overlay_squares: Vec<Square>,
+    last_move: Option<(Color, Square)>,
+    /// Paths of the pieces moved in the most recent moves, oldest first, for the trail.
+    trail: VecDeque<Vec<(Square, Square)>>,
+    /// Number of times a piece landed on each square, for the heatmap.
+    landings: [u32; 64],
+    /// Avatars drawn in player bars, already the size they are drawn at.
+    avatars: ByColor<Option<RgbaImage>>,
+    /// Watermark drawn over every frame, already the size it is drawn at.
+    watermark: Option<RgbaImage>,
+    bar_cache: BarCache,
     svgs: SVGForest,
 }
 
 impl PGNGiffer {
     pub fn new(config: Config) -> Result<Self, GifferError> {
-        let drawer = BoardDrawer::new(
-            config.flip,
-            config.size,
-            config.colors.dark.clone(),
-            config.colors.light.clone(),
-        )
-        .map_err(|source| GifferError::DrawerError { source })?;
-        let circle_size = config.size / 8 / 3;
-        let termination_drawer = TerminationDrawer::new(circle_size as u32, circle_size as u32)
-            .map_err(|source| GifferError::DrawerError { source })?;
-
         let svg_font_config = SVGFontConfig {
             font_path: config.font_path.clone(),
             font_family: Some(config.font_family.clone()),
+            fallback_paths: config.fallback_fonts.clone(),
+            system_fonts: config.system_fonts,
             ..Default::default()
         };
 
-        let svgs = SVGForest::new(
+        let mut svgs = SVGForest::new(
             svg_font_config,
             &config.svgs_path,
             &config.pieces_family,
             "terminations",
         )?;
+        svgs.set_termination_map(config.termination_map.clone());
+        if let Some(family) = &config.pieces_family_white {
+            svgs.set_pieces_family(Color::White, family)?;
+        }
+        if let Some(family) = &config.pieces_family_black {
+            svgs.set_pieces_family(Color::Black, family)?;
+        }
+        if let Some(path) = &config.pieces_sprite {
+            svgs.set_pieces_sprite(path)?;
+        }
+
+        PGNGiffer::with_svgs(config, svgs)
+    }
+
+    /// Like new, but with a pre-built SVGForest, e.g. one with SVGs already in memory. The
+    /// svgs_path, pieces and termination_map options of the config are ignored.
+    pub fn with_svgs(mut config: Config, svgs: SVGForest) -> Result<Self, GifferError> {
+        if !(1..=30).contains(&config.encoder_speed) {
+            return Err(GifferError::InvalidConfig {
+                reason: format!(
+                    "Encoder speed must be between 1 and 30, got {}",
+                    config.encoder_speed
+                ),
+            });
+        }
+        if !(1..=4).contains(&config.supersample) {
+            return Err(GifferError::InvalidConfig {
+                reason: format!(
+                    "Supersample must be between 1 and 4, got {}",
+                    config.supersample
+                ),
+            });
+        }
+
+        let height_fraction = config.bar_style.height_fraction;
+        if !(height_fraction > 0.0 && height_fraction <= 2.0) {
+            return Err(GifferError::InvalidConfig {
+                reason: format!(
+                    "Bar height must be greater than 0 and at most 2 squares, got {}",
+                    height_fraction
+                ),
+            });
+        }
+
+        if let Some((width, height)) = config.canvas {
+            if width > u16::MAX.into() || height > u16::MAX.into() {
+                return Err(GifferError::InvalidConfig {
+                    reason: format!(
+                        "Canvas must be at most {} pixels wide and high, got {}x{}",
+                        u16::MAX,
+                        width,
+                        height
+                    ),
+                });
+            }
+        }
+
+        // Move highlights are a trail of only the last move
+        if config.style_components.move_highlights() && config.trail == 0 {
+            config.trail = 1;
+        }
+
+        // The trail uses the highlight color, so it's converted to grays along with the others
+        if config.trail > 0 && config.colors.highlight.is_none() {
+            config.colors.highlight = Some(crate::config::Color(utils::TRAIL_COLOR.0));
+        }
+
+        if config.grayscale {
+            config.colors = config.colors.grayscale();
+            config.border_color = config.border_color.grayscale();
+            config.background = config.background.grayscale();
+            config.bar_style = config.bar_style.grayscale();
+        }
+
+        // Everything is drawn at the supersampled size, and only frames are downscaled. Boards
+        // are drawn at a multiple of 8 so all squares are the same size, and resized to any
+        // other size.
+        let scale = u32::from(config.supersample);
+        let mut drawer = BoardDrawer::new(
+            config.orientation.flip(Color::White),
+            config.size.div_ceil(8) * 8 * scale,
+            config.colors.dark.clone(),
+            config.colors.light.clone(),
+        )
+        .map_err(|source| GifferError::DrawerError { source })?;
+        drawer.set_material_balance(config.style_components.material_balance());
+        drawer.set_move_times(config.style_components.move_times());
+        drawer.set_move_list(config.style_components.move_list());
+        drawer.set_captions(config.style_components.captions());
+        if config.style_components.ranks() || config.style_components.files() {
+            drawer.set_coordinates(config.coordinates, config.coordinate_sides);
+        } else {
+            drawer.set_coordinates(CoordinateMode::None, config.coordinate_sides);
+        }
+        drawer.set_coordinate_font(CoordinateFont {
+            size: config.coordinate_font.size.scaled(scale as f32),
+            ..config.coordinate_font
+        });
+        drawer.set_frame(config.border_width * scale, config.border_color.clone());
+        drawer.set_check_highlight(config.colors.check_highlight.clone());
+        drawer.set_title_color(config.colors.title.clone());
+        drawer.set_name_fit(config.name_fit);
+        drawer.set_bar_style(&config.bar_style);
+        drawer.set_bar_layout(config.bar_layout);
+        drawer.set_low_time_bold(config.low_time_bold);
+        if config.style_components.move_highlights() {
+            drawer.set_move_highlight(config.colors.highlight.clone());
+        }
+        drawer.set_piece_scale(config.piece_scale);
+        drawer.set_background(config.background.clone());
+        drawer.set_hatch(config.accessible);
+        let circle_size = (drawer.square_size() as f32 * config.termination_scale) as u32;
+        let mut termination_drawer = TerminationDrawer::new(circle_size, circle_size)
+            .map_err(|source| GifferError::DrawerError { source })?;
+        termination_drawer.set_anchor(config.termination_anchor);
+        termination_drawer.set_flip(config.orientation.flip(Color::White));
+        termination_drawer.set_marks(config.accessible);
+
+        if let Some(path) = &config.board_texture {
+            drawer.set_texture(path, &svgs)?;
+        }
+
+        // Avatars are optional, so bars are drawn without the ones that can't be loaded
+        let avatars = ByColor::new_with(|color| {
+            let path = match color {
+                Color::White => config.white_avatar.as_ref(),
+                Color::Black => config.black_avatar.as_ref(),
+            }?;
+            drawer
+                .load_avatar(path, &svgs)
+                .map_err(|e| log::warn!("Avatar will not be drawn: {}", e))
+                .ok()
+        });
+
+        // Bars are only known to be drawn once headers are read, so text overlays and the canvas
+        // are checked against the biggest image there may be, and again when frames are done
+        let drawn_size = drawer.size();
+        let output = |n: u32| (n * config.size + drawn_size / 2) / drawn_size;
+        let may_draw_bars = config.style_components.player_bars() || config.force_player_bars;
+        drawer.set_event_bar(
+            config.style_components.event_bar() || config.style_components.opening(),
+        );
+        let max_height = output(drawer.layout_height(may_draw_bars));
+        drawer.set_event_bar(false);
+        validate_overlays(&config.overlays, output(drawer.layout_width()), max_height)?;
+        if let Some((canvas_width, canvas_height)) = config.canvas {
+            let width = output(drawer.layout_width());
+            if canvas_width < width || canvas_height < max_height {
+                return Err(GifferError::InvalidConfig {
+                    reason: format!(
+                        "Canvas of {}x{} is smaller than the {}x{} board",
+                        canvas_width, canvas_height, width, max_height
+                    ),
+                });
+            }
+        }
+
+        // Watermarks are placed over the whole output image, bars and canvas included, once it's
+        // resized, so they're scaled to it
+        let watermark = match &config.watermark {
+            Some(watermark) => {
+                let output_width = match config.canvas {
+                    Some((canvas_width, _)) => canvas_width,
+                    None => output(drawer.layout_width()),
+                };
+                let width = (output_width as f32 * watermark.scale).round() as u32;
+                Some(drawer.load_watermark(
+                    &watermark.path,
+                    width.max(1),
+                    watermark.opacity,
+                    &svgs,
+                )?)
+            }
+            None => None,
+        };
 
         Ok(PGNGiffer {
             drawer,
@@ -427,10 +1027,34 @@ impl PGNGiffer {
             position: Chess::default(),
             config: config,
             termination: None,
+            event: None,
+            site: None,
+            round: None,
+            date: None,
+            time_control: None,
+            eco: None,
+            opening: None,
+            result: None,
+            metadata: None,
+            title_card: None,
             players: Players::default(),
             boards: Vec::new(),
+            frames: Vec::new(),
+            moves: 0,
+            san_moves: Vec::new(),
+            assessments: Vec::new(),
+            caption_shown: false,
             clocks: GameClocks::default(),
+            captures: ByColor::default(),
+            material_balance: 0,
             to_clear: Vec::new(),
+            overlay_squares: Vec::new(),
+            last_move: None,
+            trail: VecDeque::new(),
+            landings: [0; 64],
+            avatars,
+            watermark,
+            bar_cache: BarCache::default(),
             svgs,
         })
     }
@@ -440,585 +1064,3924 @@ impl PGNGiffer {
         width: u16,
         height: u16,
     ) -> Result<Encoder<GifWriter>, GifferError> {
-        let writer = GifWriter::from_output(&self.config.output)?;
+        let writer =
+            GifWriter::from_output(&self.config.output, self.config.write_buffer_capacity)?;
 
         let mut encoder = Encoder::new(writer, width, height, &[])
             .map_err(|source| GifferError::InitializeEncoder { source })?;
+        // The gif crate counts repetitions after the first play, and Repeat::Finite(0) skips the
+        // NETSCAPE extension altogether, which is the only way to get viewers to play a GIF once.
+        // A loop count of 0 in the extension means looping forever.
+        let repeat = if self.config.play_once {
+            Repeat::Finite(0)
+        } else {
+            Repeat::Infinite
+        };
         encoder
-            .set_repeat(Repeat::Infinite)
+            .set_repeat(repeat)
             .map_err(|source| GifferError::InitializeEncoder { source })?;
 
         Ok(encoder)
     }
-}
 
-impl Visitor for PGNGiffer {
-    type Result = Result<Option<Vec<u8>>, GifferError>;
+    /// Metadata of the last game rendered. None until a game ends.
+    pub fn metadata(&self) -> Option<&GameMetadata> {
+        self.metadata.as_ref()
+    }
 
-    fn begin_game(&mut self) {
-        log::info!("Rendering initial board");
+    fn game_metadata(&self, frame_delays_cs: Vec<u16>) -> GameMetadata {
+        let (result, termination) = match self.result {
+            Some((o, reason)) => (o.to_string(), Some(reason.description().to_string())),
+            None => ("*".to_string(), None),
+        };
+
+        GameMetadata {
+            white: self.players.white.clone(),
+            black: self.players.black.clone(),
+            event: self.event.clone(),
+            date: self.date.clone(),
+            eco: self.eco.clone(),
+            result,
+            termination,
+            ply_count: self.moves,
+            frame_delays_cs,
+        }
+    }
+
+    /// Draw %cal arrows and %csl circles on the latest board. The squares they cover are
+    /// cleared when drawing the next move, so they only show up in this frame.
+    fn draw_annotations(&mut self, annotations: &Annotations) {
+        let size = self.drawer.canvas_size();
+        let square_size = self.drawer.square_size();
+        // The latest board only holds the squares that changed, so annotations are drawn on the
+        // complete board and the squares they cover are copied over.
+        let composite = composite_boards(&self.boards);
+        let y_offset = self.board_offset();
+        let mut full_board = imageops::crop_imm(&composite, 0, y_offset, size, size).to_image();
+
+        let mut covered = Vec::new();
+        for (color, from, to) in annotations.arrows.iter() {
+            self.drawer
+                .draw_arrow(*from, *to, color.rgba(), &mut full_board, &self.svgs)
+                .expect("Failed to draw annotation arrow");
+            covered.extend(utils::arrow_squares(*from, *to));
+        }
+        for (color, square) in annotations.circles.iter() {
+            self.drawer
+                .draw_circle(*square, color.rgba(), &mut full_board, &self.svgs)
+                .expect("Failed to draw annotation circle");
+            covered.push(*square);
+        }
+
+        let board = self.boards.last_mut().expect("No board written");
+        for square in covered.iter() {
+            let (x, y) = self.drawer.square_center(*square);
+            let x = x as u32 - square_size / 2;
+            let y = y as u32 - square_size / 2;
+            let square_img = imageops::crop_imm(&full_board, x, y, square_size, square_size);
+            imageops::replace(
+                board,
+                &square_img.to_image(),
+                x.into(),
+                (y + y_offset).into(),
+            );
+        }
+        self.overlay_squares.extend(covered);
+    }
+
+    /// Store the evaluation of the latest move and, with move badges enabled, draw a badge on its
+    /// destination square if the evaluation swung enough since the previous move.
+    fn record_eval(&mut self, eval: Eval) {
+        let n = self.frames.len();
+        self.frames.last_mut().expect("No frame written").eval = Some(eval);
+
+        let (mover, to) = match self.last_move {
+            Some(last_move) if n >= 2 && self.config.style_components.move_badges() => last_move,
+            _ => return,
+        };
+        // Games start roughly even, so the first move is compared against a 0.00 evaluation.
+        let before = match self.frames[n - 2].eval {
+            Some(before) => before,
+            None if n == 2 => Eval::Centipawns(0),
+            None => return,
+        };
+
+        if let Some(class) = MoveClass::classify(before, eval, mover) {
+            log::debug!("Move to {} classified as {:?}", to, class);
+            let size = self.drawer.canvas_size();
+            let y_offset = self.board_offset();
+            let board = self.boards.last_mut().expect("No board written");
+            let mut board_area = imageops::crop_imm(board, 0, y_offset, size, size).to_image();
+            self.drawer
+                .draw_badge(
+                    to,
+                    &class.to_string(),
+                    class.color(),
+                    &mut board_area,
+                    &self.svgs,
+                )
+                .expect("Failed to draw move badge");
+            imageops::replace(board, &board_area, 0, y_offset.into());
+            self.overlay_squares.push(to);
+        }
+    }
+
+    /// Send a progress report to the progress hook, if there is one.
+    fn report_progress(&self, stage: Stage, current: usize, total: Option<usize>) {
+        if let Some(hook) = &self.config.on_progress {
+            hook.report(Progress {
+                stage,
+                current,
+                total,
+            });
+        }
+    }
+
+    /// Find out why the game ended. Checks the final position first, and falls back to the
+    /// Termination header or assumes the losing side resigned.
+    fn termination_reason(&self, outcome: Outcome) -> TerminationReason {
+        let reason = if self.position.is_checkmate() {
+            "checkmate"
+        } else if self.position.is_stalemate() {
+            "stalemate"
+        } else if self.position.is_insufficient_material() {
+            "insufficient material"
+        } else {
+            match &self.termination {
+                Some(s) => termination_header_reason(s, outcome),
+                None => match outcome {
+                    Outcome::Draw => "agreement",
+                    Outcome::Decisive { winner: _ } => "resignation",
+                },
+            }
+        };
+
+        TerminationReason::from_outcome(outcome, Some(reason))
+    }
+
+    /// Flip the board if the player named pov plays black, and keep it as is if they play
+    /// white. The initial board is drawn before headers are read, so it's drawn again.
+    fn orient_to(&mut self, pov: &str) {
+        let flip = match self.players.color_of(pov) {
+            Some(color) => color == Color::Black,
+            None => {
+                log::warn!("No player named {} found, keeping the orientation", pov);
+                return;
+            }
+        };
+        if flip == self.drawer.flip() {
+            return;
+        }
+
+        log::debug!("Flipping the board to {}'s point of view", pov);
+        self.config.orientation = if flip {
+            Orientation::Black
+        } else {
+            Orientation::White
+        };
+        self.drawer.set_flip(flip);
+        self.termination_drawer.set_flip(flip);
+        self.boards.pop().expect("Initial board should exist");
         let board = self
             .drawer
             .draw_initial_position(&self.svgs)
-            .expect(&format!(
-                "Failed to draw initial position: {}",
-                self.position.board()
-            ));
+            .expect("Failed to draw initial position");
         self.boards.push(board);
     }
 
-    fn begin_variation(&mut self) -> Skip {
-        Skip(true) // stay in the mainline
+    /// Whether boards have player bars: they are drawn if any player is known, which is
+    /// always the case after headers if they are forced. Every board and the GIF's size
+    /// depend on this, so it must not change after the headers are read.
+    fn should_draw_bars(&self) -> bool {
+        self.players.exist()
+            && (self.config.style_components.player_bars() || self.config.force_player_bars)
     }
 
-    /// Parses PGN headers to extract player information
-    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
-        match std::str::from_utf8(key) {
-            Ok("White") => {
-                let name = &value.decode_utf8_lossy().to_string();
+    /// Whether a clock has less time left than the low time threshold.
+    fn is_low_time(&self, clock: &Clock) -> bool {
+        self.config
+            .low_time
+            .is_some_and(|threshold| clock.duration < threshold)
+    }
 
-                log::debug!("White: {}", name);
-                match self.players.white {
-                    Some(_) => self
-                        .players
-                        .update_player_name(shakmaty::Color::White, name),
-                    None => self.players.create_player(
-                        shakmaty::Color::White,
-                        Some(name.to_string()),
-                        None,
-                        None,
-                    ),
-                };
+    /// Draw a player's clock in their bar, or in a box over the board if there are no bars.
+    /// Normal clocks low on time are drawn as such. Nothing is drawn without the clocks style
+    /// component.
+    fn draw_clock(&mut self, clock: &Clock, color: Color, style: ClockStyle, img: &mut RgbaImage) {
+        if !self.config.style_components.clocks() {
+            return;
+        }
+        let style = match style {
+            ClockStyle::Normal if self.is_low_time(clock) => ClockStyle::LowTime,
+            style => style,
+        };
+        let clock = clock.format(self.config.clock_format);
+        if self.should_draw_bars() {
+            self.drawer
+                .draw_one_player_clock(&clock, color, style, img, &self.svgs)
+                .expect("Failed to draw clock");
+        } else {
+            self.drawer
+                .draw_floating_clock(&clock, color, style, img, &self.svgs)
+                .expect("Failed to draw clock");
+        }
+    }
+
+    /// Draw the time a player spent on their last move next to their clock, like +0:14.0, or
+    /// clear it if unknown. Move times are only drawn in player bars, with the move times
+    /// style component.
+    fn draw_move_time(&mut self, color: Color, move_time: Option<u32>, img: &mut RgbaImage) {
+        if !self.config.style_components.move_times() || !self.should_draw_bars() {
+            return;
+        }
+        let text = match move_time {
+            Some(millis) => format!(
+                "+{}",
+                Clock::from_millis(millis).format(self.config.clock_format)
+            ),
+            None => String::new(),
+        };
+        self.drawer
+            .draw_move_time(&text, color, img, &self.svgs)
+            .expect("Failed to draw move time");
+    }
+
+    /// Draw the clocks recorded for the last frame: the last clock of both players, or their
+    /// starting clock before their first move, if any.
+    fn draw_last_clocks(&mut self, img: &mut RgbaImage) {
+        for color in [Color::White, Color::Black] {
+            if let Some(clock) = self.last_frame_clock(color) {
+                self.draw_clock(&clock, color, ClockStyle::Normal, img);
             }
-            Ok("Black") => {
-                let name = &value.decode_utf8_lossy().to_string();
+        }
+    }
 
-                log::debug!("Black: {}", name);
-                match self.players.black {
-                    Some(_) => self
-                        .players
-                        .update_player_name(shakmaty::Color::Black, name),
-                    None => self.players.create_player(
-                        shakmaty::Color::Black,
-                        Some(name.to_string()),
-                        None,
-                        None,
+    /// The clock of a player recorded for the last frame.
+    fn last_frame_clock(&self, color: Color) -> Option<Clock> {
+        self.frames
+            .last()
+            .and_then(|info| info.clocks.get(color).clone())
+    }
+
+    /// Draw a player's bar on an image laid out by add_bar_space. The bar is taken from the bar
+    /// cache, and rendered by the drawer only the first time it's drawn on each side of the
+    /// board, and after every capture.
+    fn draw_player_bar(&mut self, color: Color, img: &mut RgbaImage) {
+        let flip = self.drawer.flip();
+        let captured = *self.captures.get(color);
+        let bar = match self.bar_cache.get(color, flip, &captured) {
+            Some(bar) => bar,
+            None => {
+                log::debug!("Rendering player bar of {:?}", color);
+                let player = self.players.label(color, &self.config.anonymous_name);
+                let mut layer = RgbaImage::new(img.width(), img.height());
+                self.drawer
+                    .draw_one_player_bar(
+                        &player,
+                        color,
+                        &captured,
+                        self.avatars.get(color).as_ref(),
+                        &mut layer,
+                        &self.svgs,
+                    )
+                    .expect("Failed to draw player bar");
+                self.bar_cache.insert(color, flip, captured, &layer)
+            }
+        };
+        imageops::overlay(img, &bar.image, bar.x.into(), bar.y.into());
+    }
+
+    /// Draw the move list panel with the moves played so far, the last one highlighted, if
+    /// the style has it.
+    fn draw_move_list(&mut self, img: &mut RgbaImage) {
+        if !self.config.style_components.move_list() {
+            return;
+        }
+        let current = self.san_moves.len().saturating_sub(1);
+        self.drawer
+            .draw_move_list(&self.san_moves, current, img, &self.svgs)
+            .expect("Failed to draw move list");
+    }
+
+    /// Write the last move played in a corner of the board, numbered like 23... Qxh2+ and
+    /// followed by its assessment glyphs, if any. Nothing is written before the first move.
+    fn draw_move_overlay(&mut self, corner: Corner, board: &mut RgbaImage) {
+        let ply = self.san_moves.len();
+        let glyphs = self.assessment_glyphs();
+        let text = match self.san_moves.last() {
+            Some(san) if ply % 2 == 1 => format!("{}. {}{}", ply.div_ceil(2), san, glyphs),
+            Some(san) => format!("{}... {}{}", ply / 2, san, glyphs),
+            None => return,
+        };
+        self.drawer
+            .draw_move_overlay(&text, corner, board, &self.svgs)
+            .expect("Failed to draw move overlay");
+    }
+
+    /// Glyphs of the assessments of the last move, concatenated like !?.
+    fn assessment_glyphs(&self) -> String {
+        self.assessments.iter().map(|a| a.to_string()).collect()
+    }
+
+    /// Show an assessment given to the last move with a NAG, appended to the move overlay if
+    /// there is one, or as a badge on the square the move landed on otherwise. Every
+    /// assessment given to the move is shown in the same badge.
+    fn draw_assessment(&mut self, assessment: MoveAssessment) {
+        let to = match self.last_move {
+            Some((_, to)) => to,
+            None => return,
+        };
+        self.assessments.push(assessment);
+
+        let size = self.drawer.canvas_size();
+        let y_offset = self.board_offset();
+        let board = self.boards.last().expect("No board written");
+        let mut board_area = imageops::crop_imm(board, 0, y_offset, size, size).to_image();
+        if let Some(corner) = self.config.move_overlay {
+            self.draw_move_overlay(corner, &mut board_area);
+        } else {
+            let glyphs = self.assessment_glyphs();
+            let color = self.assessments[0].color();
+            self.drawer
+                .draw_badge(to, &glyphs, color, &mut board_area, &self.svgs)
+                .expect("Failed to draw assessment badge");
+            self.overlay_squares.push(to);
+        }
+        let board = self.boards.last_mut().expect("No board written");
+        imageops::replace(board, &board_area, 0, y_offset.into());
+    }
+
+    /// Write the caption of the last frame in the caption strip, cut short to the configured
+    /// length, or clear the strip if the frame has none.
+    fn draw_caption(&mut self, img: &mut RgbaImage) {
+        if !self.config.style_components.captions() {
+            return;
+        }
+        let caption = self.frames.last().and_then(|info| info.caption.as_deref());
+        let text = match caption {
+            Some(caption) if caption.chars().count() > self.config.caption_length => {
+                let cut: String = caption.chars().take(self.config.caption_length).collect();
+                format!("{}…", cut.trim_end())
+            }
+            Some(caption) => caption.to_string(),
+            None => String::new(),
+        };
+        self.drawer
+            .draw_caption(&text, img, &self.svgs)
+            .expect("Failed to draw caption");
+        self.caption_shown = !text.is_empty();
+    }
+
+    /// Render the text overlays as layers over frames width by height. Positions and sizes are
+    /// in pixels of the GIF, so they're scaled to the size boards are drawn at.
+    fn text_overlay_layers(&mut self, width: u32, height: u32) -> Result<Vec<Layer>, GifferError> {
+        let scale = self.drawer.size() as f32 / self.config.size as f32;
+        let scaled = |n: u32| (n as f32 * scale).round() as u32;
+        let mut layers = Vec::new();
+
+        for overlay in self.config.overlays.clone() {
+            let (x, y) = (scaled(overlay.x), scaled(overlay.y));
+            if x >= width || y >= height {
+                return Err(GifferError::InvalidConfig {
+                    reason: format!(
+                        "Text overlay \"{}\" at {},{} is outside of the {}x{} image",
+                        overlay.text,
+                        overlay.x,
+                        overlay.y,
+                        (width as f32 / scale).round(),
+                        (height as f32 / scale).round(),
                     ),
-                };
+                });
             }
-            Ok("WhiteElo") => {
-                let elo = &value
-                    .decode_utf8_lossy()
-                    .to_string()
-                    .parse::<u32>()
-                    .expect("WhiteElo could not be parsed");
+            let color = image::Rgba(overlay.color.to_arr());
+            let image = self.drawer.render_text_overlay(
+                &overlay.text,
+                overlay.size * scale,
+                color,
+                width - x,
+                &self.svgs,
+            )?;
+            layers.push(Layer {
+                image,
+                x,
+                y,
+                frames: overlay.frames,
+            });
+        }
 
-                match self.players.white {
-                    Some(_) => self.players.update_player_elo(shakmaty::Color::White, *elo),
-                    None => {
-                        self.players
-                            .create_player(shakmaty::Color::White, None, None, Some(*elo))
-                    }
-                };
+        Ok(layers)
+    }
+
+    /// Row where the board starts in every frame, under the event and player bars if any.
+    fn board_offset(&self) -> u32 {
+        self.drawer.board_offset(self.should_draw_bars())
+    }
+
+    /// See the board from turn's side, for boards following the turn. Termination circles
+    /// are placed on the last board drawn, so they're kept in the same orientation.
+    fn orient_for(&mut self, turn: Color) {
+        let flip = self.config.orientation.flip(turn);
+        self.drawer.set_flip(flip);
+        self.termination_drawer.set_flip(flip);
+    }
+
+    /// Add frames of the pieces moved by m sliding to their squares, before the move's own
+    /// frame. Captured pieces disappear in the first one, along with last frame's overlays.
+    /// Returns the squares covered in the last frame, which the move's frame must draw again.
+    fn push_move_animation(&mut self, m: &Move) -> Vec<Square> {
+        let mover = self.position.turn();
+        let mut pieces = self.position.board().clone();
+        let slides = match m {
+            Move::Normal { role, from, to, .. } => vec![(*role, *from, *to)],
+            Move::EnPassant { from, to } => {
+                pieces.discard_piece_at(Square::from_coords(to.file(), from.rank()));
+                vec![(Role::Pawn, *from, *to)]
             }
-            Ok("BlackElo") => {
-                let elo = &value
-                    .decode_utf8_lossy()
-                    .to_string()
-                    .parse::<u32>()
-                    .expect("BlackElo could not be parsed");
+            Move::Castle { king, rook } => {
+                let (king_to, rook_to) = utils::castling_destinations(*king, *rook);
+                vec![(Role::King, *king, king_to), (Role::Rook, *rook, rook_to)]
+            }
+            // Pieces put on the board come from nowhere, so there's nothing to slide
+            Move::Put { .. } => Vec::new(),
+        };
+        if slides.is_empty() {
+            return Vec::new();
+        }
 
-                log::debug!("BlackElo: {}", elo);
-                match self.players.black {
-                    Some(_) => self.players.update_player_elo(shakmaty::Color::Black, *elo),
-                    None => {
-                        self.players
-                            .create_player(shakmaty::Color::Black, None, None, Some(*elo))
+        let slides: Vec<(shakmaty::Piece, Square, Square)> = slides
+            .into_iter()
+            .map(|(role, from, to)| (role.of(mover), from, to))
+            .collect();
+        let mut redraw = Vec::new();
+        for (_, from, to) in slides.iter() {
+            // The destination may hold a captured piece, which disappears as the move starts
+            pieces.discard_piece_at(*from);
+            pieces.discard_piece_at(*to);
+            redraw.extend([*from, *to]);
+        }
+        redraw.extend(self.to_clear.iter().map(|(square, _, _)| *square));
+        redraw.extend(self.overlay_squares.iter().copied());
+        if let Move::EnPassant { from, to } = m {
+            redraw.push(Square::from_coords(to.file(), from.rank()));
+        }
+
+        let sub_frames = self.config.animate_moves;
+        for n in 1..=sub_frames {
+            let t = n as f32 / (sub_frames + 1) as f32;
+            let mut board = self.drawer.image_buffer();
+            redraw = self
+                .drawer
+                .draw_sliding_pieces(&slides, t, &pieces, &redraw, &mut board, &self.svgs)
+                .expect("Failed to draw sliding pieces");
+
+            board = self.drawer.add_bar_space(board, self.should_draw_bars());
+            self.boards.push(board);
+            self.frames.push(FrameInfo {
+                keep: true,
+                hold: Some(ANIMATION_FRAME_DELAY_MS),
+                animation: true,
+                ..FrameInfo::default()
+            });
+        }
+
+        redraw
+    }
+
+    /// Add a frame with the heatmap of square activity drawn over final_board, a full board
+    /// with the final position, held for the configured heatmap delay.
+    fn push_heatmap(&mut self, final_board: RgbaImage) {
+        log::debug!("Drawing heatmap");
+        let canvas_size = self.drawer.canvas_size();
+        let bar_offset = self.board_offset();
+        let mut canvas =
+            imageops::crop_imm(&final_board, 0, bar_offset, canvas_size, canvas_size).to_image();
+        self.drawer.draw_heatmap(&self.landings, &mut canvas);
+
+        // Like boards for moves, the frame only holds the squares that changed
+        let mut heatmap = RgbaImage::new(final_board.width(), final_board.height());
+        imageops::replace(&mut heatmap, &canvas, 0, bar_offset.into());
+        for (pixel, final_pixel) in heatmap.pixels_mut().zip(final_board.pixels()) {
+            if pixel == final_pixel {
+                *pixel = image::Rgba([0, 0, 0, 0]);
+            }
+        }
+
+        self.boards.push(heatmap);
+        self.frames.push(FrameInfo {
+            keep: true,
+            hold: Some(u32::from(self.config.heatmap_delay)),
+            ..FrameInfo::default()
+        });
+    }
+
+    /// Draw termination circles on the kings' squares of a board that has player bars above it
+    /// if bar_offset is not 0.
+    fn draw_termination_circles(
+        &mut self,
+        reason: TerminationReason,
+        winner_king: PieceInBoard,
+        loser_king: PieceInBoard,
+        bar_offset: u32,
+        board: &mut RgbaImage,
+    ) {
+        // Circles are placed relative to the board, without its border or player bars
+        let size = self.drawer.size();
+        let x = self.drawer.border_size();
+        let y = x + bar_offset;
+        let mut board_area = imageops::crop_imm(board, x, y, size, size).to_image();
+        self.termination_drawer
+            .draw_termination_circles(reason, winner_king, loser_king, &mut board_area, &self.svgs)
+            .expect("Failed to draw termination circle");
+        imageops::replace(board, &board_area, x.into(), y.into());
+    }
+
+    /// Draw a crown on the winning king of a board that has player bars above it if bar_offset
+    /// is not 0. The king is redrawn first if the board may not contain it, as boards only
+    /// contain the squares that changed.
+    fn draw_winner_crown(
+        &mut self,
+        winner_king: PieceInBoard,
+        redraw_king: bool,
+        bar_offset: u32,
+        board: &mut RgbaImage,
+    ) {
+        let canvas_size = self.drawer.canvas_size();
+        let mut canvas =
+            imageops::crop_imm(board, 0, bar_offset, canvas_size, canvas_size).to_image();
+        if redraw_king {
+            self.drawer
+                .redraw_piece(winner_king, &mut canvas, &self.svgs)
+                .expect("Failed to draw winning king");
+        }
+        self.drawer
+            .draw_piece_decoration(winner_king.square, "crown", &mut canvas, &self.svgs)
+            .expect("Failed to draw crown");
+        imageops::replace(board, &canvas, 0, bar_offset.into());
+    }
+
+    /// Lines of text to show in the title card: players, event, date, and time control.
+    fn title_card_lines(&self) -> Vec<String> {
+        let white = self
+            .players
+            .label(Color::White, &self.config.anonymous_name);
+        let black = self
+            .players
+            .label(Color::Black, &self.config.anonymous_name);
+        let mut lines = vec![white.to_string(), "vs".to_string(), black.to_string()];
+
+        // PGN uses "?" for unknown header values
+        let known = |value: &Option<String>| value.clone().filter(|v| !v.contains('?'));
+        let event_line = [known(&self.event), known(&self.date)]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<String>>()
+            .join(" · ");
+        if !event_line.is_empty() {
+            lines.push(event_line);
+        }
+
+        // Unknown time controls, written ?, and untimed games, written -, have none
+        if let Some(time_control) = &self.time_control {
+            lines.push(time_control.to_string());
+        }
+
+        lines
+    }
+
+    /// Text of the event bar: the event, round, site, and date, skipping the ones unknown.
+    fn event_bar_text(&self) -> String {
+        // PGN uses "?" for unknown header values, and "-" for rounds of casual games
+        let known = |value: &Option<String>| value.clone().filter(|v| !v.contains('?') && v != "-");
+        [
+            known(&self.event),
+            known(&self.round).map(|round| format!("Round {}", round)),
+            known(&self.site),
+            known(&self.date),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<String>>()
+        .join(" · ")
+    }
+
+    /// Text of the opening caption: the ECO code and name of the opening, skipping the ones
+    /// unknown.
+    fn opening_text(&self) -> String {
+        [&self.eco, &self.opening]
+            .into_iter()
+            .flatten()
+            .filter(|v| !v.contains('?'))
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Whether the opening is shown in the event bar in the first moves.
+    fn shows_opening(&self) -> bool {
+        self.config.style_components.opening()
+            && self.config.opening_moves > 0
+            && !self.opening_text().is_empty()
+    }
+
+    /// Opacity of the opening caption after a number of plies: it's shown in full for the
+    /// configured number of moves, and fades out over the next OPENING_FADE_PLIES.
+    fn opening_opacity(&self, plies: usize) -> f32 {
+        if !self.shows_opening() {
+            return 0.0;
+        }
+        let last_ply = self.config.opening_moves as usize * 2;
+        let left = (last_ply + OPENING_FADE_PLIES).saturating_sub(plies);
+        (left as f32 / OPENING_FADE_PLIES as f32).min(1.0)
+    }
+
+    /// Redraw the event bar of a frame laid out by add_bar_space if the opening caption
+    /// changed from the last frame. Once faded out, the bar shows the event again, if any.
+    fn draw_opening_caption(&mut self, img: &mut RgbaImage) {
+        let opacity = self.opening_opacity(self.moves);
+        if opacity == self.opening_opacity(self.moves.saturating_sub(1)) {
+            return;
+        }
+        let (text, opacity) = if opacity > 0.0 {
+            (self.opening_text(), opacity)
+        } else if self.config.style_components.event_bar() {
+            (self.event_bar_text(), 1.0)
+        } else {
+            (String::new(), 1.0)
+        };
+        self.drawer
+            .draw_faded_event_bar(&text, opacity, img, &self.svgs)
+            .expect("Failed to draw opening");
+    }
+
+    /// Hide or repeat the initial position frame according to the configuration. Delays must be
+    /// calculated beforehand, as they depend on the frame number matching the ply.
+    fn apply_initial_frame(&mut self, delays: &mut Vec<u32>) {
+        match self.config.initial_position {
+            InitialFrame::Show => (),
+            InitialFrame::Hide => {
+                if self.boards.len() < 2 {
+                    log::warn!("Game has no moves, initial position will not be hidden");
+                    return;
+                }
+                // The first move's board only contains the squares that changed, so it's
+                // drawn on top of the initial board instead of discarding it.
+                let mut initial_board = self.boards.remove(0);
+                imageops::overlay(&mut initial_board, &self.boards[0], 0, 0);
+                self.boards[0] = initial_board;
+                delays.remove(0);
+                self.frames.remove(0);
+            }
+            InitialFrame::Repeat(times) => {
+                for _ in 1..times {
+                    self.boards.insert(0, self.boards[0].clone());
+                    delays.insert(0, delays[0]);
+                    self.frames.insert(
+                        1,
+                        FrameInfo {
+                            keep: true,
+                            ..FrameInfo::default()
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Calculate the delay in ms after frame number n, out of total_frames.
+    fn frame_delay(&self, frame: usize, total_frames: usize) -> u32 {
+        let delays = &self.config.delays;
+        if let Some(hold) = self.frames.get(frame).and_then(|info| info.hold) {
+            return hold;
+        }
+
+        // Animation frames are skipped so frames can be counted as moves
+        let animation_frames = |n: usize| {
+            self.frames
+                .iter()
+                .take(n)
+                .filter(|info| info.animation)
+                .count()
+        };
+        let n = frame - animation_frames(frame);
+        let total_frames = total_frames - animation_frames(total_frames);
+        if n == (total_frames - 1) {
+            log::debug!("LAST FRAME");
+            u32::from(
+                delays
+                    .last_frame_delay()
+                    .expect("Last frame delay not defined"),
+            )
+        } else if self.frames.get(frame).is_some_and(|info| info.fade) {
+            TERMINATION_FADE_DELAY_MS
+        } else if n == 0 || n == 1 {
+            u32::from(
+                delays
+                    .first_frame_delay()
+                    .expect("First frame delay not defined"),
+            )
+        } else if let Some(ramp) = &delays.ramp {
+            // Ramp over frames that are not the first two or the last
+            u32::from(ramp.delay_at(n - 2, total_frames - 3))
+        } else {
+            match delays.frame {
+                Delay::Duration(d) => u32::from(d),
+                Delay::Real => {
+                    // A frame is shown while the player to move thinks, so it's held for the
+                    // time spent on the next ply
+                    let fallback = self.config.real_delay_fallback;
+                    let real_delay =
+                        self.frames
+                            .get(frame)
+                            .and_then(|info| info.ply)
+                            .and_then(|ply| {
+                                let color = if ply % 2 == 0 {
+                                    Color::White
+                                } else {
+                                    Color::Black
+                                };
+                                log::debug!("Calculating delay for ply: {}", ply);
+                                self.clocks.real_delay(ply / 2, color, fallback)
+                            });
+                    match real_delay {
+                        Some(d) => d,
+                        // First move, no previous clock, and no fallback
+                        None => u32::from(
+                            delays
+                                .first_frame_delay()
+                                .expect("First frame delay not defined"),
+                        ),
                     }
-                };
+                }
             }
-            Ok("TimeControl") => {
-                let inc = &value
-                    .decode_utf8_lossy()
-                    .to_string()
-                    .split("+")
-                    .collect::<Vec<&str>>()
-                    .get(1)
-                    .map_or_else(|| None, |s| Some(s.parse::<u16>().unwrap() * 1000));
-                self.clocks.increment = *inc;
+        }
+    }
+}
+
+/// Decode a PGN header value to be drawn: invalid UTF-8 is replaced, control characters
+/// are dropped, and surrounding whitespace is trimmed.
+fn normalize_header(value: &RawHeader<'_>) -> String {
+    value
+        .decode_utf8_lossy()
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Title in a WhiteTitle or BlackTitle header, like GM. None for untitled players, which
+/// lichess marks with "-".
+fn header_title(value: &RawHeader<'_>) -> Option<String> {
+    let title = normalize_header(value);
+    match title.as_str() {
+        "" | "-" | "?" => None,
+        _ => Some(title),
+    }
+}
+
+/// Country code in a header like WhiteCountry, uppercased, like NOR. Only codes of 2 or 3
+/// letters are kept, as they are also used to look up flag SVGs.
+fn header_country(value: &RawHeader<'_>) -> Option<String> {
+    let country = normalize_header(value).to_ascii_uppercase();
+    match country.len() {
+        2 | 3 if country.chars().all(|c| c.is_ascii_alphabetic()) => Some(country),
+        _ => None,
+    }
+}
+
+/// Color of move arrows: a light orange that stands out on both light and dark squares.
+const ARROW_COLOR: image::Rgba<u8> = image::Rgba([255, 170, 0, 255]);
+
+/// Count the piece captured by a move, if any, as taken by the moving color. Returns whether
+/// the move was a capture.
+fn record_capture(captures: &mut ByColor<ByRole<u8>>, m: &Move, mover: Color) -> bool {
+    let role = match m {
+        Move::Normal {
+            capture: Some(role),
+            ..
+        } => *role,
+        Move::EnPassant { .. } => Role::Pawn,
+        _ => return false,
+    };
+    *captures.get_mut(mover).get_mut(role) += 1;
+    true
+}
+
+/// Material difference between white and black using the standard piece values. Positive when
+/// white is ahead.
+fn material_balance(board: &Board) -> i32 {
+    let value = |material: ByRole<u8>| {
+        material.pawn as i32
+            + 3 * material.knight as i32
+            + 3 * material.bishop as i32
+            + 5 * material.rook as i32
+            + 9 * material.queen as i32
+    };
+    value(board.material_side(Color::White)) - value(board.material_side(Color::Black))
+}
+
+/// Squares pieces move between in a move, to draw arrows and highlights. Castling moves both
+/// the king and the rook, with the king first.
+fn move_paths(m: &Move) -> Vec<(Square, Square)> {
+    match m {
+        Move::Normal { from, to, .. } | Move::EnPassant { from, to } => vec![(*from, *to)],
+        Move::Castle { king, rook } => {
+            let (king_to, rook_to) = utils::castling_destinations(*king, *rook);
+            vec![(*king, king_to), (*rook, rook_to)]
+        }
+        Move::Put { .. } => Vec::new(),
+    }
+}
+
+/// Squares pieces land on in a move. Castling lands both the king and the rook.
+fn landing_squares(m: &Move) -> Vec<Square> {
+    match m {
+        Move::Put { to, .. } => vec![*to],
+        _ => move_paths(m).into_iter().map(|(_, to)| to).collect(),
+    }
+}
+
+/// Map a Termination header into one of the reasons understood by
+/// TerminationReason::from_outcome. Handles both chess.com phrasing, like "Hikaru won on time"
+/// or "Game drawn by timeout vs insufficient material", and lichess values like "Time forfeit".
+/// Anything else is assumed to be a resignation or a draw by agreement.
+fn termination_header_reason(header: &str, outcome: Outcome) -> &'static str {
+    let header = header.to_lowercase();
+    match outcome {
+        Outcome::Decisive { winner: _ } => {
+            if header.contains("checkmate") {
+                "checkmate"
+            } else if header.contains("on time")
+                || header.contains("time forfeit")
+                || header.contains("timeout")
+            {
+                "timeout"
+            } else {
+                "resignation"
             }
-            Ok("Termination") => {
-                self.termination = Some(value.decode_utf8_lossy().to_string());
+        }
+        Outcome::Draw => {
+            if header.contains("timeout vs insufficient material") {
+                "timeout"
+            } else if header.contains("insufficient material") {
+                "insufficient material"
+            } else if header.contains("stalemate") {
+                "stalemate"
+            } else if header.contains("repetition") {
+                "repetition"
+            } else {
+                "agreement"
             }
-            _ => (),
         }
     }
+}
 
-    /// Check if we managed to parse players and adjust the initial board
-    fn end_headers(&mut self) -> Skip {
-        log::debug!("Players: {}", self.players.exist());
-        if self.players.exist() && self.config.style_components.player_bars() == true {
-            log::debug!("Adding player bars to first board");
-            let board = self.boards.pop().expect("Initial board should exist");
-            let mut new_board = self.drawer.add_player_bar_space(board);
-            log::debug!(
-                "New board width: {}, height: {}",
-                new_board.width(),
-                new_board.height()
-            );
+/// Short result string for an outcome, using ½ for draws.
+fn result_text(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Decisive {
+            winner: shakmaty::Color::White,
+        } => "1-0",
+        Outcome::Decisive {
+            winner: shakmaty::Color::Black,
+        } => "0-1",
+        Outcome::Draw => "½-½",
+    }
+}
 
-            let white_player = self.players.white.as_ref().unwrap().to_string();
-            let black_player = self.players.black.as_ref().unwrap().to_string();
-            self.drawer
-                .draw_player_bars(&white_player, &black_player, &mut new_board, &self.svgs)
-                .expect("Failed to draw player bars");
+/// Smallest rectangle holding every pixel drawn on a transparent image, as its top left
+/// corner, width and height. Images with nothing drawn have an empty rectangle.
+fn drawn_bounds(img: &RgbaImage) -> (u32, u32, u32, u32) {
+    let (mut left, mut top, mut right, mut bottom) = (img.width(), img.height(), 0, 0);
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if pixel[3] > 0 {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x + 1);
+            bottom = bottom.max(y + 1);
+        }
+    }
+    if right <= left || bottom <= top {
+        return (0, 0, 0, 0);
+    }
+    (left, top, right - left, bottom - top)
+}
 
-            self.boards.push(new_board);
+/// Overlay all boards on top of each other to produce the complete latest frame.
+fn composite_boards(boards: &[RgbaImage]) -> RgbaImage {
+    let mut iter = boards.iter();
+    let mut composite = iter.next().expect("No boards drawn!").clone();
+    for board in iter {
+        imageops::overlay(&mut composite, board, 0, 0);
+    }
+    composite
+}
+
+/// Clock players start with given a time control, like 3:00 for 180+2, or 1:30:00 for the
+/// first stage of 40/5400+30:1800+30. Correspondence time controls, like 1/86400 for a move a
+/// day, have none.
+fn starting_clock(time_control: &TimeControl) -> Option<Clock> {
+    if time_control.is_correspondence() {
+        log::debug!("No starting clock for correspondence time control");
+        return None;
+    }
+    Some(Clock {
+        duration: time_control.base(),
+    })
+}
+
+/// Overlay every board on top of all the boards that came before it. Boards drawn for
+/// each move only contain the squares that changed, flattening them produces a list of
+/// complete frames that can be encoded in any order.
+fn flatten_boards<I>(boards: I) -> Vec<RgbaImage>
+where
+    I: IntoIterator<Item = RgbaImage>,
+{
+    let mut flattened: Vec<RgbaImage> = Vec::new();
+
+    for board in boards {
+        let full_board = match flattened.last() {
+            Some(previous) => {
+                let mut full_board = previous.clone();
+                imageops::overlay(&mut full_board, &board, 0, 0);
+                full_board
+            }
+            None => board,
+        };
+        flattened.push(full_board);
+    }
+
+    flattened
+}
+
+/// Resize boards drawn at a bigger size to width by height. Boards only contain the squares that
+/// changed, so each one is overlaid on the ones before it and resized whole, as resizing a
+/// partial board would blend its edges with the transparent squares around them. Pixels that
+/// are the same as in the previous frame are left transparent, so frames stay small.
+fn downscale_boards<I>(boards: I, width: u32, height: u32) -> Vec<RgbaImage>
+where
+    I: IntoIterator<Item = RgbaImage>,
+{
+    let mut full_board: Option<RgbaImage> = None;
+    let mut previous: Option<RgbaImage> = None;
+    let mut downscaled = Vec::new();
+
+    for board in boards {
+        let full = match full_board.take() {
+            Some(mut full) if full.dimensions() == board.dimensions() => {
+                imageops::overlay(&mut full, &board, 0, 0);
+                full
+            }
+            _ => board,
+        };
+        let frame = imageops::resize(&full, width, height, imageops::FilterType::Lanczos3);
+        full_board = Some(full);
+
+        let mut changed = frame.clone();
+        if let Some(previous) = &previous {
+            for (pixel, previous_pixel) in changed.pixels_mut().zip(previous.pixels()) {
+                if pixel == previous_pixel {
+                    *pixel = image::Rgba([0, 0, 0, 0]);
+                }
+            }
         }
+        previous = Some(frame);
+        downscaled.push(changed);
+    }
 
-        Skip(false)
+    downscaled
+}
+
+/// Check text overlays can be drawn on images width by height, before their frames are
+/// rendered: they need a size, a range with frames in it, and a position in the image.
+fn validate_overlays(overlays: &[TextOverlay], width: u32, height: u32) -> Result<(), GifferError> {
+    for overlay in overlays {
+        let reason = if !(overlay.size.is_finite() && overlay.size > 0.0) {
+            format!("must have a size greater than 0, got {}", overlay.size)
+        } else if let Some(frames) = overlay.frames.as_ref().filter(|frames| frames.is_empty()) {
+            format!("must be shown on some frames, got {:?}", frames)
+        } else if overlay.x >= width || overlay.y >= height {
+            format!(
+                "at {},{} is outside of the {}x{} image",
+                overlay.x, overlay.y, width, height
+            )
+        } else {
+            continue;
+        };
+        return Err(GifferError::InvalidConfig {
+            reason: format!("Text overlay \"{}\" {}", overlay.text, reason),
+        });
     }
+    Ok(())
+}
 
-    /// Calls BoardDrawer.draw_move with every move and stores the resulting board
-    fn san(&mut self, san_plus: SanPlus) {
-        if let Ok(m) = san_plus.san.to_move(&self.position) {
-            let mut board = self.drawer.image_buffer();
-            for (square, role, color) in self.to_clear.drain(..) {
-                self.drawer
-                    .draw_piece(
-                        &square, &role, color, false, &mut board, None, &self.svgs, false,
-                    )
-                    .expect(&format!("Failed to clear piece"));
+/// An image drawn over boards, like a watermark or a text overlay, with its top left corner at
+/// x, y, on a range of frames or on all of them.
+struct Layer {
+    image: RgbaImage,
+    x: u32,
+    y: u32,
+    frames: Option<Range<usize>>,
+}
+
+impl Layer {
+    fn shown_on(&self, frame: usize) -> bool {
+        self.frames
+            .as_ref()
+            .is_none_or(|frames| frames.contains(&frame))
+    }
+
+    /// Pixel of the layer over the pixel x, y of a board, if the layer covers it.
+    fn pixel_at(&self, x: u32, y: u32) -> Option<image::Rgba<u8>> {
+        if x < self.x || y < self.y {
+            return None;
+        }
+        self.image
+            .get_pixel_checked(x - self.x, y - self.y)
+            .copied()
+    }
+}
+
+/// Draw layers over boards, in order, on the frames they're shown on. Boards only contain the
+/// squares that changed, so each one is overlaid on the ones before it to blend the layers over
+/// what's under them. Pixels under the layers that look the same as in the previous frame are
+/// left transparent, so layers that come and go are drawn and cleared, and frames stay small.
+fn overlay_boards<I>(boards: I, layers: &[Layer]) -> Vec<RgbaImage>
+where
+    I: IntoIterator<Item = RgbaImage>,
+{
+    let mut full_board: Option<RgbaImage> = None;
+    let mut previous: Option<RgbaImage> = None;
+    let mut overlaid = Vec::new();
+
+    for (n, mut board) in boards.into_iter().enumerate() {
+        let full = match full_board.take() {
+            Some(mut full) if full.dimensions() == board.dimensions() => {
+                imageops::overlay(&mut full, &board, 0, 0);
+                full
             }
+            _ => board.clone(),
+        };
+        let previous_frame = previous
+            .take()
+            .filter(|previous| previous.dimensions() == board.dimensions());
+        let mut frame = previous_frame.clone().unwrap_or_else(|| full.clone());
 
-            self.drawer
-                .draw_move(&m, self.position.turn(), &mut board, &self.svgs)
-                .expect(&format!("Failed to draw move: {}", m));
+        let (width, height) = board.dimensions();
+        for layer in layers {
+            let right = (layer.x + layer.image.width()).min(width);
+            let bottom = (layer.y + layer.image.height()).min(height);
+            for (x, y) in (layer.y..bottom).flat_map(|y| (layer.x..right).map(move |x| (x, y))) {
+                let mut pixel = *full.get_pixel(x, y);
+                for top in layers.iter().filter(|l| l.shown_on(n)) {
+                    if let Some(top_pixel) = top.pixel_at(x, y) {
+                        utils::blend_pixel(&mut pixel, top_pixel);
+                    }
+                }
+                let unchanged = previous_frame
+                    .as_ref()
+                    .is_some_and(|previous_frame| *previous_frame.get_pixel(x, y) == pixel);
+                *board.get_pixel_mut(x, y) = match unchanged {
+                    true => image::Rgba([0, 0, 0, 0]),
+                    false => pixel,
+                };
+                frame.put_pixel(x, y, pixel);
+            }
+        }
+        imageops::overlay(&mut frame, &board, 0, 0);
 
-            log::debug!("Pushing board for move {:?}", m);
-            self.position.play_unchecked(&m);
+        full_board = Some(full);
+        previous = Some(frame);
+        overlaid.push(board);
+    }
 
-            if self.position.is_check() {
-                let color = self.position.turn();
-                let king_square = self
-                    .position
-                    .board()
-                    .king_of(color)
-                    .expect("King square should exist");
-                let king_piece = PieceInBoard::new_king(king_square, color);
-                self.drawer
-                    .draw_checked_king(king_piece, &mut board, &self.svgs)
-                    .expect(&format!("Failed to draw checked king: {}", king_square));
+    overlaid
+}
+
+/// Center boards on a canvas of width by height. Only the first board is drawn over the
+/// background, as the ones after it only contain what changed.
+fn center_boards(
+    boards: Vec<RgbaImage>,
+    width: u32,
+    height: u32,
+    background: image::Rgba<u8>,
+) -> Vec<RgbaImage> {
+    boards
+        .into_iter()
+        .enumerate()
+        .map(|(n, board)| {
+            let mut canvas = if n == 0 {
+                RgbaImage::from_pixel(width, height, background)
+            } else {
+                RgbaImage::new(width, height)
+            };
+            let x = (width - board.width()) / 2;
+            let y = (height - board.height()) / 2;
+            imageops::overlay(&mut canvas, &board, x.into(), y.into());
+            canvas
+        })
+        .collect()
+}
+
+/// Convert a board to grays, keeping transparent pixels transparent.
+fn grayscale(mut board: RgbaImage) -> RgbaImage {
+    for pixel in board.pixels_mut() {
+        let [luma] = pixel.to_luma().0;
+        *pixel = image::Rgba([luma, luma, luma, pixel[3]]);
+    }
+    board
+}
+
+/// Delay of the last frame, in hundredths of a second, when playing once. This is the largest
+/// delay a GIF frame can hold, so viewers that loop regardless still stop on the last frame.
+const PLAY_ONCE_LAST_FRAME_DELAY: u16 = u16::MAX;
+
+/// Convert a delay in ms to the hundredths of a second used by GIF frames. Delays are rounded to
+/// the nearest hundredth, floored at MIN_FRAME_DELAY_MS as many viewers replace shorter delays
+/// with a much longer default, and clamped if too long to be represented.
+fn gif_delay(delay_ms: u32) -> u16 {
+    let centiseconds = std::cmp::max(delay_ms.saturating_add(5) / 10, MIN_FRAME_DELAY_MS / 10);
+    match u16::try_from(centiseconds) {
+        Ok(d) => d,
+        Err(_) => {
+            log::warn!(
+                "Delay of {}ms is longer than the maximum GIF frame delay, clamping to {}ms",
+                delay_ms,
+                u16::MAX as u32 * 10
+            );
+            u16::MAX
+        }
+    }
+}
+
+/// The shortest delay in ms that GIF viewers reliably honor.
+const MIN_FRAME_DELAY_MS: u32 = 20;
+
+/// Delay in ms of each frame fading in termination circles.
+const TERMINATION_FADE_DELAY_MS: u32 = 80;
+
+/// Number of plies the opening caption fades out over.
+const OPENING_FADE_PLIES: usize = 4;
+
+/// Delay in ms of each frame of pieces sliding to their squares.
+const ANIMATION_FRAME_DELAY_MS: u32 = 30;
+
+/// Scale delays in ms proportionally so they add up to target_ms. Every frame is held for at least
+/// MIN_FRAME_DELAY_MS, so if the target is too short for the number of frames, the shortest feasible
+/// delays are returned instead.
+fn scale_delays(delays: &[u32], target_ms: u32) -> Vec<u32> {
+    let total_frames = delays.len();
+    let min_total_ms = MIN_FRAME_DELAY_MS * total_frames as u32;
+    if min_total_ms >= target_ms {
+        log::warn!(
+            "Cannot fit {} frames in {}ms, producing the shortest possible GIF of {}ms instead",
+            total_frames,
+            target_ms,
+            min_total_ms
+        );
+        return vec![MIN_FRAME_DELAY_MS; total_frames];
+    }
+
+    // Frames that would go below the minimum are pinned to it, and the remaining frames are
+    // scaled again to make up for the difference, until no more frames need to be pinned.
+    let mut pinned = vec![false; total_frames];
+    let scaled: Vec<f64> = loop {
+        let pinned_count = pinned.iter().filter(|p| **p).count() as u32;
+        let free_target = (target_ms - MIN_FRAME_DELAY_MS * pinned_count) as f64;
+        let free_sum: f64 = delays
+            .iter()
+            .zip(pinned.iter())
+            .filter(|(_, p)| !**p)
+            .map(|(d, _)| *d as f64)
+            .sum();
+
+        let scaled: Vec<f64> = delays
+            .iter()
+            .zip(pinned.iter())
+            .map(|(d, p)| {
+                if *p {
+                    MIN_FRAME_DELAY_MS as f64
+                } else if free_sum == 0.0 {
+                    free_target / (total_frames as u32 - pinned_count) as f64
+                } else {
+                    *d as f64 * free_target / free_sum
+                }
+            })
+            .collect();
+
+        let mut newly_pinned = false;
+        for (delay, p) in scaled.iter().zip(pinned.iter_mut()) {
+            if !*p && *delay < MIN_FRAME_DELAY_MS as f64 {
+                *p = true;
+                newly_pinned = true;
+            }
+        }
+
+        if !newly_pinned {
+            break scaled;
+        }
+    };
+
+    scaled
+        .into_iter()
+        // GIF delays are set in hundredths of a second
+        .map(|d| ((d / 10.0).round() * 10.0) as u32)
+        .collect()
+}
+
+/// Merge consecutive identical frames into a single frame that is held for the sum of their
+/// delays. Boards only contain what changed, so each one is overlaid on the ones before it and
+/// the whole frames are compared: a board that changes nothing, like a fully transparent one, is
+/// a duplicate, and the same partial board drawn over different frames isn't. Boards marked to
+/// keep are never merged into the previous one.
+fn merge_duplicate_frames<I>(
+    boards: I,
+    delays: Vec<u32>,
+    keep: &[bool],
+) -> (Vec<RgbaImage>, Vec<u32>)
+where
+    I: IntoIterator<Item = RgbaImage>,
+{
+    let mut merged_boards: Vec<RgbaImage> = Vec::new();
+    let mut merged_delays: Vec<u32> = Vec::new();
+    let mut previous: Option<(u64, RgbaImage)> = None;
+
+    for (n, (board, delay)) in boards.into_iter().zip(delays).enumerate() {
+        let frame = match &previous {
+            Some((_, previous_frame)) if previous_frame.dimensions() == board.dimensions() => {
+                let mut frame = previous_frame.clone();
+                imageops::overlay(&mut frame, &board, 0, 0);
+                frame
+            }
+            _ => board.clone(),
+        };
+        let mut hasher = DefaultHasher::new();
+        frame.as_raw().hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let is_duplicate = !keep.get(n).copied().unwrap_or(false)
+            && previous
+                .as_ref()
+                .is_some_and(|(previous_hash, previous_frame)| {
+                    *previous_hash == hash && *previous_frame == frame
+                });
+
+        if is_duplicate {
+            log::debug!("Merging duplicate board number: {}", n);
+            let last_delay = merged_delays
+                .last_mut()
+                .expect("Duplicate has a previous frame");
+            *last_delay = last_delay.saturating_add(delay);
+        } else {
+            merged_boards.push(board);
+            merged_delays.push(delay);
+        }
+        previous = Some((hash, frame));
+    }
+
+    (merged_boards, merged_delays)
+}
+
+/// Reverse a list of frame delays. When playing backwards, each frame is held for as long as
+/// it took to reach its position when playing forward. The first frame is held for
+/// first_frame_delay and the last frame, which is now the initial position, for last_frame_delay.
+/// Transitions, like sliding pieces or fading circles, keep their own brief delays and are
+/// skipped when shifting the delays of the positions around them.
+fn reverse_delays(
+    delays: &[u32],
+    transitions: &[bool],
+    first_frame_delay: u32,
+    last_frame_delay: u32,
+) -> Vec<u32> {
+    let positions: Vec<u32> = delays
+        .iter()
+        .zip(transitions)
+        .filter(|(_, transition)| !**transition)
+        .map(|(delay, _)| *delay)
+        .collect();
+    let total_positions = positions.len();
+    let mut reversed_positions = (0..total_positions).map(|n| {
+        if n == total_positions - 1 {
+            last_frame_delay
+        } else if n == 0 {
+            first_frame_delay
+        } else {
+            positions[total_positions - n - 2]
+        }
+    });
+
+    delays
+        .iter()
+        .zip(transitions)
+        .rev()
+        .map(|(delay, transition)| {
+            if *transition {
+                *delay
+            } else {
+                reversed_positions
+                    .next()
+                    .expect("A delay for every position")
+            }
+        })
+        .collect()
+}
+
+impl Visitor for PGNGiffer {
+    type Result = Result<Option<Vec<u8>>, GifferError>;
+
+    fn begin_game(&mut self) {
+        log::info!("Rendering initial board");
+        let board = self
+            .drawer
+            .draw_initial_position(&self.svgs)
+            .expect(&format!(
+                "Failed to draw initial position: {}",
+                self.position.board()
+            ));
+        self.boards.push(board);
+        self.frames.push(FrameInfo {
+            ply: Some(0),
+            ..FrameInfo::default()
+        });
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    /// Parses PGN headers to extract player information
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        match std::str::from_utf8(key) {
+            Ok("White") => {
+                let name = &normalize_header(&value);
+
+                log::debug!("White: {}", name);
+                match self.players.white {
+                    Some(_) => self
+                        .players
+                        .update_player_name(shakmaty::Color::White, name),
+                    None => self.players.create_player(
+                        shakmaty::Color::White,
+                        Some(name.to_string()),
+                        None,
+                        None,
+                    ),
+                };
+            }
+            Ok("Black") => {
+                let name = &normalize_header(&value);
+
+                log::debug!("Black: {}", name);
+                match self.players.black {
+                    Some(_) => self
+                        .players
+                        .update_player_name(shakmaty::Color::Black, name),
+                    None => self.players.create_player(
+                        shakmaty::Color::Black,
+                        Some(name.to_string()),
+                        None,
+                        None,
+                    ),
+                };
+            }
+            Ok("WhiteElo") => {
+                let elo = &value
+                    .decode_utf8_lossy()
+                    .to_string()
+                    .parse::<u32>()
+                    .expect("WhiteElo could not be parsed");
+
+                match self.players.white {
+                    Some(_) => self.players.update_player_elo(shakmaty::Color::White, *elo),
+                    None => {
+                        self.players
+                            .create_player(shakmaty::Color::White, None, None, Some(*elo))
+                    }
+                };
+            }
+            Ok("BlackElo") => {
+                let elo = &value
+                    .decode_utf8_lossy()
+                    .to_string()
+                    .parse::<u32>()
+                    .expect("BlackElo could not be parsed");
+
+                log::debug!("BlackElo: {}", elo);
+                match self.players.black {
+                    Some(_) => self.players.update_player_elo(shakmaty::Color::Black, *elo),
+                    None => {
+                        self.players
+                            .create_player(shakmaty::Color::Black, None, None, Some(*elo))
+                    }
+                };
+            }
+            Ok(key @ ("WhiteTitle" | "BlackTitle")) => {
+                let color = if key == "WhiteTitle" {
+                    shakmaty::Color::White
+                } else {
+                    shakmaty::Color::Black
+                };
+                let title = match header_title(&value) {
+                    Some(title) => title,
+                    None => return,
+                };
+
+                log::debug!("{}: {}", key, title);
+                let player = match color {
+                    shakmaty::Color::White => &self.players.white,
+                    shakmaty::Color::Black => &self.players.black,
+                };
+                match player {
+                    Some(_) => self.players.update_player_title(color, &title),
+                    None => self.players.create_player(color, None, Some(title), None),
+                };
+            }
+            Ok(key @ ("WhiteRatingDiff" | "BlackRatingDiff")) => {
+                let color = if key == "WhiteRatingDiff" {
+                    shakmaty::Color::White
+                } else {
+                    shakmaty::Color::Black
+                };
+                match normalize_header(&value).parse::<i32>() {
+                    Ok(diff) => self.players.update_player_rating_diff(color, diff),
+                    Err(_) => log::debug!("Ignoring malformed {}: {:?}", key, value),
+                }
+            }
+            Ok("Event") => {
+                self.event = Some(normalize_header(&value));
+            }
+            Ok("Site") => {
+                self.site = Some(normalize_header(&value));
+            }
+            Ok("Round") => {
+                self.round = Some(normalize_header(&value));
+            }
+            Ok("Date") => {
+                self.date = Some(normalize_header(&value));
+            }
+            Ok("TimeControl") => {
+                let time_control = TimeControl::from_header(&value.decode_utf8_lossy());
+                self.clocks.increment = time_control
+                    .as_ref()
+                    .map(|tc| u32::try_from(tc.increment().as_millis()).unwrap_or(u32::MAX));
+                self.clocks.start = time_control.as_ref().and_then(starting_clock);
+                self.time_control = time_control;
+            }
+            Ok("ECO") => {
+                self.eco = Some(normalize_header(&value));
+            }
+            Ok("Opening") => {
+                self.opening = Some(normalize_header(&value));
+            }
+            Ok("Termination") => {
+                self.termination = Some(normalize_header(&value));
+            }
+            Ok(key)
+                if key == self.config.white_country_header
+                    || key == self.config.black_country_header =>
+            {
+                let color = if key == self.config.white_country_header {
+                    shakmaty::Color::White
+                } else {
+                    shakmaty::Color::Black
+                };
+                match header_country(&value) {
+                    Some(country) => self.players.update_player_country(color, country),
+                    None => log::debug!("Ignoring malformed {}: {:?}", key, value),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Check if we managed to parse players and adjust the initial board
+    fn end_headers(&mut self) -> Skip {
+        if self.config.force_player_bars && !self.players.exist() {
+            log::debug!("No player headers found, forcing player bars");
+            for (color, name) in [(Color::White, "White"), (Color::Black, "Black")] {
+                self.players
+                    .create_player(color, Some(name.to_string()), None, None);
+            }
+        }
+
+        if let Some(pov) = self.config.pov.clone() {
+            if self.config.orientation == Orientation::FollowTurn {
+                log::warn!(
+                    "Ignoring point of view {} as the board follows the turn",
+                    pov
+                );
+            } else {
+                self.orient_to(&pov);
+            }
+        }
+
+        log::debug!("Players: {}", self.players.exist());
+        let board = self.boards.pop().expect("Initial board should exist");
+        let shows_opening = self.shows_opening();
+        self.drawer
+            .set_event_bar(self.config.style_components.event_bar() || shows_opening);
+        let mut new_board = self.drawer.add_bar_space(board, self.should_draw_bars());
+        if shows_opening {
+            self.drawer
+                .draw_event_bar(&self.opening_text(), &mut new_board, &self.svgs)
+                .expect("Failed to draw opening");
+        } else if self.config.style_components.event_bar() {
+            self.drawer
+                .draw_event_bar(&self.event_bar_text(), &mut new_board, &self.svgs)
+                .expect("Failed to draw event bar");
+        }
+
+        if self.should_draw_bars() {
+            log::debug!("Adding player bars to first board");
+            log::debug!(
+                "New board width: {}, height: {}",
+                new_board.width(),
+                new_board.height()
+            );
+
+            self.draw_player_bar(Color::White, &mut new_board);
+            self.draw_player_bar(Color::Black, &mut new_board);
+        }
+        // Starting clocks, if the time control is known, until the first clock comments
+        self.frames.last_mut().expect("No frame written").clocks = self.clocks.snapshot();
+        self.draw_last_clocks(&mut new_board);
+        self.draw_move_list(&mut new_board);
+        self.draw_caption(&mut new_board);
+        self.boards.push(new_board);
+
+        if self.config.title_card.is_some() {
+            if self.players.exist() {
+                let height = self
+                    .boards
+                    .last()
+                    .expect("Initial board should exist")
+                    .height();
+                let card = self
+                    .drawer
+                    .draw_title_card(&self.title_card_lines(), height, &self.svgs)
+                    .expect("Failed to draw title card");
+                self.title_card = Some(card);
+            } else {
+                log::info!("No player headers found, skipping title card");
+            }
+        }
+
+        Skip(false)
+    }
+
+    /// Calls BoardDrawer.draw_move with every move and stores the resulting board
+    fn san(&mut self, san_plus: SanPlus) {
+        if let Ok(m) = san_plus.san.to_move(&self.position) {
+            let animated = if self.config.animate_moves > 0 {
+                self.push_move_animation(&m)
+            } else {
+                Vec::new()
+            };
+
+            // Following the turn flips the board every ply, so nothing can be kept from the
+            // last frame and the whole position is drawn before the move.
+            let follow_turn = self.config.orientation == Orientation::FollowTurn;
+            let mut board = if follow_turn {
+                self.orient_for(!self.position.turn());
+                self.drawer
+                    .draw_position(&self.position, &self.svgs)
+                    .expect("Failed to draw position")
+            } else {
+                self.drawer.image_buffer()
+            };
+            for (square, role, color) in self.to_clear.drain(..) {
+                self.drawer
+                    .draw_piece(&square, role.of(color), false, &mut board, None, &self.svgs)
+                    .expect(&format!("Failed to clear piece"));
+            }
+
+            // Squares under the last frame's overlays, like arrows, need to be cleared, and
+            // squares under the next arrow need to be drawn so it can be blended with them.
+            // In Chess960 the king may castle without moving, which leaves nothing to point at
+            let arrows: Vec<(Square, Square)> = if self.config.style_components.arrows() {
+                move_paths(&m)
+                    .into_iter()
+                    .filter(|(from, to)| from != to)
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let mut arrow_squares: Vec<Square> = arrows
+                .iter()
+                .flat_map(|(from, to)| utils::arrow_squares(*from, *to))
+                .collect();
+            for square in self
+                .overlay_squares
+                .drain(..)
+                .chain(animated)
+                .chain(arrow_squares.iter().copied())
+            {
+                match self.position.board().piece_at(square) {
+                    Some(piece) => self
+                        .drawer
+                        .draw_piece(&square, piece, true, &mut board, None, &self.svgs),
+                    None => self.drawer.draw_square(&square, &mut board, &self.svgs),
+                }
+                .expect("Failed to draw square under arrow");
+            }
+
+            self.drawer
+                .draw_move(&m, self.position.turn(), &mut board, &self.svgs)
+                .expect(&format!("Failed to draw move: {}", m));
+            // Promoted pieces are only drawn with their promotion artwork in this frame
+            if let Move::Normal {
+                to,
+                promotion: Some(role),
+                ..
+            } = m
+            {
+                self.to_clear.push((to, role, self.position.turn()));
+            }
+            // The taken pawn's square is marked with move highlights, so it's cleared next move
+            if let Move::EnPassant { from, to } = m {
+                if self.config.style_components.move_highlights() {
+                    self.overlay_squares
+                        .push(Square::from_coords(to.file(), from.rank()));
+                }
+            }
+
+            log::debug!("Pushing board for move {:?}", m);
+            let mover = self.position.turn();
+            let captured = record_capture(&mut self.captures, &m, mover);
+            self.last_move = move_paths(&m).first().map(|(_, to)| (mover, *to));
+            for square in landing_squares(&m) {
+                self.landings[square as usize] += 1;
+            }
+            self.position.play_unchecked(&m);
+            self.moves += 1;
+            self.san_moves.push(san_plus.to_string());
+            self.assessments.clear();
+            self.frames.push(FrameInfo {
+                key_moments: KeyMoments::from_move(&m, &self.position),
+                ply: Some(self.moves),
+                clocks: self.clocks.snapshot(),
+                ..FrameInfo::default()
+            });
+            self.report_progress(Stage::Drawing, self.moves, None);
+
+            // The trail fades every frame, so all of its squares are drawn again in the next one
+            if self.config.trail > 0 {
+                let length = self.config.trail as usize;
+                self.trail.push_back(move_paths(&m));
+                if self.trail.len() > length {
+                    self.trail.pop_front();
+                }
+                let color = self
+                    .config
+                    .colors
+                    .highlight
+                    .as_ref()
+                    .expect("Highlight color is set when there is a trail");
+                self.drawer
+                    .draw_trail(
+                        &self.trail,
+                        length,
+                        image::Rgba(color.to_arr()),
+                        self.position.board(),
+                        &mut board,
+                        &self.svgs,
+                    )
+                    .expect("Failed to draw trail");
+                self.overlay_squares.extend(
+                    self.trail
+                        .iter()
+                        .flatten()
+                        .flat_map(|(from, to)| [*from, *to]),
+                );
+            }
+
+            if self.position.is_check() {
+                let color = self.position.turn();
+                let king_square = self
+                    .position
+                    .board()
+                    .king_of(color)
+                    .expect("King square should exist");
+                let king_piece = PieceInBoard::new_king(king_square, color);
+                self.drawer
+                    .draw_checked_king(king_piece, &mut board, &self.svgs)
+                    .expect(&format!("Failed to draw checked king: {}", king_square));
+
+                let to_be_cleared = (king_square, Role::King, color);
+                self.to_clear.push(to_be_cleared);
+
+                // A double check highlights both checkers and their rays
+                for checker in self.position.checkers() {
+                    let piece = self
+                        .position
+                        .board()
+                        .piece_at(checker)
+                        .expect("Checker square should have a piece");
+                    self.drawer
+                        .draw_check_ray(checker, king_square, &mut board, &self.svgs)
+                        .expect("Failed to draw check ray");
+                    self.drawer
+                        .draw_checker(checker, piece, &mut board, &self.svgs)
+                        .expect("Failed to draw checker");
+
+                    self.to_clear.push((checker, piece.role, piece.color));
+                    self.overlay_squares
+                        .extend(shakmaty::attacks::between(checker, king_square));
+                }
+            };
+
+            for (from, to) in arrows {
+                self.drawer
+                    .draw_arrow(from, to, ARROW_COLOR, &mut board, &self.svgs)
+                    .expect("Failed to draw arrow");
+            }
+            self.overlay_squares.append(&mut arrow_squares);
+
+            if let Some(corner) = self.config.move_overlay {
+                self.draw_move_overlay(corner, &mut board);
+            }
+
+            let mut new_board = if self.should_draw_bars() {
+                log::debug!("Adding player bars");
+                let mut new_board = self.drawer.add_bar_space(board, true);
+
+                // Bars swap places when the board flips, so both are drawn again with their
+                // clocks. The mover's new clock is drawn over its last one when commented.
+                if follow_turn {
+                    self.draw_player_bar(Color::White, &mut new_board);
+                    self.draw_player_bar(Color::Black, &mut new_board);
+                    self.draw_last_clocks(&mut new_board);
+                } else if captured {
+                    // Bars were drawn in the initial board, so only the capturing player's
+                    // bar needs to be updated. Redrawing it covers the clock, so it's drawn
+                    // again.
+                    self.draw_player_bar(mover, &mut new_board);
+
+                    if let Some(clock) = self.last_frame_clock(mover) {
+                        self.draw_clock(&clock, mover, ClockStyle::Normal, &mut new_board);
+                    }
+                }
+
+                // Only the move just played shows its time, so the opponent's is cleared
+                self.draw_move_time(!mover, None, &mut new_board);
+
+                // Redrawing the capturing player's bar also cleared its balance
+                let balance = material_balance(self.position.board());
+                if self.config.style_components.material_balance()
+                    && (balance != self.material_balance || captured || follow_turn)
+                {
+                    self.drawer
+                        .draw_material_balance(balance, &mut new_board, &self.svgs)
+                        .expect("Failed to draw material balance");
+                    self.material_balance = balance;
+                }
+
+                new_board
+            } else {
+                // Clocks are drawn over the board, so they're drawn again over any square
+                // drawn under them
+                let mut new_board = self.drawer.add_bar_space(board, false);
+                self.draw_last_clocks(&mut new_board);
+                new_board
+            };
+            self.draw_opening_caption(&mut new_board);
+            self.draw_move_list(&mut new_board);
+            if self.caption_shown {
+                self.draw_caption(&mut new_board);
+            }
+            self.boards.push(new_board);
+        }
+    }
+
+    /// Draws assessments of the last move given with NAGs, like $2 or ?
+    fn nag(&mut self, nag: Nag) {
+        if let Some(assessment) = MoveAssessment::from_nag(nag) {
+            self.draw_assessment(assessment);
+        }
+    }
+
+    /// Parses comments to extract %clk (clock) comments
+    fn comment(&mut self, comment: RawComment<'_>) {
+        match std::str::from_utf8(comment.as_bytes()) {
+            Ok(s) => {
+                let annotations = Annotations::parse(s);
+                if !annotations.is_empty() {
+                    self.draw_annotations(&annotations);
+                    self.frames
+                        .last_mut()
+                        .expect("No frame written")
+                        .annotations
+                        .extend(annotations);
+                }
+
+                if let Some(eval) = Eval::from_comment(s) {
+                    self.record_eval(eval);
+                }
+
+                if let Some(text) = comment_text(s) {
+                    let info = self.frames.last_mut().expect("No frame written");
+                    info.caption = match info.caption.take() {
+                        Some(caption) => Some(format!("{} {}", caption, text)),
+                        None => Some(text),
+                    };
+                    let mut board = self.boards.pop().expect("No board written");
+                    self.draw_caption(&mut board);
+                    self.boards.push(board);
+                }
+
+                if let Some(elapsed) = Clock::from_comment(s, "emt") {
+                    log::debug!("Appending elapsed move time: {:?}", elapsed);
+                    self.clocks.append_elapsed(elapsed, !self.position.turn());
+                }
+
+                if let Some(clock) = Clock::from_comment(s, "clk") {
+                    log::debug!("Appending clock: {:?}", clock);
+
+                    // The turn is of the player that moves next, so the clock is of the
+                    // player that moved last
+                    let color = !self.position.turn();
+                    let mut board = self.boards.pop().expect("No board written");
+                    self.draw_clock(&clock, color, ClockStyle::Normal, &mut board);
+                    *self
+                        .frames
+                        .last_mut()
+                        .expect("No frame written")
+                        .clocks
+                        .get_mut(color) = Some(clock.clone());
+                    self.clocks.append(clock, color);
+                    let move_time = self.clocks.last_move_delay(color);
+                    self.draw_move_time(color, move_time, &mut board);
+                    self.boards.push(board);
+                }
+            }
+            Err(_) => (),
+        }
+    }
+
+    /// Check the outcome of the game to draw the appropiate termination circle
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        // If the game didn't end, we don't do anything
+        let o = match outcome {
+            Some(o) => o,
+            None => return,
+        };
+        let termination_reason = self.termination_reason(o);
+        self.result = Some((o, termination_reason));
+
+        if let TerminationReason::Timeout { winner } = termination_reason {
+            let loser = !winner;
+            let last_clock = match loser {
+                Color::White => self.clocks.white().last(),
+                Color::Black => self.clocks.black().last(),
+            };
+            match last_clock.cloned() {
+                Some(clock) => {
+                    let mut board = self.boards.pop().expect("No boards drawn!");
+                    self.draw_clock(&clock, loser, ClockStyle::FlagFall, &mut board);
+                    self.boards.push(board);
+                }
+                None => log::debug!("No clock to draw flag fall for {:?}", loser),
+            }
+        }
+
+        if self.config.style_components.terminations() {
+            let mut latest_board = self.boards.pop().expect("No boards drawn!");
+            let (winner_king, loser_king) = match o {
+                Outcome::Draw => {
+                    // Doesn't really matter which king is which, since in draw there is no
+                    // winner or loser.
+                    let square1 = self
+                        .position
+                        .board()
+                        .king_of(shakmaty::Color::White)
+                        .expect("King doesn't exist");
+                    let square2 = self
+                        .position
+                        .board()
+                        .king_of(shakmaty::Color::Black)
+                        .expect("King doesn't exist");
+
+                    let king1 = PieceInBoard::new_king(square1, shakmaty::Color::White);
+                    let king2 = PieceInBoard::new_king(square2, shakmaty::Color::Black);
+
+                    (king1, king2)
+                }
+                Outcome::Decisive { winner: w } => {
+                    let winner = self
+                        .position
+                        .board()
+                        .king_of(w)
+                        .expect("King doesn't exist");
+                    let loser_color = match w {
+                        shakmaty::Color::Black => shakmaty::Color::White,
+                        shakmaty::Color::White => shakmaty::Color::Black,
+                    };
+                    let loser = self
+                        .position
+                        .board()
+                        .king_of(loser_color)
+                        .expect("King doesn't exist");
+
+                    let winner_king = PieceInBoard::new_king(winner, w);
+                    let loser_king = PieceInBoard::new_king(loser, loser_color);
+
+                    (winner_king, loser_king)
+                }
+            };
+
+            // Player bars, if any, are above the board
+            let bar_offset = self.board_offset();
+
+            if let TerminationReason::Checkmate { .. } = termination_reason {
+                // The game is over, so these kings never need to be cleared
+                let canvas_size = self.drawer.canvas_size();
+                let mut canvas =
+                    imageops::crop_imm(&latest_board, 0, bar_offset, canvas_size, canvas_size)
+                        .to_image();
+                self.drawer
+                    .draw_win_king(
+                        PieceInBoard::new_king(winner_king.square, winner_king.color),
+                        &mut canvas,
+                        &self.svgs,
+                    )
+                    .expect("Failed to draw winning king");
+                self.drawer
+                    .draw_mated_king(
+                        PieceInBoard::new_king(loser_king.square, loser_king.color),
+                        &mut canvas,
+                        &self.svgs,
+                    )
+                    .expect("Failed to draw checkmated king");
+                imageops::replace(&mut latest_board, &canvas, 0, bar_offset.into());
+            }
+
+            // Crowns go under the circles, and on the win artwork if the king was mated
+            if self.config.style_components.winner_crown() && o != Outcome::Draw {
+                let is_checkmate =
+                    matches!(termination_reason, TerminationReason::Checkmate { .. });
+                self.draw_winner_crown(winner_king, !is_checkmate, bar_offset, &mut latest_board);
+            }
+
+            log::debug!(
+                "Drawing termination: {:?}, {:?}, {:?}, {:?}",
+                o,
+                termination_reason,
+                winner_king,
+                loser_king
+            );
+
+            let fade_frames = self.config.termination_fade;
+            if fade_frames > 0 {
+                // Faded circles are partially transparent, so they can't be drawn on a board that
+                // only contains the squares that changed. Keep the last move as is, and fade the
+                // circles in over the full board.
+                self.boards.push(latest_board);
+                latest_board = composite_boards(&self.boards);
+                for n in 1..=fade_frames {
+                    let mut faded_board = latest_board.clone();
+                    self.termination_drawer
+                        .set_opacity(n as f32 / (fade_frames + 1) as f32);
+                    self.draw_termination_circles(
+                        termination_reason,
+                        winner_king,
+                        loser_king,
+                        bar_offset,
+                        &mut faded_board,
+                    );
+                    self.boards.push(faded_board);
+                    self.frames.push(FrameInfo {
+                        keep: true,
+                        fade: true,
+                        ..FrameInfo::default()
+                    });
+                }
+                self.termination_drawer.set_opacity(1.0);
+                self.frames.push(FrameInfo {
+                    keep: true,
+                    ..FrameInfo::default()
+                });
+            }
+
+            self.draw_termination_circles(
+                termination_reason,
+                winner_king,
+                loser_king,
+                bar_offset,
+                &mut latest_board,
+            );
+            self.boards.push(latest_board);
+        }
+
+        if self.config.style_components.winner_crown()
+            && !self.config.style_components.terminations()
+        {
+            if let Outcome::Decisive { winner } = o {
+                let mut latest_board = self.boards.pop().expect("No boards drawn!");
+                let bar_offset = self.board_offset();
+                let square = self
+                    .position
+                    .board()
+                    .king_of(winner)
+                    .expect("King doesn't exist");
+                self.draw_winner_crown(
+                    PieceInBoard::new_king(square, winner),
+                    true,
+                    bar_offset,
+                    &mut latest_board,
+                );
+                self.boards.push(latest_board);
+            }
+        }
+
+        if self.config.style_components.termination_text() {
+            let mut latest_board = self.boards.pop().expect("No boards drawn!");
+            let canvas_size = self.drawer.canvas_size();
+            let bar_offset = self.board_offset();
+            let mut canvas =
+                imageops::crop_imm(&latest_board, 0, bar_offset, canvas_size, canvas_size)
+                    .to_image();
+            self.drawer
+                .draw_termination_text(&termination_reason.summary(), &mut canvas, &self.svgs)
+                .expect("Failed to draw termination text");
+            imageops::replace(&mut latest_board, &canvas, 0, bar_offset.into());
+            self.boards.push(latest_board);
+        }
+
+        let (heatmap, end_card) = (
+            self.config.style_components.heatmap(),
+            self.config.style_components.end_card(),
+        );
+        if !heatmap && !end_card {
+            return;
+        }
+
+        // The end card shows the final position, so it's taken before the heatmap is added
+        let final_board = composite_boards(&self.boards);
+        if heatmap {
+            self.push_heatmap(final_board.clone());
+        }
+
+        if end_card {
+            log::debug!("Drawing end card");
+            let mut end_card = final_board;
+            let text = format!("{} · {}", result_text(o), termination_reason.description());
+            self.drawer
+                .draw_result_banner(&text, &mut end_card, &self.svgs)
+                .expect("Failed to draw end card");
+            self.boards.push(end_card);
+            self.frames.push(FrameInfo {
+                keep: true,
+                ..FrameInfo::default()
+            });
+        }
+    }
+
+    /// Iterates over boards collected for every move to encode GIF frames for each move.
+    /// Assigns delays to each frame based on self.config.delay and self.last_frame_multiplier.
+    fn end_game(&mut self) -> Self::Result {
+        // Finished games get their heatmap before the end card
+        if self.result.is_none() && self.config.style_components.heatmap() {
+            self.push_heatmap(composite_boards(&self.boards));
+        }
+        self.report_progress(Stage::Drawing, self.moves, Some(self.moves));
+        let total_frames = self.boards.len();
+        let (height, width) = (
+            self.drawer.layout_height(self.should_draw_bars()) as u16,
+            self.drawer.layout_width() as u16,
+        );
+        log::debug!(
+            "Size: {}, width: {}, height: {}",
+            self.drawer.size(),
+            width,
+            height
+        );
+
+        if let Some(simulation) = self.config.clock_simulation {
+            if self.config.delays.is_delay_real() && self.clocks.is_empty() {
+                if self.clocks.simulate(self.moves, simulation) {
+                    log::info!(
+                        "No clock comments found, simulating clocks {:?}",
+                        simulation
+                    );
+                } else {
+                    log::info!("No clock comments nor time control found to simulate clocks");
+                }
+            }
+        }
+
+        let mut delays: Vec<u32> = (0..total_frames)
+            .map(|n| self.frame_delay(n, total_frames))
+            .collect();
+
+        if self.config.pace == Pace::Smart {
+            log::info!("Pacing key moments");
+            let last_frame = total_frames - 1;
+            for (delay, info) in delays[..last_frame].iter_mut().zip(self.frames.iter()) {
+                let factor = info.key_moments.factor(&self.config.key_moment_factors);
+                *delay = (*delay as f64 * factor as f64).min(u32::MAX as f64) as u32;
+            }
+        }
+
+        self.apply_initial_frame(&mut delays);
+
+        let transitions: Vec<bool> = self
+            .frames
+            .iter()
+            .map(|info| info.animation || info.fade)
+            .collect();
+        let mut keep: Vec<bool> = self.frames.drain(..).map(|info| info.keep).collect();
+
+        if self.config.reverse {
+            log::info!("Reversing frames");
+            keep.reverse();
+            // Boards only contain the squares that changed, so they must be flattened
+            // before their order can be changed.
+            let boards = flatten_boards(self.boards.drain(..));
+            self.boards = boards.into_iter().rev().collect();
+            delays = reverse_delays(
+                &delays,
+                &transitions,
+                u32::from(
+                    self.config
+                        .delays
+                        .first_frame_delay()
+                        .expect("First frame delay not defined"),
+                ),
+                u32::from(
+                    self.config
+                        .delays
+                        .last_frame_delay()
+                        .expect("Last frame delay not defined"),
+                ),
+            );
+        }
+
+        if let Some(card) = self.title_card.take() {
+            self.boards.insert(0, card);
+            delays.insert(
+                0,
+                u32::from(self.config.title_card.expect("Title card delay is set")),
+            );
+            keep.insert(0, true);
+        }
+
+        if let Some(total_duration) = self.config.total_duration {
+            log::info!("Scaling delays to a total duration of {:?}", total_duration);
+            // When playing once the last frame is held for good, so the GIF lasts until it's reached
+            let scaled_frames = if self.config.play_once && delays.len() > 1 {
+                delays.len() - 1
+            } else {
+                delays.len()
+            };
+            let scaled = scale_delays(&delays[..scaled_frames], total_duration.as_millis() as u32);
+            delays[..scaled_frames].copy_from_slice(&scaled);
+        }
+
+        // Text overlays are drawn over everything else on the board and its bars
+        let layers = self.text_overlay_layers(width.into(), height.into())?;
+        if !layers.is_empty() {
+            self.boards = overlay_boards(self.boards.drain(..), &layers);
+        }
+
+        let (drawn_size, size) = (self.drawer.size(), self.config.size);
+        let (boards, width, height) = if drawn_size != size {
+            log::info!(
+                "Resizing frames from a {}px board to {}px",
+                drawn_size,
+                size
+            );
+            let resize = |n: u16| ((u32::from(n) * size + drawn_size / 2) / drawn_size) as u16;
+            let (width, height) = (resize(width), resize(height));
+            let boards = downscale_boards(self.boards.drain(..), width.into(), height.into());
+            (boards, width, height)
+        } else {
+            (self.boards.drain(..).collect(), width, height)
+        };
+
+        let (boards, width, height) = match self.config.canvas {
+            Some((canvas_width, canvas_height)) => {
+                if canvas_width < width.into() || canvas_height < height.into() {
+                    return Err(GifferError::InvalidConfig {
+                        reason: format!(
+                            "Canvas of {}x{} is smaller than the {}x{} board",
+                            canvas_width, canvas_height, width, height
+                        ),
+                    });
+                }
+                // GIFs have no partial transparency, so the background is always opaque
+                let [r, g, b, _] = self.config.background.to_arr();
+                let background = image::Rgba([r, g, b, 255]);
+                let boards = center_boards(boards, canvas_width, canvas_height, background);
+                (boards, canvas_width as u16, canvas_height as u16)
+            }
+            None => (boards, width, height),
+        };
+
+        // The watermark goes in a corner of the output image, canvas included
+        let boards = match (self.watermark.take(), &self.config.watermark) {
+            (Some(watermark), Some(config)) => {
+                let inset = self.drawer.square_size() * size / drawn_size / 8;
+                let (x, y) = config.corner.origin(
+                    (width.into(), height.into()),
+                    watermark.dimensions(),
+                    inset,
+                );
+                let layer = Layer {
+                    image: watermark,
+                    x,
+                    y,
+                    frames: None,
+                };
+                overlay_boards(boards, &[layer])
+            }
+            _ => boards,
+        };
+
+        let boards = match self.config.grayscale {
+            true => boards.into_iter().map(grayscale).collect(),
+            false => boards,
+        };
+
+        let (boards, delays) = merge_duplicate_frames(boards, delays, &keep);
+        let total_frames = boards.len();
+
+        let (send, recv) = std::sync::mpsc::channel();
+        let play_once = self.config.play_once;
+        let speed = self.config.encoder_speed;
+        let on_progress = self.config.on_progress.clone();
+        let encoded = AtomicUsize::new(0);
+
+        boards
+            .into_iter()
+            .zip(delays)
+            .enumerate()
+            .par_bridge()
+            .try_for_each(move |(n, (b, delay))| {
+                log::debug!("Building frame for board number: {}", n);
+                log::debug!("Board width: {}, height: {}", b.width(), b.height());
+
+                let mut frame = Frame::from_rgba_speed(width, height, &mut b.into_raw(), speed);
+                frame.delay = if play_once && n == total_frames - 1 {
+                    PLAY_ONCE_LAST_FRAME_DELAY
+                } else {
+                    gif_delay(delay)
+                };
+
+                log::debug!("Frame delay set to: {}", frame.delay);
+                log::debug!("Encoding frame for board number: {}", n);
+                frame.make_lzw_pre_encoded();
+
+                if let Some(hook) = &on_progress {
+                    hook.report(Progress {
+                        stage: Stage::Encoding,
+                        current: encoded.fetch_add(1, Ordering::SeqCst) + 1,
+                        total: Some(total_frames),
+                    });
+                }
+
+                send.send((n, frame)).unwrap();
+
+                Ok::<(), GifferError>(())
+            })?;
+
+        let mut encoder = self.build_encoder(width, height)?;
+
+        let mut next_frame_number = 0;
+        let mut frames_to_process = Vec::new();
+        let mut written_delays = Vec::with_capacity(total_frames);
+        for (frame_number, frame) in recv {
+            frames_to_process.push((frame_number, frame));
+
+            while let Some(index) = frames_to_process
+                .iter()
+                .position(|&(num, _)| num == next_frame_number)
+            {
+                log::debug!("Writing frame number: ({}, {})", index, next_frame_number);
+
+                let frame = frames_to_process.remove(index).1;
+
+                encoder
+                    .write_lzw_pre_encoded_frame(&frame)
+                    .map_err(|source| GifferError::FrameEncoding { source })?;
+                written_delays.push(frame.delay);
+
+                log::debug!("Writing frame number: ({}, {})", index, next_frame_number);
+
+                next_frame_number += 1;
+                self.report_progress(Stage::Writing, next_frame_number, Some(total_frames));
+            }
+        }
+
+        self.metadata = Some(self.game_metadata(written_delays));
+
+        match encoder.into_inner() {
+            Ok(writer) => match writer.into_buffer() {
+                Ok(buf) => Ok(Some(buf)),
+                Err(_) => Ok(None),
+            },
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_from_time_str() {
+        let clock = Clock::from_time_str("1:10:45.1").unwrap();
+        assert_eq!(clock.duration, Duration::from_millis(4245100));
+
+        let clock = Clock::from_time_str("2:52:01").unwrap();
+        assert_eq!(clock.duration, Duration::from_millis(10321000));
+
+        let clock = Clock::from_time_str("26:00:00").unwrap();
+        assert_eq!(clock.duration, Duration::from_secs(26 * 60 * 60));
+
+        // Days come before the hours in correspondence clocks
+        let clock = Clock::from_time_str("1:02:03:04").unwrap();
+        assert_eq!(
+            clock.duration,
+            Duration::from_secs(((26 * 60) + 3) * 60 + 4)
+        );
+    }
+
+    #[test]
+    fn test_clock_from_comment() {
+        let millis = |comment| Clock::from_comment(comment, "clk").map(|c| c.as_millis());
+        assert_eq!(millis("[%clk 0:02:59.5]"), Some(179_500));
+        assert_eq!(millis("[%eval 0.17] [%clk 0:02:59]"), Some(179_000));
+        assert_eq!(millis("Only 1:00:00 left [%clk 0:00:30]"), Some(30_000));
+        assert_eq!(
+            millis("[%clk 2:03:15:00]"),
+            Some(((2 * 24 + 3) * 60 + 15) * 60_000)
+        );
+
+        // Times that are not clock commands are not clocks
+        assert_eq!(millis("mate in 1:00:00 according to engine"), None);
+        assert_eq!(millis("[%emt 0:00:07]"), None);
+        assert_eq!(millis("[%clk 1:2]"), None);
+        assert_eq!(millis("[%clk]"), None);
+        assert_eq!(
+            Clock::from_comment("[%clk 0:03:00] [%emt 0:00:07]", "emt").map(|c| c.as_millis()),
+            Some(7000)
+        );
+    }
+
+    #[test]
+    fn test_clock_from_malformed_time_str() {
+        for s in ["1:2", "a:00:00", "0:00:xx", "1:2:3:4:5", "0:00:-1", ""] {
+            assert!(matches!(
+                Clock::from_time_str(s),
+                Err(GifferError::InvalidClock { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_clock_from_oversized_time_str() {
+        for s in [
+            "10000000000000:00:00",
+            "213503982334601:00:00:00",
+            "0:307445734561825860:00",
+            "0:00:1e300",
+        ] {
+            assert!(matches!(
+                Clock::from_time_str(s),
+                Err(GifferError::InvalidClock { .. })
+            ));
+        }
+        assert!(Clock::from_comment("1. e4 {[%emt 10000000000000:00:00]}", "emt").is_none());
+    }
+
+    #[test]
+    fn test_clock_substract_ref() {
+        let clock_1 = Clock::from_time_str("1:10:45.1").unwrap();
+        let clock_2 = Clock::from_time_str("1:00:00").unwrap();
+        let result = &clock_1 - &clock_2;
+        assert_eq!(result.duration, Duration::from_millis(645100));
+    }
+
+    #[test]
+    fn test_game_clocks_turn_delay() {
+        let white_clocks = vec![
+            Clock::from_time_str("0:01:00").unwrap(),
+            Clock::from_time_str("0:00:59.1").unwrap(),
+            Clock::from_time_str("0:00:55.3").unwrap(),
+        ];
+        let black_clocks = vec![
+            Clock::from_time_str("0:01:00").unwrap(),
+            Clock::from_time_str("0:00:58.5").unwrap(),
+            Clock::from_time_str("0:00:52.2").unwrap(),
+        ];
+        let game_clocks = GameClocks {
+            white: white_clocks,
+            black: black_clocks,
+            increment: None,
+            ..GameClocks::default()
+        };
+        let turn: usize = 0;
+
+        assert_eq!(game_clocks.turn_delay(turn, Color::Black), None);
+        assert_eq!(game_clocks.turn_delay(turn, Color::White), None);
+
+        assert_eq!(game_clocks.turn_delay(turn + 1, Color::Black), Some(1500));
+        assert_eq!(game_clocks.turn_delay(turn + 1, Color::White), Some(900));
+
+        assert_eq!(game_clocks.turn_delay(turn + 2, Color::Black), Some(6300));
+        assert_eq!(game_clocks.turn_delay(turn + 2, Color::White), Some(3800));
+    }
+
+    #[test]
+    fn test_game_clocks_turn_delay_with_increment() {
+        let white_clocks = vec![
+            Clock::from_time_str("0:01:00").unwrap(),
+            Clock::from_time_str("0:01:01.1").unwrap(),
+            Clock::from_time_str("0:00:57.3").unwrap(),
+        ];
+        let black_clocks = vec![
+            Clock::from_time_str("0:01:00").unwrap(),
+            Clock::from_time_str("0:01:02.5").unwrap(),
+            Clock::from_time_str("0:01:05.2").unwrap(),
+        ];
+        let game_clocks = GameClocks {
+            white: white_clocks,
+            black: black_clocks,
+            increment: Some(3000),
+            ..GameClocks::default()
+        };
+        let turn: usize = 0;
+
+        assert_eq!(game_clocks.turn_delay(turn, Color::Black), None);
+        assert_eq!(game_clocks.turn_delay(turn, Color::White), None);
+
+        assert_eq!(game_clocks.turn_delay(turn + 1, Color::Black), Some(500));
+        assert_eq!(game_clocks.turn_delay(turn + 1, Color::White), Some(1900));
+
+        assert_eq!(game_clocks.turn_delay(turn + 2, Color::Black), Some(300));
+        assert_eq!(game_clocks.turn_delay(turn + 2, Color::White), Some(6800));
+    }
+
+    #[test]
+    fn test_clock_substract_saturates() {
+        let clock_1 = Clock::from_time_str("0:00:30").unwrap();
+        let clock_2 = Clock::from_time_str("0:01:00").unwrap();
+        let result = &clock_1 - &clock_2;
+        assert_eq!(result.duration, Duration::from_millis(0));
+    }
+
+    #[test]
+    fn test_game_clocks_turn_delay_with_increasing_clocks() {
+        let white_clocks = vec![
+            Clock::from_time_str("0:01:00").unwrap(),
+            Clock::from_time_str("0:01:10").unwrap(),
+            Clock::from_time_str("0:05:00").unwrap(),
+        ];
+        let black_clocks = vec![
+            Clock::from_time_str("0:01:00").unwrap(),
+            Clock::from_time_str("0:00:58").unwrap(),
+            Clock::from_time_str("0:02:00").unwrap(),
+        ];
+        let game_clocks = GameClocks {
+            white: white_clocks,
+            black: black_clocks,
+            increment: Some(2000),
+            ..GameClocks::default()
+        };
+
+        assert_eq!(game_clocks.turn_delay(1_usize, Color::White), Some(0));
+        assert_eq!(game_clocks.turn_delay(2_usize, Color::White), Some(0));
+        assert_eq!(game_clocks.turn_delay(1_usize, Color::Black), Some(4000));
+        assert_eq!(game_clocks.turn_delay(2_usize, Color::Black), Some(0));
+    }
+
+    #[test]
+    fn test_game_clocks_turn_delay_long_think() {
+        let game_clocks = GameClocks {
+            white: vec![
+                Clock::from_time_str("0:10:00").unwrap(),
+                Clock::from_time_str("0:08:00").unwrap(),
+            ],
+            black: Vec::new(),
+            increment: None,
+            ..GameClocks::default()
+        };
+
+        assert_eq!(game_clocks.turn_delay(1_usize, Color::White), Some(120000));
+    }
+
+    #[test]
+    fn test_game_clocks_move_delay_prefers_elapsed() {
+        let mut game_clocks = GameClocks {
+            white: vec![
+                Clock::from_time_str("0:03:00").unwrap(),
+                Clock::from_time_str("0:02:50").unwrap(),
+                Clock::from_time_str("0:02:40").unwrap(),
+            ],
+            ..GameClocks::default()
+        };
+        game_clocks.append_elapsed(Clock::from_time_str("0:00:01").unwrap(), Color::White);
+        game_clocks.append_elapsed(Clock::from_time_str("0:00:07").unwrap(), Color::White);
+
+        // Elapsed move times are used as they are, and clocks for moves without them
+        assert_eq!(game_clocks.move_delay(0_usize, Color::White), Some(1000));
+        assert_eq!(game_clocks.move_delay(1_usize, Color::White), Some(7000));
+        assert_eq!(game_clocks.move_delay(2_usize, Color::White), Some(10000));
+        assert_eq!(game_clocks.move_delay(1_usize, Color::Black), None);
+    }
+
+    #[test]
+    fn test_game_clocks_real_delay_one_side() {
+        let game_clocks = GameClocks {
+            white: vec![
+                Clock::from_time_str("0:03:00").unwrap(),
+                Clock::from_time_str("0:02:58").unwrap(),
+                Clock::from_time_str("0:02:52").unwrap(),
+            ],
+            ..GameClocks::default()
+        };
+        let delays = |fallback| {
+            [Color::White, Color::Black].map(|color| {
+                (1..3)
+                    .map(|turn| game_clocks.real_delay(turn, color, fallback))
+                    .collect::<Vec<Option<u32>>>()
+            })
+        };
+
+        // White's delays are known, so only black falls back
+        assert_eq!(
+            delays(RealDelayFallback::Opponent),
+            [vec![Some(2000), Some(6000)], vec![Some(2000), Some(6000)]]
+        );
+        assert_eq!(
+            delays(RealDelayFallback::Average),
+            [vec![Some(2000), Some(6000)], vec![Some(4000), Some(4000)]]
+        );
+        assert_eq!(
+            delays(RealDelayFallback::FirstFrame),
+            [vec![Some(2000), Some(6000)], vec![None, None]]
+        );
+        // Moves without clocks keep the first frame delay unless a fallback is picked
+        assert_eq!(
+            delays(RealDelayFallback::default()),
+            delays(RealDelayFallback::FirstFrame)
+        );
+
+        // Without any known delay there is nothing to fall back to
+        let no_clocks = GameClocks::default();
+        assert_eq!(
+            no_clocks.real_delay(1, Color::Black, RealDelayFallback::Average),
+            None
+        );
+        assert_eq!(
+            no_clocks.real_delay(1, Color::Black, RealDelayFallback::Opponent),
+            None
+        );
+    }
+
+    #[test]
+    fn test_times_outside_clock_commands() {
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        giffer.begin_game();
+        giffer.san(SanPlus::from_ascii(b"e4").unwrap());
+        giffer.comment(RawComment(b"mate in 1:00:00 according to engine"));
+        giffer.comment(RawComment(b"[%clk 0:61]"));
+        assert!(giffer.clocks.white().is_empty());
+
+        giffer.comment(RawComment(b"Fast! [%clk 0:02:59]"));
+        assert_eq!(giffer.clocks.white()[0].as_millis(), 179_000);
+    }
+
+    /// Visit a game without encoding it, so its frames can be inspected.
+    struct UnfinishedGame<'a>(&'a mut PGNGiffer);
+
+    impl Visitor for UnfinishedGame<'_> {
+        type Result = ();
+
+        fn begin_game(&mut self) {
+            self.0.begin_game()
+        }
+
+        fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+            self.0.header(key, value)
+        }
+
+        fn end_headers(&mut self) -> Skip {
+            self.0.end_headers()
+        }
+
+        fn san(&mut self, san_plus: SanPlus) {
+            self.0.san(san_plus)
+        }
+
+        fn nag(&mut self, nag: Nag) {
+            self.0.nag(nag)
+        }
+
+        fn comment(&mut self, comment: RawComment<'_>) {
+            self.0.comment(comment)
+        }
+
+        fn begin_variation(&mut self) -> Skip {
+            self.0.begin_variation()
+        }
+
+        fn outcome(&mut self, outcome: Option<Outcome>) {
+            self.0.outcome(outcome)
+        }
+
+        fn end_game(&mut self) -> Self::Result {}
+    }
+
+    #[test]
+    fn test_frame_captions() {
+        let pgn = "{ A classic trap } 1. e4 { [%clk 0:03:00] } e5 \
+                   2. Qh5 { An early sortie } { [%cal Gh5f7] eyeing f7 } *";
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        let mut reader = pgn_reader::BufferedReader::new_cursor(pgn);
+        reader.read_game(&mut UnfinishedGame(&mut giffer)).unwrap();
+
+        let caption_at = |ply: usize| {
+            giffer
+                .frames
+                .iter()
+                .find(|info| info.ply == Some(ply))
+                .expect("Ply has a frame")
+                .caption
+                .clone()
+        };
+        assert_eq!(caption_at(0).as_deref(), Some("A classic trap"));
+        assert_eq!(caption_at(1), None);
+        assert_eq!(caption_at(2), None);
+        assert_eq!(caption_at(3).as_deref(), Some("An early sortie eyeing f7"));
+    }
+
+    #[test]
+    fn test_nag_assessments() {
+        let pgn = "1. e4 e5 2. Qh5 $2 $6 Nc6 3. Bc4 Nf6 $4 $18 *";
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        let mut reader = pgn_reader::BufferedReader::new_cursor(pgn);
+        reader.read_game(&mut UnfinishedGame(&mut giffer)).unwrap();
+
+        // Assessments are kept for the last move only, and NAGs without a glyph are ignored
+        assert_eq!(giffer.assessments, vec![MoveAssessment::Blunder]);
+        assert_eq!(giffer.assessment_glyphs(), "??");
+        assert!(giffer.overlay_squares.contains(&Square::F6));
+
+        let pgn = "1. e4 e5 2. Qh5 $2 $6 *";
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        let mut reader = pgn_reader::BufferedReader::new_cursor(pgn);
+        reader.read_game(&mut UnfinishedGame(&mut giffer)).unwrap();
+        assert_eq!(giffer.assessment_glyphs(), "??!");
+    }
+
+    #[test]
+    fn test_frame_clocks_bullet_example() {
+        let pgn = fs::read_to_string("example/example_bullet.pgn").unwrap();
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        pgn_reader::BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut UnfinishedGame(&mut giffer))
+            .unwrap();
+
+        let clocks_at = |ply: usize| {
+            let info = giffer
+                .frames
+                .iter()
+                .find(|info| info.ply == Some(ply))
+                .expect("Ply has a frame");
+            [Color::White, Color::Black].map(|color| {
+                info.clocks
+                    .get(color)
+                    .as_ref()
+                    .map(|clock| clock.format(ClockFormat::Smart))
+            })
+        };
+        let clocks = |white: &str, black: &str| [Some(white.to_string()), Some(black.to_string())];
+
+        // Starting clocks from the TimeControl header until each player's first clock
+        assert_eq!(clocks_at(0), clocks("0:30.0", "0:30.0"));
+        assert_eq!(clocks_at(3), clocks("0:29.7", "0:30.0"));
+        assert_eq!(clocks_at(4), clocks("0:29.7", "0:29.7"));
+        // After white's move, black's clock is the one from black's last move
+        assert_eq!(clocks_at(21), clocks("0:27.8", "0:27.6"));
+        assert_eq!(clocks_at(22), clocks("0:27.8", "0:27.0"));
+        assert_eq!(clocks_at(121), clocks("0:07.5", "0:06.9"));
+        assert_eq!(clocks_at(122), clocks("0:07.5", "0:06.6"));
+    }
+
+    #[test]
+    fn test_game_clocks_last_move_delay() {
+        let mut game_clocks = GameClocks::default();
+        assert_eq!(game_clocks.last_move_delay(Color::White), None);
+
+        // The first clock has no previous one to take the time spent from
+        game_clocks.append(Clock::from_time_str("0:03:00").unwrap(), Color::White);
+        assert_eq!(game_clocks.last_move_delay(Color::White), None);
+        game_clocks.append(Clock::from_time_str("0:02:46").unwrap(), Color::White);
+        assert_eq!(game_clocks.last_move_delay(Color::White), Some(14000));
+        assert_eq!(game_clocks.last_move_delay(Color::Black), None);
+    }
+
+    #[test]
+    fn test_game_clocks_simulate() {
+        let mut game_clocks = GameClocks::default();
+        assert!(game_clocks.is_empty());
+        assert!(!game_clocks.simulate(5, ClockSimulation::Even));
+        assert!(game_clocks.is_empty());
+
+        // Three moves for white and two for black, each spending 60 seconds and increments
+        game_clocks.start = Some(Clock::from_time_str("0:01:00").unwrap());
+        game_clocks.increment = Some(3000);
+        assert!(game_clocks.simulate(5, ClockSimulation::Even));
+        assert!(!game_clocks.is_empty());
+        let delays = |color| {
+            (0..3)
+                .map(|turn| game_clocks.move_delay(turn as usize, color))
+                .collect::<Vec<Option<u32>>>()
+        };
+        assert_eq!(
+            delays(Color::White),
+            [Some(23000), Some(23000), Some(23000)]
+        );
+        assert_eq!(delays(Color::Black), [Some(33000), Some(33000), None]);
+    }
+
+    #[test]
+    fn test_elapsed_move_time_comments() {
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        giffer.begin_game();
+        giffer.san(SanPlus::from_ascii(b"e4").unwrap());
+        giffer.comment(RawComment(b"[%emt 0:00:07]"));
+        assert_eq!(giffer.clocks.white_elapsed[0].as_millis(), 7000);
+        // An elapsed move time is not a clock
+        assert!(giffer.clocks.white().is_empty());
+
+        giffer.san(SanPlus::from_ascii(b"e5").unwrap());
+        giffer.comment(RawComment(b"[%clk 0:02:58] [%emt 0:00:02]"));
+        assert_eq!(giffer.clocks.black_elapsed[0].as_millis(), 2000);
+        assert_eq!(giffer.clocks.black()[0].as_millis(), 178_000);
+    }
+
+    #[test]
+    fn test_gif_delay() {
+        assert_eq!(gif_delay(0), 2);
+        assert_eq!(gif_delay(9), 2);
+        assert_eq!(gif_delay(14), 2);
+        assert_eq!(gif_delay(25), 3);
+        assert_eq!(gif_delay(1999), 200);
+        assert_eq!(gif_delay(65535), 6554);
+        assert_eq!(gif_delay(120000), 12000);
+        assert_eq!(gif_delay(655350), u16::MAX);
+        assert_eq!(gif_delay(700000), u16::MAX);
+    }
+
+    #[test]
+    fn test_display_clocks() {
+        let clock = Clock::from_time_str("0:01:00").unwrap();
+        assert_eq!(format!("{}", clock), "0:01:00.0");
+
+        let clock = Clock::from_millis(60000 as u32);
+        assert_eq!(format!("{}", clock), "0:01:00.0");
+
+        let clock = Clock::from_millis(55100 as u32);
+        assert_eq!(format!("{}", clock), "0:00:55.1");
+    }
+
+    #[test]
+    fn test_format_clocks() {
+        let format = |s: &str, format| Clock::from_time_str(s).unwrap().format(format);
+        // Hours only if there are any, tenths only under a minute
+        assert_eq!(format("0:03:00", ClockFormat::Smart), "3:00");
+        assert_eq!(format("0:10:05.7", ClockFormat::Smart), "10:05");
+        assert_eq!(format("1:30:00", ClockFormat::Smart), "1:30:00");
+        assert_eq!(format("0:01:00", ClockFormat::Smart), "1:00");
+        assert_eq!(format("0:00:59.9", ClockFormat::Smart), "0:59.9");
+        assert_eq!(format("0:00:05", ClockFormat::Smart), "0:05.0");
+        assert_eq!(format("26:00:00", ClockFormat::Smart), "1d 2:00");
+        assert_eq!(format("2:03:15:00", ClockFormat::Smart), "2d 3:15");
+
+        assert_eq!(format("0:03:00", ClockFormat::Compact), "3:00");
+        assert_eq!(format("1:30:00", ClockFormat::Compact), "90:00");
+        assert_eq!(format("0:00:59.9", ClockFormat::Compact), "0:59");
+
+        assert_eq!(format("0:03:00", ClockFormat::Full), "0:03:00.0");
+        assert_eq!(format("0:00:59.9", ClockFormat::Full), "0:00:59.9");
+        assert_eq!(format("26:00:00", ClockFormat::Full), "26:00:00.0");
+    }
+
+    #[test]
+    fn test_clocks_as_millis() {
+        let clock = Clock::from_time_str("0:01:05.1").unwrap();
+        assert_eq!(clock.as_millis(), 65100);
+    }
+
+    #[test]
+    fn test_reverse_delays() {
+        let delays = vec![1000, 1000, 300, 500, 700, 5000];
+        let reversed = reverse_delays(&delays, &[false; 6], 1000, 5000);
+
+        assert_eq!(reversed, vec![1000, 500, 300, 1000, 1000, 5000]);
+    }
+
+    #[test]
+    fn test_reverse_delays_with_transitions() {
+        // Pieces slide for two frames before each move, and circles fade in before the end
+        let delays = vec![900, 30, 30, 700, 30, 30, 500, 80, 80, 5000];
+        let transitions = [
+            false, true, true, false, true, true, false, true, true, false,
+        ];
+        let reversed = reverse_delays(&delays, &transitions, 1000, 5000);
+
+        assert_eq!(reversed, vec![1000, 80, 80, 700, 30, 30, 900, 30, 30, 5000]);
+    }
+
+    #[test]
+    fn test_flatten_boards() {
+        let first = RgbaImage::from_pixel(2, 1, image::Rgba([255, 0, 0, 255]));
+        let mut second = RgbaImage::new(2, 1);
+        second.put_pixel(1, 0, image::Rgba([0, 255, 0, 255]));
+
+        let flattened = flatten_boards(vec![first, second]);
+
+        assert_eq!(flattened.len(), 2);
+        assert_eq!(flattened[1].get_pixel(0, 0), &image::Rgba([255, 0, 0, 255]));
+        assert_eq!(flattened[1].get_pixel(1, 0), &image::Rgba([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn test_key_moments_from_move() {
+        use shakmaty::{fen::Fen, CastlingMode};
+
+        // White to move, with a capture that gives checkmate available in Qxf7#.
+        let fen: Fen = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4"
+            .parse()
+            .unwrap();
+        let mut position: Chess = fen.into_position(CastlingMode::Standard).unwrap();
+        let m = Move::Normal {
+            role: Role::Queen,
+            from: Square::H5,
+            capture: Some(Role::Pawn),
+            to: Square::F7,
+            promotion: None,
+        };
+        position.play_unchecked(&m);
+
+        let moments = KeyMoments::from_move(&m, &position);
+        assert_eq!(
+            moments,
+            KeyMoments {
+                capture: true,
+                check: true,
+                promotion: false,
+                mate: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_key_moments_factor() {
+        let factors = KeyMomentFactors {
+            capture: 1.5,
+            check: 2.0,
+            promotion: 3.0,
+            mate: 4.0,
+        };
+
+        assert_eq!(KeyMoments::default().factor(&factors), 1.0);
+
+        let moments = KeyMoments {
+            capture: true,
+            check: true,
+            ..KeyMoments::default()
+        };
+        assert_eq!(moments.factor(&factors), 2.0);
+    }
+
+    #[test]
+    fn test_scale_delays() {
+        let delays = vec![1000, 1000, 500, 1500, 5000];
+        let scaled = scale_delays(&delays, 4500);
+
+        assert_eq!(scaled, vec![500, 500, 250, 750, 2500]);
+        assert_eq!(scaled.iter().map(|d| *d as u32).sum::<u32>(), 4500);
+    }
+
+    #[test]
+    fn test_scale_delays_respects_minimum() {
+        let delays = vec![10, 1000, 1000, 2000];
+        let scaled = scale_delays(&delays, 1020);
+
+        assert_eq!(scaled, vec![20, 250, 250, 500]);
+    }
+
+    #[test]
+    fn test_scale_delays_unfeasible_target() {
+        let delays = vec![1000, 1000, 1000];
+        let scaled = scale_delays(&delays, 50);
+
+        assert_eq!(scaled, vec![20, 20, 20]);
+    }
+
+    #[test]
+    fn test_merge_duplicate_frames() {
+        let red = RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255]));
+        let green = RgbaImage::from_pixel(2, 2, image::Rgba([0, 255, 0, 255]));
+        let boards = vec![red.clone(), green.clone(), green.clone(), red.clone()];
+
+        let (merged, delays) =
+            merge_duplicate_frames(boards.clone(), vec![100, 200, 300, 400], &[false; 4]);
+        assert_eq!(merged, vec![red.clone(), green.clone(), red.clone()]);
+        assert_eq!(delays, vec![100, 500, 400]);
+
+        let (merged, delays) = merge_duplicate_frames(
+            boards,
+            vec![100, 200, 300, 400],
+            &[false, false, true, false],
+        );
+        assert_eq!(merged.len(), 4);
+        assert_eq!(delays, vec![100, 200, 300, 400]);
+    }
+
+    #[test]
+    fn test_merge_duplicate_frames_partial_boards() {
+        let red = RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255]));
+        let green = RgbaImage::from_pixel(2, 2, image::Rgba([0, 255, 0, 255]));
+        let mut blue = RgbaImage::new(2, 2);
+        blue.put_pixel(0, 0, image::Rgba([0, 0, 255, 128]));
+        let nothing = RgbaImage::new(2, 2);
+
+        // Boards that change nothing repeat the previous frame
+        let boards = vec![red.clone(), nothing.clone(), blue.clone(), nothing];
+        let (merged, delays) =
+            merge_duplicate_frames(boards, vec![100, 200, 300, 400], &[false; 4]);
+        assert_eq!(merged, vec![red.clone(), blue.clone()]);
+        assert_eq!(delays, vec![300, 700]);
+
+        // The same partial board drawn over different frames makes different frames
+        let boards = vec![red, blue.clone(), green, blue];
+        let (merged, delays) =
+            merge_duplicate_frames(boards, vec![100, 200, 300, 400], &[false; 4]);
+        assert_eq!(merged.len(), 4);
+        assert_eq!(delays, vec![100, 200, 300, 400]);
+    }
+
+    #[test]
+    fn test_end_game_merges_unchanged_frames() {
+        let render = |unchanged_frame: bool| {
+            let config = Config {
+                output: Output::Buffer,
+                size: 80,
+                ..Config::default()
+            };
+            let mut giffer = PGNGiffer::new(config).unwrap();
+            let mut reader = pgn_reader::BufferedReader::new_cursor("1. e4 e5 *");
+            reader.read_game(&mut UnfinishedGame(&mut giffer)).unwrap();
+            if unchanged_frame {
+                // A frame that draws nothing, like a null move would
+                let (width, height) = giffer.boards[0].dimensions();
+                giffer.boards.push(RgbaImage::new(width, height));
+                let info = giffer.frames.last().cloned().unwrap();
+                giffer.frames.push(info);
+            }
+            let bytes = giffer.end_game().unwrap().unwrap();
+
+            let mut decoder = gif::DecodeOptions::new().read_info(&bytes[..]).unwrap();
+            let mut delays = Vec::new();
+            while let Some(frame) = decoder.read_next_frame().unwrap() {
+                delays.push(frame.delay);
+            }
+            delays
+        };
+
+        let delays = render(false);
+        let merged_delays = render(true);
+        assert_eq!(merged_delays.len(), delays.len());
+        assert!(merged_delays.last() > delays.last());
+    }
+
+    #[test]
+    fn test_apply_initial_frame() {
+        let initial = RgbaImage::from_pixel(2, 1, image::Rgba([255, 0, 0, 255]));
+        let mut first_move = RgbaImage::new(2, 1);
+        first_move.put_pixel(1, 0, image::Rgba([0, 255, 0, 255]));
+        let delays = vec![1000, 2000, 3000];
+
+        let mut giffer = PGNGiffer::new(Config {
+            initial_position: InitialFrame::Hide,
+            ..Config::default()
+        })
+        .unwrap();
+        giffer.boards = vec![initial.clone(), first_move.clone(), first_move.clone()];
+        giffer.frames = vec![FrameInfo::default(); 3];
+        let mut hidden_delays = delays.clone();
+        giffer.apply_initial_frame(&mut hidden_delays);
+
+        assert_eq!(giffer.boards.len(), 2);
+        assert_eq!(giffer.frames.len(), 2);
+        assert_eq!(hidden_delays, vec![2000, 3000]);
+        assert_eq!(
+            giffer.boards[0].get_pixel(0, 0),
+            &image::Rgba([255, 0, 0, 255])
+        );
+        assert_eq!(
+            giffer.boards[0].get_pixel(1, 0),
+            &image::Rgba([0, 255, 0, 255])
+        );
+
+        let mut giffer = PGNGiffer::new(Config {
+            initial_position: InitialFrame::Repeat(3),
+            ..Config::default()
+        })
+        .unwrap();
+        giffer.boards = vec![initial.clone(), first_move.clone(), first_move];
+        giffer.frames = vec![FrameInfo::default(); 3];
+        let mut repeated_delays = delays;
+        giffer.apply_initial_frame(&mut repeated_delays);
+
+        assert_eq!(giffer.boards.len(), 5);
+        assert_eq!(giffer.boards[..3], vec![initial; 3][..]);
+        assert_eq!(repeated_delays, vec![1000, 1000, 1000, 2000, 3000]);
+        assert_eq!(
+            giffer.frames.iter().map(|f| f.keep).collect::<Vec<_>>(),
+            vec![false, true, true, false, false]
+        );
+    }
+
+    #[test]
+    fn test_starting_clock() {
+        let millis = |time_control| {
+            TimeControl::from_header(time_control)
+                .and_then(|tc| starting_clock(&tc))
+                .map(|c| c.as_millis())
+        };
+        assert_eq!(millis("180+2"), Some(180_000));
+        assert_eq!(millis("600"), Some(600_000));
+        assert_eq!(millis("40/5400+30:1800+30"), Some(5_400_000));
+        assert_eq!(millis("?"), None);
+        assert_eq!(millis("-"), None);
+        assert_eq!(millis("1/86400"), None);
+    }
+
+    #[test]
+    fn test_time_control_increment() {
+        let increment = |value: &[u8]| {
+            let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+            giffer.header(b"TimeControl", RawHeader(value));
+            giffer.clocks.increment
+        };
+        assert_eq!(increment(b"180+2"), Some(2000));
+        assert_eq!(increment(b"900+120"), Some(120_000));
+        assert_eq!(increment(b"40/5400+30:1800+30"), Some(30_000));
+        assert_eq!(increment(b"40/7200:3600"), Some(0));
+        assert_eq!(increment(b"?"), None);
+        assert_eq!(increment(b"180+x"), None);
+    }
+
+    #[test]
+    fn test_bar_cache() {
+        let mut giffer = PGNGiffer::new(Config {
+            orientation: Orientation::FollowTurn,
+            style_components: crate::style::StyleComponents::new(&[
+                crate::style::StyleComponent::PlayerBars,
+            ]),
+            ..Config::default()
+        })
+        .unwrap();
+        giffer.begin_game();
+        giffer.header(b"White", RawHeader(b"penguingm1"));
+        giffer.header(b"Black", RawHeader(b"DanielNaroditsky"));
+        let _ = giffer.end_headers();
+        assert_eq!(giffer.bar_cache.bars.len(), 2);
+
+        // Cached bars are the same as bars drawn by the drawer
+        let (width, height) = giffer.boards[0].dimensions();
+        let mut expected = RgbaImage::new(width, height);
+        let player = giffer.players.label(Color::White, "Anonymous");
+        giffer
+            .drawer
+            .draw_one_player_bar(
+                &player,
+                Color::White,
+                &ByRole::default(),
+                None,
+                &mut expected,
+                &giffer.svgs,
+            )
+            .unwrap();
+        let mut cached = RgbaImage::new(width, height);
+        giffer.draw_player_bar(Color::White, &mut cached);
+        assert!(cached == expected);
+
+        // Flipped boards get their own bars, and captures replace the capturing player's
+        for san in ["e4", "d5", "exd5"] {
+            giffer.san(SanPlus::from_ascii(san.as_bytes()).unwrap());
+        }
+        assert_eq!(giffer.bar_cache.bars.len(), 4);
+        let captured = ByRole {
+            pawn: 1,
+            ..ByRole::default()
+        };
+        let flip = giffer.drawer.flip();
+        assert!(giffer
+            .bar_cache
+            .get(Color::White, flip, &captured)
+            .is_some());
+        assert!(giffer
+            .bar_cache
+            .get(Color::White, flip, &ByRole::default())
+            .is_none());
+    }
+
+    #[test]
+    fn test_starting_clocks_until_first_comment() {
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        assert!(giffer.clocks.last(Color::White).is_none());
+        giffer.header(b"TimeControl", RawHeader(b"180+2"));
+        assert_eq!(
+            giffer.clocks.last(Color::White).unwrap().as_millis(),
+            180_000
+        );
+        assert_eq!(
+            giffer.clocks.last(Color::Black).unwrap().as_millis(),
+            180_000
+        );
+
+        giffer
+            .clocks
+            .append(Clock::from_time_str("0:02:58").unwrap(), Color::White);
+        assert_eq!(
+            giffer.clocks.last(Color::White).unwrap().as_millis(),
+            178_000
+        );
+        assert_eq!(
+            giffer.clocks.last(Color::Black).unwrap().as_millis(),
+            180_000
+        );
+        // The starting clock isn't a move, so it doesn't change delays
+        assert_eq!(giffer.clocks.turn_delay(0_usize, Color::White), None);
+    }
+
+    #[test]
+    fn test_title_card_lines() {
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        giffer.players.create_player(
+            Color::White,
+            Some("Firouzja2003".to_string()),
+            None,
+            Some(3152),
+        );
+        giffer.event = Some("Live Chess".to_string());
+        giffer.date = Some("2021.02.25".to_string());
+        giffer.time_control = TimeControl::from_header("180");
+
+        assert_eq!(
+            giffer.title_card_lines(),
+            vec![
+                "Firouzja2003 (3152)",
+                "vs",
+                "Anonymous",
+                "Live Chess · 2021.02.25",
+                "3+0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_encoder_speed() {
+        let config = Config {
+            encoder_speed: 31,
+            ..Config::default()
+        };
+        assert!(matches!(
+            PGNGiffer::new(config),
+            Err(GifferError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn test_size_not_multiple_of_8() {
+        let config = Config {
+            size: 500,
+            supersample: 2,
+            ..Config::default()
+        };
+        let giffer = PGNGiffer::new(config).unwrap();
+        assert_eq!(giffer.drawer.size(), 1008);
+    }
+
+    #[test]
+    fn test_invalid_supersample() {
+        let config = Config {
+            supersample: 0,
+            ..Config::default()
+        };
+        assert!(matches!(
+            PGNGiffer::new(config),
+            Err(GifferError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_bar_height() {
+        for height_fraction in [0.0, 2.5, f32::NAN] {
+            let config = Config {
+                bar_style: crate::config::BarStyle {
+                    height_fraction,
+                    ..Default::default()
+                },
+                ..Config::default()
+            };
+            assert!(matches!(
+                PGNGiffer::new(config),
+                Err(GifferError::InvalidConfig { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_is_low_time() {
+        let giffer = PGNGiffer::new(Config::default()).unwrap();
+        assert!(giffer.is_low_time(&Clock::from_millis(19_900u64)));
+        assert!(giffer.is_low_time(&Clock::from_millis(0u64)));
+        assert!(!giffer.is_low_time(&Clock::from_millis(20_000u64)));
+        assert!(!giffer.is_low_time(&Clock::from_time_str("0:05:00").unwrap()));
+
+        let config = Config {
+            low_time: Some(std::time::Duration::from_secs(60)),
+            ..Config::default()
+        };
+        let giffer = PGNGiffer::new(config).unwrap();
+        assert!(giffer.is_low_time(&Clock::from_time_str("0:00:59.9").unwrap()));
+        assert!(!giffer.is_low_time(&Clock::from_time_str("0:01:00").unwrap()));
 
-                let to_be_cleared = (king_square, Role::King, color);
-                self.to_clear.push(to_be_cleared);
-            };
+        let config = Config {
+            low_time: None,
+            ..Config::default()
+        };
+        let giffer = PGNGiffer::new(config).unwrap();
+        assert!(!giffer.is_low_time(&Clock::from_millis(0u64)));
+    }
 
-            if self.players.exist() && self.config.style_components.player_bars() == true {
-                log::debug!("Adding player bars");
-                let mut new_board = self.drawer.add_player_bar_space(board);
+    #[test]
+    fn test_end_game_merges_duplicate_frames() {
+        let config = Config {
+            output: Output::Buffer,
+            size: 16,
+            ..Config::default()
+        };
+        let mut giffer = PGNGiffer::new(config).unwrap();
+        let red = RgbaImage::from_pixel(16, 16, image::Rgba([255, 0, 0, 255]));
+        let green = RgbaImage::from_pixel(16, 16, image::Rgba([0, 255, 0, 255]));
+        giffer.boards = vec![red.clone(), green.clone(), green, red];
+        giffer.frames = vec![FrameInfo::default(); 4];
 
-                if self.position.fullmoves() >= std::num::NonZeroU32::new(1).unwrap() {
-                    self.boards.push(new_board);
-                    return;
-                }
+        let bytes = giffer.end_game().unwrap().unwrap();
 
-                log::debug!(
-                    "New board width: {}, height: {}",
-                    new_board.width(),
-                    new_board.height()
-                );
-                let white_player = self.players.white.as_ref().unwrap().to_string();
-                let black_player = self.players.black.as_ref().unwrap().to_string();
-                self.drawer
-                    .draw_player_bars(&white_player, &black_player, &mut new_board, &self.svgs)
-                    .expect("Failed to draw player bars");
-                self.boards.push(new_board);
-            } else {
-                self.boards.push(board);
+        let mut decoder = gif::DecodeOptions::new().read_info(&bytes[..]).unwrap();
+        let mut frames = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        assert_eq!(frames, 3);
+    }
+
+    #[test]
+    fn test_downscale_boards() {
+        let red = image::Rgba([255, 0, 0, 255]);
+        let green = image::Rgba([0, 255, 0, 255]);
+        let first = RgbaImage::from_pixel(8, 8, red);
+        let unchanged = RgbaImage::new(8, 8);
+        let mut corner = RgbaImage::new(8, 8);
+        for (x, y, pixel) in corner.enumerate_pixels_mut() {
+            if x < 4 && y < 4 {
+                *pixel = green;
             }
         }
+
+        let downscaled = downscale_boards(vec![first, unchanged, corner], 4, 4);
+
+        assert!(downscaled[0].pixels().all(|p| *p == red));
+        assert!(downscaled[1].pixels().all(|p| p[3] == 0));
+        // The corner is resized as part of the whole board, so only its far side is transparent
+        assert_eq!(*downscaled[2].get_pixel(0, 0), green);
+        assert_eq!(downscaled[2].get_pixel(3, 3)[3], 0);
     }
 
-    /// Parses comments to extract %clk (clock) comments
-    fn comment(&mut self, comment: RawComment<'_>) {
-        match std::str::from_utf8(comment.as_bytes()) {
-            Ok(s) => {
-                // Capture clock comments with regexp, assuming
-                // no other time-like comment appears
-                let re = Regex::new(r"\d{1,2}:\d{2}:(\d{2}.\d{1}|\d{2})").unwrap();
+    #[test]
+    fn test_overlay_boards() {
+        let red = image::Rgba([255, 0, 0, 255]);
+        let green = image::Rgba([0, 255, 0, 255]);
+        let blue = image::Rgba([0, 0, 255, 128]);
+        let watermark = Layer {
+            image: RgbaImage::from_pixel(2, 2, blue),
+            x: 2,
+            y: 2,
+            frames: None,
+        };
+        let first = RgbaImage::from_pixel(4, 4, red);
+        let mut second = RgbaImage::new(4, 4);
+        second.put_pixel(3, 3, green);
+        second.put_pixel(0, 0, green);
 
-                if let Some(m) = re.find(s) {
-                    log::debug!("Found clock time: {}", m.as_str());
-                    let clock = Clock::from_time_str(m.as_str());
-                    log::debug!("Appending clock: {:?}", clock);
+        let overlaid = overlay_boards(vec![first, second], &[watermark]);
 
-                    match self.position.turn() {
-                        // This represents the player that moves next, we need to
-                        // set the clock of the player that moved last
-                        Color::Black => {
-                            self.drawer
-                                .draw_one_player_clock(
-                                    &clock.to_string(),
-                                    Color::White,
-                                    self.boards.last_mut().expect("No board written"),
-                                    &self.svgs,
-                                )
-                                .expect("Failed to draw clock");
-                            self.clocks.append(clock, Color::White);
-                        }
-                        Color::White => {
-                            self.drawer
-                                .draw_one_player_clock(
-                                    &clock.to_string(),
-                                    Color::Black,
-                                    self.boards.last_mut().expect("No board written"),
-                                    &self.svgs,
-                                )
-                                .expect("Failed to draw clock");
-                            self.clocks.append(clock, Color::Black);
-                        }
-                    }
-                }
-            }
-            Err(_) => (),
-        }
+        // The layer is blended over the whole first board
+        let blended = overlaid[0].get_pixel(2, 2);
+        assert!(blended[0] > 100 && blended[2] > 100 && blended[3] == 255);
+        assert_eq!(*overlaid[0].get_pixel(1, 1), red);
+        // Only what changed under it is drawn again after that
+        assert_eq!(overlaid[1].get_pixel(2, 2)[3], 0);
+        let blended = overlaid[1].get_pixel(3, 3);
+        assert!(blended[1] > 100 && blended[2] > 100 && blended[3] == 255);
+        assert_eq!(*overlaid[1].get_pixel(0, 0), green);
     }
 
-    /// Check the outcome of the game to draw the appropiate termination circle
-    fn outcome(&mut self, outcome: Option<Outcome>) {
-        if !self.config.style_components.terminations() {
-            return;
+    #[test]
+    fn test_overlay_boards_frames() {
+        let red = image::Rgba([255, 0, 0, 255]);
+        let white = image::Rgba([255, 255, 255, 255]);
+        let text = Layer {
+            image: RgbaImage::from_pixel(2, 1, white),
+            x: 0,
+            y: 0,
+            frames: Some(1..2),
+        };
+        let boards = vec![
+            RgbaImage::from_pixel(2, 2, red),
+            RgbaImage::new(2, 2),
+            RgbaImage::new(2, 2),
+        ];
+
+        let overlaid = flatten_boards(overlay_boards(boards, &[text]));
+
+        // The layer is drawn on its frames only, and cleared after them
+        assert_eq!(*overlaid[0].get_pixel(0, 0), red);
+        assert_eq!(*overlaid[1].get_pixel(0, 0), white);
+        assert_eq!(*overlaid[1].get_pixel(0, 1), red);
+        assert_eq!(*overlaid[2].get_pixel(0, 0), red);
+    }
+
+    #[test]
+    fn test_validate_overlays() {
+        let overlay = |x, y, size, frames| TextOverlay {
+            text: "Round 1".to_string(),
+            x,
+            y,
+            size,
+            color: crate::config::Color([255, 255, 255, 255]),
+            frames,
+        };
+
+        assert!(validate_overlays(&[], 80, 100).is_ok());
+        assert!(validate_overlays(&[overlay(79, 99, 10.0, Some(0..1))], 80, 100).is_ok());
+        for invalid in [
+            overlay(80, 0, 10.0, None),
+            overlay(0, 100, 10.0, None),
+            overlay(0, 0, 0.0, None),
+            overlay(0, 0, f32::NAN, None),
+            overlay(0, 0, 10.0, Some(1..1)),
+        ] {
+            assert!(validate_overlays(&[invalid], 80, 100).is_err());
         }
 
-        let mut latest_board = self.boards.pop().expect("No boards drawn!");
-        match outcome {
-            Some(o) => {
-                let reason = if self.position.is_checkmate() {
-                    "checkmate"
-                } else if self.position.is_stalemate() {
-                    "stalemate"
-                } else if self.position.is_insufficient_material() {
-                    "insufficient material"
-                } else {
-                    match &self.termination {
-                        Some(s) => {
-                            if s.contains("resignation") {
-                                "resignation"
-                            } else if s.contains("agreement") {
-                                "agreement"
-                            } else if s.contains("repetition") {
-                                "repetition"
-                            } else {
-                                "timeout"
-                            }
-                        }
-                        None => match o {
-                            Outcome::Draw => "agreement",
-                            Outcome::Decisive { winner: _ } => "resignation",
-                        },
-                    }
-                };
-                let termination_reason = TerminationReason::from_outcome(o, Some(reason));
-
-                let (mut winner_king, mut loser_king) = match o {
-                    Outcome::Draw => {
-                        // Doesn't really matter which king is which, since in draw there is no
-                        // winner or loser.
-                        let square1 = self
-                            .position
-                            .board()
-                            .king_of(shakmaty::Color::White)
-                            .expect("King doesn't exist");
-                        let square2 = self
-                            .position
-                            .board()
-                            .king_of(shakmaty::Color::Black)
-                            .expect("King doesn't exist");
-
-                        let king1 = PieceInBoard::new_king(square1, shakmaty::Color::White);
-                        let king2 = PieceInBoard::new_king(square2, shakmaty::Color::Black);
-
-                        (king1, king2)
-                    }
-                    Outcome::Decisive { winner: w } => {
-                        let winner = self
-                            .position
-                            .board()
-                            .king_of(w)
-                            .expect("King doesn't exist");
-                        let loser_color = match w {
-                            shakmaty::Color::Black => shakmaty::Color::White,
-                            shakmaty::Color::White => shakmaty::Color::Black,
-                        };
-                        let loser = self
-                            .position
-                            .board()
-                            .king_of(loser_color)
-                            .expect("King doesn't exist");
-
-                        let winner_king = PieceInBoard::new_king(winner, w);
-                        let loser_king = PieceInBoard::new_king(loser, loser_color);
-
-                        (winner_king, loser_king)
-                    }
-                };
+        // Overlays are checked when the giffer is made, before any frame is drawn
+        let config = Config {
+            size: 80,
+            overlays: vec![overlay(10, 90, 10.0, None)],
+            ..Config::default()
+        };
+        assert!(PGNGiffer::new(config).is_err());
+        let config = Config {
+            size: 80,
+            style_components: crate::style::StyleComponents::new(&[
+                crate::style::StyleComponent::PlayerBars,
+            ]),
+            overlays: vec![overlay(10, 90, 10.0, None)],
+            ..Config::default()
+        };
+        assert!(PGNGiffer::new(config).is_ok());
+    }
 
-                if self.drawer.flip() {
-                    // This should be moved to the drawer
-                    winner_king.flip_both();
-                    loser_king.flip_both();
-                }
+    #[test]
+    fn test_text_overlay_layers() {
+        let overlay = |x, y| TextOverlay {
+            text: "Round 1".to_string(),
+            x,
+            y,
+            size: 10.0,
+            color: crate::config::Color([255, 255, 255, 255]),
+            frames: Some(0..1),
+        };
+        let config = Config {
+            size: 80,
+            supersample: 2,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            overlays: vec![overlay(10, 20)],
+            ..Config::default()
+        };
+        let mut giffer = PGNGiffer::new(config).unwrap();
 
-                log::debug!(
-                    "Drawing termination: {:?}, {:?}, {:?}, {:?}",
-                    o,
-                    termination_reason,
-                    winner_king,
-                    loser_king
-                );
-                self.termination_drawer
-                    .draw_termination_circles(
-                        termination_reason,
-                        winner_king,
-                        loser_king,
-                        &mut latest_board,
-                        &self.svgs,
-                    )
-                    .expect("Failed to draw termination circle");
-                self.boards.push(latest_board);
-            }
-            // If the game didn't end, we don't do anything
-            None => (),
+        // Positions are scaled along with the supersampled board
+        let layers = giffer.text_overlay_layers(160, 200).unwrap();
+        assert_eq!(layers.len(), 1);
+        assert_eq!((layers[0].x, layers[0].y), (20, 40));
+        assert_eq!(layers[0].frames, Some(0..1));
+        assert!(layers[0].image.width() <= 140);
+        assert!(layers[0].image.pixels().any(|p| p[3] > 0));
+
+        giffer.config.overlays = vec![overlay(10, 100)];
+        assert!(giffer.text_overlay_layers(160, 200).is_err());
+    }
+
+    #[test]
+    fn test_end_game_supersample() {
+        let config = Config {
+            output: Output::Buffer,
+            size: 16,
+            supersample: 2,
+            ..Config::default()
         };
+        let mut giffer = PGNGiffer::new(config).unwrap();
+        assert_eq!(giffer.drawer.size(), 32);
+        let red = RgbaImage::from_pixel(32, 32, image::Rgba([255, 0, 0, 255]));
+        let green = RgbaImage::from_pixel(32, 32, image::Rgba([0, 255, 0, 255]));
+        giffer.boards = vec![red, green];
+        giffer.frames = vec![FrameInfo::default(); 2];
+
+        let bytes = giffer.end_game().unwrap().unwrap();
+
+        let decoder = gif::DecodeOptions::new().read_info(&bytes[..]).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (16, 16));
     }
 
-    /// Iterates over boards collected for every move to encode GIF frames for each move.
-    /// Assigns delays to each frame based on self.config.delay and self.last_frame_multiplier.
-    fn end_game(&mut self) -> Self::Result {
-        let total_frames = self.boards.len();
-        let (height, width) =
-            if self.players.exist() && self.config.style_components.player_bars() == true {
-                let bar_size = self.drawer.square_size() * 2;
-                (
-                    (self.drawer.size() + bar_size) as u16,
-                    self.drawer.size() as u16,
-                )
-            } else {
-                (self.drawer.size() as u16, self.drawer.size() as u16)
-            };
-        log::debug!(
-            "Size: {}, width: {}, height: {}",
-            self.drawer.size(),
-            width,
-            height
-        );
+    #[test]
+    fn test_end_game_canvas() {
+        let config = Config {
+            output: Output::Buffer,
+            size: 16,
+            canvas: Some((32, 24)),
+            background: crate::config::Color([0, 0, 255, 255]),
+            ..Config::default()
+        };
+        let mut giffer = PGNGiffer::new(config).unwrap();
+        let red = image::Rgba([255, 0, 0, 255]);
+        giffer.boards = vec![RgbaImage::from_pixel(16, 16, red), RgbaImage::new(16, 16)];
+        giffer.frames = vec![FrameInfo::default(); 2];
 
-        let (send, recv) = std::sync::mpsc::channel();
+        let bytes = giffer.end_game().unwrap().unwrap();
 
-        // Taking a reference to allow us to move into the channel and take ownership of the sender.
-        // This way, sender will be dropped and the channel be closed.
-        let config = &self.config;
-        let clocks = &self.clocks;
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(&bytes[..]).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (32, 24));
+        let frame = decoder.read_next_frame().unwrap().unwrap();
+        let pixel = |x: usize, y: usize| {
+            let start = (y * frame.width as usize + x) * 4;
+            frame.buffer[start..start + 4].to_vec()
+        };
+        // The board is centered, with the background around it
+        assert_eq!(pixel(7, 4), vec![0, 0, 255, 255]);
+        assert_eq!(pixel(8, 4), vec![255, 0, 0, 255]);
+        assert_eq!(pixel(23, 19), vec![255, 0, 0, 255]);
+        assert_eq!(pixel(24, 20), vec![0, 0, 255, 255]);
+    }
 
-        self.boards
-            .drain(..)
-            .enumerate()
-            .par_bridge()
-            .try_for_each(move |(n, b)| {
-                log::debug!("Building frame for board number: {}", n);
-                log::debug!("Board width: {}, height: {}", b.width(), b.height());
+    #[test]
+    fn test_canvas_too_small() {
+        let config = |canvas| Config {
+            output: Output::Buffer,
+            size: 16,
+            canvas: Some(canvas),
+            style_components: crate::style::StyleComponents::new(&[
+                crate::style::StyleComponent::PlayerBars,
+            ]),
+            ..Config::default()
+        };
+        assert!(matches!(
+            PGNGiffer::new(config((32, 8))),
+            Err(GifferError::InvalidConfig { .. })
+        ));
+        // Player bars may be drawn, so the canvas must fit them too
+        assert!(matches!(
+            PGNGiffer::new(config((16, 16))),
+            Err(GifferError::InvalidConfig { .. })
+        ));
+        assert!(PGNGiffer::new(config((16, 20))).is_ok());
+    }
 
-                let turn = if n == 0 { n } else { (n - 1) / 2 };
+    #[test]
+    fn test_result_text() {
+        let white = Outcome::Decisive {
+            winner: shakmaty::Color::White,
+        };
+        let black = Outcome::Decisive {
+            winner: shakmaty::Color::Black,
+        };
+        assert_eq!(result_text(white), "1-0");
+        assert_eq!(result_text(black), "0-1");
+        assert_eq!(result_text(Outcome::Draw), "½-½");
+    }
 
-                let mut frame = Frame::from_rgba_speed(width, height, &mut b.into_raw(), 10);
+    #[test]
+    fn test_composite_boards() {
+        let red = image::Rgba([255, 0, 0, 255]);
+        let green = image::Rgba([0, 255, 0, 255]);
+        let base = RgbaImage::from_pixel(2, 1, red);
+        let mut delta = RgbaImage::new(2, 1);
+        delta.put_pixel(1, 0, green);
 
-                log::debug!("Calculating delay for turn: {}", turn);
-                if n == (total_frames - 1) {
-                    log::debug!("LAST FRAME");
-                    frame.delay = config
-                        .delays
-                        .last_frame_delay()
-                        .expect("Last frame delay not defined")
-                        / 10;
-                } else if n == 0 || n == 1 {
-                    frame.delay = config
-                        .delays
-                        .first_frame_delay()
-                        .expect("First frame delay not defined")
-                        / 10;
-                } else {
-                    match config.delays.frame {
-                        Delay::Duration(d) => {
-                            frame.delay = d / 10;
-                        }
-                        Delay::Real => {
-                            if n & 1 != 0 {
-                                frame.delay = match clocks.turn_delay(turn, Color::Black) {
-                                    Some(d) => d / 10,
-                                    // First move, no previous clock
-                                    None => {
-                                        config
-                                            .delays
-                                            .first_frame_delay()
-                                            .expect("First frame delay not defined")
-                                            / 10
-                                    }
-                                };
-                            } else {
-                                frame.delay = match clocks.turn_delay(turn, Color::White) {
-                                    Some(d) => d / 10,
-                                    // First move, no previous clock
-                                    None => {
-                                        config
-                                            .delays
-                                            .first_frame_delay()
-                                            .expect("First frame delay not defined")
-                                            / 10
-                                    }
-                                };
-                            }
-                        }
-                    }
-                }
-                log::debug!("Frame delay set to: {}", frame.delay);
-                log::debug!("Encoding frame for board number: {}", n);
-                frame.make_lzw_pre_encoded();
+        let composite = composite_boards(&[base, delta]);
 
-                send.send((n, frame)).unwrap();
+        assert_eq!(*composite.get_pixel(0, 0), red);
+        assert_eq!(*composite.get_pixel(1, 0), green);
+    }
 
-                Ok::<(), GifferError>(())
-            })?;
+    #[test]
+    fn test_end_game_reports_progress() {
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let config = Config {
+            output: Output::Buffer,
+            size: 16,
+            on_progress: Some(crate::progress::ProgressHook::new(move |p| {
+                sink.lock().unwrap().push(p)
+            })),
+            ..Config::default()
+        };
+        let mut giffer = PGNGiffer::new(config).unwrap();
+        let red = RgbaImage::from_pixel(16, 16, image::Rgba([255, 0, 0, 255]));
+        let green = RgbaImage::from_pixel(16, 16, image::Rgba([0, 255, 0, 255]));
+        giffer.boards = vec![red, green];
+        giffer.frames = vec![FrameInfo::default(); 2];
 
-        let mut encoder = self.build_encoder(width, height)?;
+        giffer.end_game().unwrap();
 
-        let mut next_frame_number = 0;
-        let mut frames_to_process = Vec::new();
-        for (frame_number, frame) in recv {
-            frames_to_process.push((frame_number, frame));
+        let reports = reports.lock().unwrap();
+        let count = |stage| reports.iter().filter(|p| p.stage == stage).count();
+        assert_eq!(count(Stage::Encoding), 2);
+        assert_eq!(count(Stage::Writing), 2);
+        assert_eq!(
+            reports.last(),
+            Some(&Progress {
+                stage: Stage::Writing,
+                current: 2,
+                total: Some(2),
+            })
+        );
+    }
 
-            while let Some(index) = frames_to_process
-                .iter()
-                .position(|&(num, _)| num == next_frame_number)
-            {
-                log::debug!("Writing frame number: ({}, {})", index, next_frame_number);
+    #[test]
+    fn test_normalize_header() {
+        let header = RawHeader(b" M\xc3\xbcller\t\x07 ");
+        assert_eq!(normalize_header(&header), "Müller");
 
-                let frame = frames_to_process.remove(index).1;
+        let header = RawHeader(b"M\xfcller");
+        assert_eq!(normalize_header(&header), "M\u{fffd}ller");
+    }
 
-                encoder
-                    .write_lzw_pre_encoded_frame(&frame)
-                    .map_err(|source| GifferError::FrameEncoding { source })?;
+    #[test]
+    fn test_header_title() {
+        assert_eq!(header_title(&RawHeader(b" GM ")), Some("GM".to_string()));
+        assert_eq!(header_title(&RawHeader(b"-")), None);
+        assert_eq!(header_title(&RawHeader(b"")), None);
+    }
 
-                log::debug!("Writing frame number: ({}, {})", index, next_frame_number);
+    #[test]
+    fn test_title_headers() {
+        // Title before name
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        giffer.header(b"WhiteTitle", RawHeader(b"GM"));
+        giffer.header(b"White", RawHeader(b"Hikaru"));
+        giffer.header(b"WhiteElo", RawHeader(b"3200"));
+        // Name before title
+        giffer.header(b"Black", RawHeader(b"DrNykterstein"));
+        giffer.header(b"BlackTitle", RawHeader(b"GM"));
 
-                next_frame_number += 1;
+        assert_eq!(
+            giffer.players.white.as_ref().unwrap().to_string(),
+            "GM Hikaru (3200)"
+        );
+        assert_eq!(
+            giffer.players.black.as_ref().unwrap().label("Anonymous"),
+            PlayerLabel {
+                title: Some("GM".to_string()),
+                name: "DrNykterstein".to_string(),
+                rating: None,
+                rating_diff: None,
+                country: None,
             }
-        }
+        );
 
-        match encoder.into_inner() {
-            Ok(writer) => match writer.into_buffer() {
-                Ok(buf) => Ok(Some(buf)),
-                Err(_) => Ok(None),
-            },
-            Err(_) => Ok(None),
-        }
+        // Untitled players are marked with a dash by lichess
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        giffer.header(b"White", RawHeader(b"Anna"));
+        giffer.header(b"WhiteTitle", RawHeader(b"-"));
+        giffer.header(b"BlackTitle", RawHeader(b"-"));
+        assert_eq!(giffer.players.white.as_ref().unwrap().to_string(), "Anna");
+        assert!(giffer.players.black.is_none());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_header_country() {
+        assert_eq!(
+            header_country(&RawHeader(b" nor ")),
+            Some("NOR".to_string())
+        );
+        assert_eq!(header_country(&RawHeader(b"US")), Some("US".to_string()));
+        assert_eq!(header_country(&RawHeader(b"-")), None);
+        assert_eq!(header_country(&RawHeader(b"1503014")), None);
+        assert_eq!(header_country(&RawHeader(b"../x")), None);
+    }
 
     #[test]
-    fn test_clock_from_time_str() {
-        let clock = Clock::from_time_str("1:10:45.1");
-        assert_eq!(clock.duration, Duration::from_millis(4245100));
+    fn test_country_headers() {
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        giffer.header(b"WhiteCountry", RawHeader(b"NOR"));
+        giffer.header(b"White", RawHeader(b"Magnus"));
+        giffer.header(b"BlackCountry", RawHeader(b"?"));
+        let white = giffer.players.white.as_ref().unwrap();
+        assert_eq!(white.label("Anonymous").country, Some("NOR".to_string()));
+        assert!(giffer.players.black.is_none());
 
-        let clock = Clock::from_time_str("2:52:01");
-        assert_eq!(clock.duration, Duration::from_millis(10321000));
+        // Header names are configurable
+        let config = Config {
+            white_country_header: "WhiteFederation".to_string(),
+            black_country_header: "BlackFederation".to_string(),
+            ..Config::default()
+        };
+        let mut giffer = PGNGiffer::new(config).unwrap();
+        giffer.header(b"WhiteCountry", RawHeader(b"NOR"));
+        giffer.header(b"BlackFederation", RawHeader(b"ind"));
+        assert!(giffer.players.white.is_none());
+        let black = giffer.players.black.as_ref().unwrap();
+        assert_eq!(black.label("Anonymous").country, Some("IND".to_string()));
     }
 
     #[test]
-    fn test_clock_substract_ref() {
-        let clock_1 = Clock::from_time_str("1:10:45.1");
-        let clock_2 = Clock::from_time_str("1:00:00");
-        let result = &clock_1 - &clock_2;
-        assert_eq!(result.duration, Duration::from_millis(645100));
+    fn test_event_bar_text() {
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        giffer.header(b"Event", RawHeader(b"Tata Steel Masters 2023"));
+        giffer.header(b"Site", RawHeader(b"Wijk aan Zee"));
+        giffer.header(b"Round", RawHeader(b"6"));
+        assert_eq!(
+            giffer.event_bar_text(),
+            "Tata Steel Masters 2023 · Round 6 · Wijk aan Zee"
+        );
+
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        giffer.header(b"Event", RawHeader(b"Casual game"));
+        giffer.header(b"Site", RawHeader(b"?"));
+        giffer.header(b"Round", RawHeader(b"-"));
+        giffer.header(b"Date", RawHeader(b"2023.??.??"));
+        assert_eq!(giffer.event_bar_text(), "Casual game");
     }
 
     #[test]
-    fn test_game_clocks_turn_delay() {
-        let white_clocks = vec![
-            Clock::from_time_str("0:01:00"),
-            Clock::from_time_str("0:00:59.1"),
-            Clock::from_time_str("0:00:55.3"),
-        ];
-        let black_clocks = vec![
-            Clock::from_time_str("0:01:00"),
-            Clock::from_time_str("0:00:58.5"),
-            Clock::from_time_str("0:00:52.2"),
-        ];
-        let game_clocks = GameClocks {
-            white: white_clocks,
-            black: black_clocks,
-            increment: None,
+    fn test_opening_caption() {
+        let config = Config {
+            style_components: crate::style::StyleComponents::new(&[
+                crate::style::StyleComponent::Opening,
+            ]),
+            opening_moves: 2,
+            ..Config::default()
         };
-        let turn: usize = 0;
+        let mut giffer = PGNGiffer::new(config.clone()).unwrap();
+        assert!(!giffer.shows_opening());
+        giffer.header(b"ECO", RawHeader(b"B90"));
+        giffer.header(b"Opening", RawHeader(b"Sicilian Defense: Najdorf"));
+        assert_eq!(giffer.opening_text(), "B90 Sicilian Defense: Najdorf");
+        // Shown in full for two moves, then faded out
+        let opacities: Vec<f32> = (0..10).map(|ply| giffer.opening_opacity(ply)).collect();
+        assert_eq!(
+            opacities,
+            [1.0, 1.0, 1.0, 1.0, 1.0, 0.75, 0.5, 0.25, 0.0, 0.0]
+        );
 
-        assert_eq!(game_clocks.turn_delay(turn, Color::Black), None);
-        assert_eq!(game_clocks.turn_delay(turn, Color::White), None);
+        let mut giffer = PGNGiffer::new(config).unwrap();
+        giffer.header(b"ECO", RawHeader(b"?"));
+        giffer.header(b"Opening", RawHeader(b"Sicilian Defense"));
+        assert_eq!(giffer.opening_text(), "Sicilian Defense");
 
-        assert_eq!(game_clocks.turn_delay(turn + 1, Color::Black), Some(1500));
-        assert_eq!(game_clocks.turn_delay(turn + 1, Color::White), Some(900));
+        // Nothing is shown without the style component
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        giffer.header(b"ECO", RawHeader(b"B90"));
+        assert!(!giffer.shows_opening());
+        assert_eq!(giffer.opening_opacity(0), 0.0);
+    }
 
-        assert_eq!(game_clocks.turn_delay(turn + 2, Color::Black), Some(6300));
-        assert_eq!(game_clocks.turn_delay(turn + 2, Color::White), Some(3800));
+    #[test]
+    fn test_rating_diff_headers() {
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        giffer.header(b"WhiteRatingDiff", RawHeader(b"+8"));
+        giffer.header(b"White", RawHeader(b"Anna"));
+        giffer.header(b"Black", RawHeader(b"Bob"));
+        giffer.header(b"BlackRatingDiff", RawHeader(b"-8"));
+
+        let white = giffer.players.white.as_ref().unwrap();
+        assert_eq!(white.label("Anonymous").rating_diff, Some(8));
+        assert_eq!(white.to_string(), "Anna");
+        assert_eq!(
+            giffer
+                .players
+                .black
+                .as_ref()
+                .unwrap()
+                .label("Anonymous")
+                .rating_diff,
+            Some(-8)
+        );
+
+        // Malformed diffs are omitted
+        let mut giffer = PGNGiffer::new(Config::default()).unwrap();
+        giffer.header(b"White", RawHeader(b"Anna"));
+        giffer.header(b"WhiteRatingDiff", RawHeader(b"?"));
+        assert_eq!(
+            giffer
+                .players
+                .white
+                .as_ref()
+                .unwrap()
+                .label("Anonymous")
+                .rating_diff,
+            None
+        );
     }
 
     #[test]
-    fn test_game_clocks_turn_delay_with_increment() {
-        let white_clocks = vec![
-            Clock::from_time_str("0:01:00"),
-            Clock::from_time_str("0:01:01.1"),
-            Clock::from_time_str("0:00:57.3"),
-        ];
-        let black_clocks = vec![
-            Clock::from_time_str("0:01:00"),
-            Clock::from_time_str("0:01:02.5"),
-            Clock::from_time_str("0:01:05.2"),
+    fn test_termination_header_reason() {
+        let white = Outcome::Decisive {
+            winner: shakmaty::Color::White,
+        };
+        let cases = [
+            ("slowbullet won by checkmate", white, "checkmate"),
+            ("slowbullet won by resignation", white, "resignation"),
+            ("slowbullet won on time", white, "timeout"),
+            ("slowbullet won - game abandoned", white, "resignation"),
+            ("Time forfeit", white, "timeout"),
+            ("Normal", white, "resignation"),
+            ("Game drawn by agreement", Outcome::Draw, "agreement"),
+            ("Game drawn by repetition", Outcome::Draw, "repetition"),
+            ("Game drawn by stalemate", Outcome::Draw, "stalemate"),
+            (
+                "Game drawn by insufficient material",
+                Outcome::Draw,
+                "insufficient material",
+            ),
+            (
+                "Game drawn by timeout vs insufficient material",
+                Outcome::Draw,
+                "timeout",
+            ),
+            ("Game drawn by 50-move rule", Outcome::Draw, "agreement"),
         ];
-        let game_clocks = GameClocks {
-            white: white_clocks,
-            black: black_clocks,
-            increment: Some(3000),
+
+        for (header, outcome, reason) in cases {
+            assert_eq!(
+                termination_header_reason(header, outcome),
+                reason,
+                "{}",
+                header
+            );
+        }
+    }
+
+    #[test]
+    fn test_players_color_of() {
+        let mut players = Players::default();
+        assert_eq!(players.color_of("hikaru"), None);
+
+        players.create_player(Color::White, Some("Hikaru".to_string()), None, None);
+        players.create_player(Color::Black, Some("DrNykterstein".to_string()), None, None);
+        assert_eq!(players.color_of("hikaru"), Some(Color::White));
+        assert_eq!(players.color_of("NYKTER"), Some(Color::Black));
+        assert_eq!(players.color_of("magnus"), None);
+    }
+
+    #[test]
+    fn test_players_label() {
+        let mut players = Players::default();
+        assert!(!players.exist());
+
+        players.create_player(Color::White, Some("Hikaru".to_string()), None, Some(3200));
+        assert!(players.exist());
+        assert_eq!(
+            players.label(Color::White, "Anonymous").to_string(),
+            "Hikaru (3200)"
+        );
+        assert_eq!(
+            players.label(Color::Black, "Opponent").to_string(),
+            "Opponent"
+        );
+    }
+
+    #[test]
+    fn test_move_paths() {
+        let pos = Chess::default();
+        let m = "Nf3"
+            .parse::<shakmaty::san::San>()
+            .unwrap()
+            .to_move(&pos)
+            .unwrap();
+        assert_eq!(move_paths(&m), vec![(Square::G1, Square::F3)]);
+
+        let castle = Move::Castle {
+            king: Square::E1,
+            rook: Square::A1,
         };
-        let turn: usize = 0;
+        assert_eq!(
+            move_paths(&castle),
+            vec![(Square::E1, Square::C1), (Square::A1, Square::D1)]
+        );
 
-        assert_eq!(game_clocks.turn_delay(turn, Color::Black), None);
-        assert_eq!(game_clocks.turn_delay(turn, Color::White), None);
+        let castle = Move::Castle {
+            king: Square::E8,
+            rook: Square::H8,
+        };
+        assert_eq!(
+            move_paths(&castle),
+            vec![(Square::E8, Square::G8), (Square::H8, Square::F8)]
+        );
 
-        assert_eq!(game_clocks.turn_delay(turn + 1, Color::Black), Some(500));
-        assert_eq!(game_clocks.turn_delay(turn + 1, Color::White), Some(1900));
+        // Chess960, with the rook passing over the king's square
+        let castle = Move::Castle {
+            king: Square::B1,
+            rook: Square::A1,
+        };
+        assert_eq!(
+            move_paths(&castle),
+            vec![(Square::B1, Square::C1), (Square::A1, Square::D1)]
+        );
+    }
 
-        assert_eq!(game_clocks.turn_delay(turn + 2, Color::Black), Some(300));
-        assert_eq!(game_clocks.turn_delay(turn + 2, Color::White), Some(6800));
+    #[test]
+    fn test_landing_squares() {
+        let pos = Chess::default();
+        let m = "e4"
+            .parse::<shakmaty::san::San>()
+            .unwrap()
+            .to_move(&pos)
+            .unwrap();
+        assert_eq!(landing_squares(&m), vec![Square::E4]);
+
+        let castle = Move::Castle {
+            king: Square::E1,
+            rook: Square::A1,
+        };
+        assert_eq!(landing_squares(&castle), vec![Square::C1, Square::D1]);
+
+        let castle = Move::Castle {
+            king: Square::E8,
+            rook: Square::H8,
+        };
+        assert_eq!(landing_squares(&castle), vec![Square::G8, Square::F8]);
     }
 
     #[test]
-    fn test_display_clocks() {
-        let clock = Clock::from_time_str("0:01:00");
-        assert_eq!(format!("{}", clock), "0:01:00.0");
+    fn test_record_capture() {
+        let mut captures = ByColor::<ByRole<u8>>::default();
 
-        let clock = Clock::from_millis(60000 as u32);
-        assert_eq!(format!("{}", clock), "0:01:00.0");
+        let capture = Move::Normal {
+            role: Role::Knight,
+            from: Square::F3,
+            capture: Some(Role::Bishop),
+            to: Square::G5,
+            promotion: None,
+        };
+        assert!(record_capture(&mut captures, &capture, Color::White));
 
-        let clock = Clock::from_millis(55100 as u32);
-        assert_eq!(format!("{}", clock), "0:00:55.1");
+        let en_passant = Move::EnPassant {
+            from: Square::D4,
+            to: Square::E3,
+        };
+        assert!(record_capture(&mut captures, &en_passant, Color::Black));
+
+        let promotion = Move::Normal {
+            role: Role::Pawn,
+            from: Square::A7,
+            capture: None,
+            to: Square::A8,
+            promotion: Some(Role::Queen),
+        };
+        assert!(!record_capture(&mut captures, &promotion, Color::White));
+
+        assert_eq!(captures.white.bishop, 1);
+        assert_eq!(captures.white.pawn, 0);
+        assert_eq!(captures.black.pawn, 1);
     }
 
     #[test]
-    fn test_clocks_as_millis() {
-        let clock = Clock::from_time_str("0:01:05.1");
-        assert_eq!(clock.as_millis(), 65100);
+    fn test_material_balance() {
+        assert_eq!(material_balance(Chess::default().board()), 0);
+
+        let board: Board = "4k3/8/8/8/8/8/PPP5/R3K3".parse().unwrap();
+        assert_eq!(material_balance(&board), 8);
+
+        let board: Board = "q3k3/1n6/8/8/8/8/7P/4K1B1".parse().unwrap();
+        assert_eq!(material_balance(&board), -8);
+
+        let board: Board = "r3k3/8/8/8/8/8/8/1N2KB2".parse().unwrap();
+        assert_eq!(material_balance(&board), 1);
     }
 }
@@ -1,10 +1,17 @@
 extern crate clap;
 extern crate include_dir;
 
+pub mod annotation;
 pub mod app;
 pub mod config;
+pub mod css_colors;
 pub mod delay;
 pub mod drawer;
 pub mod error;
+pub mod eval;
 pub mod giffer;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod progress;
 pub mod style;
+pub mod time_control;
@@ -0,0 +1,290 @@
+use serde::Deserialize;
+
+use super::NetError;
+
+const SITE: &str = "chess.com";
+
+/// Kind of chess.com game, as found in game URLs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameKind {
+    Live,
+    Daily,
+}
+
+impl GameKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            GameKind::Live => "live",
+            GameKind::Daily => "daily",
+        }
+    }
+}
+
+/// A reference to a game played in chess.com.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChessComGame {
+    /// A game URL, like https://www.chess.com/game/live/12345678.
+    Url { kind: GameKind, id: u64 },
+    /// A game from a player's monthly archive. Games are numbered from 1 in the order they
+    /// were played, and no number picks the last game of the month.
+    Archive {
+        username: String,
+        year: u16,
+        month: u8,
+        number: Option<usize>,
+    },
+}
+
+#[derive(Deserialize)]
+struct Archive {
+    games: Vec<ArchiveGame>,
+}
+
+#[derive(Deserialize)]
+struct ArchiveGame {
+    url: String,
+    pgn: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Callback {
+    game: CallbackGame,
+}
+
+#[derive(Deserialize)]
+struct CallbackGame {
+    #[serde(rename = "pgnHeaders")]
+    pgn_headers: CallbackHeaders,
+}
+
+#[derive(Deserialize)]
+struct CallbackHeaders {
+    #[serde(rename = "White")]
+    white: String,
+    #[serde(rename = "Date")]
+    date: String,
+}
+
+impl ChessComGame {
+    /// Parse a chess.com game URL or a username/YYYY/MM[/N] archive reference.
+    pub fn parse(s: &str) -> Result<Self, NetError> {
+        let invalid = || NetError::InvalidGameReference(s.to_string());
+        let s = s.trim();
+
+        if s.contains(SITE) {
+            let path = s
+                .trim_start_matches("https://")
+                .trim_start_matches("http://")
+                .trim_start_matches("www.")
+                .trim_start_matches(SITE)
+                .split(['?', '#'])
+                .next()
+                .ok_or_else(invalid)?;
+            let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+            let (kind, id) = match segments[..] {
+                ["game", "live", id] | ["live", "game", id] | ["live", id] => (GameKind::Live, id),
+                ["game", "daily", id] | ["daily", "game", id] | ["daily", id] => {
+                    (GameKind::Daily, id)
+                }
+                _ => return Err(invalid()),
+            };
+            let id = id.parse::<u64>().map_err(|_| invalid())?;
+            return Ok(ChessComGame::Url { kind, id });
+        }
+
+        let segments: Vec<&str> = s.split('/').collect();
+        let (username, year, month, number) = match segments[..] {
+            [username, year, month] => (username, year, month, None),
+            [username, year, month, number] => (username, year, month, Some(number)),
+            _ => return Err(invalid()),
+        };
+        let year = year.parse::<u16>().map_err(|_| invalid())?;
+        let month = match month.parse::<u8>() {
+            Ok(m) if (1..=12).contains(&m) => m,
+            _ => return Err(invalid()),
+        };
+        let number = match number.map(|n| n.parse::<usize>()) {
+            Some(Ok(n)) if n > 0 => Some(n),
+            Some(_) => return Err(invalid()),
+            None => None,
+        };
+        if username.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(ChessComGame::Archive {
+            username: username.to_lowercase(),
+            year,
+            month,
+            number,
+        })
+    }
+
+    /// Fetch the PGN of the game, including %clk comments.
+    pub fn fetch_pgn(&self) -> Result<String, NetError> {
+        match self {
+            ChessComGame::Archive {
+                username,
+                year,
+                month,
+                number,
+            } => {
+                let games = fetch_archive(username, *year, *month)?;
+                let game = match number {
+                    Some(n) => games.get(n - 1),
+                    None => games.last(),
+                };
+                game.and_then(|g| g.pgn.clone())
+                    .ok_or_else(|| NetError::GameNotFound(self.to_string()))
+            }
+            ChessComGame::Url { kind, id } => {
+                let url = format!(
+                    "https://www.{}/callback/{}/game/{}",
+                    SITE,
+                    kind.as_str(),
+                    id
+                );
+                let body = super::get(&url, "application/json", &self.to_string(), SITE)?;
+                let callback: Callback = serde_json::from_str(&body)
+                    .map_err(|source| NetError::InvalidResponse { url, source })?;
+                let headers = callback.game.pgn_headers;
+                let (year, month) = parse_date(&headers.date)
+                    .ok_or_else(|| NetError::GameNotFound(self.to_string()))?;
+
+                // Archives are grouped by the month a game ended, which may be the month
+                // after the Date header for games played around midnight.
+                let (next_year, next_month) = match month {
+                    12 => (year + 1, 1),
+                    m => (year, m + 1),
+                };
+                let suffix = format!("/{}", id);
+                for (y, m) in [(year, month), (next_year, next_month)] {
+                    let games = fetch_archive(&headers.white.to_lowercase(), y, m)?;
+                    if let Some(pgn) = games
+                        .into_iter()
+                        .find(|g| g.url.ends_with(&suffix))
+                        .and_then(|g| g.pgn)
+                    {
+                        return Ok(pgn);
+                    }
+                }
+
+                Err(NetError::GameNotFound(self.to_string()))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ChessComGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChessComGame::Url { kind, id } => {
+                write!(f, "https://www.{}/game/{}/{}", SITE, kind.as_str(), id)
+            }
+            ChessComGame::Archive {
+                username,
+                year,
+                month,
+                number: Some(n),
+            } => write!(f, "{}/{}/{:02}/{}", username, year, month, n),
+            ChessComGame::Archive {
+                username,
+                year,
+                month,
+                number: None,
+            } => write!(f, "{}/{}/{:02}", username, year, month),
+        }
+    }
+}
+
+fn archive_url(username: &str, year: u16, month: u8) -> String {
+    format!(
+        "https://api.{}/pub/player/{}/games/{}/{:02}",
+        SITE, username, year, month
+    )
+}
+
+fn fetch_archive(username: &str, year: u16, month: u8) -> Result<Vec<ArchiveGame>, NetError> {
+    let url = archive_url(username, year, month);
+    let game = format!("{}/{}/{:02}", username, year, month);
+    let body = super::get(&url, "application/json", &game, SITE)?;
+    let archive: Archive =
+        serde_json::from_str(&body).map_err(|source| NetError::InvalidResponse { url, source })?;
+    Ok(archive.games)
+}
+
+/// Parse the year and month of a PGN Date header, like 2021.04.18.
+fn parse_date(date: &str) -> Option<(u16, u8)> {
+    let mut parts = date.split('.');
+    let year = parts.next()?.parse::<u16>().ok()?;
+    let month = parts.next()?.parse::<u8>().ok()?;
+    Some((year, month))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            ChessComGame::parse("https://www.chess.com/game/live/12345678").unwrap(),
+            ChessComGame::Url {
+                kind: GameKind::Live,
+                id: 12345678
+            }
+        );
+        assert_eq!(
+            ChessComGame::parse("chess.com/daily/game/42?tab=review").unwrap(),
+            ChessComGame::Url {
+                kind: GameKind::Daily,
+                id: 42
+            }
+        );
+        assert!(ChessComGame::parse("https://www.chess.com/member/hikaru").is_err());
+        assert!(ChessComGame::parse("https://www.chess.com/game/live/abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_archive() {
+        assert_eq!(
+            ChessComGame::parse("Hikaru/2021/04").unwrap(),
+            ChessComGame::Archive {
+                username: "hikaru".to_string(),
+                year: 2021,
+                month: 4,
+                number: None
+            }
+        );
+        assert_eq!(
+            ChessComGame::parse("hikaru/2021/4/3").unwrap(),
+            ChessComGame::Archive {
+                username: "hikaru".to_string(),
+                year: 2021,
+                month: 4,
+                number: Some(3)
+            }
+        );
+        assert!(ChessComGame::parse("hikaru/2021/13").is_err());
+        assert!(ChessComGame::parse("hikaru/2021/04/0").is_err());
+        assert!(ChessComGame::parse("hikaru").is_err());
+    }
+
+    #[test]
+    fn test_archive_json() {
+        let body = r#"{"games": [{"url": "https://www.chess.com/game/live/1", "pgn": "1. e4 *", "time_class": "bullet"}]}"#;
+        let archive: Archive = serde_json::from_str(body).unwrap();
+        assert_eq!(archive.games.len(), 1);
+        assert_eq!(archive.games[0].pgn.as_deref(), Some("1. e4 *"));
+        assert_eq!(
+            archive_url("hikaru", 2021, 4),
+            "https://api.chess.com/pub/player/hikaru/games/2021/04"
+        );
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2021.04.18"), Some((2021, 4)));
+        assert_eq!(parse_date("????.??.??"), None);
+    }
+}
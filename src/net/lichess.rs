@@ -0,0 +1,102 @@
+use super::NetError;
+
+const SITE: &str = "lichess.org";
+
+/// Length of a lichess game ID. URLs for a player's view of a game append 4 more characters.
+const GAME_ID_LENGTH: usize = 8;
+
+/// A reference to a game played in lichess.org.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LichessGame {
+    pub id: String,
+    /// The game was shared from black's point of view.
+    pub black: bool,
+}
+
+impl LichessGame {
+    /// Parse a game ID or a lichess.org game URL, like https://lichess.org/abcdefgh/black.
+    pub fn parse(s: &str) -> Result<Self, NetError> {
+        let invalid = || NetError::InvalidGameReference(s.to_string());
+        let path = s
+            .trim()
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_start_matches("www.")
+            .trim_start_matches(SITE)
+            .split(['?', '#'])
+            .next()
+            .ok_or_else(invalid)?;
+        let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+
+        let id = segments.next().ok_or_else(invalid)?;
+        if !(id.len() == GAME_ID_LENGTH || id.len() == GAME_ID_LENGTH + 4)
+            || !id.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(invalid());
+        }
+        let black = segments.next() == Some("black");
+
+        Ok(LichessGame {
+            id: id[..GAME_ID_LENGTH].to_string(),
+            black,
+        })
+    }
+
+    pub fn export_url(&self) -> String {
+        format!("https://{}/game/export/{}?clocks=true", SITE, self.id)
+    }
+
+    /// Fetch the PGN of the game, including %clk comments.
+    pub fn fetch_pgn(&self) -> Result<String, NetError> {
+        super::get(
+            &self.export_url(),
+            "application/x-chess-pgn",
+            &self.id,
+            SITE,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_id() {
+        let game = LichessGame::parse("q7ZvsdUF").unwrap();
+        assert_eq!(
+            game,
+            LichessGame {
+                id: "q7ZvsdUF".to_string(),
+                black: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let game = LichessGame::parse("https://lichess.org/q7ZvsdUF/black#32").unwrap();
+        assert_eq!(game.id, "q7ZvsdUF");
+        assert!(game.black);
+
+        let game = LichessGame::parse("lichess.org/q7ZvsdUFa1b2?foo=bar").unwrap();
+        assert_eq!(game.id, "q7ZvsdUF");
+        assert!(!game.black);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(LichessGame::parse("https://lichess.org/").is_err());
+        assert!(LichessGame::parse("https://lichess.org/@/player").is_err());
+        assert!(LichessGame::parse("not a game").is_err());
+    }
+
+    #[test]
+    fn test_export_url() {
+        let game = LichessGame::parse("q7ZvsdUF").unwrap();
+        assert_eq!(
+            game.export_url(),
+            "https://lichess.org/game/export/q7ZvsdUF?clocks=true"
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! Fetch games from online chess sites. Only available with the net feature.
+pub mod chess_com;
+pub mod lichess;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum NetError {
+    #[error("Unable to find a game in {0}")]
+    InvalidGameReference(String),
+    #[error("Game {0} not found")]
+    GameNotFound(String),
+    #[error("Too many requests to {site}, wait a minute before trying again")]
+    RateLimited { site: String },
+    #[error("Request to {url} failed with status {status}")]
+    UnexpectedStatus { url: String, status: u16 },
+    #[error("Request failed: {source}")]
+    Request {
+        url: String,
+        source: Box<ureq::Transport>,
+    },
+    #[error("Unexpected response from {url}")]
+    InvalidResponse {
+        url: String,
+        source: serde_json::Error,
+    },
+    #[error("Failed to read response from {url}")]
+    ReadResponse { url: String, source: std::io::Error },
+}
+
+/// Send a GET request and read the response body as a String. 404 and 429 responses are
+/// mapped to GameNotFound and RateLimited respectively.
+pub(crate) fn get(url: &str, accept: &str, game: &str, site: &str) -> Result<String, NetError> {
+    log::info!("Fetching {}", url);
+    let response = ureq::get(url)
+        .set("Accept", accept)
+        .call()
+        .map_err(|e| match e {
+            ureq::Error::Status(404, _) => NetError::GameNotFound(game.to_string()),
+            ureq::Error::Status(429, _) => NetError::RateLimited {
+                site: site.to_string(),
+            },
+            ureq::Error::Status(status, _) => NetError::UnexpectedStatus {
+                url: url.to_string(),
+                status,
+            },
+            ureq::Error::Transport(t) => NetError::Request {
+                url: url.to_string(),
+                source: Box::new(t),
+            },
+        })?;
+
+    response
+        .into_string()
+        .map_err(|source| NetError::ReadResponse {
+            url: url.to_string(),
+            source,
+        })
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+/// Stages of rendering a GIF that report progress.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    /// A move was parsed and its board drawn. The total is only known once the game ends.
+    Drawing,
+    /// A frame was quantized and compressed.
+    Encoding,
+    /// A frame was written to the output.
+    Writing,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Drawing => write!(f, "Drawing"),
+            Stage::Encoding => write!(f, "Encoding"),
+            Stage::Writing => write!(f, "Writing"),
+        }
+    }
+}
+
+/// A progress report sent to a ProgressHook.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub stage: Stage,
+    /// Number of moves or frames done so far in this stage.
+    pub current: usize,
+    /// Total number of moves or frames in this stage, if known.
+    pub total: Option<usize>,
+}
+
+impl Progress {
+    /// Percentage of the stage done, if the total is known.
+    pub fn percent(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(100.0),
+            Some(t) => Some(self.current as f64 * 100.0 / t as f64),
+            None => None,
+        }
+    }
+}
+
+/// A callback invoked with Progress reports while rendering. May be called from
+/// multiple threads while encoding.
+#[derive(Clone)]
+pub struct ProgressHook(Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl ProgressHook {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        ProgressHook(Arc::new(f))
+    }
+
+    /// Call the hook with a Progress report. A panicking hook is logged and ignored
+    /// so it can't abort the render.
+    pub fn report(&self, progress: Progress) {
+        let hook = &self.0;
+        if panic::catch_unwind(AssertUnwindSafe(|| hook(progress))).is_err() {
+            log::warn!("Progress hook panicked on {:?}", progress);
+        }
+    }
+}
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_percent() {
+        let progress = Progress {
+            stage: Stage::Encoding,
+            current: 5,
+            total: Some(20),
+        };
+        assert_eq!(progress.percent(), Some(25.0));
+
+        let progress = Progress {
+            stage: Stage::Drawing,
+            current: 5,
+            total: None,
+        };
+        assert_eq!(progress.percent(), None);
+    }
+
+    #[test]
+    fn test_report_survives_panic() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let hook = ProgressHook::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            panic!("Hook failed");
+        });
+        let progress = Progress {
+            stage: Stage::Writing,
+            current: 1,
+            total: Some(1),
+        };
+
+        hook.report(progress);
+        hook.report(progress);
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
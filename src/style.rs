@@ -12,6 +12,20 @@ pub enum StyleComponent {
     Coordinates,
     Ranks,
     Files,
+    EndCard,
+    Arrows,
+    MoveBadges,
+    MaterialBalance,
+    TerminationText,
+    WinnerCrown,
+    Heatmap,
+    MoveHighlights,
+    EventBar,
+    Opening,
+    Clocks,
+    MoveTimes,
+    MoveList,
+    Captions,
 }
 
 impl StyleComponent {
@@ -22,10 +36,25 @@ impl StyleComponent {
             StyleComponent::Files => &[StyleComponent::Files],
             StyleComponent::PlayerBars => &[StyleComponent::PlayerBars],
             StyleComponent::Terminations => &[StyleComponent::Terminations],
+            StyleComponent::EndCard => &[StyleComponent::EndCard],
+            StyleComponent::Arrows => &[StyleComponent::Arrows],
+            StyleComponent::MoveBadges => &[StyleComponent::MoveBadges],
+            StyleComponent::MaterialBalance => &[StyleComponent::MaterialBalance],
+            StyleComponent::TerminationText => &[StyleComponent::TerminationText],
+            StyleComponent::WinnerCrown => &[StyleComponent::WinnerCrown],
+            StyleComponent::Heatmap => &[StyleComponent::Heatmap],
+            StyleComponent::MoveHighlights => &[StyleComponent::MoveHighlights],
+            StyleComponent::EventBar => &[StyleComponent::EventBar],
+            StyleComponent::Opening => &[StyleComponent::Opening],
+            StyleComponent::Clocks => &[StyleComponent::Clocks],
+            StyleComponent::MoveTimes => &[StyleComponent::MoveTimes],
+            StyleComponent::MoveList => &[StyleComponent::MoveList],
+            StyleComponent::Captions => &[StyleComponent::Captions],
             StyleComponent::Full => &[
                 StyleComponent::Ranks,
                 StyleComponent::Files,
                 StyleComponent::PlayerBars,
+                StyleComponent::Clocks,
                 StyleComponent::Terminations,
             ],
             StyleComponent::Plain => &[],
@@ -42,6 +71,20 @@ impl FromStr for StyleComponent {
             "files" => Ok(StyleComponent::Files),
             "player-bars" => Ok(StyleComponent::PlayerBars),
             "terminations" => Ok(StyleComponent::Terminations),
+            "end-card" => Ok(StyleComponent::EndCard),
+            "arrows" => Ok(StyleComponent::Arrows),
+            "move-badges" => Ok(StyleComponent::MoveBadges),
+            "material-balance" => Ok(StyleComponent::MaterialBalance),
+            "termination-text" => Ok(StyleComponent::TerminationText),
+            "winner-crown" => Ok(StyleComponent::WinnerCrown),
+            "heatmap" => Ok(StyleComponent::Heatmap),
+            "move-highlights" => Ok(StyleComponent::MoveHighlights),
+            "event-bar" => Ok(StyleComponent::EventBar),
+            "opening" => Ok(StyleComponent::Opening),
+            "clocks" => Ok(StyleComponent::Clocks),
+            "move-times" => Ok(StyleComponent::MoveTimes),
+            "move-list" => Ok(StyleComponent::MoveList),
+            "captions" => Ok(StyleComponent::Captions),
             "full" => Ok(StyleComponent::Full),
             "plain" => Ok(StyleComponent::Plain),
             _ => Err(C2GError::UnknownStyle(s.to_string())),
@@ -73,6 +116,62 @@ impl StyleComponents {
         self.0.contains(&StyleComponent::Files)
     }
 
+    pub fn end_card(&self) -> bool {
+        self.0.contains(&StyleComponent::EndCard)
+    }
+
+    pub fn arrows(&self) -> bool {
+        self.0.contains(&StyleComponent::Arrows)
+    }
+
+    pub fn move_badges(&self) -> bool {
+        self.0.contains(&StyleComponent::MoveBadges)
+    }
+
+    pub fn material_balance(&self) -> bool {
+        self.0.contains(&StyleComponent::MaterialBalance)
+    }
+
+    pub fn termination_text(&self) -> bool {
+        self.0.contains(&StyleComponent::TerminationText)
+    }
+
+    pub fn winner_crown(&self) -> bool {
+        self.0.contains(&StyleComponent::WinnerCrown)
+    }
+
+    pub fn heatmap(&self) -> bool {
+        self.0.contains(&StyleComponent::Heatmap)
+    }
+
+    pub fn move_highlights(&self) -> bool {
+        self.0.contains(&StyleComponent::MoveHighlights)
+    }
+
+    pub fn event_bar(&self) -> bool {
+        self.0.contains(&StyleComponent::EventBar)
+    }
+
+    pub fn opening(&self) -> bool {
+        self.0.contains(&StyleComponent::Opening)
+    }
+
+    pub fn clocks(&self) -> bool {
+        self.0.contains(&StyleComponent::Clocks)
+    }
+
+    pub fn move_times(&self) -> bool {
+        self.0.contains(&StyleComponent::MoveTimes)
+    }
+
+    pub fn move_list(&self) -> bool {
+        self.0.contains(&StyleComponent::MoveList)
+    }
+
+    pub fn captions(&self) -> bool {
+        self.0.contains(&StyleComponent::Captions)
+    }
+
     pub fn plain(&self) -> bool {
         self.0.iter().all(|c| c == &StyleComponent::Plain)
     }
@@ -0,0 +1,237 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A stage of a time control, like 40 moves in 90 minutes with 30 seconds added per move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stage {
+    /// Moves to play in the stage, or None for sudden death, where the rest of the game is
+    /// played in it.
+    pub moves: Option<u32>,
+    /// Time added to the clock at the start of the stage.
+    pub base: Duration,
+    /// Time added to the clock after every move of the stage.
+    pub increment: Duration,
+    /// Whether the stage is played with a sandclock, where the time one player spends is added
+    /// to the other's clock.
+    pub sandclock: bool,
+}
+
+/// A time control as found in TimeControl headers, like 180+2 for 3 minutes with a 2 second
+/// increment, or 40/5400+30:1800+30 for the FIDE classical time control, made up of stages
+/// separated by colons.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeControl {
+    pub stages: Vec<Stage>,
+}
+
+impl TimeControl {
+    /// Parse a TimeControl header value. Unknown time controls, written ?, untimed games,
+    /// written -, and values that can't be parsed have none.
+    pub fn from_header(value: &str) -> Option<Self> {
+        match value.trim() {
+            "?" | "-" | "" => None,
+            value => match value.parse::<TimeControl>() {
+                Ok(time_control) => Some(time_control),
+                Err(e) => {
+                    log::debug!("Ignoring time control: {}", e);
+                    None
+                }
+            },
+        }
+    }
+
+    /// Time on the clock at the start of the game.
+    pub fn base(&self) -> Duration {
+        self.stages[0].base
+    }
+
+    /// Time added after every move at the start of the game.
+    pub fn increment(&self) -> Duration {
+        self.stages[0].increment
+    }
+
+    /// Whether the game is played over days with a move or more a day, like 1/86400.
+    pub fn is_correspondence(&self) -> bool {
+        self.stages[0].base >= Duration::from_secs(24 * 60 * 60)
+            && self.stages[0].moves.is_some_and(|moves| moves <= 1)
+    }
+}
+
+impl fmt::Display for TimeControl {
+    /// Format for people, in minutes and increment seconds like 3+2 for 180+2, with stages
+    /// separated by commas, like 40/90+30, 30+30 for 40/5400+30:1800+30.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (n, stage) in self.stages.iter().enumerate() {
+            if n > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", stage)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Stage {
+    /// Format for people, like 3+2, 40/120+0, or *3 for a sandclock. Bases that aren't whole
+    /// minutes are written in seconds, like 45s+1, and whole days in days, like 1/1d+0.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.base.as_secs();
+        let base = if seconds > 0 && seconds.is_multiple_of(24 * 60 * 60) {
+            format!("{}d", seconds / (24 * 60 * 60))
+        } else if seconds.is_multiple_of(60) {
+            (seconds / 60).to_string()
+        } else {
+            format!("{}s", seconds)
+        };
+
+        if self.sandclock {
+            return write!(f, "*{}", base);
+        }
+        if let Some(moves) = self.moves {
+            write!(f, "{}/", moves)?;
+        }
+        write!(f, "{}+{}", base, self.increment.as_secs())
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stages = s
+            .split(':')
+            .map(|stage| {
+                stage
+                    .parse::<Stage>()
+                    .map_err(|e| format!("Invalid time control {}: {}", s, e))
+            })
+            .collect::<Result<Vec<Stage>, String>>()?;
+        Ok(TimeControl { stages })
+    }
+}
+
+impl FromStr for Stage {
+    type Err = String;
+
+    /// Parse a stage like 300 for sudden death, 180+2 with an increment, 40/7200 for a number
+    /// of moves, or *180 for a sandclock.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let seconds = |field: &str| {
+            field
+                .parse::<u64>()
+                .map(Duration::from_secs)
+                .map_err(|_| format!("{} is not a number of seconds", field))
+        };
+
+        if let Some(base) = s.strip_prefix('*') {
+            return Ok(Stage {
+                moves: None,
+                base: seconds(base)?,
+                increment: Duration::ZERO,
+                sandclock: true,
+            });
+        }
+
+        let (moves, time) = match s.split_once('/') {
+            Some((moves, time)) => {
+                let moves = moves
+                    .parse::<u32>()
+                    .map_err(|_| format!("{} is not a number of moves", moves))?;
+                (Some(moves), time)
+            }
+            None => (None, s),
+        };
+        let (base, increment) = match time.split_once('+') {
+            Some((base, increment)) => (seconds(base)?, seconds(increment)?),
+            None => (seconds(time)?, Duration::ZERO),
+        };
+
+        Ok(Stage {
+            moves,
+            base,
+            increment,
+            sandclock: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(moves: Option<u32>, base: u64, increment: u64) -> Stage {
+        Stage {
+            moves,
+            base: Duration::from_secs(base),
+            increment: Duration::from_secs(increment),
+            sandclock: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_time_control() {
+        let blitz = TimeControl::from_header("180+2").unwrap();
+        assert_eq!(blitz.stages, [stage(None, 180, 2)]);
+        assert_eq!(blitz.base(), Duration::from_secs(180));
+        assert_eq!(blitz.increment(), Duration::from_secs(2));
+
+        // Sudden death
+        let rapid = TimeControl::from_header("600").unwrap();
+        assert_eq!(rapid.stages, [stage(None, 600, 0)]);
+        assert_eq!(rapid.increment(), Duration::ZERO);
+
+        let sandclock = TimeControl::from_header("*180").unwrap();
+        assert!(sandclock.stages[0].sandclock);
+        assert_eq!(sandclock.base(), Duration::from_secs(180));
+    }
+
+    #[test]
+    fn test_parse_multi_stage_time_control() {
+        let classical = TimeControl::from_header("40/7200:3600").unwrap();
+        assert_eq!(
+            classical.stages,
+            [stage(Some(40), 7200, 0), stage(None, 3600, 0)]
+        );
+        assert_eq!(classical.base(), Duration::from_secs(7200));
+
+        let fide = TimeControl::from_header("40/5400+30:1800+30").unwrap();
+        assert_eq!(
+            fide.stages,
+            [stage(Some(40), 5400, 30), stage(None, 1800, 30)]
+        );
+        assert_eq!(fide.increment(), Duration::from_secs(30));
+        assert!(!fide.is_correspondence());
+    }
+
+    #[test]
+    fn test_parse_unknown_time_control() {
+        for value in [
+            "?", "-", "", "abc", "180+", "40/", "40/7200:", "+2", "x/300",
+        ] {
+            assert_eq!(TimeControl::from_header(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_format_time_control() {
+        let format = |value| TimeControl::from_header(value).unwrap().to_string();
+        assert_eq!(format("180+2"), "3+2");
+        assert_eq!(format("600"), "10+0");
+        assert_eq!(format("45+1"), "45s+1");
+        assert_eq!(format("*180"), "*3");
+        assert_eq!(format("40/7200:3600"), "40/120+0, 60+0");
+        assert_eq!(format("40/5400+30:1800+30"), "40/90+30, 30+30");
+        assert_eq!(format("1/86400"), "1/1d+0");
+    }
+
+    #[test]
+    fn test_correspondence_time_control() {
+        let correspondence = TimeControl::from_header("1/86400").unwrap();
+        assert_eq!(correspondence.stages, [stage(Some(1), 86400, 0)]);
+        assert!(correspondence.is_correspondence());
+        assert!(!TimeControl::from_header("86400")
+            .unwrap()
+            .is_correspondence());
+    }
+}
@@ -1,6 +1,13 @@
 /// Test the examples provided with C2G.
-use c2g::{app::Chess2Gif, config};
+use c2g::{
+    app::{decode_pgn, Chess2Gif},
+    config,
+    drawer::{SVGFontConfig, SVGForest},
+    style::{StyleComponent, StyleComponents},
+};
+use std::collections::HashMap;
 use std::fs;
+use std::time::Duration;
 
 #[test]
 fn test_example() {
@@ -42,3 +49,1558 @@ fn test_example_bullet() {
     let bytes = maybe_bytes.expect("Already checked this is Ok");
     assert!(bytes.len() > 0);
 }
+
+#[test]
+fn test_example_with_svg_sources() {
+    let contents = fs::read_to_string("example/example.pgn").expect("Failed to read example PGN");
+    let mut sources = HashMap::new();
+    for piece in ["w_p", "b_p"] {
+        let svg = fs::read_to_string(format!("svgs/cburnett/{}.svg", piece))
+            .expect("Failed to read piece SVG");
+        sources.insert(format!("{}.svg", piece), svg);
+    }
+    let svgs = SVGForest::from_sources(SVGFontConfig::default(), sources)
+        .expect("Failed to build SVGForest");
+    let config = config::Config {
+        output: config::Output::Buffer,
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::with_svgs(contents, config, svgs).expect("Failed to initialize Chess2Gif");
+
+    let bytes = app
+        .run()
+        .expect("Failed to run Chess2Gif")
+        .expect("Output is a buffer");
+
+    assert!(bytes.len() > 0);
+}
+
+#[test]
+fn test_example_play_once() {
+    let contents =
+        fs::read_to_string("example/example_no_clock.pgn").expect("Failed to read example PGN");
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 160,
+        play_once: true,
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::new(contents, config).expect("Failed to initialize Chess2Gif");
+
+    let bytes = app
+        .run()
+        .expect("Failed to run Chess2Gif")
+        .expect("Output is a buffer");
+
+    assert!(!bytes
+        .windows(b"NETSCAPE2.0".len())
+        .any(|w| w == b"NETSCAPE2.0"));
+
+    let mut decoder = gif::DecodeOptions::new()
+        .read_info(&bytes[..])
+        .expect("Failed to decode GIF");
+    let mut last_delay = 0;
+    while let Some(frame) = decoder.read_next_frame().expect("Failed to read frame") {
+        last_delay = frame.delay;
+    }
+
+    // Viewers that loop regardless of the missing NETSCAPE extension still stop on the last frame
+    assert_eq!(decoder.repeat(), gif::Repeat::Finite(0));
+    assert_eq!(last_delay, u16::MAX);
+}
+
+#[test]
+fn test_example_play_once_total_duration() {
+    let contents =
+        fs::read_to_string("example/example_no_clock.pgn").expect("Failed to read example PGN");
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 160,
+        play_once: true,
+        total_duration: Some(Duration::from_secs(10)),
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::new(contents, config).expect("Failed to initialize Chess2Gif");
+
+    let bytes = app
+        .run()
+        .expect("Failed to run Chess2Gif")
+        .expect("Output is a buffer");
+
+    let mut decoder = gif::DecodeOptions::new()
+        .read_info(&bytes[..])
+        .expect("Failed to decode GIF");
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().expect("Failed to read frame") {
+        delays.push(u32::from(frame.delay));
+    }
+
+    // The last frame is held for good, and the frames before it take the total duration.
+    // Delays are rounded to hundredths of a second, half of one at most per frame
+    let (last, rest) = delays.split_last().expect("GIF has frames");
+    assert_eq!(*last, u32::from(u16::MAX));
+    let total: u32 = rest.iter().sum();
+    assert!(total.abs_diff(1000) <= rest.len() as u32 / 2 + 1);
+}
+
+#[test]
+fn test_encoder_settings() {
+    let contents = fs::read_to_string("example/example.pgn").expect("Failed to read example PGN");
+    let render = |encoder_speed: i32, write_buffer_capacity: usize| {
+        let output = std::env::temp_dir().join(format!(
+            "c2g_test_encoder_{}_{}.gif",
+            encoder_speed, write_buffer_capacity
+        ));
+        let config = config::Config {
+            output: config::Output::Path(output.to_string_lossy().to_string()),
+            size: 160,
+            encoder_speed,
+            write_buffer_capacity,
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(contents.clone(), config).expect("Failed to initialize Chess2Gif");
+        app.run().expect("Failed to run Chess2Gif");
+        fs::read(&output).expect("Failed to read GIF")
+    };
+    let frames = |bytes: &[u8]| {
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(bytes)
+            .expect("Failed to decode GIF");
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().expect("Failed to read frame") {
+            delays.push(frame.delay);
+        }
+        (decoder.width(), decoder.height(), delays)
+    };
+
+    // The write buffer only changes how often the file is written to, not what is written
+    let bytes = render(10, 64 * 1024);
+    for capacity in [1, 1000, 1024 * 1024] {
+        assert!(
+            render(10, capacity) == bytes,
+            "Capacity {} changed the GIF",
+            capacity
+        );
+    }
+
+    // Encoder speed trades palette quality for time, but frames and their delays are the same
+    for encoder_speed in [1, 30] {
+        assert_eq!(frames(&render(encoder_speed, 64 * 1024)), frames(&bytes));
+    }
+}
+
+#[test]
+fn test_example_latin1() {
+    let bytes = fs::read("example/example_latin1.pgn").expect("Failed to read example PGN");
+    assert!(String::from_utf8(bytes.clone()).is_err());
+    let contents = decode_pgn(bytes);
+    assert!(contents.contains("[White \"Müller\"]"));
+
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 160,
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::new(contents, config).expect("Failed to initialize Chess2Gif");
+
+    let bytes = app.run().unwrap().expect("Buffer output returns bytes");
+    assert!(bytes.len() > 0);
+}
+
+#[test]
+fn test_example_bom() {
+    let bytes = fs::read("example/example_bom.pgn").expect("Failed to read example PGN");
+    assert!(bytes.starts_with(&[0xef, 0xbb, 0xbf]));
+    let contents = decode_pgn(bytes);
+    assert!(contents.starts_with("[Event"));
+
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 160,
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::new(contents, config).expect("Failed to initialize Chess2Gif");
+
+    let bytes = app.run().unwrap().expect("Buffer output returns bytes");
+    assert!(bytes.len() > 0);
+}
+
+#[test]
+fn test_example_metadata() {
+    let contents = fs::read_to_string("example/example.pgn").expect("Failed to read example PGN");
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 160,
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::new(contents, config).expect("Failed to initialize Chess2Gif");
+
+    let report = app.render().expect("Failed to render example");
+    let metadata = report.metadata.expect("Game metadata is available");
+    let bytes = report.gif.expect("Buffer output returns bytes");
+
+    assert_eq!(metadata.result, "0-1");
+    assert_eq!(metadata.eco.as_deref(), Some("C65"));
+    assert_eq!(metadata.ply_count, 78);
+
+    let mut decoder = gif::DecodeOptions::new()
+        .read_info(&bytes[..])
+        .expect("Failed to decode GIF");
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().expect("Failed to read frame") {
+        delays.push(frame.delay);
+    }
+    assert_eq!(metadata.frame_delays_cs, delays);
+
+    let json = serde_json::to_value(&metadata).expect("Metadata is serializable");
+    assert_eq!(json["black"]["name"], "Hikaru");
+}
+
+#[test]
+fn test_real_delays_from_elapsed_move_times() {
+    let delays = |pgn: &str| {
+        let second = c2g::delay::Delay::Duration(1000);
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 160,
+            delays: c2g::delay::Delays::new(&c2g::delay::Delay::Real, &second, &second),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let report = app.render().expect("Failed to render game");
+        report
+            .metadata
+            .expect("Game metadata is available")
+            .frame_delays_cs
+    };
+
+    // Black's second move took 4 seconds, shown after white's second move
+    let emt_only = "1. e4 {[%emt 0:00:03]} e5 {[%emt 0:00:05]} 2. Nf3 {[%emt 0:00:07]} Nc6 {[%emt 0:00:04]} 3. Bb5 *";
+    assert_eq!(delays(emt_only)[3], 400);
+
+    // Elapsed move times win over clocks, which would say 9 seconds
+    let mixed = "1. e4 {[%clk 0:03:00] [%emt 0:00:01]} e5 {[%clk 0:03:00] [%emt 0:00:01]} 2. Nf3 {[%clk 0:02:55] [%emt 0:00:05]} Nc6 {[%clk 0:02:51] [%emt 0:00:04]} 3. Bb5 *";
+    assert_eq!(delays(mixed)[3], 400);
+}
+
+#[test]
+fn test_simulated_clocks() {
+    let pgn = "[TimeControl \"60\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 *";
+    let delays = |clock_simulation| {
+        let second = c2g::delay::Delay::Duration(1000);
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 160,
+            delays: c2g::delay::Delays::new(&c2g::delay::Delay::Real, &second, &second),
+            clock_simulation,
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let report = app.render().expect("Failed to render game");
+        report
+            .metadata
+            .expect("Game metadata is available")
+            .frame_delays_cs
+    };
+
+    // Without clock comments, moves take the first frame delay unless clocks are simulated,
+    // with each player spending their minute over three moves
+    assert_eq!(delays(None)[3], 100);
+    assert_eq!(delays(Some(c2g::delay::ClockSimulation::Even))[3], 2000);
+    // Frames are held for the next move, and the first moves take the first frame delay
+    let curve = delays(Some(c2g::delay::ClockSimulation::Curve));
+    assert_eq!(curve[2..6], [2000, 2000, 3000, 3000]);
+}
+
+/// Decode a GIF into the RGBA pixels of its last frame, along with its width and height. Frames
+/// only hold what changed, so they are stacked to get the final image.
+fn last_frame(bytes: &[u8]) -> (usize, usize, Vec<u8>) {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(bytes).expect("Failed to decode GIF");
+    let width = decoder.width() as usize;
+    let height = decoder.height() as usize;
+    let mut image = vec![0u8; width * height * 4];
+    while let Some(frame) = decoder.read_next_frame().expect("Failed to read frame") {
+        for (i, pixel) in frame.buffer.chunks(4).enumerate() {
+            if pixel[3] == 0 {
+                continue;
+            }
+            let x = frame.left as usize + i % frame.width as usize;
+            let y = frame.top as usize + i / frame.width as usize;
+            let start = (y * width + x) * 4;
+            image[start..start + 4].copy_from_slice(pixel);
+        }
+    }
+    (width, height, image)
+}
+
+/// Number of RGBA pixels that differ by more than the error GIF palettes may introduce.
+fn differing_pixels(a: &[u8], b: &[u8]) -> usize {
+    a.chunks(4)
+        .zip(b.chunks(4))
+        .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| a.abs_diff(*b) > 16))
+        .count()
+}
+
+#[test]
+fn test_termination_circles_with_player_bars() {
+    let contents =
+        fs::read_to_string("example/example_bullet.pgn").expect("Failed to read example PGN");
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 320,
+        style_components: StyleComponents::new(&[
+            StyleComponent::PlayerBars,
+            StyleComponent::Terminations,
+        ]),
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::new(contents, config).expect("Failed to initialize Chess2Gif");
+    let bytes = app
+        .run()
+        .expect("Failed to run Chess2Gif")
+        .expect("Output is a buffer");
+
+    let (width, height, image) = last_frame(&bytes);
+    let pixel = |x: usize, y: usize| &image[(y * width + x) * 4..(y * width + x) * 4 + 3];
+    let is_win_circle = |p: &[u8]| p[0] > 200 && p[1] > 90 && p[1] < 170 && p[2] < 60;
+
+    // Black mates with the king on a3, which is 40px down from the top player bar
+    assert_eq!(height, 400);
+    assert!(is_win_circle(pixel(6, 40 + 200 + 1)));
+    // Circles placed on the whole image instead of the board land a square off, on a4 or a2
+    assert!(!is_win_circle(pixel(6, 40 + 160 + 1)));
+    assert!(!is_win_circle(pixel(6, 40 + 240 + 1)));
+}
+
+#[test]
+fn test_termination_fade() {
+    let contents =
+        fs::read_to_string("example/example_bullet.pgn").expect("Failed to read example PGN");
+    let delays = |termination_fade: u32| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 160,
+            style_components: StyleComponents::new(&[StyleComponent::Terminations]),
+            termination_fade,
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(contents.clone(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(&bytes[..])
+            .expect("Failed to decode GIF");
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().expect("Failed to read frame") {
+            delays.push(frame.delay);
+        }
+        delays
+    };
+
+    let popped = delays(0);
+    let faded = delays(3);
+
+    // The last move is shown without circles, followed by 3 short fade frames
+    assert_eq!(faded.len(), popped.len() + 4);
+    let last = faded.len() - 1;
+    assert_eq!(faded[last - 3..last], [8, 8, 8]);
+    // Only the fully faded in frame is held for the last frame delay
+    assert_eq!(faded[last], popped[popped.len() - 1]);
+    assert_eq!(faded[last - 4], popped[popped.len() - 2]);
+}
+
+#[test]
+fn test_sizes_not_multiple_of_8() {
+    let pgn = "1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0";
+    for size in [500, 1000] {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size,
+            style_components: StyleComponents::new(&[]),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+
+        let (width, height, image) = last_frame(&bytes);
+        assert_eq!((width, height), (size as usize, size as usize));
+
+        // The h file is flush with the right edge: the last column shows the colors of h8 and h1
+        let right_edge = |y: usize| &image[(y * width + width - 1) * 4..(y * width + width) * 4];
+        let close_to = |p: &[u8], color: [u8; 3]| {
+            p[3] == 255
+                && p.iter()
+                    .zip(color)
+                    .all(|(a, b)| (*a as i32 - b as i32).abs() <= 8)
+        };
+        let square = width / 8;
+        assert!(close_to(right_edge(square / 2), [118, 150, 86]));
+        assert!(close_to(right_edge(width - square / 2), [238, 238, 210]));
+    }
+}
+
+#[test]
+fn test_grayscale() {
+    let contents =
+        fs::read_to_string("example/example_bullet.pgn").expect("Failed to read example PGN");
+    let render = |grayscale: bool| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            colors: config::Colors::from_strs("#b58863", "#f0d9b5").unwrap(),
+            grayscale,
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(contents.clone(), config).expect("Failed to initialize Chess2Gif");
+        app.run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer")
+    };
+    let color = render(false);
+    let gray = render(true);
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(&gray[..]).expect("Failed to decode GIF");
+    while let Some(frame) = decoder.read_next_frame().expect("Failed to read frame") {
+        for pixel in frame.buffer.chunks(4) {
+            assert!(pixel[0] == pixel[1] && pixel[1] == pixel[2], "{:?}", pixel);
+        }
+    }
+    assert!(
+        gray.len() < color.len(),
+        "{} >= {}",
+        gray.len(),
+        color.len()
+    );
+}
+
+#[test]
+fn test_heatmap() {
+    let pgn = "1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0";
+    let render = |components: &[StyleComponent]| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            style_components: StyleComponents::new(components),
+            heatmap_delay: 2500,
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        app.run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer")
+    };
+
+    let bytes = render(&[StyleComponent::Heatmap]);
+    let (width, _, image) = last_frame(&bytes);
+    let pixel = |x: usize, y: usize| &image[(y * width + x) * 4..(y * width + x) * 4 + 3];
+    // e4 had a pawn land on it, while a3 was never visited
+    let e4 = pixel(4 * 40 + 2, 4 * 40 + 2);
+    assert!(e4[0] > e4[1] + 80, "{:?}", e4);
+    assert_eq!(pixel(2, 5 * 40 + 2), &[118, 150, 86]);
+
+    // The heatmap is held for its own delay, and the end card still comes last
+    let bytes = render(&[StyleComponent::Heatmap, StyleComponent::EndCard]);
+    let mut decoder = gif::DecodeOptions::new()
+        .read_info(&bytes[..])
+        .expect("Failed to decode GIF");
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().expect("Failed to read frame") {
+        delays.push(frame.delay);
+    }
+    assert_eq!(delays.len(), 10);
+    assert_eq!(delays[8..], [250, 100]);
+}
+
+#[test]
+fn test_trail() {
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 320,
+        style_components: StyleComponents::new(&[]),
+        trail: 2,
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::new("1. e4 e5 2. Nf3 *".to_string(), config)
+        .expect("Failed to initialize Chess2Gif");
+    let bytes = app
+        .run()
+        .expect("Failed to run Chess2Gif")
+        .expect("Output is a buffer");
+
+    let (width, _, image) = last_frame(&bytes);
+    let pixel = |x: usize, y: usize| &image[(y * width + x) * 4..(y * width + x) * 4 + 3];
+    let distance = |p: &[u8], color: [u8; 3]| -> i32 {
+        p.iter()
+            .zip(color)
+            .map(|(a, b)| (*a as i32 - b as i32).abs())
+            .sum()
+    };
+    let (dark, light) = ([118, 150, 86], [238, 238, 210]);
+
+    // Nf3 is tinted more than e5, and e4 is no longer part of the trail
+    let g1 = distance(pixel(6 * 40 + 2, 7 * 40 + 2), dark);
+    let e7 = distance(pixel(4 * 40 + 2, 40 + 2), dark);
+    assert!(g1 > e7 && e7 > 24, "{} {}", g1, e7);
+    assert!(distance(pixel(4 * 40 + 2, 6 * 40 + 2), light) <= 8);
+    assert!(distance(pixel(4 * 40 + 2, 4 * 40 + 2), light) <= 8);
+}
+
+#[test]
+fn test_animate_moves() {
+    let pgn = "1. e4 d5 2. exd5 Nf6 3. Bc4 Nxd5 4. Nf3 e6 5. O-O *";
+    let render = |animate_moves: u32| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            animate_moves,
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        app.run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer")
+    };
+    let still = render(0);
+    let animated = render(3);
+
+    // Sliding pieces leave nothing behind, including captured pieces and the castling rook.
+    // Squares redrawn in other frames may be quantized to slightly different colors
+    let (still_image, animated_image) = (last_frame(&still).2, last_frame(&animated).2);
+    let max_difference = still_image
+        .iter()
+        .zip(animated_image.iter())
+        .map(|(a, b)| (*a as i32 - *b as i32).abs())
+        .max();
+    assert!(max_difference <= Some(16), "{:?}", max_difference);
+
+    let mut decoder = gif::DecodeOptions::new()
+        .read_info(&animated[..])
+        .expect("Failed to decode GIF");
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().expect("Failed to read frame") {
+        delays.push(frame.delay);
+    }
+    assert_eq!(delays.len(), 1 + 9 * 4);
+    assert_eq!(delays[1..4], [3, 3, 3]);
+    assert_eq!(delays[4], 100);
+}
+
+#[test]
+fn test_animate_moves_reverse() {
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 160,
+        animate_moves: 3,
+        reverse: true,
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::new("1. e4 e5 2. Nf3 *".to_string(), config)
+        .expect("Failed to initialize Chess2Gif");
+    let bytes = app
+        .run()
+        .expect("Failed to run Chess2Gif")
+        .expect("Output is a buffer");
+
+    let mut decoder = gif::DecodeOptions::new()
+        .read_info(&bytes[..])
+        .expect("Failed to decode GIF");
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().expect("Failed to read frame") {
+        delays.push(frame.delay);
+    }
+
+    // Positions are held as long as when playing forward, and pieces slide between them
+    assert_eq!(delays.len(), 1 + 3 * 4);
+    for (n, delay) in delays.iter().enumerate() {
+        if n % 4 == 0 {
+            assert!(*delay > 3, "{:?}", delays);
+        } else {
+            assert_eq!(*delay, 3, "{:?}", delays);
+        }
+    }
+}
+
+#[test]
+fn test_en_passant_move_highlight() {
+    let render = |pgn: &str| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            style_components: StyleComponents::new(&[StyleComponent::MoveHighlights]),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        let (width, _, image) = last_frame(&bytes);
+        // Corner of d5, the square of the pawn taken en passant
+        let start = (3 * 40 + 2) * width * 4 + (3 * 40 + 2) * 4;
+        image[start..start + 3].to_vec()
+    };
+
+    let marked = render("1. e4 a6 2. e5 d5 3. exd6 *");
+    assert!(marked != [238, 238, 210], "{:?}", marked);
+    let cleared = render("1. e4 a6 2. e5 d5 3. exd6 a5 *");
+    assert_eq!(cleared, [238, 238, 210]);
+}
+
+#[test]
+fn test_castling_move_highlights() {
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 320,
+        style_components: StyleComponents::new(&[
+            StyleComponent::MoveHighlights,
+            StyleComponent::Arrows,
+        ]),
+        ..config::Config::default()
+    };
+    let pgn = "1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O *";
+    let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+    let bytes = app
+        .run()
+        .expect("Failed to run Chess2Gif")
+        .expect("Output is a buffer");
+
+    let (width, _, image) = last_frame(&bytes);
+    let pixel = |x: usize, y: usize| &image[(y * width + x) * 4..(y * width + x) * 4 + 3];
+    // Corners of the squares both the king and the rook left and landed on are tinted
+    assert_ne!(pixel(4 * 40 + 2, 7 * 40 + 2), [238, 238, 210]);
+    assert_ne!(pixel(5 * 40 + 2, 7 * 40 + 2), [238, 238, 210]);
+    assert_ne!(pixel(6 * 40 + 2, 7 * 40 + 2), [118, 150, 86]);
+    assert_ne!(pixel(7 * 40 + 2, 7 * 40 + 2), [238, 238, 210]);
+    // The rook's arrow crosses from h1 into g1
+    assert_ne!(
+        pixel(7 * 40 + 2, 7 * 40 + 20),
+        pixel(7 * 40 + 2, 7 * 40 + 2)
+    );
+}
+
+#[test]
+fn test_underpromotion() {
+    let contents = fs::read_to_string("example/example_underpromotion.pgn")
+        .expect("Failed to read example PGN");
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 320,
+        style_components: StyleComponents::new(&[]),
+        pace: c2g::delay::Pace::Smart,
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::new(contents, config).expect("Failed to initialize Chess2Gif");
+    let bytes = app
+        .run()
+        .expect("Failed to run Chess2Gif")
+        .expect("Output is a buffer");
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(&bytes[..]).expect("Failed to decode GIF");
+    let width = decoder.width() as usize;
+    let mut image = vec![0u8; width * decoder.height() as usize * 4];
+    let (mut delays, mut a8s) = (Vec::new(), Vec::new());
+    while let Some(frame) = decoder.read_next_frame().expect("Failed to read frame") {
+        for (i, pixel) in frame.buffer.chunks(4).enumerate() {
+            if pixel[3] != 0 {
+                let x = frame.left as usize + i % frame.width as usize;
+                let y = frame.top as usize + i / frame.width as usize;
+                image[(y * width + x) * 4..(y * width + x) * 4 + 4].copy_from_slice(pixel);
+            }
+        }
+        delays.push(frame.delay);
+        a8s.push(
+            (0..40)
+                .flat_map(|y| image[y * width * 4..(y * width + 40) * 4].to_vec())
+                .collect::<Vec<u8>>(),
+        );
+    }
+
+    // The knight glows on the frame of the promotion, which is held longer, and not after it
+    assert_eq!(delays.len(), 11);
+    assert_eq!(delays[9], 2 * delays[8]);
+    assert_ne!(a8s[9], a8s[10]);
+    assert_ne!(a8s[8], a8s[10]);
+}
+
+#[test]
+fn test_pov() {
+    let contents =
+        fs::read_to_string("example/example_no_clock.pgn").expect("Failed to read example PGN");
+    let render = |orientation: config::Orientation, pov: Option<&str>| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            orientation,
+            pov: pov.map(str::to_string),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(contents.clone(), config).expect("Failed to initialize Chess2Gif");
+        app.run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer")
+    };
+
+    // ChessBrah plays black, and slowbullet white
+    let (white, black) = (config::Orientation::White, config::Orientation::Black);
+    let flipped = render(black, None);
+    assert_eq!(render(white, Some("chessbrah")), flipped);
+    assert_eq!(render(black, Some("SlowBullet")), render(white, None));
+    // Unknown players keep the orientation
+    assert_eq!(render(black, Some("magnus")), flipped);
+}
+
+#[test]
+fn test_follow_turn() {
+    let contents =
+        fs::read_to_string("example/example_bullet.pgn").expect("Failed to read example PGN");
+    // Keep the headers and the first three moves, so black is to move at the end
+    let headers: Vec<&str> = contents
+        .lines()
+        .take_while(|l| l.starts_with('['))
+        .collect();
+    let pgn = format!(
+        "{}\n\n1. e4 {{[%clk 0:00:30.0]}} 1... c5 {{[%clk 0:00:30.0]}} 2. Nc3 {{[%clk 0:00:29.7]}} *",
+        headers.join("\n")
+    );
+    let render = |orientation: config::Orientation| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            orientation,
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.clone(), config).expect("Failed to initialize Chess2Gif");
+        app.run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer")
+    };
+
+    // The last frame is seen from black's side, with bars and clocks swapped
+    let (_, _, following) = last_frame(&render(config::Orientation::FollowTurn));
+    let (_, _, flipped) = last_frame(&render(config::Orientation::Black));
+    let (_, _, unflipped) = last_frame(&render(config::Orientation::White));
+    // Frames are quantized to their own palettes, so a few pixels may differ
+    let pixels = following.len() / 4;
+    assert_eq!(following.len(), flipped.len());
+    assert!(differing_pixels(&following, &flipped) < pixels / 100);
+    assert!(differing_pixels(&following, &unflipped) > pixels / 10);
+}
+
+#[test]
+fn test_one_player_header() {
+    let contents =
+        fs::read_to_string("example/example_one_player.pgn").expect("Failed to read example PGN");
+    let render = |anonymous_name: &str| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            anonymous_name: anonymous_name.to_string(),
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(&[StyleComponent::PlayerBars]),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(contents.clone(), config).expect("Failed to initialize Chess2Gif");
+        app.run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer")
+    };
+
+    // Bars are drawn with only a White header, and Black gets the placeholder name
+    let (width, height, anonymous) = last_frame(&render("Anonymous"));
+    assert_eq!((width, height), (320, 400));
+    let (_, _, opponent) = last_frame(&render("Opponent"));
+    let top_bar = width * 40 * 4;
+    assert_ne!(anonymous[..top_bar], opponent[..top_bar]);
+    // Frames are quantized to their own palettes, so a few pixels may differ elsewhere
+    let rest = differing_pixels(&anonymous[top_bar..], &opponent[top_bar..]);
+    assert!(rest < width * (height - 40) / 100);
+}
+
+#[test]
+fn test_force_player_bars() {
+    let moves = "1. e4 {[%clk 0:03:00]} 1... e5 {[%clk 0:02:58]} 2. Nf3 {[%clk 0:02:55]} *";
+    let render = |pgn: String, force_player_bars: bool| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            force_player_bars,
+            style_components: StyleComponents::new(&[StyleComponent::Clocks]),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn, config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+
+    let (width, height, _) = render(moves.to_string(), false);
+    assert_eq!((width, height), (320, 320));
+
+    // Forced bars look like those of a game between players named White and Black, clocks
+    // included, even though the style only has clocks
+    let (width, height, forced) = render(moves.to_string(), true);
+    assert_eq!((width, height), (320, 400));
+    let headers = "[White \"White\"]\n[Black \"Black\"]\n\n";
+    let (_, _, named) = render(format!("{}{}", headers, moves), true);
+    assert!(differing_pixels(&forced, &named) < width * height / 100);
+    // White's clock is in the right end of the bottom bar
+    let clock: Vec<&[u8]> = (365..395)
+        .flat_map(|y| (235..315).map(move |x| (y * width + x) * 4))
+        .map(|i| &forced[i..i + 4])
+        .collect();
+    assert!(clock.iter().any(|p| p != &clock[0]));
+}
+
+#[test]
+fn test_clocks() {
+    let pgn = "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 {[%clk 0:03:00]} 1... e5 {[%clk 0:02:58]} 2. Nf3 {[%clk 0:02:55]} *";
+    let render = |components: &[StyleComponent]| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(components),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+    let uniform =
+        |img: &[u8], width: usize, xs: std::ops::Range<usize>, ys: std::ops::Range<usize>| {
+            let pixels: Vec<&[u8]> = ys
+                .flat_map(|y| xs.clone().map(move |x| (y * width + x) * 4))
+                .map(|i| &img[i..i + 4])
+                .collect();
+            pixels.iter().all(|p| p == &pixels[0])
+        };
+
+    // Bars are drawn without clocks unless asked for
+    let (width, height, bars) = render(&[StyleComponent::PlayerBars]);
+    assert_eq!((width, height), (320, 400));
+    assert!(uniform(&bars, width, 235..315, 365..395));
+    let (_, _, bars) = render(&[StyleComponent::PlayerBars, StyleComponent::Clocks]);
+    assert!(!uniform(&bars, width, 235..315, 365..395));
+
+    // Without bars, clocks are drawn in the right corners of the board
+    let (width, height, plain) = render(&[]);
+    assert_eq!((width, height), (320, 320));
+    let (width, height, clocks) = render(&[StyleComponent::Clocks]);
+    assert_eq!((width, height), (320, 320));
+    let region = |img: &[u8], ys: std::ops::Range<usize>| -> Vec<u8> {
+        ys.flat_map(|y| img[(y * width + 265) * 4..(y * width + 318) * 4].to_vec())
+            .collect()
+    };
+    for ys in [2..22, 298..318] {
+        let differing = differing_pixels(&region(&plain, ys.clone()), &region(&clocks, ys));
+        assert!(differing > 53 * 20 / 2);
+    }
+    let rest = |img: &[u8]| img[22 * width * 4..298 * width * 4].to_vec();
+    assert!(differing_pixels(&rest(&plain), &rest(&clocks)) < width * height / 100);
+}
+
+#[test]
+fn test_starting_clocks() {
+    let render = |time_control: &str| {
+        let pgn = format!(
+            "[White \"Anna\"]\n[Black \"Bea\"]\n[TimeControl \"{}\"]\n\n*",
+            time_control
+        );
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(&[
+                StyleComponent::PlayerBars,
+                StyleComponent::Clocks,
+            ]),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn, config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+    let uniform = |img: &[u8], width: usize, ys: std::ops::Range<usize>| {
+        let pixels: Vec<&[u8]> = ys
+            .flat_map(|y| (235..315).map(move |x| (y * width + x) * 4))
+            .map(|i| &img[i..i + 4])
+            .collect();
+        pixels.iter().all(|p| p == &pixels[0])
+    };
+
+    // Both clocks show the base time before any move
+    let (width, _, frame) = render("180+2");
+    assert!(!uniform(&frame, width, 5..35));
+    assert!(!uniform(&frame, width, 365..395));
+
+    for time_control in ["?", "-", "1/86400"] {
+        let (width, _, frame) = render(time_control);
+        assert!(uniform(&frame, width, 5..35));
+        assert!(uniform(&frame, width, 365..395));
+    }
+}
+
+#[test]
+fn test_low_time_clock() {
+    let pgn = "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 {[%clk 0:00:30]} 1... e5 {[%clk 0:03:00]} 2. Nf3 {[%clk 0:00:12]} *";
+    let render = |low_time: Option<std::time::Duration>| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(&[
+                StyleComponent::PlayerBars,
+                StyleComponent::Clocks,
+            ]),
+            low_time,
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+    let warning_pixels = |img: &[u8], width: usize, ys: std::ops::Range<usize>| {
+        ys.flat_map(|y| (235..315).map(move |x| (y * width + x) * 4))
+            .filter(|&i| img[i] > 150 && img[i + 1] < 100 && img[i + 2] < 100)
+            .count()
+    };
+
+    // White's clock at the bottom is below the default 20 seconds, black's isn't
+    let (width, _, frame) = render(Some(std::time::Duration::from_secs(20)));
+    assert!(warning_pixels(&frame, width, 365..395) > 0);
+    assert_eq!(warning_pixels(&frame, width, 5..35), 0);
+
+    let (width, _, frame) = render(None);
+    assert_eq!(warning_pixels(&frame, width, 365..395), 0);
+}
+
+#[test]
+fn test_move_times() {
+    let pgn = "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 {[%clk 0:03:00]} 1... e5 {[%clk 0:03:00]} 2. Nf3 {[%clk 0:02:50]} 2... Nc6 {[%clk 0:02:50]} 3. Bb5 {[%clk 0:02:36]} *";
+    let render = |style_components: &[StyleComponent]| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(style_components),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+    // Pixels of the box left of a clock that are not the color of its top left corner
+    let text_pixels = |img: &[u8], width: usize, ys: std::ops::Range<usize>| {
+        let corner = (ys.start * width + 185) * 4;
+        ys.flat_map(|y| (185..235).map(move |x| (y * width + x) * 4))
+            .filter(|&i| img[i..i + 3] != img[corner..corner + 3])
+            .count()
+    };
+
+    // White spent 14 seconds on the last move, and black's 10 seconds before it are cleared
+    let (width, _, frame) = render(&[
+        StyleComponent::PlayerBars,
+        StyleComponent::Clocks,
+        StyleComponent::MoveTimes,
+    ]);
+    assert!(text_pixels(&frame, width, 365..395) > 0);
+    assert_eq!(text_pixels(&frame, width, 5..35), 0);
+
+    let (width, _, frame) = render(&[StyleComponent::PlayerBars, StyleComponent::Clocks]);
+    assert_eq!(text_pixels(&frame, width, 365..395), 0);
+}
+
+#[test]
+fn test_move_list() {
+    let contents =
+        "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 c5 2. Nc3 d6 3. g3 Nc6 4. Bg2 g6 5. Nge2 Bg7 *";
+    let render = |style_components: &[StyleComponent]| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(style_components),
+            ..config::Config::default()
+        };
+        let app =
+            Chess2Gif::new(contents.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+
+    // The panel is three squares wide, right of the board and the player bars
+    let (width, height, frame) = render(&[StyleComponent::PlayerBars, StyleComponent::MoveList]);
+    assert_eq!((width, height), (440, 400));
+    // The board and bars are drawn the same, up to the GIF palette
+    let (plain_width, _, plain_frame) = render(&[StyleComponent::PlayerBars]);
+    assert_eq!(plain_width, 320);
+    let board: Vec<u8> = frame
+        .chunks(width * 4)
+        .flat_map(|row| row[..plain_width * 4].to_vec())
+        .collect();
+    assert!(differing_pixels(&board, &plain_frame) < 320 * 400 / 100);
+}
+
+#[test]
+fn test_move_overlay() {
+    let contents = "1. e4 c5 2. Nc3 d6 3. g3 Nc6 4. Bg2 g6 5. Nge2 Bg7+ *";
+    let render = |move_overlay: Option<config::Corner>| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            move_overlay,
+            ..config::Config::default()
+        };
+        let app =
+            Chess2Gif::new(contents.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+
+    let (width, height, frame) = render(Some(config::Corner::TopRight));
+    let (_, _, plain_frame) = render(None);
+    assert_eq!((width, height), (320, 320));
+    // The move is written in a box two and a half squares wide and half a square high, and
+    // the rest of the board is drawn the same, up to the GIF palette
+    let split = |frame: &[u8]| {
+        let (mut inside, mut outside) = (Vec::new(), Vec::new());
+        for (n, pixel) in frame.chunks(4).enumerate() {
+            let (x, y) = (n % width, n / width);
+            if (218..318).contains(&x) && (2..22).contains(&y) {
+                inside.extend_from_slice(pixel);
+            } else {
+                outside.extend_from_slice(pixel);
+            }
+        }
+        (inside, outside)
+    };
+    let (inside, outside) = split(&frame);
+    let (plain_inside, plain_outside) = split(&plain_frame);
+    assert!(differing_pixels(&inside, &plain_inside) > 100 * 20 / 2);
+    assert!(differing_pixels(&outside, &plain_outside) < 320 * 320 / 100);
+}
+
+#[test]
+fn test_nag_badges() {
+    let render = |pgn: &str| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+
+    let (width, _, frame) = render("1. e4 e5 2. Qh5 $2 Nc6 3. Bc4 Nf6 $4 *");
+    let (_, _, plain_frame) = render("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 *");
+    // Only the last move's badge is left, on the top right corner of f6, and the badge of
+    // Qh5 was cleared
+    let split = |frame: &[u8]| {
+        let (mut badge, mut rest) = (Vec::new(), Vec::new());
+        for (n, pixel) in frame.chunks(4).enumerate() {
+            let (x, y) = (n % width, n / width);
+            if (220..240).contains(&x) && (80..100).contains(&y) {
+                badge.extend_from_slice(pixel);
+            } else {
+                rest.extend_from_slice(pixel);
+            }
+        }
+        (badge, rest)
+    };
+    let (badge, rest) = split(&frame);
+    let (plain_badge, plain_rest) = split(&plain_frame);
+    assert!(differing_pixels(&badge, &plain_badge) > 20 * 20 / 3);
+    assert!(differing_pixels(&rest, &plain_rest) < 320 * 320 / 100);
+    // The badge is red, for a blunder
+    assert!(badge
+        .chunks(4)
+        .any(|p| p[0] > 200 && (60..110).contains(&p[1]) && (60..110).contains(&p[2])));
+}
+
+#[test]
+fn test_captions() {
+    let render = |pgn: &str| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(&[StyleComponent::Captions]),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+
+    // The caption strip is two lines of 16 pixels under the board
+    let (width, height, frame) = render("1. e4 e5 2. Qh5 { Threatening f7 } *");
+    assert_eq!((width, height), (320, 352));
+    // Clock comments leave the strip empty
+    let (_, _, clock_frame) = render("1. e4 e5 2. Qh5 { [%clk 0:02:58] } *");
+    let board_size = 320 * 320 * 4;
+    assert!(differing_pixels(&frame[..board_size], &clock_frame[..board_size]) < 320 * 320 / 100);
+    assert!(differing_pixels(&frame[board_size..], &clock_frame[board_size..]) > 100);
+    let strip = &clock_frame[board_size..];
+    assert!(strip.chunks(4).all(|p| p == &strip[..4]));
+}
+
+#[test]
+fn test_watermark() {
+    let logo = std::env::temp_dir().join("c2g_test_logo.png");
+    image::RgbaImage::from_pixel(40, 20, image::Rgba([30, 30, 200, 255]))
+        .save(&logo)
+        .expect("Failed to save logo");
+    let render = |watermark: Option<config::Watermark>| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(&[StyleComponent::PlayerBars]),
+            watermark,
+            ..config::Config::default()
+        };
+        let pgn = "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 e5 2. Nf3 Nc6 *";
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+
+    let watermark = config::Watermark::new(logo, config::Corner::BottomRight, 0.5, 0.25)
+        .expect("Logo is a PNG file");
+    let (width, height, frame) = render(Some(watermark));
+    let (_, _, plain_frame) = render(None);
+    assert_eq!((width, height), (320, 400));
+    // The watermark is a quarter of the width of the image, in the corner of the bottom bar
+    let split = |frame: &[u8]| {
+        let (mut logo, mut rest) = (Vec::new(), Vec::new());
+        for (n, pixel) in frame.chunks(4).enumerate() {
+            let (x, y) = (n % width, n / width);
+            if (235..315).contains(&x) && (355..395).contains(&y) {
+                logo.extend_from_slice(pixel);
+            } else {
+                rest.extend_from_slice(pixel);
+            }
+        }
+        (logo, rest)
+    };
+    let (logo, rest) = split(&frame);
+    let (plain_logo, plain_rest) = split(&plain_frame);
+    assert!(differing_pixels(&logo, &plain_logo) > 80 * 40 * 9 / 10);
+    assert!(differing_pixels(&rest, &plain_rest) < 320 * 400 / 100);
+}
+
+#[test]
+fn test_watermark_canvas() {
+    let logo = std::env::temp_dir().join("c2g_test_canvas_logo.png");
+    image::RgbaImage::from_pixel(40, 20, image::Rgba([30, 30, 200, 255]))
+        .save(&logo)
+        .expect("Failed to save logo");
+    let render = |watermark: Option<config::Watermark>| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(&[StyleComponent::PlayerBars]),
+            canvas: Some((480, 480)),
+            watermark,
+            ..config::Config::default()
+        };
+        let pgn = "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 e5 2. Nf3 Nc6 *";
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+
+    let watermark = config::Watermark::new(logo, config::Corner::BottomRight, 0.5, 0.25)
+        .expect("Logo is a PNG file");
+    let (width, height, frame) = render(Some(watermark));
+    let (_, _, plain_frame) = render(None);
+    assert_eq!((width, height), (480, 480));
+    // The watermark is a quarter of the width of the canvas, in its corner, outside the board
+    let split = |frame: &[u8]| {
+        let (mut logo, mut rest) = (Vec::new(), Vec::new());
+        for (n, pixel) in frame.chunks(4).enumerate() {
+            let (x, y) = (n % width, n / width);
+            if (355..475).contains(&x) && (415..475).contains(&y) {
+                logo.extend_from_slice(pixel);
+            } else {
+                rest.extend_from_slice(pixel);
+            }
+        }
+        (logo, rest)
+    };
+    let (logo, rest) = split(&frame);
+    let (plain_logo, plain_rest) = split(&plain_frame);
+    assert!(differing_pixels(&logo, &plain_logo) > 120 * 60 * 9 / 10);
+    assert!(differing_pixels(&rest, &plain_rest) < 480 * 480 / 100);
+}
+
+#[test]
+fn test_canvas_too_small() {
+    let pgn = "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 e5 2. Nf3 Nc6 *";
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 320,
+        canvas: Some((100, 100)),
+        ..config::Config::default()
+    };
+    assert!(Chess2Gif::new(pgn.to_string(), config).is_err());
+}
+
+#[test]
+fn test_render_error() {
+    let pgn = "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 e5 2. Nf3 Nc6 *";
+    let output = std::env::temp_dir()
+        .join("c2g_missing_directory")
+        .join("out.gif");
+    let config = config::Config {
+        output: config::Output::Path(output.to_string_lossy().to_string()),
+        size: 160,
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+
+    // Errors writing the GIF are returned instead of an empty result
+    assert!(app.run().is_err());
+}
+
+#[test]
+fn test_text_overlays() {
+    let pgn = "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 e5 2. Nf3 Nc6 *";
+    let config = |overlays: Vec<config::TextOverlay>| config::Config {
+        output: config::Output::Buffer,
+        size: 320,
+        font_path: "fonts/".to_string(),
+        font_family: "Roboto".to_string(),
+        style_components: StyleComponents::new(&[StyleComponent::PlayerBars]),
+        overlays,
+        ..config::Config::default()
+    };
+    let render = |overlays| {
+        let app = Chess2Gif::new(pgn.to_string(), config(overlays))
+            .expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+    let overlay = |text: &str, y, frames| config::TextOverlay {
+        text: text.to_string(),
+        x: 10,
+        y,
+        size: 16.0,
+        color: config::Color([200, 30, 30, 255]),
+        frames,
+    };
+
+    let (width, height, plain_frame) = render(Vec::new());
+    let (_, _, frame) = render(vec![
+        overlay("Round 1 <A & B>", 100, None),
+        overlay("Opening", 200, Some(0..1)),
+    ]);
+    assert_eq!((width, height), (320, 400));
+    // Only the overlay shown on every frame makes it to the last one
+    let split = |frame: &[u8]| {
+        let (mut text, mut rest) = (Vec::new(), Vec::new());
+        for (n, pixel) in frame.chunks(4).enumerate() {
+            let (x, y) = (n % width, n / width);
+            if (10..310).contains(&x) && (100..140).contains(&y) {
+                text.extend_from_slice(pixel);
+            } else {
+                rest.extend_from_slice(pixel);
+            }
+        }
+        (text, rest)
+    };
+    let (text, rest) = split(&frame);
+    let (plain_text, plain_rest) = split(&plain_frame);
+    assert!(differing_pixels(&text, &plain_text) > 100);
+    assert!(differing_pixels(&rest, &plain_rest) < 320 * 400 / 100);
+
+    // Overlays outside of the image, or without frames to be shown on, fail before rendering
+    for overlay in [
+        overlay("Outside", 400, None),
+        overlay("Never shown", 100, Some(2..2)),
+    ] {
+        assert!(Chess2Gif::new(pgn.to_string(), config(vec![overlay])).is_err());
+    }
+}
+
+#[test]
+fn test_player_avatars() {
+    let pgn = "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 e5 2. Nf3 *";
+    let avatar = std::env::temp_dir().join("c2g_test_avatar.png");
+    image::RgbaImage::from_pixel(64, 64, image::Rgba([200, 30, 30, 255]))
+        .save(&avatar)
+        .expect("Failed to save avatar");
+    let render = |white_avatar: Option<std::path::PathBuf>, black_avatar| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(&[StyleComponent::PlayerBars]),
+            white_avatar,
+            black_avatar,
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+
+    // A missing avatar doesn't fail the render, and its bar is drawn as if there was none
+    let (width, height, plain) = render(None, None);
+    let (_, _, with_avatar) = render(
+        Some(avatar.clone()),
+        Some(std::path::PathBuf::from("missing/avatar.png")),
+    );
+    fs::remove_file(&avatar).ok();
+    assert_eq!((width, height), (320, 400));
+
+    // White's avatar is at the left edge of the bottom bar
+    let pixel = |img: &[u8], x: usize, y: usize| {
+        let i = (y * width + x) * 4;
+        img[i..i + 4].to_vec()
+    };
+    let red = pixel(&with_avatar, 20, 380);
+    assert!(red[0] > 150 && red[1] < 80 && red[2] < 80);
+    assert_ne!(pixel(&plain, 20, 380), red);
+    // Black's bar at the top is unchanged
+    let top = 40 * width * 4;
+    assert_eq!(differing_pixels(&plain[..top], &with_avatar[..top]), 0);
+}
+
+#[test]
+fn test_event_bar() {
+    let pgn = "[Event \"Tata Steel Masters 2023\"]\n[Site \"Wijk aan Zee\"]\n[Round \"6\"]\n[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0";
+    let render = |components: &[StyleComponent]| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(components),
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+
+    let (width, height, bars) = render(&[StyleComponent::PlayerBars]);
+    assert_eq!((width, height), (320, 400));
+    let (width, height, event) = render(&[StyleComponent::PlayerBars, StyleComponent::EventBar]);
+    assert_eq!((width, height), (320, 420));
+    // Everything below the event bar is shifted down by its height, save for palette shifts at
+    // the edges of pieces and text
+    let strip = 20 * width * 4;
+    let shifted = |a: &[u8], b: &[u8]| differing_pixels(a, &b[strip..]) < width * height / 100;
+    assert!(shifted(&bars, &event));
+    assert!(!shifted(&bars, &event[strip..]));
+
+    let (width, height, plain) = render(&[]);
+    assert_eq!((width, height), (320, 320));
+    let (width, height, event) = render(&[StyleComponent::EventBar]);
+    assert_eq!((width, height), (320, 340));
+    assert!(shifted(&plain, &event));
+}
+
+#[test]
+fn test_bar_height() {
+    let pgn = "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0";
+    let render = |components: &[StyleComponent], height_fraction: f32| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(components),
+            bar_style: config::BarStyle {
+                height_fraction,
+                ..config::BarStyle::default()
+            },
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+
+    let (width, height, plain) = render(&[StyleComponent::Terminations], 0.5);
+    assert_eq!((width, height), (320, 320));
+    let components = [StyleComponent::PlayerBars, StyleComponent::Terminations];
+    let (width, height, bars) = render(&components, 0.5);
+    assert_eq!((width, height), (320, 360));
+    // The board, with its termination circles, starts right below the top bar
+    let board = &bars[20 * width * 4..340 * width * 4];
+    assert!(differing_pixels(&plain, board) < width * width / 100);
+
+    let (width, height, _) = render(&components, 1.5);
+    assert_eq!((width, height), (320, 440));
+}
+
+#[test]
+fn test_single_bar_layout() {
+    let pgn = "[White \"Anna\"]\n[Black \"Bea\"]\n\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0";
+    let render = |components: &[StyleComponent]| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(components),
+            bar_layout: config::BarLayout::Single,
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+
+    let (_, _, plain) = render(&[StyleComponent::Terminations]);
+    let (width, height, bars) = render(&[StyleComponent::PlayerBars, StyleComponent::Terminations]);
+    assert_eq!((width, height), (320, 360));
+    // The board starts at the top, with the only bar below it
+    let board = &bars[..320 * width * 4];
+    assert!(differing_pixels(&plain, board) < width * width / 100);
+}
+
+#[test]
+fn test_opening() {
+    let render = |pgn: &str| {
+        let config = config::Config {
+            output: config::Output::Buffer,
+            size: 320,
+            font_path: "fonts/".to_string(),
+            font_family: "Roboto".to_string(),
+            style_components: StyleComponents::new(&[StyleComponent::Opening]),
+            opening_moves: 1,
+            ..config::Config::default()
+        };
+        let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+        let bytes = app
+            .run()
+            .expect("Failed to run Chess2Gif")
+            .expect("Output is a buffer");
+        last_frame(&bytes)
+    };
+    let moves = "1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0";
+
+    let (width, height, opening) = render(&format!(
+        "[ECO \"C20\"]\n[Opening \"King's Pawn Game\"]\n\n{}",
+        moves
+    ));
+    assert_eq!((width, height), (320, 340));
+    // The opening faded out by the last frame, leaving the bar empty
+    let bar = &opening[..20 * width * 4];
+    assert!(bar.chunks(4).all(|p| p == bar[..4].to_vec()));
+
+    // Nothing is drawn without the headers
+    let (width, height, _) = render(moves);
+    assert_eq!((width, height), (320, 320));
+}
+
+#[test]
+fn test_hostile_player_names() {
+    let pgn = r#"[Event "Smith & Jones <Invitational>"]
+[White "Smith & Jones"]
+[Black "</text><svg onload='x'> \"Bobby\" 🐴"]
+[WhiteTitle "G&M"]
+[Result "1-0"]
+
+1. e4 {[%clk 0:03:00]} 1... e5 {[%clk 0:02:58]} 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0"#;
+    let config = config::Config {
+        output: config::Output::Buffer,
+        size: 320,
+        font_path: "fonts/".to_string(),
+        font_family: "Roboto".to_string(),
+        style_components: StyleComponents::new(&[
+            StyleComponent::PlayerBars,
+            StyleComponent::Terminations,
+        ]),
+        title_card: Some(1000),
+        ..config::Config::default()
+    };
+    let app = Chess2Gif::new(pgn.to_string(), config).expect("Failed to initialize Chess2Gif");
+    let bytes = app
+        .run()
+        .expect("Failed to run Chess2Gif")
+        .expect("Output is a buffer");
+
+    let (width, height, _) = last_frame(&bytes);
+    assert_eq!((width, height), (320, 400));
+}